@@ -10,7 +10,9 @@ use rubato::interpolator_neon::NeonInterpolator;
 #[cfg(target_arch = "x86_64")]
 use rubato::interpolator_sse::SseInterpolator;
 
-use rubato::{FftFixedIn, InterpolationType, Resampler, SincFixedIn, WindowFunction};
+use rubato::{Accumulation, SincFixedIn, SincStorage, WindowFunction};
+use rubato::{FftFixedIn, FftFixedInOut, HybridFixedIn, RealFftPlanner};
+use rubato::{FilterPhase, InterpolationParameters, InterpolationType, Resampler};
 
 fn bench_fftfixedin(c: &mut Criterion) {
     let chunksize = 1024;
@@ -30,6 +32,168 @@ fn bench_fftfixedin_32(c: &mut Criterion) {
     });
 }
 
+fn bench_interleave_after(c: &mut Criterion) {
+    let chunksize = 1024;
+    let mut resampler = FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 2);
+    let waveform = vec![vec![0.0 as f32; chunksize]; 2];
+    let mut waveform_out = vec![vec![0.0 as f32; resampler.output_frames_max()]; 2];
+    let mut interleaved = vec![0.0 as f32; 2 * resampler.output_frames_max()];
+    c.bench_function("FftFixedIn f32, interleave after", |b| {
+        b.iter(|| {
+            let (_, frames) = resampler
+                .process_into_buffer(&waveform, &mut waveform_out, None)
+                .unwrap();
+            for (n, frame) in interleaved.chunks_mut(2).take(frames).enumerate() {
+                frame[0] = waveform_out[0][n];
+                frame[1] = waveform_out[1][n];
+            }
+        })
+    });
+}
+
+fn bench_into_interleaved(c: &mut Criterion) {
+    let chunksize = 1024;
+    let mut resampler = FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 2);
+    let waveform = vec![vec![0.0 as f32; chunksize]; 2];
+    let mut interleaved = vec![0.0 as f32; 2 * resampler.output_frames_max()];
+    c.bench_function("FftFixedIn f32, into interleaved", |b| {
+        b.iter(|| {
+            resampler
+                .process_into_interleaved_buffer(&waveform, &mut interleaved, None)
+                .unwrap()
+        })
+    });
+}
+
+// Create many resamplers for the same rates, each with a planner of its own,
+// or all with one shared planner that reuses the planned FFTs.
+fn bench_create_many(c: &mut Criterion) {
+    c.bench_function("FftFixedInOut f32, create 100", |b| {
+        b.iter(|| {
+            (0..100)
+                .map(|_| FftFixedInOut::<f32>::new(44100, 48000, 1024, 2))
+                .collect::<Vec<_>>()
+        })
+    });
+    c.bench_function("FftFixedInOut f32, create 100 with shared planner", |b| {
+        b.iter(|| {
+            let mut planner = RealFftPlanner::<f32>::new();
+            (0..100)
+                .map(|_| {
+                    FftFixedInOut::<f32>::new_with_planner(&mut planner, 44100, 48000, 1024, 2)
+                })
+                .collect::<Vec<_>>()
+        })
+    });
+}
+
+// Resample a sine that decays from full scale to below the smallest normal f32,
+// so that the end of the stream is processed with denormal numbers.
+fn bench_decaying_sine(c: &mut Criterion, flush_denormals: bool, desc: &str) {
+    let chunksize = 1024;
+    let length = 64 * chunksize;
+    let decay = (1.0e-45_f64.ln() / length as f64).exp();
+    let waveform: Vec<f32> = (0..length)
+        .map(|n| (decay.powi(n as i32) * (0.05 * n as f64).sin()) as f32)
+        .collect();
+    let mut resampler = SincFixedIn::<f32>::new(
+        48000 as f64 / 44100 as f64,
+        InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        },
+        chunksize,
+        1,
+    );
+    resampler.set_flush_denormals(flush_denormals);
+    let mut waveform_out = vec![vec![0.0 as f32; resampler.output_frames_max()]; 1];
+    c.bench_function(desc, |b| {
+        b.iter(|| {
+            resampler.reset();
+            for chunk in waveform.chunks(chunksize) {
+                resampler
+                    .process_into_buffer(&[chunk], &mut waveform_out, None)
+                    .unwrap();
+            }
+        })
+    });
+}
+
+fn bench_decaying_sine_denormals(c: &mut Criterion) {
+    bench_decaying_sine(c, false, "SincFixedIn f32, decaying sine");
+}
+
+fn bench_decaying_sine_flushed(c: &mut Criterion) {
+    bench_decaying_sine(c, true, "SincFixedIn f32, decaying sine, flush denormals");
+}
+
+// Resample with a long sinc in f32, to compare the cost of the ways to sum the products.
+fn bench_long_sinc(c: &mut Criterion, accumulation: Accumulation, desc: &str) {
+    let chunksize = 1024;
+    let mut resampler = SincFixedIn::<f32>::new(
+        48000 as f64 / 44100 as f64,
+        InterpolationParameters {
+            sinc_len: 512,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation,
+        },
+        chunksize,
+        1,
+    );
+    let waveform = vec![vec![0.0 as f32; chunksize]; 1];
+    c.bench_function(desc, |b| b.iter(|| resampler.process(&waveform).unwrap()));
+}
+
+fn bench_long_sinc_native(c: &mut Criterion) {
+    bench_long_sinc(c, Accumulation::Native, "SincFixedIn f32, sinc_len 512");
+}
+
+fn bench_long_sinc_double(c: &mut Criterion) {
+    bench_long_sinc(
+        c,
+        Accumulation::Double,
+        "SincFixedIn f32, sinc_len 512, double accumulation",
+    );
+}
+
+/// Compare the hybrid resampler with a single sinc stage, for a ratio without small terms.
+/// The FFT stage does the filtering, so a short sinc is enough for the hybrid,
+/// while the single stage needs a long sinc for a similar stopband attenuation.
+fn bench_arbitrary_ratio(c: &mut Criterion) {
+    let chunksize = 1024;
+    let ratio = 48037 as f64 / 44100 as f64;
+    let params = |sinc_len| InterpolationParameters {
+        sinc_len,
+        f_cutoff: 0.95,
+        interpolation: InterpolationType::Cubic,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+        phase: FilterPhase::Linear,
+        storage: SincStorage::Table,
+        accumulation: Accumulation::Native,
+    };
+    let waveform = vec![vec![0.0 as f32; chunksize]; 1];
+    let mut resampler = HybridFixedIn::<f32>::new(ratio, params(64), chunksize, 1);
+    c.bench_function("HybridFixedIn f32, 44100 to 48037", |b| {
+        b.iter(|| resampler.process(&waveform).unwrap())
+    });
+    let mut resampler = SincFixedIn::<f32>::new(ratio, params(512), chunksize, 1);
+    c.bench_function("SincFixedIn f32, 44100 to 48037, sinc_len 512", |b| {
+        b.iter(|| resampler.process(&waveform).unwrap())
+    });
+}
+
 /// Helper to unwrap the constructed interpolator if appropriate.
 macro_rules! unwrap_helper {
     (infallible $var:ident) => {
@@ -273,6 +437,14 @@ criterion_group!(
     benches,
     bench_fftfixedin,
     bench_fftfixedin_32,
+    bench_interleave_after,
+    bench_into_interleaved,
+    bench_create_many,
+    bench_decaying_sine_denormals,
+    bench_decaying_sine_flushed,
+    bench_long_sinc_native,
+    bench_long_sinc_double,
+    bench_arbitrary_ratio,
     bench_scalar_async_cubic_32,
     bench_scalar_async_linear_32,
     bench_scalar_async_nearest_32,
@@ -292,6 +464,14 @@ criterion_group!(
     benches,
     bench_fftfixedin,
     bench_fftfixedin_32,
+    bench_interleave_after,
+    bench_into_interleaved,
+    bench_create_many,
+    bench_decaying_sine_denormals,
+    bench_decaying_sine_flushed,
+    bench_long_sinc_native,
+    bench_long_sinc_double,
+    bench_arbitrary_ratio,
     bench_scalar_async_cubic_32,
     bench_scalar_async_linear_32,
     bench_scalar_async_nearest_32,
@@ -317,6 +497,14 @@ criterion_group!(
     benches,
     bench_fftfixedin,
     bench_fftfixedin_32,
+    bench_interleave_after,
+    bench_into_interleaved,
+    bench_create_many,
+    bench_decaying_sine_denormals,
+    bench_decaying_sine_flushed,
+    bench_long_sinc_native,
+    bench_long_sinc_double,
+    bench_arbitrary_ratio,
     bench_scalar_async_cubic_32,
     bench_scalar_async_linear_32,
     bench_scalar_async_nearest_32,
@@ -330,6 +518,14 @@ criterion_group!(
     benches,
     bench_fftfixedin,
     bench_fftfixedin_32,
+    bench_interleave_after,
+    bench_into_interleaved,
+    bench_create_many,
+    bench_decaying_sine_denormals,
+    bench_decaying_sine_flushed,
+    bench_long_sinc_native,
+    bench_long_sinc_double,
+    bench_arbitrary_ratio,
     bench_scalar_async_cubic_32,
     bench_scalar_async_linear_32,
     bench_scalar_async_nearest_32,
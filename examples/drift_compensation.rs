@@ -0,0 +1,77 @@
+extern crate rubato;
+use rubato::{
+    Accumulation, FilterPhase, InterpolationParameters, InterpolationType, RateAdjuster, Resampler,
+    SincFixedOut, SincStorage, WindowFunction,
+};
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::time::Duration;
+
+///! An example of bridging two devices that run on separate clocks.
+///! A simulated capture device runs 80 ppm faster than a simulated playback device,
+///! and writes a tone into a FIFO. A SincFixedOut reads from the FIFO and produces
+///! the fixed chunks that the playback device asks for, and a RateAdjuster
+///! keeps the FIFO at the wanted fill level by adjusting the resample ratio.
+///! The example takes no arguments, and prints the fill level and the ratio once per second.
+///! ```
+///! cargo run --release --example drift_compensation
+///! ```
+
+const SAMPLE_RATE: f64 = 48000.0;
+const CHUNK_SIZE: usize = 480;
+const TARGET_FILL: usize = 2048;
+const DRIFT: f64 = 80.0e-6;
+
+fn main() {
+    let params = InterpolationParameters {
+        sinc_len: 128,
+        f_cutoff: 0.95,
+        interpolation: InterpolationType::Cubic,
+        oversampling_factor: 64,
+        window: WindowFunction::BlackmanHarris2,
+        phase: FilterPhase::Linear,
+        storage: SincStorage::Table,
+        accumulation: Accumulation::Native,
+    };
+    let mut resampler = SincFixedOut::<f64>::new(1.0, params, CHUNK_SIZE, 1);
+    let mut adjuster = RateAdjuster::new(SAMPLE_RATE, TARGET_FILL, 0.1);
+
+    let mut fifo: VecDeque<f64> = VecDeque::new();
+    let mut captured = 0;
+    let mut capture_position = 0.0;
+    fifo.extend(std::iter::repeat(0.0).take(TARGET_FILL));
+
+    // Each step is the time it takes the playback device to play one chunk.
+    let step = Duration::from_secs_f64(CHUNK_SIZE as f64 / SAMPLE_RATE);
+    let steps_per_second = (SAMPLE_RATE / CHUNK_SIZE as f64) as usize;
+    for n in 0..60 * steps_per_second {
+        // The capture device delivers slightly more than a chunk per step.
+        capture_position += CHUNK_SIZE as f64 * (1.0 + DRIFT);
+        while (captured as f64) < capture_position {
+            let time = captured as f64 / (SAMPLE_RATE * (1.0 + DRIFT));
+            fifo.push_back((2.0 * PI * 1000.0 * time).sin());
+            captured += 1;
+        }
+
+        // The playback device asks for a chunk.
+        let frames = resampler.nbr_frames_needed();
+        let wave_in: Vec<f64> = fifo.drain(..frames).collect();
+        let _output = resampler.process(&[wave_in]).unwrap();
+
+        let ratio = adjuster.update(fifo.len(), step);
+        resampler.set_resample_ratio_relative(ratio).unwrap();
+        if n % steps_per_second == 0 {
+            println!(
+                "{:3} s, fill: {:5}, ratio: {:.7}",
+                n / steps_per_second,
+                fifo.len(),
+                ratio
+            );
+        }
+    }
+    println!(
+        "Estimated drift: {:.1} ppm, actual: {:.1} ppm",
+        (1.0 / adjuster.drift_ratio() - 1.0) * 1.0e6,
+        DRIFT * 1.0e6
+    );
+}
@@ -1,5 +1,8 @@
 extern crate rubato;
-use rubato::{InterpolationParameters, InterpolationType, Resampler, SincFixedIn, WindowFunction};
+use rubato::{
+    Accumulation, FilterPhase, InterpolationParameters, InterpolationType, Resampler, SincFixedIn,
+    SincStorage, WindowFunction,
+};
 use std::convert::TryInto;
 use std::env;
 use std::fs::File;
@@ -109,6 +112,8 @@ fn main() {
     //    interpolation: InterpolationType::Linear,
     //    oversampling_factor: 1024,
     //    window: WindowFunction::Hann2,
+    //    phase: FilterPhase::Linear,
+    //    storage: SincStorage::Table,
     //};
 
     // Balanced for sync for 44100 -> 96000 etc (note that for sync it's better to use the fft resampler)
@@ -120,6 +125,8 @@ fn main() {
     //    interpolation: InterpolationType::Nearest,
     //    oversampling_factor: 320,
     //    window: WindowFunction::Blackman2,
+    //    phase: FilterPhase::Linear,
+    //    storage: SincStorage::Table,
     //};
 
     // Balanced for async
@@ -131,6 +138,8 @@ fn main() {
     //    interpolation: InterpolationType::Linear,
     //    oversampling_factor: 2048,
     //    window: WindowFunction::Blackman2,
+    //    phase: FilterPhase::Linear,
+    //    storage: SincStorage::Table,
     //};
     //
     //// Best for sync for 44100 -> 96000 etc (note that for sync it's better to use the fft resampler)
@@ -142,6 +151,9 @@ fn main() {
         interpolation: InterpolationType::Nearest,
         oversampling_factor: 320,
         window: WindowFunction::BlackmanHarris2,
+        phase: FilterPhase::Linear,
+        storage: SincStorage::Table,
+        accumulation: Accumulation::Native,
     };
 
     // Best quality for async
@@ -153,6 +165,8 @@ fn main() {
     //    interpolation: InterpolationType::Cubic,
     //    oversampling_factor: 256,
     //    window: WindowFunction::BlackmanHarris2,
+    //    phase: FilterPhase::Linear,
+    //    storage: SincStorage::Table,
     //};
 
     let mut resampler = SincFixedIn::<f64>::new(f_ratio, params, 1024, channels);
@@ -1,5 +1,8 @@
 extern crate rubato;
-use rubato::{InterpolationParameters, InterpolationType, Resampler, SincFixedOut, WindowFunction};
+use rubato::{
+    Accumulation, FilterPhase, InterpolationParameters, InterpolationType, Resampler, SincFixedOut,
+    SincStorage, WindowFunction,
+};
 use std::convert::TryInto;
 use std::env;
 use std::fs::File;
@@ -104,6 +107,9 @@ fn main() {
         interpolation: InterpolationType::Linear,
         oversampling_factor: 2048,
         window: WindowFunction::Blackman2,
+        phase: FilterPhase::Linear,
+        storage: SincStorage::Table,
+        accumulation: Accumulation::Native,
     };
 
     let mut resampler = SincFixedOut::<f64>::new(f_ratio, params, 1024, channels);
@@ -0,0 +1,226 @@
+use std::f64::consts::PI;
+use std::time::Duration;
+
+/// The default largest deviation of the relative resample ratio from 1.0.
+const DEFAULT_MAX_DEVIATION: f64 = 0.001;
+
+/// A controller that keeps the fill level of a buffer constant, by adjusting the
+/// resample ratio of a resampler that fills or empties it.
+///
+/// This is meant for bridging two devices that run on separate clocks,
+/// for example a capture device feeding a playback device through a FIFO.
+/// Even when both run at the same nominal sample rate, their clocks drift apart,
+/// and the buffer between them slowly fills up or runs empty.
+/// Resampling with a ratio that is adjusted by this controller compensates for the drift.
+///
+/// The controller is a PI controller, that reads the fill level of the buffer at regular intervals
+/// and returns a relative resample ratio to apply with
+/// [set_resample_ratio_relative](crate::Resampler::set_resample_ratio_relative).
+/// The buffer may be placed either after the resampler, between the resampler and the playback,
+/// or before it, between the capture and the resampler. In both cases a buffer that is
+/// too full gives a lower ratio.
+///
+/// The gains are chosen for a critically damped loop with the given bandwidth.
+/// A lower bandwidth gives a slower but smoother correction, and filters out more of the
+/// jitter in the fill level that comes from processing in chunks.
+/// The returned ratio is limited to 1.0 plus or minus a largest deviation,
+/// see [set_max_deviation](RateAdjuster::set_max_deviation).
+/// The integral part stops accumulating while the ratio is limited, so that the controller
+/// doesn't overshoot after a long period at the limit.
+///
+/// The controller only does computations, and can be updated from any thread.
+///
+/// ```
+/// use rubato::RateAdjuster;
+/// use std::time::Duration;
+///
+/// let mut adjuster = RateAdjuster::new(48000.0, 2048, 0.1);
+/// // The buffer has more frames than wanted, so the ratio is lowered.
+/// let ratio = adjuster.update(2148, Duration::from_millis(10));
+/// assert!(ratio < 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateAdjuster {
+    target_fill: f64,
+    proportional_gain: f64,
+    integral_gain: f64,
+    integral: f64,
+    max_deviation: f64,
+}
+
+impl RateAdjuster {
+    /// Create a new RateAdjuster
+    ///
+    /// Parameters are:
+    /// - `sample_rate`: the sample rate of the buffer, in frames per second.
+    ///   A change of the ratio changes the fill level at a speed that is proportional to this rate,
+    ///   so it is needed for setting the gains.
+    /// - `target_fill_frames`: the wanted number of frames in the buffer.
+    /// - `loop_bandwidth_hz`: the bandwidth of the control loop.
+    ///   Values between 0.01 and 1 Hz are typical.
+    pub fn new(sample_rate: f64, target_fill_frames: usize, loop_bandwidth_hz: f64) -> Self {
+        debug!(
+            "Create new RateAdjuster, sample_rate: {}, target fill: {}, bandwidth: {} Hz",
+            sample_rate, target_fill_frames, loop_bandwidth_hz
+        );
+        let omega = 2.0 * PI * loop_bandwidth_hz;
+        RateAdjuster {
+            target_fill: target_fill_frames as f64,
+            proportional_gain: 2.0 * omega / sample_rate,
+            integral_gain: omega * omega / sample_rate,
+            integral: 0.0,
+            max_deviation: DEFAULT_MAX_DEVIATION,
+        }
+    }
+
+    /// Set the largest deviation of the returned ratio from 1.0.
+    /// The default is 0.001, meaning that the ratio stays between 0.999 and 1.001.
+    /// This must be larger than the drift between the clocks, and within the range
+    /// allowed by [max_resample_ratio_relative](crate::Resampler::max_resample_ratio_relative).
+    pub fn set_max_deviation(&mut self, max_deviation: f64) {
+        self.max_deviation = max_deviation.abs();
+        self.integral = self
+            .integral
+            .max(-self.max_deviation)
+            .min(self.max_deviation);
+    }
+
+    /// Get the largest deviation of the returned ratio from 1.0.
+    pub fn max_deviation(&self) -> f64 {
+        self.max_deviation
+    }
+
+    /// Update the controller with the current fill level of the buffer,
+    /// and the time that passed since the previous update.
+    /// Returns the relative resample ratio to apply.
+    pub fn update(&mut self, current_fill: usize, elapsed: Duration) -> f64 {
+        let error = current_fill as f64 - self.target_fill;
+        let integral = self.integral + self.integral_gain * error * elapsed.as_secs_f64();
+        let deviation = self.proportional_gain * error + integral;
+        if deviation.abs() <= self.max_deviation {
+            self.integral = integral;
+        } else if integral.abs() < self.integral.abs() {
+            // The ratio is limited, only let the integral move away from the limit.
+            self.integral = integral;
+        }
+        trace!(
+            "RateAdjuster fill: {}, error: {}, deviation: {}",
+            current_fill,
+            error,
+            deviation
+        );
+        1.0 - deviation.max(-self.max_deviation).min(self.max_deviation)
+    }
+
+    /// Get the relative ratio from the last update, without the proportional part.
+    /// This is the controller's estimate of the drift between the clocks.
+    pub fn drift_ratio(&self) -> f64 {
+        1.0 - self.integral
+    }
+
+    /// Forget the accumulated drift estimate, for example after the devices were restarted.
+    pub fn reset(&mut self) {
+        self.integral = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::RateAdjuster;
+    use std::time::Duration;
+
+    const SAMPLE_RATE: f64 = 48000.0;
+    const CHUNK: usize = 480;
+
+    // Simulate a capture device that runs `drift` faster than the playback device,
+    // with a FIFO between the resampler and the playback.
+    // Returns the fill level after each update, and the final ratio.
+    fn simulate(adjuster: &mut RateAdjuster, start_fill: f64, drift: f64) -> (Vec<f64>, f64) {
+        let elapsed = Duration::from_secs_f64(CHUNK as f64 / SAMPLE_RATE);
+        let mut fill = start_fill;
+        let mut ratio = 1.0;
+        let mut levels = Vec::new();
+        // Two minutes of 10 ms chunks.
+        for _ in 0..12000 {
+            fill += CHUNK as f64 * (1.0 + drift) * ratio;
+            fill -= CHUNK as f64;
+            levels.push(fill);
+            // The fill level is read in whole frames.
+            ratio = adjuster.update(fill.round() as usize, elapsed);
+        }
+        (levels, ratio)
+    }
+
+    // Count how many times the fill level crosses over to the other side of the target,
+    // ignoring differences smaller than `margin`.
+    fn crossings(levels: &[f64], target: f64, margin: f64) -> usize {
+        let mut side = 0.0;
+        let mut count = 0;
+        for level in levels.iter() {
+            let error = level - target;
+            if error.abs() > margin {
+                if side * error < 0.0 {
+                    count += 1;
+                }
+                side = error.signum();
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn compensate_drift() {
+        for drift in [80.0e-6, -50.0e-6].iter() {
+            let mut adjuster = RateAdjuster::new(SAMPLE_RATE, 2048, 0.1);
+            let (levels, ratio) = simulate(&mut adjuster, 2048.0, *drift);
+            let expected = 1.0 / (1.0 + drift);
+            assert!((ratio - expected).abs() < 1.0e-6, "ratio {}", ratio);
+            assert!((adjuster.drift_ratio() - expected).abs() < 1.0e-6);
+            let last = levels.last().unwrap();
+            assert!((last - 2048.0).abs() < 2.0, "fill {}", last);
+            // The drift moves the fill level away from the target in one direction,
+            // and it returns without swinging over to the other side.
+            assert_eq!(crossings(&levels, 2048.0, 2.0), 0);
+        }
+    }
+
+    #[test]
+    fn converge_from_offset() {
+        let mut adjuster = RateAdjuster::new(SAMPLE_RATE, 2048, 0.2);
+        let (levels, ratio) = simulate(&mut adjuster, 3048.0, 0.0);
+        assert!((ratio - 1.0).abs() < 1.0e-6, "ratio {}", ratio);
+        // A critically damped loop overshoots by at most about 14%
+        // for an offset in the fill level, and then settles without oscillating.
+        let lowest = levels
+            .iter()
+            .fold(std::f64::MAX, |acc, level| acc.min(*level));
+        assert!(lowest > 2048.0 - 150.0, "lowest fill {}", lowest);
+        assert!(crossings(&levels, 2048.0, 2.0) <= 1);
+        assert!((levels.last().unwrap() - 2048.0).abs() < 2.0);
+    }
+
+    #[test]
+    fn limit_and_anti_windup() {
+        let mut adjuster = RateAdjuster::new(SAMPLE_RATE, 2048, 0.2);
+        adjuster.set_max_deviation(100.0e-6);
+        let elapsed = Duration::from_millis(10);
+        // A buffer far above the target saturates the ratio at the limit.
+        for _ in 0..1000 {
+            let ratio = adjuster.update(20000, elapsed);
+            assert!((ratio - (1.0 - 100.0e-6)).abs() < 1.0e-12);
+        }
+        // The integral didn't accumulate while the ratio was limited,
+        // so the ratio returns to 1.0 as soon as the fill level is back at the target.
+        let ratio = adjuster.update(2048, elapsed);
+        assert!((ratio - 1.0).abs() < 1.0e-12);
+
+        adjuster.set_max_deviation(0.01);
+        for _ in 0..100 {
+            adjuster.update(2148, elapsed);
+        }
+        assert!(adjuster.drift_ratio() < 1.0);
+        adjuster.reset();
+        assert_eq!(adjuster.drift_ratio(), 1.0);
+        assert_eq!(adjuster.update(2048, elapsed), 1.0);
+    }
+}
@@ -1,4 +1,12 @@
-use crate::error::{ResampleError, ResampleResult};
+use crate::buffers::validate_sequential_to_interleaved;
+use crate::buffers::{fill_inactive, validate_finite, validate_partial, validate_sequential};
+use crate::buffers::{resize_channels, resize_mask, InputBuffer, OutputBuffer};
+use crate::buffers::{store_mask, update_mask, update_mask_from_buffers, validate_interleaved};
+use crate::buffers::{DelayTrim, TrimmedOutput};
+use crate::buffers::{InterleavedInput, InterleavedOutput, SequentialInput, SequentialOutput};
+use crate::control::{RatioControl, RatioReceiver};
+use crate::denormals::DenormalGuard;
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
 use crate::interpolation::*;
 #[cfg(all(target_arch = "x86_64", feature = "avx"))]
 use crate::interpolator_avx::AvxInterpolator;
@@ -6,13 +14,65 @@ use crate::interpolator_avx::AvxInterpolator;
 use crate::interpolator_neon::NeonInterpolator;
 #[cfg(target_arch = "x86_64")]
 use crate::interpolator_sse::SseInterpolator;
-use crate::sinc::make_sincs;
+#[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+use crate::interpolator_wasm::WasmInterpolator;
+use crate::response::{frequency_response, FilterResponse};
+use crate::sinc::{drop_mirrored_sincs, make_sincs_from_taps, make_sincs_with_phase};
+use crate::sinc::{mirrored_subindex, sinc_branch_sums, windowed_sinc_at};
+use crate::sinc::{Accumulation, FilterPhase, SincStorage};
 use crate::windows::WindowFunction;
+use crate::{process_final, InactiveOutput, Resampler, Sample};
 use crate::{InterpolationParameters, InterpolationType};
-use crate::{Resampler, Sample};
+use num_integer as integer;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
 
 /// Functions for making the scalar product with a sinc
-pub trait SincInterpolator<T>: Send {
+///
+/// The asynchronous resamplers use this to calculate the intermediate points.
+/// The built-in `ScalarInterpolator`, `OnTheFlyInterpolator` and the SIMD interpolators
+/// implement it, and the resamplers pick the fastest one that the cpu supports.
+/// A specific one, or a custom implementation, can be used by passing it to
+/// [SincFixedIn::new_with_interpolator] or [SincFixedOut::new_with_interpolator].
+///
+/// An interpolator provides `nbr_sincs()` sincs of length `len()`. The sinc at `subindex`
+/// gives the point `(subindex + 1) / nbr_sincs()` frames after the sample at
+/// `index + center() - 1`, so the last one gives the sample at `index + center()`.
+/// The length must be a multiple of 8.
+///
+/// ```
+/// use rubato::{InterpolationType, Resampler, SincFixedIn, SincInterpolator};
+///
+/// // A crude interpolator that just picks a sample, with two points per frame.
+/// struct Nearest;
+///
+/// impl SincInterpolator<f64> for Nearest {
+///     fn get_sinc_interpolated(&self, wave: &[f64], index: usize, subindex: usize) -> f64 {
+///         // The center is at 4, use the sample before it for the point half way.
+///         wave[index + 3 + subindex]
+///     }
+///
+///     fn len(&self) -> usize {
+///         8
+///     }
+///
+///     fn nbr_sincs(&self) -> usize {
+///         2
+///     }
+/// }
+///
+/// let mut resampler = SincFixedIn::<f64>::new_with_interpolator(
+///     2.0,
+///     InterpolationType::Nearest,
+///     Box::new(Nearest),
+///     64,
+///     1,
+/// );
+/// let output = resampler.process(&[vec![1.0; 64]]).unwrap();
+/// assert_eq!(output.len(), 1);
+/// ```
+pub trait SincInterpolator<T>: Send + Sync {
     /// Make the scalar product between the waveform starting at `index` and the sinc of `subindex`.
     fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T;
 
@@ -26,6 +86,20 @@ pub trait SincInterpolator<T>: Send {
 
     /// Get number of sincs used for oversampling
     fn nbr_sincs(&self) -> usize;
+
+    /// Get the position of the center of the sincs, the sample that lines up with
+    /// the interpolated point. This is the middle for linear phase sincs,
+    /// and close to the end for minimum phase ones.
+    fn center(&self) -> usize {
+        self.len() / 2
+    }
+
+    /// Get the name of the instruction set used for the scalar products,
+    /// for example "avx+fma" for the AVX interpolator on a cpu with fused multiply-add.
+    /// The default is "scalar", for interpolators that don't use SIMD instructions.
+    fn kernel(&self) -> &'static str {
+        "scalar"
+    }
 }
 
 /// A plain scalar interpolator
@@ -33,6 +107,20 @@ pub struct ScalarInterpolator<T> {
     sincs: Vec<Vec<T>>,
     length: usize,
     nbr_sincs: usize,
+    center: usize,
+    mirrored: bool,
+    wide: bool,
+}
+
+impl<T> fmt::Debug for ScalarInterpolator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ScalarInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .field("mirrored", &self.mirrored)
+            .field("wide", &self.wide)
+            .finish()
+    }
 }
 
 impl<T> SincInterpolator<T> for ScalarInterpolator<T>
@@ -53,8 +141,19 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        let wave_cut = &wave[index..(index + self.sincs[subindex].len())];
+        let wave_cut = &wave[index..(index + self.length)];
+        if self.mirrored {
+            if let Some(stored) = mirrored_subindex(subindex, self.nbr_sincs) {
+                if self.wide {
+                    return get_sinc_interpolated_wide(wave_cut, &self.sincs[stored], true);
+                }
+                return get_sinc_interpolated_reversed(wave_cut, &self.sincs[stored]);
+            }
+        }
         let sinc = &self.sincs[subindex];
+        if self.wide {
+            return get_sinc_interpolated_wide(wave_cut, sinc, false);
+        }
         unsafe {
             let mut acc0 = T::zero();
             let mut acc1 = T::zero();
@@ -87,6 +186,59 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn center(&self) -> usize {
+        self.center
+    }
+}
+
+/// Calculate the scalar product of a wave and a sinc in reverse order,
+/// with the products summed in the same order as for a sinc that is stored reversed.
+fn get_sinc_interpolated_reversed<T>(wave_cut: &[T], sinc: &[T]) -> T
+where
+    T: Sample,
+{
+    let last = sinc.len() - 1;
+    unsafe {
+        let mut acc0 = T::zero();
+        let mut acc1 = T::zero();
+        let mut acc2 = T::zero();
+        let mut acc3 = T::zero();
+        let mut acc4 = T::zero();
+        let mut acc5 = T::zero();
+        let mut acc6 = T::zero();
+        let mut acc7 = T::zero();
+        let mut idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            acc0 += *wave_cut.get_unchecked(idx) * *sinc.get_unchecked(last - idx);
+            acc1 += *wave_cut.get_unchecked(idx + 1) * *sinc.get_unchecked(last - idx - 1);
+            acc2 += *wave_cut.get_unchecked(idx + 2) * *sinc.get_unchecked(last - idx - 2);
+            acc3 += *wave_cut.get_unchecked(idx + 3) * *sinc.get_unchecked(last - idx - 3);
+            acc4 += *wave_cut.get_unchecked(idx + 4) * *sinc.get_unchecked(last - idx - 4);
+            acc5 += *wave_cut.get_unchecked(idx + 5) * *sinc.get_unchecked(last - idx - 5);
+            acc6 += *wave_cut.get_unchecked(idx + 6) * *sinc.get_unchecked(last - idx - 6);
+            acc7 += *wave_cut.get_unchecked(idx + 7) * *sinc.get_unchecked(last - idx - 7);
+            idx += 8;
+        }
+        acc0 + acc1 + acc2 + acc3 + acc4 + acc5 + acc6 + acc7
+    }
+}
+
+/// Calculate the scalar product of a wave and a sinc with the products summed in f64,
+/// with the sinc in reverse order if `reversed` is set.
+/// The products are summed in the same order as by the other scalar functions,
+/// so that the result for f64 samples is the same.
+fn get_sinc_interpolated_wide<T>(wave_cut: &[T], sinc: &[T], reversed: bool) -> T
+where
+    T: Sample,
+{
+    let last = sinc.len() - 1;
+    let mut acc = [0.0; 8];
+    for (idx, value) in wave_cut.iter().enumerate() {
+        let sinc_idx = if reversed { last - idx } else { idx };
+        acc[idx % 8] += value.to_f64() * sinc[sinc_idx].to_f64();
+    }
+    T::coerce(acc.iter().sum::<f64>())
 }
 
 impl<T> ScalarInterpolator<T>
@@ -105,15 +257,249 @@ where
         oversampling_factor: usize,
         f_cutoff: f32,
         window: WindowFunction,
+    ) -> Self {
+        Self::new_with_phase(
+            sinc_len,
+            oversampling_factor,
+            f_cutoff,
+            window,
+            FilterPhase::Linear,
+        )
+    }
+
+    /// Create a new ScalarInterpolator with the given phase response of the sincs.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `phase`: Phase response of the sincs, see `FilterPhase`.
+    pub fn new_with_phase(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+        phase: FilterPhase,
     ) -> Self {
         assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
-        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        let (sincs, center) =
+            make_sincs_with_phase(sinc_len, oversampling_factor, f_cutoff, window, phase);
+        Self::from_sincs(sincs, center, phase == FilterPhase::Linear)
+    }
+
+    /// Create a new ScalarInterpolator from an already calculated set of sincs,
+    /// where the point at `center` in each sinc lines up with the interpolated sample.
+    /// If `mirrored` is set, the table must be symmetric,
+    /// and only the half that isn't mirror images of the other half is kept.
+    pub(crate) fn from_sincs(mut sincs: Vec<Vec<T>>, center: usize, mirrored: bool) -> Self {
+        let length = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(length % 8 == 0, "Sinc length must be a multiple of 8");
+        if mirrored {
+            drop_mirrored_sincs(&mut sincs);
+        }
         Self {
             sincs,
+            length,
+            nbr_sincs,
+            center,
+            mirrored,
+            wide: false,
+        }
+    }
+
+    /// Choose how the products are summed, see `Accumulation`.
+    pub(crate) fn with_accumulation(mut self, accumulation: Accumulation) -> Self {
+        self.wide = accumulation == Accumulation::Double;
+        self
+    }
+}
+
+/// An interpolator that calculates the sincs when they are needed, see `SincStorage::OnTheFly`.
+/// The values are the same as in the table used by `ScalarInterpolator`,
+/// but each one is calculated from the sinc and window functions every time it is used.
+pub struct OnTheFlyInterpolator<T> {
+    length: usize,
+    nbr_sincs: usize,
+    f_cutoff: f32,
+    window: WindowFunction,
+    branch_sums: Vec<f64>,
+    wide: bool,
+    sample_type: PhantomData<T>,
+}
+
+impl<T> fmt::Debug for OnTheFlyInterpolator<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OnTheFlyInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .field("f_cutoff", &self.f_cutoff)
+            .field("window", &self.window)
+            .field("wide", &self.wide)
+            .finish()
+    }
+}
+
+impl<T> SincInterpolator<T> for OnTheFlyInterpolator<T>
+where
+    T: Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        let wave_cut = &wave[index..(index + self.length)];
+        let totpoints = self.length * self.nbr_sincs;
+        let offset = self.nbr_sincs - 1 - subindex;
+        let sinc_value = |p: usize| {
+            let x = self.nbr_sincs * p + offset;
+            let y =
+                windowed_sinc_at::<f64>(x, totpoints, self.nbr_sincs, self.f_cutoff, self.window);
+            y / self.branch_sums[offset]
+        };
+        if self.wide {
+            let sum = wave_cut
+                .iter()
+                .enumerate()
+                .fold(0.0, |acc, (p, value)| acc + value.to_f64() * sinc_value(p));
+            return T::coerce(sum);
+        }
+        wave_cut
+            .iter()
+            .enumerate()
+            .fold(T::zero(), |acc, (p, value)| {
+                acc + *value * T::coerce(sinc_value(p))
+            })
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+}
+
+impl<T> OnTheFlyInterpolator<T>
+where
+    T: Sample,
+{
+    /// Create a new OnTheFlyInterpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+    ) -> Self {
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8");
+        let branch_sums = sinc_branch_sums(sinc_len, oversampling_factor, f_cutoff, window);
+        Self {
             length: sinc_len,
             nbr_sincs: oversampling_factor,
+            f_cutoff,
+            window,
+            branch_sums,
+            wide: false,
+            sample_type: PhantomData,
         }
     }
+
+    /// Choose how the products are summed, see `Accumulation`.
+    pub(crate) fn with_accumulation(mut self, accumulation: Accumulation) -> Self {
+        self.wide = accumulation == Accumulation::Double;
+        self
+    }
+}
+
+/// A snapshot of the processing state of a sinc resampler,
+/// created by `save_state` and applied by `restore_state`.
+///
+/// The state holds the filter history, the position between input samples,
+/// and the resample ratio including any ongoing ramp.
+/// It can only be restored into a resampler of the same type,
+/// with the same number of channels, sinc length and chunk size.
+#[derive(Clone)]
+pub struct ResamplerState<T> {
+    resampler: &'static str,
+    nbr_channels: usize,
+    sinc_len: usize,
+    chunk_size: usize,
+    buffer: Vec<Vec<T>>,
+    last_index: f64,
+    current_buffer_fill: usize,
+    needed_input_size: usize,
+    ramp: RatioRamp,
+    channel_mask: Vec<bool>,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
+    trim: DelayTrim,
+}
+
+impl<T> ResamplerState<T> {
+    /// Check that the state was saved from a resampler with the given configuration.
+    fn check_compatible(
+        &self,
+        resampler: &'static str,
+        nbr_channels: usize,
+        sinc_len: usize,
+        chunk_size: usize,
+    ) -> ResampleResult<()> {
+        let parameter = if self.resampler != resampler {
+            "resampler type"
+        } else if self.nbr_channels != nbr_channels {
+            "number of channels"
+        } else if self.sinc_len != sinc_len {
+            "sinc length"
+        } else if self.chunk_size != chunk_size {
+            "chunk size"
+        } else {
+            return Ok(());
+        };
+        Err(ResampleError::IncompatibleState { parameter })
+    }
+}
+
+/// The processing state of a single channel stream, that is swapped in and out of a
+/// single channel [SincFixedIn] by a [MultiResampler](crate::MultiResampler).
+/// It holds only the filter history and the position and ratio of the stream,
+/// while the table of sincs and the buffer for the input are shared by all streams.
+#[derive(Clone)]
+pub(crate) struct StreamState<T> {
+    history: Vec<T>,
+    last_index: f64,
+    ramp: RatioRamp,
+    input_frames_total: u64,
+}
+
+impl<T> StreamState<T> {
+    /// Get the current resample ratio of the stream.
+    pub fn resample_ratio(&self) -> f64 {
+        self.ramp.target
+    }
+
+    /// Get the number of input frames the stream has processed.
+    pub fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
+
+    /// Change the duration of the ramp used when the ratio of the stream is changed.
+    pub fn set_ramp_duration(&mut self, frames: usize) {
+        self.ramp.set_duration(frames);
+    }
 }
 
 /// An asynchronous resampler that accepts a fixed number of audio frames for input
@@ -121,15 +507,37 @@ where
 ///
 /// The resampling is done by creating a number of intermediate points (defined by oversampling_factor)
 /// by sinc interpolation. The new samples are then calculated by interpolating between these points.
+///
+/// A clone starts from the current processing state of the original,
+/// and shares the table of sincs with it instead of computing a new one.
+#[derive(Clone)]
 pub struct SincFixedIn<T> {
     nbr_channels: usize,
     chunk_size: usize,
     last_index: f64,
-    resample_ratio: f64,
+    current_buffer_fill: usize,
     resample_ratio_original: f64,
-    interpolator: Box<dyn SincInterpolator<T>>,
+    max_resample_ratio_relative: f64,
+    working_ratio_range: Option<(f64, f64)>,
+    ramp: RatioRamp,
+    exact: Option<ExactRatio>,
+    interpolator: Arc<dyn SincInterpolator<T>>,
+    interpolator_low: Option<Arc<dyn SincInterpolator<T>>>,
+    interpolator_spare: Option<(f32, Arc<dyn SincInterpolator<T>>)>,
+    interpolator_previous: Option<Arc<dyn SincInterpolator<T>>>,
+    adaptive_cutoff: bool,
     buffer: Vec<Vec<T>>,
     interpolation: InterpolationType,
+    parameters: Option<InterpolationParameters>,
+    channel_mask: Vec<bool>,
+    active_channels_mask: Vec<bool>,
+    inactive_output: InactiveOutput,
+    validate_input: bool,
+    flush_denormals: bool,
+    trim: DelayTrim,
+    control: RatioReceiver,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
 }
 
 /// An asynchronous resampler that return a fixed number of audio frames.
@@ -137,29 +545,59 @@ pub struct SincFixedIn<T> {
 ///
 /// The resampling is done by creating a number of intermediate points (defined by oversampling_factor)
 /// by sinc interpolation. The new samples are then calculated by interpolating between these points.
+///
+/// A clone starts from the current processing state of the original,
+/// and shares the table of sincs with it instead of computing a new one.
+#[derive(Clone)]
 pub struct SincFixedOut<T> {
     nbr_channels: usize,
     chunk_size: usize,
     needed_input_size: usize,
     last_index: f64,
     current_buffer_fill: usize,
-    resample_ratio: f64,
     resample_ratio_original: f64,
-    interpolator: Box<dyn SincInterpolator<T>>,
+    max_resample_ratio_relative: f64,
+    working_ratio_range: Option<(f64, f64)>,
+    ramp: RatioRamp,
+    exact: Option<ExactRatio>,
+    interpolator: Arc<dyn SincInterpolator<T>>,
+    interpolator_low: Option<Arc<dyn SincInterpolator<T>>>,
+    interpolator_spare: Option<(f32, Arc<dyn SincInterpolator<T>>)>,
+    interpolator_previous: Option<Arc<dyn SincInterpolator<T>>>,
+    adaptive_cutoff: bool,
     buffer: Vec<Vec<T>>,
     interpolation: InterpolationType,
+    parameters: Option<InterpolationParameters>,
+    channel_mask: Vec<bool>,
+    active_channels_mask: Vec<bool>,
+    inactive_output: InactiveOutput,
+    validate_input: bool,
+    flush_denormals: bool,
+    trim: DelayTrim,
+    control: RatioReceiver,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
 }
 
+/// Create the interpolator described by `parameters`, for the given resample ratio
+/// and relative cutoff.
 pub fn make_interpolator<T>(
-    sinc_len: usize,
+    parameters: &InterpolationParameters,
     resample_ratio: f64,
     f_cutoff: f32,
-    oversampling_factor: usize,
-    window: WindowFunction,
 ) -> Box<dyn SincInterpolator<T>>
 where
     T: Sample,
 {
+    let InterpolationParameters {
+        sinc_len,
+        oversampling_factor,
+        window,
+        phase,
+        storage,
+        accumulation,
+        ..
+    } = *parameters;
     let sinc_len = 8 * (((sinc_len as f32) / 8.0).ceil() as usize);
     let f_cutoff = if resample_ratio >= 1.0 {
         f_cutoff
@@ -167,33 +605,225 @@ where
         f_cutoff * resample_ratio as f32
     };
 
+    if storage == SincStorage::OnTheFly {
+        if phase == FilterPhase::Linear {
+            return Box::new(
+                OnTheFlyInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
+                    .with_accumulation(accumulation),
+            );
+        }
+        warn!("Minimum phase sincs can't be calculated on the fly, storing them in a table");
+    }
+
     #[cfg(all(target_arch = "x86_64", feature = "avx"))]
     if let Ok(interpolator) =
-        AvxInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
+        AvxInterpolator::<T>::new_with_phase(sinc_len, oversampling_factor, f_cutoff, window, phase)
     {
-        return Box::new(interpolator);
+        return Box::new(interpolator.with_accumulation(accumulation));
     }
 
     #[cfg(target_arch = "x86_64")]
     if let Ok(interpolator) =
-        SseInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
+        SseInterpolator::<T>::new_with_phase(sinc_len, oversampling_factor, f_cutoff, window, phase)
     {
-        return Box::new(interpolator);
+        return Box::new(interpolator.with_accumulation(accumulation));
     }
 
     #[cfg(all(target_arch = "aarch64", feature = "neon"))]
-    if let Ok(interpolator) =
-        NeonInterpolator::<T>::new(sinc_len, oversampling_factor, f_cutoff, window)
-    {
-        return Box::new(interpolator);
+    if let Ok(interpolator) = NeonInterpolator::<T>::new_with_phase(
+        sinc_len,
+        oversampling_factor,
+        f_cutoff,
+        window,
+        phase,
+    ) {
+        return Box::new(interpolator.with_accumulation(accumulation));
     }
 
-    Box::new(ScalarInterpolator::<T>::new(
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    if let Ok(interpolator) = WasmInterpolator::<T>::new_with_phase(
         sinc_len,
         oversampling_factor,
         f_cutoff,
         window,
-    ))
+        phase,
+    ) {
+        return Box::new(interpolator.with_accumulation(accumulation));
+    }
+
+    Box::new(
+        ScalarInterpolator::<T>::new_with_phase(
+            sinc_len,
+            oversampling_factor,
+            f_cutoff,
+            window,
+            phase,
+        )
+        .with_accumulation(accumulation),
+    )
+}
+
+/// Create an interpolator from a user supplied filter, see `make_sincs_from_taps`.
+fn make_interpolator_from_taps<T>(
+    taps: &[f64],
+    oversampling_factor: usize,
+) -> Result<Box<dyn SincInterpolator<T>>, ResamplerConstructionError>
+where
+    T: Sample,
+{
+    let (sincs, center) = make_sincs_from_taps::<T>(taps, oversampling_factor)?;
+
+    #[cfg(all(target_arch = "x86_64", feature = "avx"))]
+    if let Ok(interpolator) = AvxInterpolator::<T>::from_sincs(sincs.clone(), center, false) {
+        return Ok(Box::new(interpolator));
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    if let Ok(interpolator) = SseInterpolator::<T>::from_sincs(sincs.clone(), center, false) {
+        return Ok(Box::new(interpolator));
+    }
+
+    #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+    if let Ok(interpolator) = NeonInterpolator::<T>::from_sincs(sincs.clone(), center, false) {
+        return Ok(Box::new(interpolator));
+    }
+
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    if let Ok(interpolator) = WasmInterpolator::<T>::from_sincs(sincs.clone(), center, false) {
+        return Ok(Box::new(interpolator));
+    }
+
+    Ok(Box::new(ScalarInterpolator::<T>::from_sincs(
+        sincs, center, false,
+    )))
+}
+
+/// Create the table of sincs used for adaptive cutoff, with the cutoff scaled
+/// for the lowest allowed resample ratio.
+/// Returns `None` when the ratio can't go below 1.0, since the main table is then always valid.
+fn make_adaptive_interpolator<T>(
+    parameters: &InterpolationParameters,
+    resample_ratio_original: f64,
+    max_resample_ratio_relative: f64,
+) -> Option<Arc<dyn SincInterpolator<T>>>
+where
+    T: Sample,
+{
+    let lowest_ratio = resample_ratio_original / max_resample_ratio_relative;
+    if lowest_ratio >= 1.0 {
+        return None;
+    }
+    Some(Arc::from(make_interpolator(
+        parameters,
+        lowest_ratio,
+        parameters.f_cutoff,
+    )))
+}
+
+/// Pick the table of sincs to use for a chunk.
+/// The adaptive table is used when the ratio goes below the one the main table was made for,
+/// and below 1.0.
+fn select_interpolator<'a, T>(
+    interpolator: &'a Arc<dyn SincInterpolator<T>>,
+    interpolator_low: &'a Option<Arc<dyn SincInterpolator<T>>>,
+    resample_ratio_original: f64,
+    min_ratio: f64,
+) -> &'a Arc<dyn SincInterpolator<T>> {
+    match interpolator_low {
+        Some(low) if min_ratio < resample_ratio_original.min(1.0) => low,
+        _ => interpolator,
+    }
+}
+
+/// Replace the main table of sincs by one with a new relative cutoff.
+/// The replaced table is kept as a spare, and is used again instead of computing
+/// a new table when switching back to its cutoff.
+/// Returns false if the cutoff is unchanged.
+fn switch_cutoff<T>(
+    parameters: &mut Option<InterpolationParameters>,
+    resample_ratio_original: f64,
+    interpolator: &mut Arc<dyn SincInterpolator<T>>,
+    spare: &mut Option<(f32, Arc<dyn SincInterpolator<T>>)>,
+    relative_cutoff: f32,
+) -> ResampleResult<bool>
+where
+    T: Sample,
+{
+    if relative_cutoff.is_nan() || relative_cutoff <= 0.0 || relative_cutoff > 1.0 {
+        return Err(ResampleError::BadCutoffUpdate);
+    }
+    let parameters = parameters
+        .as_mut()
+        .ok_or(ResampleError::NoInterpolationParameters)?;
+    if relative_cutoff == parameters.f_cutoff {
+        return Ok(false);
+    }
+    trace!("Change cutoff to {}", relative_cutoff);
+    let new_interpolator = match spare.take() {
+        Some((cutoff, spare_interpolator)) if cutoff == relative_cutoff => spare_interpolator,
+        _ => Arc::from(make_interpolator(
+            parameters,
+            resample_ratio_original,
+            relative_cutoff,
+        )),
+    };
+    let old_interpolator = std::mem::replace(interpolator, new_interpolator);
+    *spare = Some((parameters.f_cutoff, old_interpolator));
+    parameters.f_cutoff = relative_cutoff;
+    Ok(true)
+}
+
+/// Get the table of sincs to crossfade from after a change of the filter,
+/// or `None` if the old and new tables have different delays and can't be crossfaded.
+fn crossfade_from<T>(
+    previous: Arc<dyn SincInterpolator<T>>,
+    current: &Arc<dyn SincInterpolator<T>>,
+) -> Option<Arc<dyn SincInterpolator<T>>> {
+    if previous.center() == current.center() && !Arc::ptr_eq(&previous, current) {
+        Some(previous)
+    } else {
+        None
+    }
+}
+
+/// Get the weight of the previous sincs during a crossfade, that goes from one to zero
+/// while the `remaining` part of the crossfade goes from `length` to zero.
+fn crossfade_weight<T>(
+    previous: &Option<Arc<dyn SincInterpolator<T>>>,
+    remaining: f64,
+    length: f64,
+) -> T
+where
+    T: Sample,
+{
+    if previous.is_some() && length > 0.0 {
+        T::coerce((remaining / length).max(0.0).min(1.0))
+    } else {
+        T::zero()
+    }
+}
+
+/// Calculate an intermediate point using the sincs of `interpolator`.
+/// During a crossfade, the point calculated with the `previous` sincs is mixed in,
+/// with the weight `fade`.
+fn sinc_point<T>(
+    interpolator: &Arc<dyn SincInterpolator<T>>,
+    previous: &Option<Arc<dyn SincInterpolator<T>>>,
+    fade: T,
+    wave: &[T],
+    index: usize,
+    subindex: usize,
+) -> T
+where
+    T: Sample,
+{
+    let point = interpolator.get_sinc_interpolated(wave, index, subindex);
+    match previous {
+        Some(previous) => {
+            point + fade * (previous.get_sinc_interpolated(wave, index, subindex) - point)
+        }
+        None => point,
+    }
 }
 
 /// Perform cubic polynomial interpolation to get value at x.
@@ -221,249 +851,464 @@ where
     (T::one() - x) * yvals[0] + x * yvals[1]
 }
 
-impl<T> SincFixedIn<T>
-where
-    T: Sample,
-{
-    /// Create a new SincFixedIn
-    ///
-    /// Parameters are:
-    /// - `resample_ratio`: Ratio between output and input sample rates.
-    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
-    /// - `chunk_size`: size of input data in frames
-    /// - `nbr_channels`: number of channels in input/output
-    pub fn new(
-        resample_ratio: f64,
-        parameters: InterpolationParameters,
-        chunk_size: usize,
-        nbr_channels: usize,
-    ) -> Self {
-        debug!(
-            "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
-            resample_ratio, chunk_size, nbr_channels, parameters
-        );
+/// Calculate the delay in output frames of a sinc resampler.
+/// Output frame `n` is interpolated at input position `(n + 1) / ratio - 1 + 1 / oversampling_factor`,
+/// which means that input frame `m` ends up at output frame `m * ratio` plus this delay.
+fn sinc_output_delay(resample_ratio: f64, oversampling_factor: usize) -> f64 {
+    resample_ratio * (1.0 - 1.0 / oversampling_factor as f64) - 1.0
+}
 
-        let interpolator = make_interpolator(
-            parameters.sinc_len,
-            resample_ratio,
-            parameters.f_cutoff,
-            parameters.oversampling_factor,
-            parameters.window,
-        );
+/// Check that a resample ratio is within `1 / max_relative` to `max_relative` times the original one.
+/// A small margin is allowed, so that ratios at the limits are not rejected due to rounding.
+fn ratio_within_bounds(resample_ratio: f64, original: f64, max_relative: f64) -> bool {
+    let relative = resample_ratio / original;
+    let margin = 1.0 + 1.0e-9;
+    relative * margin >= 1.0 / max_relative && relative <= max_relative * margin
+}
 
-        Self::new_with_interpolator(
-            resample_ratio,
-            parameters.interpolation,
-            interpolator,
-            chunk_size,
-            nbr_channels,
-        )
+/// Check that a resample ratio is within a range of absolute ratios,
+/// with the same margin as [ratio_within_bounds].
+fn ratio_within_range(resample_ratio: f64, range: (f64, f64)) -> bool {
+    let margin = 1.0 + 1.0e-9;
+    resample_ratio * margin >= range.0 && resample_ratio <= range.1 * margin
+}
+
+/// Get the relative resample ratio for playing at `percent` of the original speed.
+fn speed_percent_ratio(percent: f64, max_relative: f64) -> ResampleResult<f64> {
+    if ratio_within_bounds(percent, 100.0, max_relative) {
+        Ok(100.0 / percent)
+    } else {
+        Err(ResampleError::RatioOutOfBounds {
+            value: percent,
+            min: 100.0 / max_relative,
+            max: 100.0 * max_relative,
+        })
     }
+}
 
-    /// Create a new SincFixedIn using an existing Interpolator
-    ///
-    /// Parameters are:
-    /// - `resample_ratio`: Ratio between output and input sample rates.
-    /// - `interpolation_type`: Parameters for interpolation, see `InterpolationParameters`
-    /// - `interpolator`:  The interpolator to use
-    /// - `chunk_size`: size of output data in frames
-    /// - `nbr_channels`: number of channels in input/output
-    pub fn new_with_interpolator(
-        resample_ratio: f64,
-        interpolation_type: InterpolationType,
-        interpolator: Box<dyn SincInterpolator<T>>,
-        chunk_size: usize,
-        nbr_channels: usize,
-    ) -> Self {
-        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
+/// Get the relative resample ratio that raises the pitch by `semitones`.
+fn pitch_semitones_ratio(semitones: f64, max_relative: f64) -> ResampleResult<f64> {
+    let rel_ratio = 2.0f64.powf(-semitones / 12.0);
+    if ratio_within_bounds(rel_ratio, 1.0, max_relative) {
+        Ok(rel_ratio)
+    } else {
+        let max = 12.0 * max_relative.log2();
+        Err(ResampleError::RatioOutOfBounds {
+            value: semitones,
+            min: -max,
+            max,
+        })
+    }
+}
 
-        SincFixedIn {
-            nbr_channels,
-            chunk_size,
-            last_index: -((interpolator.len() / 2) as f64),
-            resample_ratio,
-            resample_ratio_original: resample_ratio,
-            interpolator,
-            buffer,
-            interpolation: interpolation_type,
+/// Get the phase of the output frames, from the position of the last output frame
+/// relative to the start of the next chunk.
+fn output_phase(last_index: f64, center: usize, resample_ratio: f64) -> f64 {
+    let position = (last_index + center as f64) * resample_ratio;
+    position - position.floor()
+}
+
+/// Move the position of the last output frame to give the wanted phase,
+/// by less than one output frame.
+fn index_with_phase(
+    last_index: f64,
+    center: usize,
+    resample_ratio: f64,
+    fraction: f64,
+) -> ResampleResult<f64> {
+    if !(0.0..1.0).contains(&fraction) {
+        return Err(ResampleError::BadPhaseUpdate);
+    }
+    let position = (last_index + center as f64) * resample_ratio;
+    Ok((position.floor() + fraction) / resample_ratio - center as f64)
+}
+
+/// Move the filter history of each channel to the start of its buffer,
+/// so that it is used as it is by the next call to "process".
+/// The history consists of the `2 * sinc_len` frames following the last `current_buffer_fill` frames.
+/// The buffers are then extended with zeros to at least `buffer_len` frames.
+fn move_history_to_start<T: Sample>(
+    buffer: &mut [Vec<T>],
+    current_buffer_fill: usize,
+    sinc_len: usize,
+    buffer_len: usize,
+) {
+    for wav in buffer.iter_mut() {
+        wav.copy_within(current_buffer_fill..current_buffer_fill + 2 * sinc_len, 0);
+        if wav.len() < buffer_len {
+            wav.resize(buffer_len, T::zero());
         }
     }
 }
 
-impl<T> Resampler<T> for SincFixedIn<T>
-where
-    T: Sample,
-{
-    /// Resample a chunk of audio. The input length is fixed, and the output varies in length.
-    /// If the waveform for a channel is empty, this channel will be ignored and produce a
-    /// corresponding empty output waveform.
-    /// # Errors
-    ///
-    /// The function returns an error if the length of the input data is not equal
-    /// to the number of channels and chunk size defined when creating the instance.
-    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
+/// Copy the last `2 * sinc_len` frames of each channel of `history` to the start of the buffers,
+/// where they are used as the filter history by the next call to "process".
+fn fill_history<T: Sample, V: AsRef<[T]>>(
+    buffer: &mut [Vec<T>],
+    history: &[V],
+    sinc_len: usize,
+) -> ResampleResult<()> {
+    if history.len() != buffer.len() {
+        return Err(ResampleError::WrongNumberOfChannels {
+            expected: buffer.len(),
+            actual: history.len(),
+        });
+    }
+    let frames = 2 * sinc_len;
+    for (chan, wav) in history.iter().enumerate() {
+        let wav = wav.as_ref();
+        if wav.len() < frames {
+            return Err(ResampleError::InsufficientInputBufferSize {
+                channel: chan,
+                expected: frames,
+                actual: wav.len(),
             });
         }
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            let wave = wave.as_ref();
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.chunk_size {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.chunk_size,
-                        actual: wave.len(),
-                    });
-                }
+    }
+    for (wav, hist) in buffer.iter_mut().zip(history.iter()) {
+        let hist = hist.as_ref();
+        wav[..frames].copy_from_slice(&hist[hist.len() - frames..]);
+    }
+    Ok(())
+}
+
+/// Get the position of the last output frame that places the next one
+/// at the first frame of the next chunk.
+fn index_after_history(center: usize, resample_ratio: f64) -> f64 {
+    1.0 - center as f64 - 1.0 / resample_ratio
+}
+
+/// Get the step in input frames for output frame `n` of a chunk.
+/// The ratio follows the ramp, or the curve of relative ratios if one is given.
+fn next_step(
+    ramp: &mut RatioRamp,
+    ratio_curve: Option<&[f64]>,
+    resample_ratio_original: f64,
+    n: usize,
+) -> f64 {
+    match ratio_curve {
+        Some(curve) => ramp.next_step_to(resample_ratio_original * curve[n]),
+        None => ramp.next_step(),
+    }
+}
+
+/// A resample ratio given exactly as a fraction `num / den`.
+/// While the ratio is in use, the read position is kept as a whole number of frames
+/// plus a fraction with denominator `num`, so that each step of `den / num` frames is exact
+/// and the position never drifts away from the ideal one.
+#[derive(Clone, Copy, Debug)]
+struct ExactRatio {
+    num: u64,
+    den: u64,
+    /// The whole and fractional parts of the last position, once synced to the stream.
+    position: Option<(i64, u64)>,
+}
+
+impl ExactRatio {
+    /// Reduce the fraction. Returns `None` if it is not a positive ratio,
+    /// or if the reduced terms are too large for the position to be stepped without overflow.
+    fn new(num: u64, den: u64) -> Option<Self> {
+        if num == 0 || den == 0 {
+            return None;
+        }
+        let gcd = integer::gcd(num, den);
+        let (num, den) = (num / gcd, den / gcd);
+        if num > std::u32::MAX as u64 || den > std::u32::MAX as u64 {
+            return None;
+        }
+        Some(ExactRatio {
+            num,
+            den,
+            position: None,
+        })
+    }
+
+    fn ratio(&self) -> f64 {
+        self.num as f64 / self.den as f64
+    }
+
+    fn index(&self, whole: i64, frac: u64) -> f64 {
+        whole as f64 + frac as f64 / self.num as f64
+    }
+
+    /// Get the position of the next output frame, after the one at `idx`.
+    /// The exact position is synced to `idx` if the stream position was changed from outside,
+    /// rounding it to the nearest multiple of `1 / num`.
+    fn advance(&mut self, idx: f64) -> f64 {
+        let (mut whole, mut frac) = match self.position {
+            Some((whole, frac)) if self.index(whole, frac) == idx => (whole, frac),
+            _ => {
+                let whole = idx.floor();
+                let frac = ((idx - whole) * self.num as f64).round() as u64;
+                (whole as i64, frac)
+            }
+        };
+        frac += self.den;
+        whole += (frac / self.num) as i64;
+        frac %= self.num;
+        self.position = Some((whole, frac));
+        self.index(whole, frac)
+    }
+}
+
+/// Get the position of the output frame after the one at `idx`, given the `step` from the ramp.
+/// An exact ratio is used instead of the step as long as the ramp holds it,
+/// and dropped once the ramp is headed for a different ratio.
+fn advance_index(idx: f64, step: f64, ramp: &RatioRamp, exact: &mut Option<ExactRatio>) -> f64 {
+    if let Some(ratio) = exact {
+        if ramp.settles_at(ratio.ratio()) {
+            if !ramp.is_changing() {
+                return ratio.advance(idx);
             }
+            ratio.position = None;
+            return idx + step;
         }
-        let sinc_len = self.interpolator.len();
-        let oversampling_factor = self.interpolator.nbr_sincs();
-        let t_ratio = 1.0 / self.resample_ratio as f64;
-        let end_idx = self.chunk_size as isize - (sinc_len as isize + 1) - t_ratio.ceil() as isize;
-        //update buffer with new data
-        for wav in self.buffer.iter_mut() {
-            for idx in 0..(2 * sinc_len) {
-                wav[idx] = wav[idx + self.chunk_size];
+    }
+    *exact = None;
+    idx + step
+}
+
+/// Move the position `idx` back by `frames` at the end of a chunk,
+/// keeping the exact position if there is one.
+fn shift_index(idx: f64, frames: usize, exact: &mut Option<ExactRatio>) -> f64 {
+    if let Some(ratio) = exact {
+        if let Some((whole, frac)) = ratio.position {
+            if ratio.index(whole, frac) == idx {
+                let whole = whole - frames as i64;
+                ratio.position = Some((whole, frac));
+                return ratio.index(whole, frac);
             }
         }
+    }
+    idx - frames as f64
+}
 
-        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+/// The shape of the curve followed by the resample ratio during a ramp.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RampType {
+    /// The ratio changes by the same amount for every frame.
+    Linear,
+    /// The ratio changes by the same factor for every frame.
+    /// This gives a pitch change that sounds even, like a tape machine slowing down.
+    Exponential,
+    /// The ratio starts and ends slowly, following a smoothstep curve.
+    SCurve,
+}
 
-        for chan in used_channels.iter() {
-            for (idx, sample) in wave_in[*chan].as_ref().iter().enumerate() {
-                self.buffer[*chan][idx + 2 * sinc_len] = *sample;
-            }
-            wave_out[*chan] =
-                vec![T::zero(); (self.chunk_size as f64 * self.resample_ratio + 10.0) as usize];
+/// A change of the resample ratio, scheduled to take effect at a given output frame.
+#[derive(Clone, Copy)]
+struct ScheduledChange {
+    ratio: f64,
+    frame: u64,
+    ramp: bool,
+}
+
+/// Keeps track of the resample ratio as it changes over time.
+/// The ratio moves from `start` to `target` over `duration` output frames.
+/// A scheduled change is applied when the output frame counter reaches the given frame.
+#[derive(Clone)]
+struct RatioRamp {
+    start: f64,
+    target: f64,
+    duration: usize,
+    position: usize,
+    ramp_type: RampType,
+    /// The factor between consecutive frames of an exponential ramp.
+    factor: f64,
+    /// The number of output frames produced so far.
+    frame: u64,
+    scheduled: Option<ScheduledChange>,
+}
+
+impl RatioRamp {
+    fn new(resample_ratio: f64) -> Self {
+        RatioRamp {
+            start: resample_ratio,
+            target: resample_ratio,
+            duration: 0,
+            position: 0,
+            ramp_type: RampType::Linear,
+            factor: 1.0,
+            frame: 0,
+            scheduled: None,
         }
+    }
 
-        let mut idx = self.last_index;
+    /// Go back to the initial state, keeping the ramp duration and type.
+    fn reset(&mut self, resample_ratio: f64) {
+        *self = RatioRamp {
+            duration: self.duration,
+            position: self.duration,
+            ramp_type: self.ramp_type,
+            ..RatioRamp::new(resample_ratio)
+        };
+    }
 
-        let mut n = 0;
+    /// Get the ratio after `position` output frames of the ramp.
+    fn ratio_at(&self, position: usize) -> f64 {
+        if position >= self.duration {
+            return self.target;
+        }
+        let x = position as f64 / self.duration as f64;
+        match self.ramp_type {
+            RampType::Linear => self.start + (self.target - self.start) * x,
+            RampType::Exponential => self.start * self.factor.powi(position as i32),
+            RampType::SCurve => self.start + (self.target - self.start) * x * x * (3.0 - 2.0 * x),
+        }
+    }
 
-        match self.interpolation {
-            InterpolationType::Cubic => {
-                let mut points = [T::zero(); 4];
-                let mut nearest = [(0isize, 0isize); 4];
-                while idx < end_idx as f64 {
-                    idx += t_ratio;
-                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
-                                buf,
-                                (n.0 + 2 * sinc_len as isize) as usize,
-                                n.1 as usize,
-                            );
-                        }
-                        wave_out[*chan][n] = interp_cubic(frac_offset, &points);
-                    }
-                    n += 1;
-                }
-            }
-            InterpolationType::Linear => {
-                let mut points = [T::zero(); 2];
-                let mut nearest = [(0isize, 0isize); 2];
-                while idx < end_idx as f64 {
-                    idx += t_ratio;
-                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
-                    let frac = idx * oversampling_factor as f64
-                        - (idx * oversampling_factor as f64).floor();
-                    let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
-                                buf,
-                                (n.0 + 2 * sinc_len as isize) as usize,
-                                n.1 as usize,
-                            );
-                        }
-                        wave_out[*chan][n] = interp_lin(frac_offset, &points);
-                    }
-                    n += 1;
-                }
-            }
-            InterpolationType::Nearest => {
-                let mut point;
-                let mut nearest;
-                while idx < end_idx as f64 {
-                    idx += t_ratio;
-                    nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        point = self.interpolator.get_sinc_interpolated(
-                            buf,
-                            (nearest.0 + 2 * sinc_len as isize) as usize,
-                            nearest.1 as usize,
-                        );
-                        wave_out[*chan][n] = point;
-                    }
-                    n += 1;
-                }
+    /// Get the ratio of the most recently produced output frame.
+    fn current(&self) -> f64 {
+        self.ratio_at(self.position)
+    }
+
+    /// Get the lowest ratio that can be used during the coming output frames.
+    fn min_ratio(&self) -> f64 {
+        let lowest = self.current().min(self.target);
+        match self.scheduled {
+            Some(change) => lowest.min(change.ratio),
+            None => lowest,
+        }
+    }
+
+    /// Get the highest ratio that can be used during the coming output frames.
+    fn max_ratio(&self) -> f64 {
+        let highest = self.current().max(self.target);
+        match self.scheduled {
+            Some(change) => highest.max(change.ratio),
+            None => highest,
+        }
+    }
+
+    /// Check that all ratios that can be used during the coming output frames
+    /// are allowed by the given maximum relative ratio.
+    fn within_bounds(&self, original: f64, max_relative: f64) -> bool {
+        max_relative >= 1.0
+            && ratio_within_bounds(self.min_ratio(), original, max_relative)
+            && ratio_within_bounds(self.max_ratio(), original, max_relative)
+    }
+
+    /// Check that all ratios that can be used during the coming output frames
+    /// are within the given range.
+    fn within_range(&self, range: (f64, f64)) -> bool {
+        ratio_within_range(self.min_ratio(), range) && ratio_within_range(self.max_ratio(), range)
+    }
+
+    /// Get the step in input frames for the next output frame, and move forward by one frame.
+    fn next_step(&mut self) -> f64 {
+        if let Some(change) = self.scheduled {
+            if change.frame <= self.frame {
+                self.apply(change);
             }
         }
+        self.frame += 1;
+        if self.position < self.duration {
+            self.position += 1;
+        }
+        1.0 / self.ratio_at(self.position)
+    }
 
-        // store last index for next iteration
-        self.last_index = idx - self.chunk_size as f64;
-        for chan in used_channels.iter() {
-            //for w in wave_out.iter_mut() {
-            wave_out[*chan].truncate(n);
+    /// Get the step in input frames for the next output frame, using a ratio given from outside
+    /// instead of the ramp, and move forward by one frame. Any ramp in progress is abandoned,
+    /// and the given ratio is kept for the following frames.
+    fn next_step_to(&mut self, ratio: f64) -> f64 {
+        self.frame += 1;
+        self.jump_to(ratio);
+        1.0 / ratio
+    }
+
+    /// Get the progress of the ramp, or `None` if there is no ramp in progress.
+    fn progress(&self) -> Option<f64> {
+        if self.position < self.duration {
+            Some(self.position as f64 / self.duration as f64)
+        } else {
+            None
         }
-        trace!(
-            "Resampling channels {:?}, {} frames in, {} frames out",
-            used_channels,
-            self.chunk_size,
-            n,
-        );
-        Ok(wave_out)
     }
 
-    /// Query for the number of frames needed for the next call to "process".
-    /// Will always return the chunk_size defined when creating the instance.
-    fn nbr_frames_needed(&self) -> usize {
-        self.chunk_size
+    /// Check if the ratio may change during the coming output frames.
+    fn is_changing(&self) -> bool {
+        self.position < self.duration || self.scheduled.is_some()
     }
 
-    /// Update the resample ratio. New value must be within +-10% of the original one
-    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
-        trace!("Change resample ratio to {}", new_ratio);
-        if (new_ratio / self.resample_ratio_original > 0.9)
-            && (new_ratio / self.resample_ratio_original < 1.1)
-        {
-            self.resample_ratio = new_ratio;
-            Ok(())
+    /// Check if the ratio ends up at `ratio` once the ramp and any scheduled change are done.
+    fn settles_at(&self, ratio: f64) -> bool {
+        match self.scheduled {
+            Some(change) => change.ratio == ratio,
+            None => self.target == ratio,
+        }
+    }
+
+    /// Start a new ramp from the current ratio towards `target`.
+    fn set_target(&mut self, target: f64) {
+        self.start = self.current();
+        self.target = target;
+        self.position = 0;
+        self.update_factor();
+    }
+
+    /// Switch to a new ratio immediately.
+    fn jump_to(&mut self, target: f64) {
+        self.start = target;
+        self.target = target;
+        self.position = self.duration;
+    }
+
+    /// Schedule a change of the ratio at output frame `frame`.
+    /// A change scheduled for a frame that has already been produced is applied immediately.
+    fn schedule(&mut self, change: ScheduledChange) {
+        if change.frame <= self.frame {
+            self.scheduled = None;
+            self.apply(change);
         } else {
-            Err(ResampleError::BadRatioUpdate)
+            self.scheduled = Some(change);
         }
     }
-    /// Update the resample ratio relative to the original one
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
-        let new_ratio = self.resample_ratio_original * rel_ratio;
-        self.set_resample_ratio(new_ratio)
+
+    fn apply(&mut self, change: ScheduledChange) {
+        self.scheduled = None;
+        if change.ramp {
+            self.set_target(change.ratio);
+        } else {
+            self.jump_to(change.ratio);
+        }
+    }
+
+    /// Change the ramp duration. A ramp in progress continues from the current ratio.
+    fn set_duration(&mut self, duration: usize) {
+        let ramping = self.progress().is_some();
+        self.start = self.current();
+        self.position = if ramping { 0 } else { duration };
+        self.duration = duration;
+        self.update_factor();
+    }
+
+    /// Change the curve shape. A ramp in progress continues from the current ratio.
+    fn set_ramp_type(&mut self, ramp_type: RampType) {
+        let ramping = self.progress().is_some();
+        self.start = self.current();
+        self.position = if ramping { 0 } else { self.duration };
+        self.ramp_type = ramp_type;
+        self.update_factor();
+    }
+
+    fn update_factor(&mut self) {
+        if self.duration > 0 {
+            self.factor = (self.target / self.start).powf(1.0 / self.duration as f64);
+        }
     }
 }
 
-impl<T> SincFixedOut<T>
+impl<T> SincFixedIn<T>
 where
     T: Sample,
 {
-    /// Create a new SincFixedOut
+    /// Create a new SincFixedIn
     ///
     /// Parameters are:
     /// - `resample_ratio`: Ratio between output and input sample rates.
     /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
-    /// - `chunk_size`: size of output data in frames
+    /// - `chunk_size`: size of input data in frames
     /// - `nbr_channels`: number of channels in input/output
     pub fn new(
         resample_ratio: f64,
@@ -475,31 +1320,81 @@ where
             "Create new SincFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
             resample_ratio, chunk_size, nbr_channels, parameters
         );
-        let interpolator = make_interpolator(
-            parameters.sinc_len,
+        let parameters = parameters.with_automatic_oversampling();
+
+        let interpolator = make_interpolator(&parameters, resample_ratio, parameters.f_cutoff);
+
+        let mut resampler = Self::new_with_interpolator(
             resample_ratio,
-            parameters.f_cutoff,
-            parameters.oversampling_factor,
-            parameters.window,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
         );
+        resampler.parameters = Some(parameters);
+        resampler
+    }
 
-        Self::new_with_interpolator(
+    /// Create a new SincFixedIn using a user supplied filter instead of a windowed sinc.
+    ///
+    /// The filter is given as its impulse response, oversampled by `oversampling_factor`,
+    /// so that `taps` holds `sinc_len * oversampling_factor` values,
+    /// where `sinc_len` must be a multiple of 8.
+    /// The filter is scaled to unity gain at low frequencies.
+    /// Its cutoff must be below the lowest of the input and output Nyquist frequencies,
+    /// since it is used as is for all resample ratios.
+    /// The resampler can't use adaptive cutoff, see `set_adaptive_cutoff`.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `taps`: The oversampled impulse response of the filter.
+    /// - `oversampling_factor`: Number of filter taps per frame.
+    /// - `interpolation_type`: Interpolation between the oversampled points, see `InterpolationType`
+    /// - `chunk_size`: size of input data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the oversampling factor is zero,
+    /// if the number of taps is not a multiple of 8 times the oversampling factor,
+    /// or if the filter has values that are not finite or zero gain at low frequencies.
+    pub fn new_with_filter(
+        resample_ratio: f64,
+        taps: &[f64],
+        oversampling_factor: usize,
+        interpolation_type: InterpolationType,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new SincFixedIn with filter, ratio: {}, chunk_size: {}, channels: {}, taps: {}",
             resample_ratio,
-            parameters.interpolation,
+            chunk_size,
+            nbr_channels,
+            taps.len()
+        );
+        let interpolator = make_interpolator_from_taps(taps, oversampling_factor)?;
+        Ok(Self::new_with_interpolator(
+            resample_ratio,
+            interpolation_type,
             interpolator,
             chunk_size,
             nbr_channels,
-        )
+        ))
     }
 
-    /// Create a new SincFixedOut using an existing Interpolator
+    /// Create a new SincFixedIn using an existing Interpolator
     ///
     /// Parameters are:
     /// - `resample_ratio`: Ratio between output and input sample rates.
     /// - `interpolation_type`: Parameters for interpolation, see `InterpolationParameters`
-    /// - `interpolator`:  The interpolator to use
+    /// - `interpolator`:  The interpolator to use, any implementation of `SincInterpolator`.
     /// - `chunk_size`: size of output data in frames
     /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// The resample ratio may be changed by up to 10% from the original value.
+    /// Use [set_max_resample_ratio_relative](Resampler::set_max_resample_ratio_relative)
+    /// to allow a larger range.
     pub fn new_with_interpolator(
         resample_ratio: f64,
         interpolation_type: InterpolationType,
@@ -507,630 +1402,5642 @@ where
         chunk_size: usize,
         nbr_channels: usize,
     ) -> Self {
-        let needed_input_size =
-            (chunk_size as f64 / resample_ratio).ceil() as usize + 2 + interpolator.len() / 2;
-        let buffer =
-            vec![vec![T::zero(); 3 * needed_input_size / 2 + 2 * interpolator.len()]; nbr_channels];
+        let buffer = vec![vec![T::zero(); chunk_size + 2 * interpolator.len()]; nbr_channels];
 
-        SincFixedOut {
+        SincFixedIn {
             nbr_channels,
             chunk_size,
-            needed_input_size,
-            last_index: -((interpolator.len() / 2) as f64),
-            current_buffer_fill: needed_input_size,
-            resample_ratio,
+            last_index: -(interpolator.center() as f64),
+            current_buffer_fill: chunk_size,
             resample_ratio_original: resample_ratio,
-            interpolator,
+            max_resample_ratio_relative: 1.1,
+            working_ratio_range: None,
+            ramp: RatioRamp::new(resample_ratio),
+            exact: None,
+            interpolator: Arc::from(interpolator),
+            interpolator_low: None,
+            interpolator_spare: None,
+            interpolator_previous: None,
+            adaptive_cutoff: false,
             buffer,
             interpolation: interpolation_type,
+            parameters: None,
+            channel_mask: vec![true; nbr_channels],
+            active_channels_mask: vec![true; nbr_channels],
+            inactive_output: InactiveOutput::Skip,
+            validate_input: false,
+            flush_denormals: false,
+            trim: DelayTrim::default(),
+            control: RatioReceiver::new(),
+            input_frames_total: 0,
+            output_frames_final: None,
         }
     }
-}
 
-impl<T> Resampler<T> for SincFixedOut<T>
-where
-    T: Sample,
-{
-    /// Query for the number of frames needed for the next call to "process".
-    fn nbr_frames_needed(&self) -> usize {
-        self.needed_input_size
+    /// Set the duration of the ramp used when the resample ratio is changed, in output frames.
+    /// The ratio then changes from the current to the new value over this many frames,
+    /// which may span several calls to "process".
+    /// A new ratio set during a ramp starts a new ramp from the current ratio.
+    /// The default duration of zero applies a new ratio immediately.
+    pub fn set_ramp_duration(&mut self, frames: usize) {
+        self.ramp.set_duration(frames);
     }
 
-    /// Resample a chunk of audio. The required input length is provided by
-    /// the "nbr_frames_needed" function, and the output length is fixed.
-    /// If the waveform for a channel is empty, this channel will be ignored and produce a
-    /// corresponding empty output waveform.
+    /// Set the shape of the curve followed by the resample ratio during a ramp.
+    /// The default is [RampType::Linear].
+    pub fn set_ramp_type(&mut self, ramp_type: RampType) {
+        self.ramp.set_ramp_type(ramp_type);
+    }
+
+    /// Enable or disable adaptive cutoff of the anti-aliasing filter.
+    ///
+    /// The table of sincs is computed for the resample ratio given when creating the resampler.
+    /// When the ratio is later lowered below 1.0, and below that original ratio,
+    /// the cutoff of that table is too high and the output gets aliasing.
+    /// With adaptive cutoff, a second table is computed with the cutoff scaled for the lowest ratio
+    /// allowed by [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// and it is used for each chunk where the ratio goes below the original one and below 1.0.
+    /// The filter is switched between chunks, and the second table is only
+    /// computed when the allowed range reaches below 1.0.
+    /// With [FilterPhase::Minimum] the two tables have slightly different delays,
+    /// so the output may jump by a few samples when switching between them.
+    ///
     /// # Errors
     ///
-    /// The function returns an error if the length of the input data is not
-    /// equal to the number of channels defined when creating the instance,
-    /// and the number of audio frames given by "nbr_frames_needed".
-    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
-        //update buffer with new data
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
+    /// The function returns an error if the resampler was created with `new_with_interpolator`,
+    /// since the parameters needed for computing the second table are then not known.
+    pub fn set_adaptive_cutoff(&mut self, enabled: bool) -> ResampleResult<()> {
+        self.interpolator_low = match (&self.parameters, enabled) {
+            (Some(parameters), true) => make_adaptive_interpolator(
+                parameters,
+                self.resample_ratio_original,
+                self.max_resample_ratio_relative,
+            ),
+            (None, true) => return Err(ResampleError::NoInterpolationParameters),
+            (_, false) => None,
+        };
+        self.adaptive_cutoff = enabled;
+        Ok(())
+    }
+
+    /// Change the relative cutoff frequency of the anti-aliasing filter,
+    /// see [InterpolationParameters::f_cutoff].
+    ///
+    /// The table of sincs is computed again for the new cutoff, so this call allocates,
+    /// and takes about as long as creating a new resampler.
+    /// The table for the previous cutoff is kept, and switching back to that cutoff
+    /// reuses it without allocating. With adaptive cutoff enabled, the second table
+    /// is always computed again.
+    ///
+    /// The next chunk is crossfaded from the old to the new filter, to avoid a click.
+    /// With [FilterPhase::Minimum] the delay of the filter may change with the cutoff,
+    /// and the filter is then switched without a crossfade.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the cutoff is not in the range (0, 1],
+    /// or if the resampler was created with `new_with_interpolator` or `new_with_filter`,
+    /// since the parameters needed for computing a new table are then not known.
+    pub fn set_cutoff(&mut self, relative_cutoff: f32) -> ResampleResult<()> {
+        let previous = select_interpolator(
+            &self.interpolator,
+            &self.interpolator_low,
+            self.resample_ratio_original,
+            self.ramp.min_ratio(),
+        )
+        .clone();
+        if !switch_cutoff(
+            &mut self.parameters,
+            self.resample_ratio_original,
+            &mut self.interpolator,
+            &mut self.interpolator_spare,
+            relative_cutoff,
+        )? {
+            return Ok(());
+        }
+        if self.adaptive_cutoff {
+            self.set_adaptive_cutoff(true)?;
+        }
+        self.interpolator_previous = crossfade_from(
+            self.interpolator_previous.take().unwrap_or(previous),
+            select_interpolator(
+                &self.interpolator,
+                &self.interpolator_low,
+                self.resample_ratio_original,
+                self.ramp.min_ratio(),
+            ),
+        );
+        Ok(())
+    }
+
+    /// Get the response of the table of sincs in use, for combining with other stages.
+    pub(crate) fn filter_response(&self) -> FilterResponse {
+        let interpolator = select_interpolator(
+            &self.interpolator,
+            &self.interpolator_low,
+            self.resample_ratio_original,
+            self.ramp.min_ratio(),
+        );
+        FilterResponse::from_interpolator(interpolator.as_ref())
+    }
+
+    /// Schedule a change of the resample ratio at a given output frame.
+    ///
+    /// Output frames are counted from the creation of the resampler, or from the last reset.
+    /// The new ratio takes effect exactly at that frame, also when it falls in the middle of a chunk.
+    /// If `ramp` is true, a ramp towards the new ratio starts at that frame,
+    /// see [set_ramp_duration](Self::set_ramp_duration). Otherwise the ratio switches immediately.
+    /// Only one change can be pending, and scheduling a new one replaces it.
+    /// A change scheduled for a frame that has already been produced is applied immediately.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new ratio is outside the range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// or outside the working range set by `set_working_ratio_range`.
+    pub fn set_resample_ratio_at(
+        &mut self,
+        new_ratio: f64,
+        at_output_frame: u64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        trace!(
+            "Schedule resample ratio {} at output frame {}",
+            new_ratio,
+            at_output_frame
+        );
+        if self.ratio_allowed(new_ratio) {
+            self.ramp.schedule(ScheduledChange {
+                ratio: new_ratio,
+                frame: at_output_frame + self.trim.frames() as u64,
+                ramp,
             });
+            Ok(())
+        } else {
+            Err(ResampleError::BadRatioUpdate)
+        }
+    }
+
+    /// Get a handle for changing the resample ratio from another thread, see [RatioControl].
+    /// All handles of a resampler control the same ratio,
+    /// and a clone of the resampler is not controlled by the handles of the original.
+    pub fn ratio_control(&self) -> RatioControl {
+        self.control.control()
+    }
+
+    /// Apply a ratio set with a [RatioControl] handle, clamped to the working range.
+    fn apply_ratio_control(&mut self) {
+        if let Some((new_ratio, ramp)) = self.control.take() {
+            let (min, max) = self.working_ratio_range();
+            let ratio = new_ratio.max(min).min(max);
+            if ratio != new_ratio {
+                self.control.set_clamped();
+            }
+            trace!("Change resample ratio to {} from control handle", ratio);
+            if ramp {
+                self.ramp.set_target(ratio);
+            } else {
+                self.ramp.jump_to(ratio);
+            }
+        }
+    }
+
+    /// Narrow the range of resample ratios that will be used, to the absolute ratios
+    /// from `min` to `max`.
+    ///
+    /// The range must be within the one allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// which stays unchanged, and must include the original ratio.
+    /// New ratios outside the working range are then rejected,
+    /// and [input_frames_max](Resampler::input_frames_max) and
+    /// [output_frames_max](Resampler::output_frames_max) are computed for the working range
+    /// instead of the full one. This makes the buffers from
+    /// [input_buffer_allocate](Resampler::input_buffer_allocate) and
+    /// [output_buffer_allocate](Resampler::output_buffer_allocate) smaller
+    /// when the ratio only varies by a small amount.
+    /// Setting the range to the full allowed range removes the restriction.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [ResampleError::BadWorkingRatioRange] if `min` is larger than `max`,
+    /// if the range is not within the allowed one, or if it excludes the original ratio
+    /// or a ratio in use.
+    pub fn set_working_ratio_range(&mut self, min: f64, max: f64) -> ResampleResult<()> {
+        trace!("Change working resample ratio range to {} - {}", min, max);
+        let original = self.resample_ratio_original;
+        let max_relative = self.max_resample_ratio_relative;
+        let range = (min, max);
+        if !(min <= max
+            && ratio_within_bounds(min, original, max_relative)
+            && ratio_within_bounds(max, original, max_relative)
+            && ratio_within_range(original, range)
+            && self.ramp.within_range(range))
+        {
+            return Err(ResampleError::BadWorkingRatioRange);
+        }
+        self.working_ratio_range = Some(range);
+        Ok(())
+    }
+
+    /// Get the range of resample ratios that may be used, as absolute ratios.
+    /// This is the range set by [set_working_ratio_range](SincFixedIn::set_working_ratio_range),
+    /// or the full range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    pub fn working_ratio_range(&self) -> (f64, f64) {
+        self.working_ratio_range.unwrap_or((
+            self.resample_ratio_original / self.max_resample_ratio_relative,
+            self.resample_ratio_original * self.max_resample_ratio_relative,
+        ))
+    }
+
+    /// Check that a new resample ratio is allowed, both by the maximum relative ratio
+    /// and by the working range.
+    fn ratio_allowed(&self, resample_ratio: f64) -> bool {
+        ratio_within_bounds(
+            resample_ratio,
+            self.resample_ratio_original,
+            self.max_resample_ratio_relative,
+        ) && self
+            .working_ratio_range
+            .map_or(true, |range| ratio_within_range(resample_ratio, range))
+    }
+
+    /// Change the playback speed, like a tape machine running faster or slower.
+    ///
+    /// A `percent` of 100 keeps the original ratio. Larger values play the input faster,
+    /// which lowers the resample ratio and raises the pitch when the output is played
+    /// at its nominal sample rate. 200 halves the ratio, and 50 doubles it.
+    /// The allowed range is from `100 / max` to `100 * max` percent, where `max` is
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    /// If `ramp` is true, the change follows the ramp set by
+    /// [set_ramp_duration](Self::set_ramp_duration), otherwise it is immediate.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [ResampleError::RatioOutOfBounds] with the value and the allowed
+    /// range in percent, if `percent` is outside that range.
+    pub fn set_speed_percent(&mut self, percent: f64, ramp: bool) -> ResampleResult<()> {
+        let rel_ratio = speed_percent_ratio(percent, self.max_resample_ratio_relative)?;
+        self.set_ratio_relative_with_ramp(rel_ratio, ramp)
+    }
+
+    /// Change the pitch by a number of semitones, together with the speed as for
+    /// [set_speed_percent](Self::set_speed_percent).
+    ///
+    /// Positive values raise the pitch and speed up the playback, by lowering the resample ratio.
+    /// +12 semitones halves the ratio, which doubles the frequency of a tone in the output,
+    /// and -12 semitones doubles the ratio.
+    /// The allowed range is `12 * log2(max)` semitones in both directions, where `max` is
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    ///
+    /// # Errors
+    ///
+    /// The function returns [ResampleError::RatioOutOfBounds] with the value and the allowed
+    /// range in semitones, if `semitones` is outside that range.
+    pub fn set_pitch_semitones(&mut self, semitones: f64, ramp: bool) -> ResampleResult<()> {
+        let rel_ratio = pitch_semitones_ratio(semitones, self.max_resample_ratio_relative)?;
+        self.set_ratio_relative_with_ramp(rel_ratio, ramp)
+    }
+
+    /// Set the resample ratio exactly, as the fraction `num / den`,
+    /// for example 160 / 147 for 44.1 kHz to 48 kHz.
+    ///
+    /// A ratio given as an `f64` is rounded, and the rounding error adds up over a long stream,
+    /// so that the output slowly drifts away from the ideal position.
+    /// With an exact ratio, the position is stepped with integer arithmetic,
+    /// and the average rate matches the fraction exactly for as long as the ratio is kept.
+    /// If `ramp` is true, the ratio ramps to the new value as for
+    /// [set_resample_ratio](Resampler::set_resample_ratio), and exact stepping starts
+    /// when the ramp is finished. Otherwise the change is immediate.
+    /// Setting any other ratio, or scheduling a change, goes back to normal stepping.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the ratio is outside the range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// or outside the working range set by `set_working_ratio_range`.
+    /// Zero terms, or terms that are larger than `u32::MAX` after reducing the fraction,
+    /// are also rejected.
+    pub fn set_resample_ratio_rational(
+        &mut self,
+        num: u64,
+        den: u64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        let exact = ExactRatio::new(num, den).ok_or(ResampleError::BadRatioUpdate)?;
+        if ramp {
+            self.set_resample_ratio(exact.ratio())?;
+        } else {
+            self.set_resample_ratio_at(exact.ratio(), 0, false)?;
+        }
+        self.exact = Some(exact);
+        Ok(())
+    }
+
+    /// Set a ratio relative to the original one, either immediately or with a ramp.
+    fn set_ratio_relative_with_ramp(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        if ramp {
+            self.set_resample_ratio(new_ratio)
+        } else {
+            self.set_resample_ratio_at(new_ratio, 0, false)
         }
+    }
+
+    /// Get the phase of the output frames relative to the input frames,
+    /// as a fraction of an output frame in the range [0, 1).
+    ///
+    /// Two resamplers with the same resample ratio and the same phase place their output
+    /// frames at the same positions relative to the input. A new resampler that joins
+    /// a stream late can be aligned with one that has processed the stream from the start,
+    /// by giving it the phase of the first one before processing the first input frame
+    /// they have in common. The output frames then match, apart from a whole number of frames.
+    /// A new or reset resampler has phase zero.
+    pub fn phase(&self) -> f64 {
+        output_phase(
+            self.last_index,
+            self.interpolator.center(),
+            self.ramp.current(),
+        )
+    }
+
+    /// Set the phase of the output frames relative to the input frames, see [phase](Self::phase).
+    /// Increasing the phase delays the output frames by that fraction of a frame.
+    /// The new phase is used from the next call to "process".
+    /// A [reset](Resampler::reset) goes back to phase zero.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `fraction` is not in the range [0, 1).
+    pub fn set_phase(&mut self, fraction: f64) -> ResampleResult<()> {
+        self.last_index = index_with_phase(
+            self.last_index,
+            self.interpolator.center(),
+            self.ramp.current(),
+            fraction,
+        )?;
+        Ok(())
+    }
+
+    /// Get the number of frames per channel that
+    /// [prime_with_history](Self::prime_with_history) uses, which is twice the sinc length.
+    pub fn history_frames_needed(&self) -> usize {
+        2 * self.interpolator.len()
+    }
+
+    /// Fill the filter history with frames that precede the next chunk,
+    /// for example the decoded frames before a seek point.
+    /// Without this, a new or reset resampler starts from silence,
+    /// and the first output frames hold the response of the filter to the start of the signal.
+    ///
+    /// The last [history_frames_needed](Self::history_frames_needed) frames of each channel
+    /// are used. The next output frame is placed exactly at the first frame of the next chunk,
+    /// so that the output is correct from the start.
+    /// The resample ratio and the other settings are kept.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels is wrong,
+    /// or if a channel holds fewer frames than needed.
+    pub fn prime_with_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
         let sinc_len = self.interpolator.len();
-        let oversampling_factor = self.interpolator.nbr_sincs();
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            let wave = wave.as_ref();
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.needed_input_size {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.needed_input_size,
-                        actual: wave.len(),
-                    });
-                }
+        fill_history(&mut self.buffer, history, sinc_len)?;
+        self.current_buffer_fill = 0;
+        self.last_index = index_after_history(self.interpolator.center(), self.ramp.current());
+        Ok(())
+    }
+
+    /// Get the number of input frames expected by each call to "process".
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Create the state of a new stream that starts from silence at the original ratio.
+    pub(crate) fn new_stream(&self) -> StreamState<T> {
+        let mut ramp = self.ramp.clone();
+        ramp.reset(self.resample_ratio_original);
+        StreamState {
+            history: vec![T::zero(); 2 * self.interpolator.len()],
+            last_index: -(self.interpolator.center() as f64),
+            ramp,
+            input_frames_total: 0,
+        }
+    }
+
+    /// Load the state of a stream, so that the next call to "process" continues that stream.
+    /// The resampler must have a single channel.
+    pub(crate) fn load_stream(&mut self, stream: &StreamState<T>) {
+        let history_len = stream.history.len();
+        self.buffer[0][..history_len].copy_from_slice(&stream.history);
+        self.current_buffer_fill = 0;
+        self.last_index = stream.last_index;
+        self.ramp.clone_from(&stream.ramp);
+        self.input_frames_total = stream.input_frames_total;
+    }
+
+    /// Store the state left by the last call to "process" into a stream.
+    pub(crate) fn store_stream(&self, stream: &mut StreamState<T>) {
+        let history_len = stream.history.len();
+        stream.history.copy_from_slice(
+            &self.buffer[0][self.current_buffer_fill..self.current_buffer_fill + history_len],
+        );
+        stream.last_index = self.last_index;
+        stream.ramp.clone_from(&self.ramp);
+        stream.input_frames_total = self.input_frames_total;
+    }
+
+    /// Update the resample ratio of a stream, within the range allowed for this resampler.
+    pub(crate) fn set_stream_ratio(
+        &self,
+        stream: &mut StreamState<T>,
+        new_ratio: f64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        if !self.ratio_allowed(new_ratio) {
+            return Err(ResampleError::BadRatioUpdate);
+        }
+        if ramp {
+            stream.ramp.set_target(new_ratio);
+        } else {
+            stream.ramp.jump_to(new_ratio);
+        }
+        Ok(())
+    }
+
+    /// Get the parameters the resampler was created with.
+    /// This is `None` for a resampler created with `new_with_interpolator`,
+    /// since the parameters used for the interpolator are not known.
+    pub fn interpolation_parameters(&self) -> Option<&InterpolationParameters> {
+        self.parameters.as_ref()
+    }
+
+    /// Get the name of the instruction set used by the interpolator,
+    /// see [SincInterpolator::kernel].
+    pub fn interpolator_kernel(&self) -> &'static str {
+        self.interpolator.kernel()
+    }
+
+    /// Save the processing state, so that it can be restored later with
+    /// [restore_state](SincFixedIn::restore_state).
+    /// Processing the same input after restoring gives the same output as when the state was saved.
+    pub fn save_state(&self) -> ResamplerState<T> {
+        ResamplerState {
+            resampler: "SincFixedIn",
+            nbr_channels: self.nbr_channels,
+            sinc_len: self.interpolator.len(),
+            chunk_size: self.chunk_size,
+            buffer: self.buffer.clone(),
+            last_index: self.last_index,
+            current_buffer_fill: self.current_buffer_fill,
+            needed_input_size: 0,
+            ramp: self.ramp.clone(),
+            channel_mask: self.channel_mask.clone(),
+            input_frames_total: self.input_frames_total,
+            output_frames_final: self.output_frames_final,
+            trim: self.trim,
+        }
+    }
+
+    /// Restore a processing state saved by [save_state](SincFixedIn::save_state).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the state was saved from a different type of resampler,
+    /// or from one with a different number of channels, sinc length or chunk size.
+    pub fn restore_state(&mut self, state: &ResamplerState<T>) -> ResampleResult<()> {
+        state.check_compatible(
+            "SincFixedIn",
+            self.nbr_channels,
+            self.interpolator.len(),
+            self.chunk_size,
+        )?;
+        self.buffer.clone_from(&state.buffer);
+        self.last_index = state.last_index;
+        self.current_buffer_fill = state.current_buffer_fill;
+        self.ramp = state.ramp.clone();
+        self.channel_mask.copy_from_slice(&state.channel_mask);
+        self.input_frames_total = state.input_frames_total;
+        self.output_frames_final = state.output_frames_final;
+        self.trim = state.trim;
+        self.interpolator_previous = None;
+        Ok(())
+    }
+
+    /// Convert into a [SincFixedOut] that returns `chunk_size_out` frames for each call to
+    /// "process". The filter history, the resample ratio and any ongoing ramp are kept,
+    /// and the table of sincs is moved over instead of being computed again.
+    /// The output of the new resampler continues without interruption.
+    pub fn into_fixed_out(self, chunk_size_out: usize) -> SincFixedOut<T> {
+        let sinc_len = self.interpolator.len();
+        let mut resampler = SincFixedOut {
+            nbr_channels: self.nbr_channels,
+            chunk_size: chunk_size_out,
+            needed_input_size: 0,
+            last_index: self.last_index,
+            current_buffer_fill: 0,
+            resample_ratio_original: self.resample_ratio_original,
+            max_resample_ratio_relative: self.max_resample_ratio_relative,
+            working_ratio_range: self.working_ratio_range,
+            ramp: self.ramp,
+            exact: self.exact,
+            interpolator: self.interpolator,
+            interpolator_low: self.interpolator_low,
+            interpolator_spare: self.interpolator_spare,
+            interpolator_previous: self.interpolator_previous,
+            adaptive_cutoff: self.adaptive_cutoff,
+            buffer: self.buffer,
+            interpolation: self.interpolation,
+            parameters: self.parameters,
+            channel_mask: self.channel_mask,
+            active_channels_mask: self.active_channels_mask,
+            inactive_output: self.inactive_output,
+            validate_input: self.validate_input,
+            flush_denormals: self.flush_denormals,
+            trim: self.trim,
+            control: self.control,
+            input_frames_total: self.input_frames_total,
+            output_frames_final: self.output_frames_final,
+        };
+        resampler.needed_input_size = resampler.calc_needed_input_size();
+        let buffer_len = 3 * resampler.needed_input_size / 2 + 2 * sinc_len;
+        move_history_to_start(
+            &mut resampler.buffer,
+            self.current_buffer_fill,
+            sinc_len,
+            buffer_len,
+        );
+        resampler
+    }
+
+    /// Split into one single channel resampler per channel.
+    ///
+    /// Each new resampler gets the filter history of its channel, and the common
+    /// resample ratio, ramp and position between input frames, so that together they
+    /// produce the same output as this resampler would have.
+    /// They share the table of sincs instead of each getting a copy,
+    /// and can be used from different threads.
+    /// The handles from [ratio_control](SincFixedIn::ratio_control) don't control the new resamplers.
+    pub fn into_mono_resamplers(mut self) -> Vec<SincFixedIn<T>> {
+        let buffers = std::mem::take(&mut self.buffer);
+        buffers
+            .into_iter()
+            .enumerate()
+            .map(|(chan, buffer)| SincFixedIn {
+                nbr_channels: 1,
+                chunk_size: self.chunk_size,
+                last_index: self.last_index,
+                current_buffer_fill: self.current_buffer_fill,
+                resample_ratio_original: self.resample_ratio_original,
+                max_resample_ratio_relative: self.max_resample_ratio_relative,
+                working_ratio_range: self.working_ratio_range,
+                ramp: self.ramp.clone(),
+                exact: self.exact,
+                interpolator: self.interpolator.clone(),
+                interpolator_low: self.interpolator_low.clone(),
+                interpolator_spare: self.interpolator_spare.clone(),
+                interpolator_previous: self.interpolator_previous.clone(),
+                adaptive_cutoff: self.adaptive_cutoff,
+                buffer: vec![buffer],
+                interpolation: self.interpolation,
+                parameters: self.parameters,
+                channel_mask: vec![self.channel_mask[chan]],
+                active_channels_mask: vec![self.active_channels_mask[chan]],
+                inactive_output: self.inactive_output,
+                validate_input: self.validate_input,
+                flush_denormals: self.flush_denormals,
+                trim: self.trim,
+                control: RatioReceiver::new(),
+                input_frames_total: self.input_frames_total,
+                output_frames_final: self.output_frames_final,
+            })
+            .collect()
+    }
+
+    /// Combine single channel resamplers into one multichannel resampler,
+    /// with one channel per resampler, in the given order.
+    /// This is the reverse of [into_mono_resamplers](SincFixedIn::into_mono_resamplers).
+    /// The settings and the handles from [ratio_control](SincFixedIn::ratio_control)
+    /// are taken from the first resampler.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there are no resamplers, if one has more than one channel,
+    /// or if they don't share the same table of sincs and the same chunk size,
+    /// resample ratio and processing state.
+    pub fn from_mono_resamplers(resamplers: Vec<SincFixedIn<T>>) -> ResampleResult<Self> {
+        let mut resamplers = resamplers.into_iter();
+        let mut resampler = resamplers
+            .next()
+            .ok_or(ResampleError::BadNbrChannelsUpdate)?;
+        resampler.check_combinable(&resampler)?;
+        for mut other in resamplers {
+            resampler.check_combinable(&other)?;
+            resampler.buffer.append(&mut other.buffer);
+            resampler.channel_mask.push(other.channel_mask[0]);
+            resampler
+                .active_channels_mask
+                .push(other.active_channels_mask[0]);
+        }
+        let buffer_len = resampler.buffer.iter().map(|wav| wav.len()).max();
+        for wav in resampler.buffer.iter_mut() {
+            wav.resize(buffer_len.unwrap_or(0), T::zero());
+        }
+        resampler.nbr_channels = resampler.buffer.len();
+        Ok(resampler)
+    }
+
+    /// Check that a single channel resampler can be combined with this one.
+    fn check_combinable(&self, other: &Self) -> ResampleResult<()> {
+        let parameter = if other.nbr_channels != 1 {
+            "number of channels"
+        } else if !Arc::ptr_eq(&other.interpolator, &self.interpolator) {
+            "table of sincs"
+        } else if other.chunk_size != self.chunk_size {
+            "chunk size"
+        } else if other.resample_ratio_original != self.resample_ratio_original
+            || other.ramp.target != self.ramp.target
+        {
+            "resample ratio"
+        } else if other.last_index != self.last_index
+            || other.current_buffer_fill != self.current_buffer_fill
+            || other.input_frames_total != self.input_frames_total
+        {
+            "processing state"
+        } else {
+            return Ok(());
+        };
+        Err(ResampleError::IncompatibleResamplers { parameter })
+    }
+
+    /// Change the number of input frames expected by the next call to "process".
+    /// The filter history is kept, so the output continues without interruption.
+    /// The internal buffers are reallocated if the new chunk size is larger than any used before.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new chunk size is zero.
+    pub fn set_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::BadChunkSizeUpdate);
+        }
+        trace!("Change chunk size to {}", chunk_size);
+        let buffer_len = chunk_size + 2 * self.interpolator.len();
+        for wav in self.buffer.iter_mut() {
+            if wav.len() < buffer_len {
+                wav.resize(buffer_len, T::zero());
             }
         }
+        self.chunk_size = chunk_size;
+        Ok(())
+    }
+
+    /// Resample all the frames of the input buffers, whatever their number.
+    ///
+    /// This is useful when the input arrives in packets of varying size.
+    /// The chunk size is set to the length of the active input channels, see
+    /// [set_chunk_size](SincFixedIn::set_chunk_size), and the input is processed
+    /// like by [process_into_buffer](Resampler::process_into_buffer).
+    /// The output buffers must have room for
+    /// [output_frames_max](Resampler::output_frames_max) frames for the new chunk size.
+    /// Empty input buffers are accepted, and produce no output.
+    ///
+    /// The internal buffers grow when a packet is longer than any seen before.
+    /// Call [set_chunk_size](SincFixedIn::set_chunk_size) with the longest expected packet size
+    /// beforehand to avoid allocating while processing.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the active input channels have different lengths,
+    /// or for the same reasons as [process_into_buffer](Resampler::process_into_buffer).
+    pub fn process_variable_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mask = active_channels_mask.unwrap_or(&self.active_channels_mask);
+        let mut lengths = wave_in
+            .iter()
+            .zip(mask.iter())
+            .enumerate()
+            .filter(|(_, (_, active))| **active)
+            .map(|(chan, (wave, _))| (chan, wave.as_ref().len()));
+        let frames = match lengths.next() {
+            Some((_, frames)) => frames,
+            None => return Ok((0, 0)),
+        };
+        if let Some((chan, len)) = lengths.find(|(_, len)| *len != frames) {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: chan,
+                expected: frames,
+                actual: len,
+            });
+        }
+        if frames == 0 {
+            return Ok((0, 0));
+        }
+        self.set_chunk_size(frames)?;
+        self.process_into_buffer(wave_in, wave_out, active_channels_mask)
+    }
+
+    /// Get the maximum number of output frames of a chunk,
+    /// for ratios between `min_ratio` and `max_ratio`.
+    fn output_frames_for_ratios(&self, min_ratio: f64, max_ratio: f64) -> usize {
+        // After a large increase of the ratio, the previous chunk may have left
+        // more input frames than usual to be processed.
+        let lowest_allowed = self.working_ratio_range().0;
+        let leftover = (1.0 / lowest_allowed).ceil() - (1.0 / min_ratio).ceil();
+        let frames = (self.chunk_size as f64 + leftover.max(0.0)) * max_ratio + 1.0;
+        frames.max(self.chunk_size as f64 * max_ratio + 10.0) as usize
+    }
+
+    /// Get the maximum number of output frames of a chunk, for any ratio that may be used.
+    pub(crate) fn output_frames_max_allowed(&self) -> usize {
+        let highest_allowed = self.working_ratio_range().1;
+        self.output_frames_for_ratios(highest_allowed, highest_allowed)
+    }
+
+    /// Get the index in the buffer where the interpolation of a chunk stops.
+    /// The frames after it are held back until the next chunk.
+    fn end_index(&self) -> isize {
+        let t_ratio_max = 1.0 / self.ramp.min_ratio();
+        self.chunk_size as isize
+            - (self.interpolator.len() as isize + 1)
+            - t_ratio_max.ceil() as isize
+    }
+
+    /// Get the number of output frames that the next chunk gives fewer than a chunk
+    /// at the current ratio, because of the frames held back since the start of the stream.
+    /// Right after a reset, this is how much shorter the first chunk is than the following ones.
+    pub(crate) fn output_frames_held_back(&self) -> usize {
+        let frames_in = self.end_index() as f64 - self.last_index;
+        let held_back = (self.chunk_size as f64 - frames_in) * self.ramp.current();
+        held_back.round().max(0.0) as usize
+    }
+
+    /// Resample one chunk from `wave_in` into `wave_out`, for the channels marked as active
+    /// in the channel mask. Returns the number of input and output frames.
+    fn process_buffers<I, O>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut O,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        if self.validate_input {
+            validate_finite(wave_in, &self.channel_mask)?;
+        }
+        let _denormals = DenormalGuard::new(self.flush_denormals);
+        let mut wave_out = TrimmedOutput::new(wave_out, self.trim.remaining());
+        let sinc_len = self.interpolator.len();
+        let oversampling_factor = self.interpolator.nbr_sincs();
+        let interpolator = select_interpolator(
+            &self.interpolator,
+            &self.interpolator_low,
+            self.resample_ratio_original,
+            self.ramp.min_ratio(),
+        );
+        let end_idx = self.end_index();
+        //update buffer with new data
         for wav in self.buffer.iter_mut() {
             for idx in 0..(2 * sinc_len) {
                 wav[idx] = wav[idx + self.current_buffer_fill];
             }
         }
-        self.current_buffer_fill = self.needed_input_size;
+        self.current_buffer_fill = self.chunk_size;
 
-        let mut wave_out = vec![Vec::new(); self.nbr_channels];
-
-        for chan in used_channels.iter() {
-            for (idx, sample) in wave_in[*chan].as_ref().iter().enumerate() {
-                self.buffer[*chan][idx + 2 * sinc_len] = *sample;
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_in.read_into(
+                    chan,
+                    0,
+                    &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.chunk_size],
+                );
             }
-            wave_out[*chan] = vec![T::zero(); self.chunk_size];
         }
 
         let mut idx = self.last_index;
-        let t_ratio = 1.0 / self.resample_ratio as f64;
+        let start_idx = idx;
+        let previous = self.interpolator_previous.take();
+
+        let mut n = 0;
 
         match self.interpolation {
             InterpolationType::Cubic => {
                 let mut points = [T::zero(); 4];
                 let mut nearest = [(0isize, 0isize); 4];
-                for n in 0..self.chunk_size {
-                    idx += t_ratio;
+                while idx < end_idx as f64 {
+                    idx = advance_index(idx, self.ramp.next_step(), &self.ramp, &mut self.exact);
+                    let fade = crossfade_weight(
+                        &previous,
+                        end_idx as f64 - idx,
+                        end_idx as f64 - start_idx,
+                    );
                     get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
                     let frac = idx * oversampling_factor as f64
                         - (idx * oversampling_factor as f64).floor();
                     let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
+                    for (chan, _) in self
+                        .channel_mask
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, active)| **active)
+                    {
+                        let buf = &self.buffer[chan];
                         for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
+                            *p = sinc_point(
+                                interpolator,
+                                &previous,
+                                fade,
                                 buf,
                                 (n.0 + 2 * sinc_len as isize) as usize,
                                 n.1 as usize,
                             );
                         }
-                        wave_out[*chan][n] = interp_cubic(frac_offset, &points);
+                        wave_out.write(chan, n, interp_cubic(frac_offset, &points));
                     }
+                    n += 1;
                 }
             }
             InterpolationType::Linear => {
                 let mut points = [T::zero(); 2];
                 let mut nearest = [(0isize, 0isize); 2];
-                for n in 0..self.chunk_size {
-                    idx += t_ratio;
+                while idx < end_idx as f64 {
+                    idx = advance_index(idx, self.ramp.next_step(), &self.ramp, &mut self.exact);
+                    let fade = crossfade_weight(
+                        &previous,
+                        end_idx as f64 - idx,
+                        end_idx as f64 - start_idx,
+                    );
                     get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
                     let frac = idx * oversampling_factor as f64
                         - (idx * oversampling_factor as f64).floor();
                     let frac_offset = T::coerce(frac);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
+                    for (chan, _) in self
+                        .channel_mask
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, active)| **active)
+                    {
+                        let buf = &self.buffer[chan];
                         for (n, p) in nearest.iter().zip(points.iter_mut()) {
-                            *p = self.interpolator.get_sinc_interpolated(
+                            *p = sinc_point(
+                                interpolator,
+                                &previous,
+                                fade,
                                 buf,
                                 (n.0 + 2 * sinc_len as isize) as usize,
                                 n.1 as usize,
                             );
                         }
-                        wave_out[*chan][n] = interp_lin(frac_offset, &points);
+                        wave_out.write(chan, n, interp_lin(frac_offset, &points));
                     }
+                    n += 1;
                 }
             }
             InterpolationType::Nearest => {
                 let mut point;
                 let mut nearest;
-                for n in 0..self.chunk_size {
-                    idx += t_ratio;
+                while idx < end_idx as f64 {
+                    idx = advance_index(idx, self.ramp.next_step(), &self.ramp, &mut self.exact);
+                    let fade = crossfade_weight(
+                        &previous,
+                        end_idx as f64 - idx,
+                        end_idx as f64 - start_idx,
+                    );
                     nearest = get_nearest_time(idx, oversampling_factor as isize);
-                    for chan in used_channels.iter() {
-                        let buf = &self.buffer[*chan];
-                        point = self.interpolator.get_sinc_interpolated(
+                    for (chan, _) in self
+                        .channel_mask
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, active)| **active)
+                    {
+                        let buf = &self.buffer[chan];
+                        point = sinc_point(
+                            interpolator,
+                            &previous,
+                            fade,
                             buf,
                             (nearest.0 + 2 * sinc_len as isize) as usize,
                             nearest.1 as usize,
                         );
-                        wave_out[*chan][n] = point;
+                        wave_out.write(chan, n, point);
                     }
+                    n += 1;
                 }
             }
         }
 
-        let prev_input_len = self.needed_input_size;
         // store last index for next iteration
-        self.last_index = idx - self.current_buffer_fill as f64;
-        self.needed_input_size = (self.last_index as f32
-            + self.chunk_size as f32 / self.resample_ratio as f32
-            + sinc_len as f32)
-            .ceil() as usize
-            + 2;
+        self.last_index = shift_index(idx, self.chunk_size, &mut self.exact);
+        self.apply_ratio_control();
+        self.input_frames_total += self.chunk_size as u64;
+        Ok((self.chunk_size, self.trim.consume(n)))
+    }
+}
+
+impl<T> fmt::Debug for SincFixedIn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SincFixedIn")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size", &self.chunk_size)
+            .field("resample_ratio", &self.ramp.target)
+            .field("resample_ratio_original", &self.resample_ratio_original)
+            .field("interpolation", &self.interpolation)
+            .field("sinc_len", &self.interpolator.len())
+            .field("oversampling_factor", &self.interpolator.nbr_sincs())
+            .field("kernel", &self.interpolator.kernel())
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for SincFixedIn<T>
+where
+    T: Sample,
+{
+    /// Resample a chunk of audio. The input length is fixed, and the output varies in length.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the length of the input data is not equal
+    /// to the number of channels and chunk size defined when creating the instance.
+    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
+        let buffer = &mut self.buffer;
+        update_mask_from_buffers(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            wave_in,
+            self.chunk_size,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_max = self.output_frames_max();
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan] = vec![T::zero(); frames_max];
+            }
+        }
+        let (_, n) = self.process_buffers(
+            &SequentialInput::new(wave_in, 0, self.chunk_size),
+            &mut SequentialOutput::new(&mut wave_out, 0),
+        )?;
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan].truncate(n);
+            }
+        }
         trace!(
-            "Resampling channels {:?}, {} frames in, {} frames out. Next needed length: {} frames, last index {}",
-            used_channels,
-            prev_input_len,
+            "Resampling channels {:?}, {} frames in, {} frames out",
+            self.channel_mask,
             self.chunk_size,
-            self.needed_input_size,
-            self.last_index
+            n,
         );
         Ok(wave_out)
     }
 
-    /// Update the resample ratio. New value must be within +-10% of the original one
-    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
-        trace!("Change resample ratio to {}", new_ratio);
-        if (new_ratio / self.resample_ratio_original > 0.9)
-            && (new_ratio / self.resample_ratio_original < 1.1)
-        {
-            self.resample_ratio = new_ratio;
-            self.needed_input_size = (self.last_index as f32
-                + self.chunk_size as f32 / self.resample_ratio as f32
-                + self.interpolator.len() as f32)
-                .ceil() as usize
-                + 2;
-            Ok(())
-        } else {
-            Err(ResampleError::BadRatioUpdate)
-        }
+    /// Resample a chunk of interleaved audio. The input length is fixed, and the output varies in length.
+    /// # Errors
+    ///
+    /// The function returns an error if the input doesn't contain `chunk_size` frames,
+    /// if the output buffer is shorter than `output_frames_max` frames,
+    /// or if the mask doesn't match the number of channels.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        validate_interleaved(
+            wave_in,
+            wave_out,
+            self.nbr_channels,
+            self.chunk_size,
+            self.output_frames_max(),
+        )?;
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let (frames_in, frames_out) = self.process_buffers(
+            &InterleavedInput::new(wave_in, self.nbr_channels, self.chunk_size),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
     }
 
-    /// Update the resample ratio relative to the original one
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
-        let new_ratio = self.resample_ratio_original * rel_ratio;
-        self.set_resample_ratio(new_ratio)
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// `input_offset` plus "nbr_frames_needed" frames, if an active output channel holds fewer
+    /// than `output_offset` plus "output_frames_max" frames, or if the number of channels
+    /// of the buffers or the mask is wrong.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential(
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = SequentialOutput::new(wave_out, output_offset);
+        let (frames_in, frames_out) = self.process_buffers(
+            &SequentialInput::new(wave_in, input_offset, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::{interp_cubic, interp_lin};
-    use crate::asynchro::ScalarInterpolator;
-    use crate::asynchro::SincInterpolator;
-    use crate::InterpolationParameters;
-    use crate::InterpolationType;
+    /// Resample a chunk of audio from sequential input buffers,
+    /// and write the result to an interleaved output buffer.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// "nbr_frames_needed" frames, if the output buffer is shorter than "output_frames_max" frames,
+    /// or if the number of channels of the input or the mask is wrong.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential_to_interleaved(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let (frames_in, frames_out) =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the last frames of a stream, when fewer frames than needed remain.
+    /// The missing input frames are replaced by zeros, and the input may also be `None`,
+    /// which processes only zeros.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds more than
+    /// "nbr_frames_needed" frames, if the active input channels have different lengths,
+    /// if an active output channel holds fewer than "output_frames_max" frames,
+    /// or if the number of channels of the buffers or the mask is wrong.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_needed = self.nbr_frames_needed();
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_in = validate_partial(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_needed,
+            self.output_frames_max(),
+        )?;
+        let wave_in = wave_in.unwrap_or(&[]);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let (_, frames_out) =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    /// Only the output frames that correspond to real input are counted,
+    /// and zero frames are returned once the stream has been flushed.
+    /// # Errors
+    ///
+    /// The function returns an error under the same conditions as "process_partial_into_buffer".
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mut output_frames_final = self.output_frames_final;
+        let result = process_final(
+            self,
+            &mut output_frames_final,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+        );
+        self.output_frames_final = output_frames_final;
+        result
+    }
+
+    /// Query for the number of frames needed for the next call to "process".
+    /// Will always return the current chunk_size.
+    fn nbr_frames_needed(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the maximum number of input frames, which is always the chunk size.
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the maximum number of output frames, given by the chunk size and the resample ratio.
+    /// While the ratio is changing, the largest ratio that can be reached is used.
+    fn output_frames_max(&self) -> usize {
+        self.output_frames_for_ratios(self.ramp.min_ratio(), self.ramp.max_ratio())
+    }
+
+    /// Reset the resampler state, and go back to the original resample ratio.
+    fn reset(&mut self) {
+        for wav in self.buffer.iter_mut() {
+            wav.iter_mut().for_each(|x| *x = T::zero());
+        }
+        self.last_index = -(self.interpolator.center() as f64);
+        self.current_buffer_fill = self.chunk_size;
+        self.ramp.reset(self.resample_ratio_original);
+        self.input_frames_total = 0;
+        self.output_frames_final = None;
+        self.interpolator_previous = None;
+        let delay = sinc_output_delay(self.ramp.target, self.interpolator.nbr_sincs());
+        self.trim.restart(delay);
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        if channel >= self.nbr_channels {
+            return Err(ResampleError::InvalidChannel {
+                channel,
+                nbr_channels: self.nbr_channels,
+            });
+        }
+        self.buffer[channel].iter_mut().for_each(|x| *x = T::zero());
+        Ok(())
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        if nbr_channels == 0 {
+            return Err(ResampleError::BadNbrChannelsUpdate);
+        }
+        trace!("Change number of channels to {}", nbr_channels);
+        let buffer_len = self.chunk_size + 2 * self.interpolator.len();
+        resize_channels(&mut self.buffer, nbr_channels, buffer_len);
+        resize_mask(&mut self.channel_mask, nbr_channels);
+        resize_mask(&mut self.active_channels_mask, nbr_channels);
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    /// Get the current resample ratio.
+    fn resample_ratio(&self) -> f64 {
+        self.ramp.target
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
+
+    // The ramp counts output frames for scheduling ratio changes.
+    fn output_frames_total(&self) -> u64 {
+        self.ramp.frame - self.trim.skipped() as u64
+    }
+
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        store_mask(&mut self.active_channels_mask, mask)
+    }
+
+    fn active_channels_mask(&self) -> &[bool] {
+        &self.active_channels_mask
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        self.inactive_output = inactive_output;
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        self.validate_input = enabled;
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        let started = self.input_frames_total > 0;
+        let delay = sinc_output_delay(self.ramp.target, self.interpolator.nbr_sincs());
+        self.trim.set_enabled(enabled, delay, started);
+    }
+
+    /// Update the resample ratio. The new value must be within the range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// and within the working range if one has been set.
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        trace!("Change resample ratio to {}", new_ratio);
+        if self.ratio_allowed(new_ratio) {
+            self.ramp.set_target(new_ratio);
+            Ok(())
+        } else {
+            Err(ResampleError::BadRatioUpdate)
+        }
+    }
+    /// Update the resample ratio relative to the original one
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(new_ratio)
+    }
+
+    fn max_resample_ratio_relative(&self) -> f64 {
+        self.max_resample_ratio_relative
+    }
+
+    /// Update the range of allowed resample ratios.
+    /// The internal buffers don't depend on the ratio, so nothing is reallocated.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()> {
+        let range_allowed = self.working_ratio_range.map_or(true, |(min, max)| {
+            ratio_within_bounds(min, self.resample_ratio_original, max_relative)
+                && ratio_within_bounds(max, self.resample_ratio_original, max_relative)
+        });
+        if !range_allowed
+            || !self
+                .ramp
+                .within_bounds(self.resample_ratio_original, max_relative)
+        {
+            return Err(ResampleError::BadMaxRatioUpdate);
+        }
+        self.max_resample_ratio_relative = max_relative;
+        if self.adaptive_cutoff {
+            self.set_adaptive_cutoff(true)?;
+        }
+        Ok(())
+    }
+
+    /// Update the resample ratio from a pair of sample rates.
+    /// Without `ramp`, the new ratio is used from the next output frame.
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()> {
+        if ramp {
+            self.set_resample_ratio(fs_out / fs_in)
+        } else {
+            self.set_resample_ratio_at(fs_out / fs_in, 0, false)
+        }
+    }
+
+    fn ramp_progress(&self) -> Option<f64> {
+        self.ramp.progress()
+    }
+
+    /// Get the delay in output frames for the current resample ratio.
+    fn output_delay_fractional(&self) -> f64 {
+        sinc_output_delay(self.ramp.target, self.interpolator.nbr_sincs())
+            - self.trim.frames() as f64
+    }
+
+    /// The response is that of the sinc filter for the current resample ratio,
+    /// without the interpolation between the oversampled points.
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        let response = self.filter_response();
+        frequency_response(self.ramp.target, n_points, |freq| response.magnitude(freq))
+    }
+
+    fn group_delay(&self) -> f64 {
+        self.filter_response().delay()
+    }
+}
+
+impl<T> SincFixedOut<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFixedOut
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    pub fn new(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        debug!(
+            "Create new SincFixedOut, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+        let parameters = parameters.with_automatic_oversampling();
+        let interpolator = make_interpolator(&parameters, resample_ratio, parameters.f_cutoff);
+
+        let mut resampler = Self::new_with_interpolator(
+            resample_ratio,
+            parameters.interpolation,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        );
+        resampler.parameters = Some(parameters);
+        resampler
+    }
+
+    /// Create a new SincFixedOut using a user supplied filter instead of a windowed sinc.
+    ///
+    /// The filter is given as its impulse response, oversampled by `oversampling_factor`,
+    /// so that `taps` holds `sinc_len * oversampling_factor` values,
+    /// where `sinc_len` must be a multiple of 8.
+    /// The filter is scaled to unity gain at low frequencies.
+    /// Its cutoff must be below the lowest of the input and output Nyquist frequencies,
+    /// since it is used as is for all resample ratios.
+    /// The resampler can't use adaptive cutoff, see `set_adaptive_cutoff`.
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `taps`: The oversampled impulse response of the filter.
+    /// - `oversampling_factor`: Number of filter taps per frame.
+    /// - `interpolation_type`: Interpolation between the oversampled points, see `InterpolationType`
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the oversampling factor is zero,
+    /// if the number of taps is not a multiple of 8 times the oversampling factor,
+    /// or if the filter has values that are not finite or zero gain at low frequencies.
+    pub fn new_with_filter(
+        resample_ratio: f64,
+        taps: &[f64],
+        oversampling_factor: usize,
+        interpolation_type: InterpolationType,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new SincFixedOut with filter, ratio: {}, chunk_size: {}, channels: {}, taps: {}",
+            resample_ratio,
+            chunk_size,
+            nbr_channels,
+            taps.len()
+        );
+        let interpolator = make_interpolator_from_taps(taps, oversampling_factor)?;
+        Ok(Self::new_with_interpolator(
+            resample_ratio,
+            interpolation_type,
+            interpolator,
+            chunk_size,
+            nbr_channels,
+        ))
+    }
+
+    /// Create a new SincFixedOut using an existing Interpolator
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `interpolation_type`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `interpolator`:  The interpolator to use, any implementation of `SincInterpolator`.
+    /// - `chunk_size`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// The resample ratio may be changed by up to 10% from the original value.
+    /// Use [set_max_resample_ratio_relative](Resampler::set_max_resample_ratio_relative)
+    /// to allow a larger range.
+    pub fn new_with_interpolator(
+        resample_ratio: f64,
+        interpolation_type: InterpolationType,
+        interpolator: Box<dyn SincInterpolator<T>>,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        let needed_input_size =
+            (chunk_size as f64 / resample_ratio).ceil() as usize + 2 + interpolator.len()
+                - interpolator.center();
+        let buffer =
+            vec![vec![T::zero(); 3 * needed_input_size / 2 + 2 * interpolator.len()]; nbr_channels];
+
+        SincFixedOut {
+            nbr_channels,
+            chunk_size,
+            needed_input_size,
+            last_index: -(interpolator.center() as f64),
+            current_buffer_fill: needed_input_size,
+            resample_ratio_original: resample_ratio,
+            max_resample_ratio_relative: 1.1,
+            working_ratio_range: None,
+            ramp: RatioRamp::new(resample_ratio),
+            exact: None,
+            interpolator: Arc::from(interpolator),
+            interpolator_low: None,
+            interpolator_spare: None,
+            interpolator_previous: None,
+            adaptive_cutoff: false,
+            buffer,
+            interpolation: interpolation_type,
+            parameters: None,
+            channel_mask: vec![true; nbr_channels],
+            active_channels_mask: vec![true; nbr_channels],
+            inactive_output: InactiveOutput::Skip,
+            validate_input: false,
+            flush_denormals: false,
+            trim: DelayTrim::default(),
+            control: RatioReceiver::new(),
+            input_frames_total: 0,
+            output_frames_final: None,
+        }
+    }
+
+    /// Set the duration of the ramp used when the resample ratio is changed, in output frames.
+    /// The ratio then changes from the current to the new value over this many frames,
+    /// which may span several calls to "process".
+    /// A new ratio set during a ramp starts a new ramp from the current ratio.
+    /// The default duration of zero applies a new ratio immediately.
+    pub fn set_ramp_duration(&mut self, frames: usize) {
+        self.ramp.set_duration(frames);
+        self.needed_input_size = self.calc_needed_input_size();
+    }
+
+    /// Set the shape of the curve followed by the resample ratio during a ramp.
+    /// The default is [RampType::Linear].
+    pub fn set_ramp_type(&mut self, ramp_type: RampType) {
+        self.ramp.set_ramp_type(ramp_type);
+        self.needed_input_size = self.calc_needed_input_size();
+    }
+
+    /// Enable or disable adaptive cutoff of the anti-aliasing filter.
+    ///
+    /// The table of sincs is computed for the resample ratio given when creating the resampler.
+    /// When the ratio is later lowered below 1.0, and below that original ratio,
+    /// the cutoff of that table is too high and the output gets aliasing.
+    /// With adaptive cutoff, a second table is computed with the cutoff scaled for the lowest ratio
+    /// allowed by [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// and it is used for each chunk where the ratio goes below the original one and below 1.0.
+    /// The filter is switched between chunks, and the second table is only
+    /// computed when the allowed range reaches below 1.0.
+    /// With [FilterPhase::Minimum] the two tables have slightly different delays,
+    /// so the output may jump by a few samples when switching between them.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the resampler was created with `new_with_interpolator`,
+    /// since the parameters needed for computing the second table are then not known.
+    pub fn set_adaptive_cutoff(&mut self, enabled: bool) -> ResampleResult<()> {
+        self.interpolator_low = match (&self.parameters, enabled) {
+            (Some(parameters), true) => make_adaptive_interpolator(
+                parameters,
+                self.resample_ratio_original,
+                self.max_resample_ratio_relative,
+            ),
+            (None, true) => return Err(ResampleError::NoInterpolationParameters),
+            (_, false) => None,
+        };
+        self.adaptive_cutoff = enabled;
+        Ok(())
+    }
+
+    /// Change the relative cutoff frequency of the anti-aliasing filter,
+    /// see [InterpolationParameters::f_cutoff].
+    ///
+    /// The table of sincs is computed again for the new cutoff, so this call allocates,
+    /// and takes about as long as creating a new resampler.
+    /// The table for the previous cutoff is kept, and switching back to that cutoff
+    /// reuses it without allocating. With adaptive cutoff enabled, the second table
+    /// is always computed again.
+    ///
+    /// The next chunk is crossfaded from the old to the new filter, to avoid a click.
+    /// With [FilterPhase::Minimum] the delay of the filter may change with the cutoff,
+    /// and the filter is then switched without a crossfade.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the cutoff is not in the range (0, 1],
+    /// or if the resampler was created with `new_with_interpolator` or `new_with_filter`,
+    /// since the parameters needed for computing a new table are then not known.
+    pub fn set_cutoff(&mut self, relative_cutoff: f32) -> ResampleResult<()> {
+        let previous = select_interpolator(
+            &self.interpolator,
+            &self.interpolator_low,
+            self.resample_ratio_original,
+            self.ramp.min_ratio(),
+        )
+        .clone();
+        if !switch_cutoff(
+            &mut self.parameters,
+            self.resample_ratio_original,
+            &mut self.interpolator,
+            &mut self.interpolator_spare,
+            relative_cutoff,
+        )? {
+            return Ok(());
+        }
+        if self.adaptive_cutoff {
+            self.set_adaptive_cutoff(true)?;
+        }
+        self.interpolator_previous = crossfade_from(
+            self.interpolator_previous.take().unwrap_or(previous),
+            select_interpolator(
+                &self.interpolator,
+                &self.interpolator_low,
+                self.resample_ratio_original,
+                self.ramp.min_ratio(),
+            ),
+        );
+        Ok(())
+    }
+
+    /// Get the response of the table of sincs in use, for combining with other stages.
+    pub(crate) fn filter_response(&self) -> FilterResponse {
+        let interpolator = select_interpolator(
+            &self.interpolator,
+            &self.interpolator_low,
+            self.resample_ratio_original,
+            self.ramp.min_ratio(),
+        );
+        FilterResponse::from_interpolator(interpolator.as_ref())
+    }
+
+    /// Schedule a change of the resample ratio at a given output frame.
+    ///
+    /// Output frames are counted from the creation of the resampler, or from the last reset.
+    /// The new ratio takes effect exactly at that frame, also when it falls in the middle of a chunk.
+    /// If `ramp` is true, a ramp towards the new ratio starts at that frame,
+    /// see [set_ramp_duration](Self::set_ramp_duration). Otherwise the ratio switches immediately.
+    /// Only one change can be pending, and scheduling a new one replaces it.
+    /// A change scheduled for a frame that has already been produced is applied immediately.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new ratio is outside the range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// or outside the working range set by `set_working_ratio_range`.
+    pub fn set_resample_ratio_at(
+        &mut self,
+        new_ratio: f64,
+        at_output_frame: u64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        trace!(
+            "Schedule resample ratio {} at output frame {}",
+            new_ratio,
+            at_output_frame
+        );
+        if self.ratio_allowed(new_ratio) {
+            self.ramp.schedule(ScheduledChange {
+                ratio: new_ratio,
+                frame: at_output_frame + self.trim.frames() as u64,
+                ramp,
+            });
+            self.needed_input_size = self.calc_needed_input_size();
+            Ok(())
+        } else {
+            Err(ResampleError::BadRatioUpdate)
+        }
+    }
+
+    /// Get a handle for changing the resample ratio from another thread, see [RatioControl].
+    /// All handles of a resampler control the same ratio,
+    /// and a clone of the resampler is not controlled by the handles of the original.
+    pub fn ratio_control(&self) -> RatioControl {
+        self.control.control()
+    }
+
+    /// Apply a ratio set with a [RatioControl] handle, clamped to the working range.
+    fn apply_ratio_control(&mut self) {
+        if let Some((new_ratio, ramp)) = self.control.take() {
+            let (min, max) = self.working_ratio_range();
+            let ratio = new_ratio.max(min).min(max);
+            if ratio != new_ratio {
+                self.control.set_clamped();
+            }
+            trace!("Change resample ratio to {} from control handle", ratio);
+            if ramp {
+                self.ramp.set_target(ratio);
+            } else {
+                self.ramp.jump_to(ratio);
+            }
+        }
+    }
+
+    /// Narrow the range of resample ratios that will be used, to the absolute ratios
+    /// from `min` to `max`.
+    ///
+    /// The range must be within the one allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// which stays unchanged, and must include the original ratio.
+    /// New ratios outside the working range are then rejected,
+    /// and [input_frames_max](Resampler::input_frames_max) and
+    /// [output_frames_max](Resampler::output_frames_max) are computed for the working range
+    /// instead of the full one. This makes the buffers from
+    /// [input_buffer_allocate](Resampler::input_buffer_allocate) and
+    /// [output_buffer_allocate](Resampler::output_buffer_allocate) smaller
+    /// when the ratio only varies by a small amount.
+    /// Setting the range to the full allowed range removes the restriction.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [ResampleError::BadWorkingRatioRange] if `min` is larger than `max`,
+    /// if the range is not within the allowed one, or if it excludes the original ratio
+    /// or a ratio in use.
+    pub fn set_working_ratio_range(&mut self, min: f64, max: f64) -> ResampleResult<()> {
+        trace!("Change working resample ratio range to {} - {}", min, max);
+        let original = self.resample_ratio_original;
+        let max_relative = self.max_resample_ratio_relative;
+        let range = (min, max);
+        if !(min <= max
+            && ratio_within_bounds(min, original, max_relative)
+            && ratio_within_bounds(max, original, max_relative)
+            && ratio_within_range(original, range)
+            && self.ramp.within_range(range))
+        {
+            return Err(ResampleError::BadWorkingRatioRange);
+        }
+        self.working_ratio_range = Some(range);
+        Ok(())
+    }
+
+    /// Get the range of resample ratios that may be used, as absolute ratios.
+    /// This is the range set by [set_working_ratio_range](SincFixedOut::set_working_ratio_range),
+    /// or the full range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    pub fn working_ratio_range(&self) -> (f64, f64) {
+        self.working_ratio_range.unwrap_or((
+            self.resample_ratio_original / self.max_resample_ratio_relative,
+            self.resample_ratio_original * self.max_resample_ratio_relative,
+        ))
+    }
+
+    /// Check that a new resample ratio is allowed, both by the maximum relative ratio
+    /// and by the working range.
+    fn ratio_allowed(&self, resample_ratio: f64) -> bool {
+        ratio_within_bounds(
+            resample_ratio,
+            self.resample_ratio_original,
+            self.max_resample_ratio_relative,
+        ) && self
+            .working_ratio_range
+            .map_or(true, |range| ratio_within_range(resample_ratio, range))
+    }
+
+    /// Change the playback speed, like a tape machine running faster or slower.
+    ///
+    /// A `percent` of 100 keeps the original ratio. Larger values play the input faster,
+    /// which lowers the resample ratio and raises the pitch when the output is played
+    /// at its nominal sample rate. 200 halves the ratio, and 50 doubles it.
+    /// The allowed range is from `100 / max` to `100 * max` percent, where `max` is
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    /// If `ramp` is true, the change follows the ramp set by
+    /// [set_ramp_duration](Self::set_ramp_duration), otherwise it is immediate.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [ResampleError::RatioOutOfBounds] with the value and the allowed
+    /// range in percent, if `percent` is outside that range.
+    pub fn set_speed_percent(&mut self, percent: f64, ramp: bool) -> ResampleResult<()> {
+        let rel_ratio = speed_percent_ratio(percent, self.max_resample_ratio_relative)?;
+        self.set_ratio_relative_with_ramp(rel_ratio, ramp)
+    }
+
+    /// Change the pitch by a number of semitones, together with the speed as for
+    /// [set_speed_percent](Self::set_speed_percent).
+    ///
+    /// Positive values raise the pitch and speed up the playback, by lowering the resample ratio.
+    /// +12 semitones halves the ratio, which doubles the frequency of a tone in the output,
+    /// and -12 semitones doubles the ratio.
+    /// The allowed range is `12 * log2(max)` semitones in both directions, where `max` is
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    ///
+    /// # Errors
+    ///
+    /// The function returns [ResampleError::RatioOutOfBounds] with the value and the allowed
+    /// range in semitones, if `semitones` is outside that range.
+    pub fn set_pitch_semitones(&mut self, semitones: f64, ramp: bool) -> ResampleResult<()> {
+        let rel_ratio = pitch_semitones_ratio(semitones, self.max_resample_ratio_relative)?;
+        self.set_ratio_relative_with_ramp(rel_ratio, ramp)
+    }
+
+    /// Set the resample ratio exactly, as the fraction `num / den`,
+    /// for example 160 / 147 for 44.1 kHz to 48 kHz.
+    ///
+    /// A ratio given as an `f64` is rounded, and the rounding error adds up over a long stream,
+    /// so that the output slowly drifts away from the ideal position.
+    /// With an exact ratio, the position is stepped with integer arithmetic,
+    /// and the average rate matches the fraction exactly for as long as the ratio is kept.
+    /// If `ramp` is true, the ratio ramps to the new value as for
+    /// [set_resample_ratio](Resampler::set_resample_ratio), and exact stepping starts
+    /// when the ramp is finished. Otherwise the change is immediate.
+    /// Setting any other ratio, or scheduling a change, goes back to normal stepping.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the ratio is outside the range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// or outside the working range set by `set_working_ratio_range`.
+    /// Zero terms, or terms that are larger than `u32::MAX` after reducing the fraction,
+    /// are also rejected.
+    pub fn set_resample_ratio_rational(
+        &mut self,
+        num: u64,
+        den: u64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        let exact = ExactRatio::new(num, den).ok_or(ResampleError::BadRatioUpdate)?;
+        if ramp {
+            self.set_resample_ratio(exact.ratio())?;
+        } else {
+            self.set_resample_ratio_at(exact.ratio(), 0, false)?;
+        }
+        self.exact = Some(exact);
+        Ok(())
+    }
+
+    /// Set a ratio relative to the original one, either immediately or with a ramp.
+    fn set_ratio_relative_with_ramp(&mut self, rel_ratio: f64, ramp: bool) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        if ramp {
+            self.set_resample_ratio(new_ratio)
+        } else {
+            self.set_resample_ratio_at(new_ratio, 0, false)
+        }
+    }
+
+    /// Get the phase of the output frames relative to the input frames,
+    /// as a fraction of an output frame in the range [0, 1).
+    ///
+    /// Two resamplers with the same resample ratio and the same phase place their output
+    /// frames at the same positions relative to the input. A new resampler that joins
+    /// a stream late can be aligned with one that has processed the stream from the start,
+    /// by giving it the phase of the first one before processing the first input frame
+    /// they have in common. The output frames then match, apart from a whole number of frames.
+    /// A new or reset resampler has phase zero.
+    pub fn phase(&self) -> f64 {
+        output_phase(
+            self.last_index,
+            self.interpolator.center(),
+            self.ramp.current(),
+        )
+    }
+
+    /// Set the phase of the output frames relative to the input frames, see [phase](Self::phase).
+    /// Increasing the phase delays the output frames by that fraction of a frame.
+    /// The new phase is used from the next call to "process".
+    /// A [reset](Resampler::reset) goes back to phase zero.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `fraction` is not in the range [0, 1).
+    pub fn set_phase(&mut self, fraction: f64) -> ResampleResult<()> {
+        self.last_index = index_with_phase(
+            self.last_index,
+            self.interpolator.center(),
+            self.ramp.current(),
+            fraction,
+        )?;
+        self.needed_input_size = self.calc_needed_input_size();
+        Ok(())
+    }
+
+    /// Get the number of frames per channel that
+    /// [prime_with_history](Self::prime_with_history) uses, which is twice the sinc length.
+    pub fn history_frames_needed(&self) -> usize {
+        2 * self.interpolator.len()
+    }
+
+    /// Fill the filter history with frames that precede the next chunk,
+    /// for example the decoded frames before a seek point.
+    /// Without this, a new or reset resampler starts from silence,
+    /// and the first output frames hold the response of the filter to the start of the signal.
+    ///
+    /// The last [history_frames_needed](Self::history_frames_needed) frames of each channel
+    /// are used. The next output frame is placed exactly at the first frame of the next chunk,
+    /// so that the output is correct from the start.
+    /// The resample ratio and the other settings are kept.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the number of channels is wrong,
+    /// or if a channel holds fewer frames than needed.
+    pub fn prime_with_history<V: AsRef<[T]>>(&mut self, history: &[V]) -> ResampleResult<()> {
+        let sinc_len = self.interpolator.len();
+        fill_history(&mut self.buffer, history, sinc_len)?;
+        self.current_buffer_fill = 0;
+        self.last_index = index_after_history(self.interpolator.center(), self.ramp.current());
+        self.needed_input_size = self.calc_needed_input_size();
+        Ok(())
+    }
+
+    /// Get the number of output frames returned by each call to "process".
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the parameters the resampler was created with.
+    /// This is `None` for a resampler created with `new_with_interpolator`,
+    /// since the parameters used for the interpolator are not known.
+    pub fn interpolation_parameters(&self) -> Option<&InterpolationParameters> {
+        self.parameters.as_ref()
+    }
+
+    /// Get the name of the instruction set used by the interpolator,
+    /// see [SincInterpolator::kernel].
+    pub fn interpolator_kernel(&self) -> &'static str {
+        self.interpolator.kernel()
+    }
+
+    /// Save the processing state, so that it can be restored later with
+    /// [restore_state](SincFixedOut::restore_state).
+    /// Processing the same input after restoring gives the same output as when the state was saved.
+    pub fn save_state(&self) -> ResamplerState<T> {
+        ResamplerState {
+            resampler: "SincFixedOut",
+            nbr_channels: self.nbr_channels,
+            sinc_len: self.interpolator.len(),
+            chunk_size: self.chunk_size,
+            buffer: self.buffer.clone(),
+            last_index: self.last_index,
+            current_buffer_fill: self.current_buffer_fill,
+            needed_input_size: self.needed_input_size,
+            ramp: self.ramp.clone(),
+            channel_mask: self.channel_mask.clone(),
+            input_frames_total: self.input_frames_total,
+            output_frames_final: self.output_frames_final,
+            trim: self.trim,
+        }
+    }
+
+    /// Restore a processing state saved by [save_state](SincFixedOut::save_state).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the state was saved from a different type of resampler,
+    /// or from one with a different number of channels, sinc length or chunk size.
+    pub fn restore_state(&mut self, state: &ResamplerState<T>) -> ResampleResult<()> {
+        state.check_compatible(
+            "SincFixedOut",
+            self.nbr_channels,
+            self.interpolator.len(),
+            self.chunk_size,
+        )?;
+        self.buffer.clone_from(&state.buffer);
+        self.last_index = state.last_index;
+        self.current_buffer_fill = state.current_buffer_fill;
+        self.needed_input_size = state.needed_input_size;
+        self.ramp = state.ramp.clone();
+        self.channel_mask.copy_from_slice(&state.channel_mask);
+        self.input_frames_total = state.input_frames_total;
+        self.output_frames_final = state.output_frames_final;
+        self.trim = state.trim;
+        self.interpolator_previous = None;
+        Ok(())
+    }
+
+    /// Convert into a [SincFixedIn] that takes `chunk_size_in` frames for each call to
+    /// "process". The filter history, the resample ratio and any ongoing ramp are kept,
+    /// and the table of sincs is moved over instead of being computed again.
+    /// The output of the new resampler continues without interruption.
+    pub fn into_fixed_in(self, chunk_size_in: usize) -> SincFixedIn<T> {
+        let sinc_len = self.interpolator.len();
+        let mut buffer = self.buffer;
+        move_history_to_start(
+            &mut buffer,
+            self.current_buffer_fill,
+            sinc_len,
+            chunk_size_in + 2 * sinc_len,
+        );
+        SincFixedIn {
+            nbr_channels: self.nbr_channels,
+            chunk_size: chunk_size_in,
+            last_index: self.last_index,
+            current_buffer_fill: 0,
+            resample_ratio_original: self.resample_ratio_original,
+            max_resample_ratio_relative: self.max_resample_ratio_relative,
+            working_ratio_range: self.working_ratio_range,
+            ramp: self.ramp,
+            exact: self.exact,
+            interpolator: self.interpolator,
+            interpolator_low: self.interpolator_low,
+            interpolator_spare: self.interpolator_spare,
+            interpolator_previous: self.interpolator_previous,
+            adaptive_cutoff: self.adaptive_cutoff,
+            buffer,
+            interpolation: self.interpolation,
+            parameters: self.parameters,
+            channel_mask: self.channel_mask,
+            active_channels_mask: self.active_channels_mask,
+            inactive_output: self.inactive_output,
+            validate_input: self.validate_input,
+            flush_denormals: self.flush_denormals,
+            trim: self.trim,
+            control: self.control,
+            input_frames_total: self.input_frames_total,
+            output_frames_final: self.output_frames_final,
+        }
+    }
+
+    /// Split into one single channel resampler per channel.
+    ///
+    /// Each new resampler gets the filter history of its channel, and the common
+    /// resample ratio, ramp and position between input frames, so that together they
+    /// produce the same output as this resampler would have.
+    /// They share the table of sincs instead of each getting a copy,
+    /// and can be used from different threads.
+    /// The handles from [ratio_control](SincFixedOut::ratio_control) don't control the new resamplers.
+    pub fn into_mono_resamplers(mut self) -> Vec<SincFixedOut<T>> {
+        let buffers = std::mem::take(&mut self.buffer);
+        buffers
+            .into_iter()
+            .enumerate()
+            .map(|(chan, buffer)| SincFixedOut {
+                nbr_channels: 1,
+                chunk_size: self.chunk_size,
+                needed_input_size: self.needed_input_size,
+                last_index: self.last_index,
+                current_buffer_fill: self.current_buffer_fill,
+                resample_ratio_original: self.resample_ratio_original,
+                max_resample_ratio_relative: self.max_resample_ratio_relative,
+                working_ratio_range: self.working_ratio_range,
+                ramp: self.ramp.clone(),
+                exact: self.exact,
+                interpolator: self.interpolator.clone(),
+                interpolator_low: self.interpolator_low.clone(),
+                interpolator_spare: self.interpolator_spare.clone(),
+                interpolator_previous: self.interpolator_previous.clone(),
+                adaptive_cutoff: self.adaptive_cutoff,
+                buffer: vec![buffer],
+                interpolation: self.interpolation,
+                parameters: self.parameters,
+                channel_mask: vec![self.channel_mask[chan]],
+                active_channels_mask: vec![self.active_channels_mask[chan]],
+                inactive_output: self.inactive_output,
+                validate_input: self.validate_input,
+                flush_denormals: self.flush_denormals,
+                trim: self.trim,
+                control: RatioReceiver::new(),
+                input_frames_total: self.input_frames_total,
+                output_frames_final: self.output_frames_final,
+            })
+            .collect()
+    }
+
+    /// Combine single channel resamplers into one multichannel resampler,
+    /// with one channel per resampler, in the given order.
+    /// This is the reverse of [into_mono_resamplers](SincFixedOut::into_mono_resamplers).
+    /// The settings and the handles from [ratio_control](SincFixedOut::ratio_control)
+    /// are taken from the first resampler.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there are no resamplers, if one has more than one channel,
+    /// or if they don't share the same table of sincs and the same chunk size,
+    /// resample ratio and processing state.
+    pub fn from_mono_resamplers(resamplers: Vec<SincFixedOut<T>>) -> ResampleResult<Self> {
+        let mut resamplers = resamplers.into_iter();
+        let mut resampler = resamplers
+            .next()
+            .ok_or(ResampleError::BadNbrChannelsUpdate)?;
+        resampler.check_combinable(&resampler)?;
+        for mut other in resamplers {
+            resampler.check_combinable(&other)?;
+            resampler.buffer.append(&mut other.buffer);
+            resampler.channel_mask.push(other.channel_mask[0]);
+            resampler
+                .active_channels_mask
+                .push(other.active_channels_mask[0]);
+        }
+        let buffer_len = resampler.buffer.iter().map(|wav| wav.len()).max();
+        for wav in resampler.buffer.iter_mut() {
+            wav.resize(buffer_len.unwrap_or(0), T::zero());
+        }
+        resampler.nbr_channels = resampler.buffer.len();
+        Ok(resampler)
+    }
+
+    /// Check that a single channel resampler can be combined with this one.
+    fn check_combinable(&self, other: &Self) -> ResampleResult<()> {
+        let parameter = if other.nbr_channels != 1 {
+            "number of channels"
+        } else if !Arc::ptr_eq(&other.interpolator, &self.interpolator) {
+            "table of sincs"
+        } else if other.chunk_size != self.chunk_size {
+            "chunk size"
+        } else if other.resample_ratio_original != self.resample_ratio_original
+            || other.ramp.target != self.ramp.target
+        {
+            "resample ratio"
+        } else if other.last_index != self.last_index
+            || other.current_buffer_fill != self.current_buffer_fill
+            || other.needed_input_size != self.needed_input_size
+            || other.input_frames_total != self.input_frames_total
+        {
+            "processing state"
+        } else {
+            return Ok(());
+        };
+        Err(ResampleError::IncompatibleResamplers { parameter })
+    }
+
+    /// Change the number of output frames returned by the next call to "process".
+    /// The filter history is kept, so the output continues without interruption.
+    /// The number of input frames needed for the next call is updated immediately.
+    /// The internal buffers are reallocated if they are too small for the new chunk size.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new chunk size is zero.
+    pub fn set_output_chunk_size(&mut self, chunk_size: usize) -> ResampleResult<()> {
+        if chunk_size == 0 {
+            return Err(ResampleError::BadChunkSizeUpdate);
+        }
+        trace!("Change output chunk size to {}", chunk_size);
+        self.chunk_size = chunk_size;
+        self.needed_input_size = self.calc_needed_input_size();
+        let sinc_len = self.interpolator.len();
+        for wav in self.buffer.iter_mut() {
+            if wav.len() < self.needed_input_size + 2 * sinc_len {
+                wav.resize(3 * self.needed_input_size / 2 + 2 * sinc_len, T::zero());
+            }
+        }
+        Ok(())
+    }
+
+    /// Calculate the number of input frames needed to produce the next chunk of output.
+    /// While the ratio is changing, the steps of the coming output frames are summed up.
+    fn calc_needed_input_size(&self) -> usize {
+        let chunk_frames_in = if self.ramp.is_changing() {
+            let mut ramp = self.ramp.clone();
+            (0..self.chunk_size).map(|_| ramp.next_step()).sum::<f64>() as f32
+        } else {
+            self.chunk_size as f32 / self.ramp.target as f32
+        };
+        self.input_size_for(chunk_frames_in)
+    }
+
+    /// Calculate the number of input frames needed for a chunk that advances
+    /// by `chunk_frames_in` input frames.
+    fn input_size_for(&self, chunk_frames_in: f32) -> usize {
+        (self.last_index as f32 + chunk_frames_in + self.interpolator.len() as f32).ceil() as usize
+            + 2
+    }
+
+    /// Check that a curve of relative ratios covers one chunk of output,
+    /// and that all its ratios are allowed.
+    fn validate_ratio_curve(&self, relative_ratios: &[f64]) -> ResampleResult<()> {
+        if relative_ratios.len() != self.chunk_size {
+            return Err(ResampleError::WrongRatioCurveLength {
+                expected: self.chunk_size,
+                actual: relative_ratios.len(),
+            });
+        }
+        if let Some(rel_ratio) = relative_ratios
+            .iter()
+            .find(|rel_ratio| !self.ratio_allowed(self.resample_ratio_original * **rel_ratio))
+        {
+            let (min, max) = self.working_ratio_range();
+            return Err(ResampleError::RatioOutOfBounds {
+                value: *rel_ratio,
+                min: min / self.resample_ratio_original,
+                max: max / self.resample_ratio_original,
+            });
+        }
+        Ok(())
+    }
+
+    /// Get the number of input frames needed by
+    /// [process_with_ratio_curve](SincFixedOut::process_with_ratio_curve)
+    /// for a curve of relative ratios.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error for the same reasons as
+    /// [process_with_ratio_curve](SincFixedOut::process_with_ratio_curve) does for the curve.
+    pub fn nbr_frames_needed_for_ratio_curve(
+        &self,
+        relative_ratios: &[f64],
+    ) -> ResampleResult<usize> {
+        self.validate_ratio_curve(relative_ratios)?;
+        let chunk_frames_in = relative_ratios
+            .iter()
+            .map(|rel_ratio| 1.0 / (self.resample_ratio_original * rel_ratio))
+            .sum::<f64>() as f32;
+        Ok(self.input_size_for(chunk_frames_in))
+    }
+
+    /// Resample a chunk of audio, with a resample ratio that changes for every output frame.
+    ///
+    /// The ratio of each output frame is given by `relative_ratio_per_output_frame`,
+    /// relative to the original ratio, which must hold one value per output frame of the chunk.
+    /// This follows a modulation like vibrato without the steps of changing the ratio
+    /// once per chunk. The input must hold the number of frames given by
+    /// [nbr_frames_needed_for_ratio_curve](SincFixedOut::nbr_frames_needed_for_ratio_curve)
+    /// for the same curve.
+    /// Any ramp in progress is abandoned, and the last ratio of the curve is kept
+    /// for the following calls to "process".
+    /// A scheduled ratio change that falls within the chunk is applied after it.
+    ///
+    /// # Errors
+    ///
+    /// The function returns [ResampleError::WrongRatioCurveLength] if the curve doesn't hold
+    /// one value per output frame, and [ResampleError::RatioOutOfBounds] if a value
+    /// is outside the allowed range of relative ratios.
+    /// Otherwise it returns an error for the same reasons as
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    pub fn process_with_ratio_curve<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [U],
+        relative_ratio_per_output_frame: &[f64],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed_for_ratio_curve(relative_ratio_per_output_frame)?;
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential(
+            wave_in,
+            0,
+            wave_out,
+            0,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let needed_input_size = std::mem::replace(&mut self.needed_input_size, frames_in);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let result = self.process_buffers_with_curve(
+            &SequentialInput::new(wave_in, 0, frames_in),
+            &mut output,
+            Some(relative_ratio_per_output_frame),
+        );
+        if result.is_err() {
+            self.needed_input_size = needed_input_size;
+        }
+        let (frames_in, frames_out) = result?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample one chunk from `wave_in` into `wave_out`, for the channels marked as active
+    /// in the channel mask. Returns the number of input and output frames.
+    fn process_buffers<I, O>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut O,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        self.process_buffers_with_curve(wave_in, wave_out, None)
+    }
+
+    /// Resample one chunk like [process_buffers](SincFixedOut::process_buffers),
+    /// with the ratio of each output frame taken from `ratio_curve` if it is given,
+    /// and from the ramp otherwise.
+    fn process_buffers_with_curve<I, O>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut O,
+        ratio_curve: Option<&[f64]>,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        if self.validate_input {
+            validate_finite(wave_in, &self.channel_mask)?;
+        }
+        let _denormals = DenormalGuard::new(self.flush_denormals);
+        let mut wave_out = TrimmedOutput::new(wave_out, self.trim.remaining());
+        let sinc_len = self.interpolator.len();
+        let oversampling_factor = self.interpolator.nbr_sincs();
+        let min_ratio = match ratio_curve {
+            Some(curve) => {
+                self.resample_ratio_original
+                    * curve.iter().fold(std::f64::INFINITY, |a, b| a.min(*b))
+            }
+            None => self.ramp.min_ratio(),
+        };
+        let interpolator = select_interpolator(
+            &self.interpolator,
+            &self.interpolator_low,
+            self.resample_ratio_original,
+            min_ratio,
+        );
+        for wav in self.buffer.iter_mut() {
+            for idx in 0..(2 * sinc_len) {
+                wav[idx] = wav[idx + self.current_buffer_fill];
+            }
+        }
+        self.current_buffer_fill = self.needed_input_size;
+
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_in.read_into(
+                    chan,
+                    0,
+                    &mut self.buffer[chan][2 * sinc_len..2 * sinc_len + self.needed_input_size],
+                );
+            }
+        }
+
+        let mut idx = self.last_index;
+        let previous = self.interpolator_previous.take();
+
+        match self.interpolation {
+            InterpolationType::Cubic => {
+                let mut points = [T::zero(); 4];
+                let mut nearest = [(0isize, 0isize); 4];
+                for n in 0..self.chunk_size {
+                    let step =
+                        next_step(&mut self.ramp, ratio_curve, self.resample_ratio_original, n);
+                    idx = advance_index(idx, step, &self.ramp, &mut self.exact);
+                    let fade = crossfade_weight(
+                        &previous,
+                        (self.chunk_size - n - 1) as f64,
+                        self.chunk_size as f64,
+                    );
+                    get_nearest_times_4(idx, oversampling_factor as isize, &mut nearest);
+                    let frac = idx * oversampling_factor as f64
+                        - (idx * oversampling_factor as f64).floor();
+                    let frac_offset = T::coerce(frac);
+                    for (chan, _) in self
+                        .channel_mask
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, active)| **active)
+                    {
+                        let buf = &self.buffer[chan];
+                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                            *p = sinc_point(
+                                interpolator,
+                                &previous,
+                                fade,
+                                buf,
+                                (n.0 + 2 * sinc_len as isize) as usize,
+                                n.1 as usize,
+                            );
+                        }
+                        wave_out.write(chan, n, interp_cubic(frac_offset, &points));
+                    }
+                }
+            }
+            InterpolationType::Linear => {
+                let mut points = [T::zero(); 2];
+                let mut nearest = [(0isize, 0isize); 2];
+                for n in 0..self.chunk_size {
+                    let step =
+                        next_step(&mut self.ramp, ratio_curve, self.resample_ratio_original, n);
+                    idx = advance_index(idx, step, &self.ramp, &mut self.exact);
+                    let fade = crossfade_weight(
+                        &previous,
+                        (self.chunk_size - n - 1) as f64,
+                        self.chunk_size as f64,
+                    );
+                    get_nearest_times_2(idx, oversampling_factor as isize, &mut nearest);
+                    let frac = idx * oversampling_factor as f64
+                        - (idx * oversampling_factor as f64).floor();
+                    let frac_offset = T::coerce(frac);
+                    for (chan, _) in self
+                        .channel_mask
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, active)| **active)
+                    {
+                        let buf = &self.buffer[chan];
+                        for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                            *p = sinc_point(
+                                interpolator,
+                                &previous,
+                                fade,
+                                buf,
+                                (n.0 + 2 * sinc_len as isize) as usize,
+                                n.1 as usize,
+                            );
+                        }
+                        wave_out.write(chan, n, interp_lin(frac_offset, &points));
+                    }
+                }
+            }
+            InterpolationType::Nearest => {
+                let mut point;
+                let mut nearest;
+                for n in 0..self.chunk_size {
+                    let step =
+                        next_step(&mut self.ramp, ratio_curve, self.resample_ratio_original, n);
+                    idx = advance_index(idx, step, &self.ramp, &mut self.exact);
+                    let fade = crossfade_weight(
+                        &previous,
+                        (self.chunk_size - n - 1) as f64,
+                        self.chunk_size as f64,
+                    );
+                    nearest = get_nearest_time(idx, oversampling_factor as isize);
+                    for (chan, _) in self
+                        .channel_mask
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, active)| **active)
+                    {
+                        let buf = &self.buffer[chan];
+                        point = sinc_point(
+                            interpolator,
+                            &previous,
+                            fade,
+                            buf,
+                            (nearest.0 + 2 * sinc_len as isize) as usize,
+                            nearest.1 as usize,
+                        );
+                        wave_out.write(chan, n, point);
+                    }
+                }
+            }
+        }
+
+        let prev_input_len = self.needed_input_size;
+        // store last index for next iteration
+        self.last_index = shift_index(idx, self.current_buffer_fill, &mut self.exact);
+        self.apply_ratio_control();
+        self.needed_input_size = self.calc_needed_input_size();
+        trace!(
+            "Resampling channels {:?}, {} frames in, {} frames out. Next needed length: {} frames, last index {}",
+            self.channel_mask,
+            prev_input_len,
+            self.chunk_size,
+            self.needed_input_size,
+            self.last_index
+        );
+        self.input_frames_total += prev_input_len as u64;
+        Ok((prev_input_len, self.trim.consume(self.chunk_size)))
+    }
+}
+
+impl<T> fmt::Debug for SincFixedOut<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SincFixedOut")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size", &self.chunk_size)
+            .field("resample_ratio", &self.ramp.target)
+            .field("resample_ratio_original", &self.resample_ratio_original)
+            .field("interpolation", &self.interpolation)
+            .field("sinc_len", &self.interpolator.len())
+            .field("oversampling_factor", &self.interpolator.nbr_sincs())
+            .field("kernel", &self.interpolator.kernel())
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for SincFixedOut<T>
+where
+    T: Sample,
+{
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize {
+        self.needed_input_size
+    }
+
+    /// Get the maximum number of input frames, needed at the lowest ratio of the working range.
+    fn input_frames_max(&self) -> usize {
+        let lowest_allowed = self.working_ratio_range().0;
+        (self.chunk_size as f64 / lowest_allowed + self.interpolator.len() as f64).ceil() as usize
+            + 2
+    }
+
+    /// Get the maximum number of output frames, which is always the chunk size.
+    fn output_frames_max(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Reset the resampler state, and go back to the original resample ratio.
+    fn reset(&mut self) {
+        for wav in self.buffer.iter_mut() {
+            wav.iter_mut().for_each(|x| *x = T::zero());
+        }
+        self.ramp.reset(self.resample_ratio_original);
+        self.last_index = -(self.interpolator.center() as f64);
+        self.needed_input_size = self.calc_needed_input_size();
+        self.current_buffer_fill = self.needed_input_size;
+        self.input_frames_total = 0;
+        self.output_frames_final = None;
+        self.interpolator_previous = None;
+        let delay = sinc_output_delay(self.ramp.target, self.interpolator.nbr_sincs());
+        self.trim.restart(delay);
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        if channel >= self.nbr_channels {
+            return Err(ResampleError::InvalidChannel {
+                channel,
+                nbr_channels: self.nbr_channels,
+            });
+        }
+        self.buffer[channel].iter_mut().for_each(|x| *x = T::zero());
+        Ok(())
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        if nbr_channels == 0 {
+            return Err(ResampleError::BadNbrChannelsUpdate);
+        }
+        trace!("Change number of channels to {}", nbr_channels);
+        let buffer_len = 3 * self.needed_input_size / 2 + 2 * self.interpolator.len();
+        resize_channels(&mut self.buffer, nbr_channels, buffer_len);
+        resize_mask(&mut self.channel_mask, nbr_channels);
+        resize_mask(&mut self.active_channels_mask, nbr_channels);
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    /// Get the current resample ratio.
+    fn resample_ratio(&self) -> f64 {
+        self.ramp.target
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
+
+    // The ramp counts output frames for scheduling ratio changes.
+    fn output_frames_total(&self) -> u64 {
+        self.ramp.frame - self.trim.skipped() as u64
+    }
+
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        store_mask(&mut self.active_channels_mask, mask)
+    }
+
+    fn active_channels_mask(&self) -> &[bool] {
+        &self.active_channels_mask
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        self.inactive_output = inactive_output;
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        self.validate_input = enabled;
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        let started = self.input_frames_total > 0;
+        let delay = sinc_output_delay(self.ramp.target, self.interpolator.nbr_sincs());
+        self.trim.set_enabled(enabled, delay, started);
+    }
+
+    /// Resample a chunk of audio. The required input length is provided by
+    /// the "nbr_frames_needed" function, and the output length is fixed.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the length of the input data is not
+    /// equal to the number of channels defined when creating the instance,
+    /// and the number of audio frames given by "nbr_frames_needed".
+    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
+        let buffer = &mut self.buffer;
+        update_mask_from_buffers(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            wave_in,
+            self.needed_input_size,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan] = vec![T::zero(); self.chunk_size];
+            }
+        }
+        let (_, frames_out) = self.process_buffers(
+            &SequentialInput::new(wave_in, 0, self.needed_input_size),
+            &mut SequentialOutput::new(&mut wave_out, 0),
+        )?;
+        for chan in wave_out.iter_mut() {
+            chan.truncate(frames_out);
+        }
+        Ok(wave_out)
+    }
+
+    /// Resample a chunk of interleaved audio. The required input length is provided by
+    /// the "nbr_frames_needed" function, and the output length is fixed.
+    /// # Errors
+    ///
+    /// The function returns an error if the input doesn't contain the number of frames
+    /// given by "nbr_frames_needed", if the output buffer is shorter than `chunk_size` frames,
+    /// or if the mask doesn't match the number of channels.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        validate_interleaved(
+            wave_in,
+            wave_out,
+            self.nbr_channels,
+            self.needed_input_size,
+            self.chunk_size,
+        )?;
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let (frames_in, frames_out) = self.process_buffers(
+            &InterleavedInput::new(wave_in, self.nbr_channels, self.needed_input_size),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// `input_offset` plus "nbr_frames_needed" frames, if an active output channel holds fewer
+    /// than `output_offset` plus "output_frames_max" frames, or if the number of channels
+    /// of the buffers or the mask is wrong.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential(
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = SequentialOutput::new(wave_out, output_offset);
+        let (frames_in, frames_out) = self.process_buffers(
+            &SequentialInput::new(wave_in, input_offset, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers,
+    /// and write the result to an interleaved output buffer.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// "nbr_frames_needed" frames, if the output buffer is shorter than "output_frames_max" frames,
+    /// or if the number of channels of the input or the mask is wrong.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential_to_interleaved(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let (frames_in, frames_out) =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the last frames of a stream, when fewer frames than needed remain.
+    /// The missing input frames are replaced by zeros, and the input may also be `None`,
+    /// which processes only zeros.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds more than
+    /// "nbr_frames_needed" frames, if the active input channels have different lengths,
+    /// if an active output channel holds fewer than "output_frames_max" frames,
+    /// or if the number of channels of the buffers or the mask is wrong.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_needed = self.nbr_frames_needed();
+        let buffer = &mut self.buffer;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_in = validate_partial(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_needed,
+            self.output_frames_max(),
+        )?;
+        let wave_in = wave_in.unwrap_or(&[]);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let (_, frames_out) =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    /// Only the output frames that correspond to real input are counted,
+    /// and zero frames are returned once the stream has been flushed.
+    /// # Errors
+    ///
+    /// The function returns an error under the same conditions as "process_partial_into_buffer".
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mut output_frames_final = self.output_frames_final;
+        let result = process_final(
+            self,
+            &mut output_frames_final,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+        );
+        self.output_frames_final = output_frames_final;
+        result
+    }
+
+    /// Update the resample ratio. The new value must be within the range allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative),
+    /// and within the working range if one has been set.
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        trace!("Change resample ratio to {}", new_ratio);
+        if self.ratio_allowed(new_ratio) {
+            self.ramp.set_target(new_ratio);
+            self.needed_input_size = self.calc_needed_input_size();
+            Ok(())
+        } else {
+            Err(ResampleError::BadRatioUpdate)
+        }
+    }
+
+    /// Update the resample ratio relative to the original one
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        let new_ratio = self.resample_ratio_original * rel_ratio;
+        self.set_resample_ratio(new_ratio)
+    }
+
+    fn max_resample_ratio_relative(&self) -> f64 {
+        self.max_resample_ratio_relative
+    }
+
+    /// Update the range of allowed resample ratios.
+    /// The internal buffers are reallocated if they are too small for the input
+    /// needed at the lowest allowed ratio.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()> {
+        let range_allowed = self.working_ratio_range.map_or(true, |(min, max)| {
+            ratio_within_bounds(min, self.resample_ratio_original, max_relative)
+                && ratio_within_bounds(max, self.resample_ratio_original, max_relative)
+        });
+        if !range_allowed
+            || !self
+                .ramp
+                .within_bounds(self.resample_ratio_original, max_relative)
+        {
+            return Err(ResampleError::BadMaxRatioUpdate);
+        }
+        trace!("Change max relative resample ratio to {}", max_relative);
+        self.max_resample_ratio_relative = max_relative;
+        if self.adaptive_cutoff {
+            self.set_adaptive_cutoff(true)?;
+        }
+        let sinc_len = self.interpolator.len();
+        let min_ratio = self.resample_ratio_original / max_relative;
+        let needed_input_size = (self.chunk_size as f64 / min_ratio).ceil() as usize + 2 + sinc_len
+            - self.interpolator.center();
+        for wav in self.buffer.iter_mut() {
+            if wav.len() < needed_input_size + 2 * sinc_len {
+                wav.resize(3 * needed_input_size / 2 + 2 * sinc_len, T::zero());
+            }
+        }
+        Ok(())
+    }
+
+    /// Update the resample ratio from a pair of sample rates.
+    /// Without `ramp`, the new ratio is used from the next output frame.
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()> {
+        if ramp {
+            self.set_resample_ratio(fs_out / fs_in)
+        } else {
+            self.set_resample_ratio_at(fs_out / fs_in, 0, false)
+        }
+    }
+
+    fn ramp_progress(&self) -> Option<f64> {
+        self.ramp.progress()
+    }
+
+    /// Get the delay in output frames for the current resample ratio.
+    fn output_delay_fractional(&self) -> f64 {
+        sinc_output_delay(self.ramp.target, self.interpolator.nbr_sincs())
+            - self.trim.frames() as f64
+    }
+
+    /// The response is that of the sinc filter for the current resample ratio,
+    /// without the interpolation between the oversampled points.
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        let response = self.filter_response();
+        frequency_response(self.ramp.target, n_points, |freq| response.magnitude(freq))
+    }
+
+    fn group_delay(&self) -> f64 {
+        self.filter_response().delay()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{interp_cubic, interp_lin, RampType, RatioRamp, ScheduledChange};
+    use crate::asynchro::SincInterpolator;
+    use crate::asynchro::{OnTheFlyInterpolator, ScalarInterpolator};
+    use crate::interpolation::{get_nearest_time, get_nearest_times_2, get_nearest_times_4};
+    #[cfg(all(target_arch = "x86_64", feature = "avx"))]
+    use crate::interpolator_avx::AvxInterpolator;
+    #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+    use crate::interpolator_neon::NeonInterpolator;
+    #[cfg(target_arch = "x86_64")]
+    use crate::interpolator_sse::SseInterpolator;
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    use crate::interpolator_wasm::WasmInterpolator;
+    use crate::sinc::sinc;
+    use crate::sinc::{make_sincs, mirrored_subindex};
+    use crate::windows::make_window;
+    use crate::InterpolationParameters;
+    use crate::InterpolationType;
     use crate::Resampler;
+    use crate::Sample;
     use crate::WindowFunction;
-    use crate::{SincFixedIn, SincFixedOut};
+    use crate::{Accumulation, FilterPhase, SincStorage};
+    use crate::{RatioControl, SincFixedIn, SincFixedOut};
+    use crate::{ResampleError, ResampleResult, ResamplerConstructionError, ResamplerState};
     use num_traits::Float;
     use rand::Rng;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
+        let wave_cut = &wave[index..(index + sinc.len())];
+        wave_cut
+            .iter()
+            .zip(sinc.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+    }
+
+    #[test]
+    fn test_scalar_interpolator_64() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f64>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+
+        let interpolator =
+            ScalarInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
+        assert!((value - check).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn test_scalar_interpolator_32() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f32>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+
+        let interpolator =
+            ScalarInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window);
+        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
+        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
+        assert!((value - check).abs() < 1.0e-6);
+    }
+
+    #[test]
+    fn test_scalar_interpolator_32_wide() {
+        let mut rng = rand::thread_rng();
+        let wave: Vec<f32> = (0..2048).map(|_| rng.gen::<f32>()).collect();
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+
+        let scalar =
+            ScalarInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window)
+                .with_accumulation(Accumulation::Double);
+        let on_the_fly =
+            OnTheFlyInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window)
+                .with_accumulation(Accumulation::Double);
+        // Both the stored and the mirrored sincs, compared with a sum in f64.
+        for subindex in [10, 123, 200].iter() {
+            let check = wave[333..333 + sinc_len]
+                .iter()
+                .zip(sincs[*subindex].iter())
+                .fold(0.0, |acc, (x, y)| acc + *x as f64 * *y as f64);
+            let value = scalar.get_sinc_interpolated(&wave, 333, *subindex);
+            assert!((value as f64 - check).abs() < 1.0e-6);
+            let value = on_the_fly.get_sinc_interpolated(&wave, 333, *subindex);
+            assert!((value as f64 - check).abs() < 1.0e-6);
+        }
+    }
+
+    #[test]
+    fn int_cubic() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
+        let yvals = [0.0f64, 2.0f64, 4.0f64, 6.0f64];
+        let interp = interp_cubic(0.5f64, &yvals);
+        assert_eq!(interp, 3.0f64);
+    }
+
+    #[test]
+    fn int_lin_32() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
+        let yvals = [1.0f32, 5.0f32];
+        let interp = interp_lin(0.25f32, &yvals);
+        assert_eq!(interp, 2.0f32);
+    }
+
+    #[test]
+    fn int_cubic_32() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
+        let yvals = [0.0f32, 2.0f32, 4.0f32, 6.0f32];
+        let interp = interp_cubic(0.5f32, &yvals);
+        assert_eq!(interp, 3.0f32);
+    }
+
+    #[test]
+    fn int_lin() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
+        let yvals = [1.0f64, 5.0f64];
+        let interp = interp_lin(0.25f64, &yvals);
+        assert_eq!(interp, 2.0f64);
+    }
+
+    #[test]
+    fn make_resampler_fi() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 1150 && out[0].len() < 1229,
+            "expected {} - {} samples, got {}",
+            1150,
+            1229,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 1226 && out2[0].len() < 1232,
+            "expected {} - {} samples, got {}",
+            1226,
+            1232,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_32() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
+        let waves = vec![vec![0.0f32; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 1150 && out[0].len() < 1229,
+            "expected {} - {} samples, got {}",
+            1150,
+            1229,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 1226 && out2[0].len() < 1232,
+            "expected {} - {} samples, got {}",
+            1226,
+            1232,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_skipped() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
+        let waves = vec![vec![0.0f64; 1024], Vec::new()];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert!(out[0].len() > 1150 && out[0].len() < 1250);
+        assert!(out[1].is_empty());
+        let waves = vec![Vec::new(), vec![0.0f64; 1024]];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert!(out[1].len() > 1150 && out[0].len() < 1250);
+        assert!(out[0].is_empty());
+    }
+
+    #[test]
+    fn make_resampler_fi_downsample() {
+        // Replicate settings from reported issue
+        let params = InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(16000 as f64 / 96000 as f64, params, 1024, 2);
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 140 && out[0].len() < 200,
+            "expected {} - {} samples, got {}",
+            140,
+            200,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 167 && out2[0].len() < 173,
+            "expected {} - {} samples, got {}",
+            167,
+            173,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fi_upsample() {
+        // Replicate settings from reported issue
+        let params = InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(192000 as f64 / 44100 as f64, params, 1024, 2);
+        let waves = vec![vec![0.0f64; 1024]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert!(
+            out[0].len() > 3800 && out[0].len() < 4458,
+            "expected {} - {} samples, got {}",
+            3800,
+            4458,
+            out[0].len()
+        );
+        let out2 = resampler.process(&waves).unwrap();
+        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
+        assert!(
+            out2[0].len() > 4455 && out2[0].len() < 4461,
+            "expected {} - {} samples, got {}",
+            4455,
+            4461,
+            out2[0].len()
+        );
+    }
+
+    #[test]
+    fn make_resampler_fo() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2);
+        let frames = resampler.nbr_frames_needed();
+        println!("{}", frames);
+        assert!(frames > 800 && frames < 900);
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].len(), 1024);
+    }
+
+    #[test]
+    fn make_resampler_fo_32() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f32>::new(1.2, params, 1024, 2);
+        let frames = resampler.nbr_frames_needed();
+        println!("{}", frames);
+        assert!(frames > 800 && frames < 900);
+        let waves = vec![vec![0.0f32; frames]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].len(), 1024);
+    }
+
+    #[test]
+    fn make_resampler_fo_skipped() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 16,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2);
+        let frames = resampler.nbr_frames_needed();
+        println!("{}", frames);
+        assert!(frames > 800 && frames < 900);
+        let mut waves = vec![vec![0.0f64; frames], Vec::new()];
+        waves[0][100] = 3.0;
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[0].len(), 1024);
+        assert!(out[1].is_empty());
+        println!("{:?}", out[0]);
+        let summed = out[0].iter().sum::<f64>();
+        println!("sum: {}", summed);
+        assert!(summed < 4.0);
+        assert!(summed > 2.0);
+
+        let frames = resampler.nbr_frames_needed();
+        let mut waves = vec![Vec::new(), vec![0.0f64; frames]];
+        waves[1][10] = 3.0;
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2);
+        assert_eq!(out[1].len(), 1024);
+        assert!(out[0].is_empty());
+        let summed = out[1].iter().sum::<f64>();
+        assert!(summed < 4.0);
+        assert!(summed > 2.0);
+    }
+
+    #[test]
+    fn make_resampler_fo_downsample() {
+        let params = InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(0.125, params, 1024, 2);
+        let frames = resampler.nbr_frames_needed();
+        println!("{}", frames);
+        assert!(
+            frames > 8192 && frames < 9000,
+            "expected {}..{} samples, got {}",
+            8192,
+            9000,
+            frames
+        );
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert_eq!(
+            out[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out[0].len()
+        );
+        let frames2 = resampler.nbr_frames_needed();
+        assert!(
+            frames2 > 8189 && frames2 < 8195,
+            "expected {}..{} samples, got {}",
+            8189,
+            8195,
+            frames2
+        );
+        let waves2 = vec![vec![0.0f64; frames2]; 2];
+        let out2 = resampler.process(&waves2).unwrap();
+        assert_eq!(
+            out2[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out2[0].len()
+        );
+    }
+
+    fn impulse_peak_position<R: Resampler<f64>>(resampler: &mut R, impulse_pos: usize) -> usize {
+        let mut output = Vec::new();
+        let mut frames_fed = 0;
+        while frames_fed < impulse_pos + 2048 {
+            let frames = resampler.nbr_frames_needed();
+            let mut waves = vec![vec![0.0f64; frames]];
+            if impulse_pos >= frames_fed && impulse_pos < frames_fed + frames {
+                waves[0][impulse_pos - frames_fed] = 1.0;
+            }
+            frames_fed += frames;
+            output.extend_from_slice(&resampler.process(&waves).unwrap()[0]);
+        }
+        let mut peak = 0;
+        for (n, value) in output.iter().enumerate() {
+            if value.abs() > output[peak].abs() {
+                peak = n;
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn output_delay() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(2.0, params, 256, 1);
+        assert_eq!(resampler.output_delay(), 1);
+        let peak = impulse_peak_position(&mut resampler, 300);
+        assert_eq!(peak, 600 + resampler.output_delay());
+
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.0, params, 256, 1);
+        assert_eq!(resampler.output_delay(), 0);
+        let peak = impulse_peak_position(&mut resampler, 300);
+        assert_eq!(peak, 300);
 
-    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
-        let wave_cut = &wave[index..(index + sinc.len())];
-        wave_cut
-            .iter()
-            .zip(sinc.iter())
-            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+        resampler.set_resample_ratio(1.09).unwrap();
+        let delay = resampler.output_delay_fractional();
+        assert!((delay - (1.09 * (1.0 - 1.0 / 256.0) - 1.0)).abs() < 1.0e-9);
+    }
+
+    fn process_sine_in_chunks<R, F>(
+        resampler: &mut R,
+        chunk_sizes: &[usize],
+        set_size: F,
+    ) -> Vec<f64>
+    where
+        R: Resampler<f64>,
+        F: Fn(&mut R, usize),
+    {
+        let mut position = 0;
+        let mut output = Vec::new();
+        for chunk_size in chunk_sizes {
+            set_size(resampler, *chunk_size);
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (0.01 * n as f64).sin())
+                .collect();
+            position += frames;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+        output
+    }
+
+    fn assert_streams_equal(expected: &[f64], actual: &[f64]) {
+        let len = expected.len().min(actual.len());
+        assert!(len > 4000);
+        for (n, (e, a)) in expected.iter().zip(actual.iter()).take(len).enumerate() {
+            assert!((e - a).abs() < 1.0e-6, "frame {}: {} != {}", n, e, a);
+        }
     }
 
     #[test]
-    fn test_scalar_interpolator_64() {
-        let mut rng = rand::thread_rng();
-        let mut wave = Vec::new();
-        for _ in 0..2048 {
-            wave.push(rng.gen::<f64>());
+    fn ratio_ramp() {
+        let mut ramp = RatioRamp::new(1.0);
+        ramp.set_duration(100);
+        ramp.set_target(1.1);
+        assert!((ramp.ratio_at(50) - 1.05).abs() < 1.0e-12);
+        assert_eq!(ramp.ratio_at(100), 1.1);
+        for _ in 0..49 {
+            ramp.next_step();
         }
-        let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
-        let oversampling_factor = 256;
-        let window = WindowFunction::BlackmanHarris2;
+        assert!((ramp.next_step() - 1.0 / 1.05).abs() < 1.0e-12);
+        assert!(ramp.is_changing());
+        // a new target starts a new ramp from the current ratio
+        ramp.set_target(0.9);
+        assert!((ramp.current() - 1.05).abs() < 1.0e-12);
+        assert!((ramp.ratio_at(50) - 0.975).abs() < 1.0e-12);
+        for _ in 0..150 {
+            ramp.next_step();
+        }
+        assert!(!ramp.is_changing());
+        assert_eq!(ramp.current(), 0.9);
+        assert_eq!(ramp.frame, 200);
+    }
 
-        let interpolator =
-            ScalarInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window);
-        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
-        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
-        assert!((value - check).abs() < 1.0e-9);
+    #[test]
+    fn ratio_ramp_scheduled() {
+        let mut ramp = RatioRamp::new(1.0);
+        ramp.schedule(ScheduledChange {
+            ratio: 1.05,
+            frame: 10,
+            ramp: false,
+        });
+        for _ in 0..10 {
+            assert_eq!(ramp.next_step(), 1.0);
+        }
+        assert_eq!(ramp.next_step(), 1.0 / 1.05);
+        assert!(!ramp.is_changing());
+
+        // a change in the past is applied immediately
+        ramp.schedule(ScheduledChange {
+            ratio: 0.95,
+            frame: 5,
+            ramp: false,
+        });
+        assert_eq!(ramp.target, 0.95);
+        assert!(ramp.scheduled.is_none());
+
+        // a ramp starts at the scheduled frame
+        ramp.set_duration(10);
+        ramp.schedule(ScheduledChange {
+            ratio: 1.05,
+            frame: 20,
+            ramp: true,
+        });
+        for _ in 11..20 {
+            assert_eq!(ramp.next_step(), 1.0 / 0.95);
+        }
+        assert!((ramp.next_step() - 1.0 / 0.96).abs() < 1.0e-12);
     }
 
     #[test]
-    fn test_scalar_interpolator_32() {
-        let mut rng = rand::thread_rng();
-        let mut wave = Vec::new();
-        for _ in 0..2048 {
-            wave.push(rng.gen::<f32>());
+    fn ratio_ramp_types() {
+        let mut ramp = RatioRamp::new(0.9);
+        ramp.set_duration(100);
+        ramp.set_ramp_type(RampType::Exponential);
+        ramp.set_target(1.1);
+        assert!((ramp.ratio_at(50) - (0.9f64 * 1.1).sqrt()).abs() < 1.0e-12);
+        assert!((ramp.ratio_at(99) - 1.1 * (0.9f64 / 1.1).powf(0.01)).abs() < 1.0e-12);
+        ramp.set_ramp_type(RampType::SCurve);
+        ramp.set_target(1.1);
+        assert!((ramp.ratio_at(25) - (0.9 + 0.2 * 0.15625)).abs() < 1.0e-12);
+        assert!((ramp.ratio_at(50) - 1.0).abs() < 1.0e-12);
+        assert!((ramp.ratio_at(75) - (0.9 + 0.2 * 0.84375)).abs() < 1.0e-12);
+    }
+
+    fn input_frames_after_ratio_change(ramp_duration: usize) -> usize {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.0, params, 100, 1);
+        resampler.set_ramp_duration(ramp_duration);
+        for _ in 0..5 {
+            let waves = vec![vec![0.0; resampler.nbr_frames_needed()]];
+            resampler.process(&waves).unwrap();
         }
-        let sinc_len = 256;
-        let f_cutoff = 0.9473371669037001;
-        let oversampling_factor = 256;
-        let window = WindowFunction::BlackmanHarris2;
+        resampler.set_resample_ratio(1.05).unwrap();
+        let mut frames = 0;
+        for _ in 0..20 {
+            frames += resampler.nbr_frames_needed();
+            let waves = vec![vec![0.0; resampler.nbr_frames_needed()]];
+            resampler.process(&waves).unwrap();
+        }
+        frames
+    }
 
-        let interpolator =
-            ScalarInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window);
-        let value = interpolator.get_sinc_interpolated(&wave, 333, 123);
-        let check = get_sinc_interpolated(&wave, 333, &interpolator.sincs[123]);
-        assert!((value - check).abs() < 1.0e-6);
+    #[test]
+    fn ramp_duration() {
+        // Without a ramp, 2000 output frames consume 2000 / 1.05 = 1904.8 input frames.
+        // With a 1000 frame linear ramp, the first 1000 output frames consume
+        // 1000 * ln(1.05) / 0.05 = 975.8 input frames, and the total becomes 1928.2.
+        let immediate = input_frames_after_ratio_change(0);
+        let ramped = input_frames_after_ratio_change(1000);
+        let difference = ramped as isize - immediate as isize;
+        assert!(
+            difference > 20 && difference < 27,
+            "difference {}",
+            difference
+        );
     }
 
     #[test]
-    fn int_cubic() {
+    fn ramp_progress() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 16,
+            oversampling_factor: 256,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let yvals = [0.0f64, 2.0f64, 4.0f64, 6.0f64];
-        let interp = interp_cubic(0.5f64, &yvals);
-        assert_eq!(interp, 3.0f64);
+        let mut resampler = SincFixedOut::<f64>::new(1.0, params, 100, 1);
+        resampler.set_ramp_duration(250);
+        assert!(!resampler.is_ramping());
+        assert_eq!(resampler.ramp_progress(), None);
+        resampler.set_resample_ratio(1.05).unwrap();
+        assert!(resampler.is_ramping());
+        assert_eq!(resampler.ramp_progress(), Some(0.0));
+        for expected in [Some(0.4), Some(0.8), None].iter() {
+            let waves = vec![vec![0.0; resampler.nbr_frames_needed()]];
+            resampler.process(&waves).unwrap();
+            assert_eq!(resampler.ramp_progress(), *expected);
+        }
+        assert!(!resampler.is_ramping());
+
+        // Through the object safe trait, and with a change that is applied at once.
+        let mut resampler = SincFixedIn::<f64>::new(1.0, params, 100, 1);
+        resampler.set_ramp_duration(1000);
+        let mut resampler: Box<dyn crate::VecResampler<f64>> = Box::new(resampler);
+        resampler.set_sample_rates(48000.0, 44100.0, true).unwrap();
+        assert_eq!(resampler.ramp_progress(), Some(0.0));
+        resampler.set_sample_rates(48000.0, 44100.0, false).unwrap();
+        assert!(!resampler.is_ramping());
+    }
+
+    // Measure the angular frequency of a sine around frame `center`,
+    // from the upward zero crossings within 200 frames.
+    // Returns the frequency, and the frame in the middle of the measured periods.
+    fn measure_frequency(wave: &[f64], center: usize) -> (f64, f64) {
+        let mut crossings = Vec::new();
+        for n in (center - 200)..(center + 200) {
+            if wave[n] < 0.0 && wave[n + 1] >= 0.0 {
+                crossings.push(n as f64 + wave[n] / (wave[n] - wave[n + 1]));
+            }
+        }
+        let first = crossings[0];
+        let last = crossings[crossings.len() - 1];
+        let periods = (crossings.len() - 1) as f64;
+        (
+            2.0 * std::f64::consts::PI * periods / (last - first),
+            0.5 * (first + last),
+        )
+    }
+
+    fn check_ramp_type(ramp_type: RampType, expected_ratio: fn(f64) -> f64) {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.0, params, 1024, 1);
+        resampler.set_resample_ratio(0.92).unwrap();
+        resampler.set_ramp_type(ramp_type);
+        resampler.set_ramp_duration(8000);
+        resampler.set_resample_ratio(1.08).unwrap();
+        let mut output = Vec::new();
+        let mut position = 0;
+        while output.len() < 8500 {
+            let wave: Vec<f64> = (position..position + 1024)
+                .map(|n| (0.1 * n as f64).sin())
+                .collect();
+            position += 1024;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+        for center in [2000, 4000, 6000].iter() {
+            let (measured, middle) = measure_frequency(&output, *center);
+            let expected = 0.1 / expected_ratio(middle / 8000.0);
+            assert!(
+                (measured - expected).abs() / expected < 2.0e-4,
+                "{:?} at {}: {} != {}",
+                ramp_type,
+                center,
+                measured,
+                expected
+            );
+        }
     }
 
     #[test]
-    fn int_lin_32() {
+    fn ramp_types() {
+        check_ramp_type(RampType::Linear, |x| 0.92 + 0.16 * x);
+        check_ramp_type(RampType::Exponential, |x| 0.92 * (1.08f64 / 0.92).powf(x));
+        check_ramp_type(RampType::SCurve, |x| 0.92 + 0.16 * x * x * (3.0 - 2.0 * x));
+    }
+
+    #[test]
+    fn scheduled_ratio_change() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 16,
+            oversampling_factor: 256,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
-        let yvals = [1.0f32, 5.0f32];
-        let interp = interp_lin(0.25f32, &yvals);
-        assert_eq!(interp, 2.0f32);
+        let mut resampler = SincFixedOut::<f64>::new(1.0, params, 256, 1);
+        resampler.set_resample_ratio_at(0.95, 300, false).unwrap();
+        let scheduled = process_sine_in_chunks(&mut resampler, &[256; 20], |_, _| {});
+
+        // the same change, applied at a chunk boundary
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(1.0, params, 256, 1);
+        let mut chunk_sizes = vec![256, 44];
+        chunk_sizes.extend_from_slice(&[256; 18]);
+        chunk_sizes.push(212);
+        let expected = process_sine_in_chunks(&mut resampler, &chunk_sizes, |r, size| {
+            r.set_output_chunk_size(size).unwrap();
+            if size == 256 && r.ramp.frame == 300 {
+                r.set_resample_ratio(0.95).unwrap();
+            }
+        });
+        assert_streams_equal(&expected, &scheduled);
     }
 
     #[test]
-    fn int_cubic_32() {
+    fn set_chunk_size() {
+        let chunk_sizes = [256, 64, 1000, 1, 300, 2000, 128, 512];
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 16,
+            oversampling_factor: 256,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let _resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
-        let yvals = [0.0f32, 2.0f32, 4.0f32, 6.0f32];
-        let interp = interp_cubic(0.5f32, &yvals);
-        assert_eq!(interp, 3.0f32);
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 256, 1);
+        let expected = process_sine_in_chunks(&mut resampler, &[256; 20], |_, _| {});
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 256, 1);
+        let actual = process_sine_in_chunks(&mut resampler, &chunk_sizes, |r, size| {
+            r.set_chunk_size(size).unwrap();
+            assert_eq!(r.nbr_frames_needed(), size);
+        });
+        assert_streams_equal(&expected, &actual);
+        assert!(resampler.set_chunk_size(0).is_err());
+    }
+
+    #[test]
+    fn process_variable_into_buffer() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 256, 1);
+        let expected = process_sine_in_chunks(&mut resampler, &[256; 40], |_, _| {});
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1920, 1);
+        let mut rng = rand::thread_rng();
+        let mut position = 0;
+        let mut actual = Vec::new();
+        let mut wave_out = vec![vec![0.0; resampler.output_frames_max()]];
+        while position < 256 * 40 {
+            let frames = rng.gen_range(80..1921);
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (0.01 * n as f64).sin())
+                .collect();
+            let (frames_in, frames_out) = resampler
+                .process_variable_into_buffer(&[wave], &mut wave_out, None)
+                .unwrap();
+            assert_eq!(frames_in, frames);
+            actual.extend_from_slice(&wave_out[0][..frames_out]);
+            position += frames;
+        }
+        assert_streams_equal(&expected, &actual);
+        assert_eq!(
+            resampler
+                .process_variable_into_buffer(&[Vec::new()], &mut wave_out, None)
+                .unwrap(),
+            (0, 0)
+        );
+    }
+
+    // Resample a tone that is above the output Nyquist frequency after lowering the ratio,
+    // and return the rms value of the output.
+    fn aliased_tone_rms<R: Resampler<f64>>(resampler: &mut R) -> f64 {
+        resampler.set_max_resample_ratio_relative(1.6).unwrap();
+        resampler.set_resample_ratio(0.7).unwrap();
+        let mut position = 0;
+        let mut output = Vec::new();
+        for _ in 0..20 {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (2.0 * std::f64::consts::PI * 0.42 * n as f64).sin())
+                .collect();
+            position += frames;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+        let tail = &output[1000..];
+        (tail.iter().map(|x| x * x).sum::<f64>() / tail.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn adaptive_cutoff() {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut fixed_in = SincFixedIn::<f64>::new(1.1, params, 256, 1);
+        assert!(aliased_tone_rms(&mut fixed_in) > 0.1);
+        let mut fixed_in = SincFixedIn::<f64>::new(1.1, params, 256, 1);
+        fixed_in.set_adaptive_cutoff(true).unwrap();
+        assert!(aliased_tone_rms(&mut fixed_in) < 1.0e-3);
+        let mut fixed_out = SincFixedOut::<f64>::new(1.1, params, 256, 1);
+        fixed_out.set_adaptive_cutoff(true).unwrap();
+        assert!(aliased_tone_rms(&mut fixed_out) < 1.0e-3);
+
+        let interpolator = Box::new(ScalarInterpolator::<f64>::new(
+            params.sinc_len,
+            params.oversampling_factor,
+            params.f_cutoff,
+            params.window,
+        ));
+        let mut resampler = SincFixedIn::<f64>::new_with_interpolator(
+            1.1,
+            params.interpolation,
+            interpolator,
+            256,
+            1,
+        );
+        match resampler.set_adaptive_cutoff(true) {
+            Err(ResampleError::NoInterpolationParameters) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    fn tone<R: Resampler<f64>>(resampler: &mut R, freq: f64, chunks: usize) -> Vec<f64> {
+        let mut position = 0;
+        let mut output = Vec::new();
+        for _ in 0..chunks {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (2.0 * std::f64::consts::PI * freq * n as f64).sin())
+                .collect();
+            position += frames;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+        output
+    }
+
+    fn rms(values: &[f64]) -> f64 {
+        (values.iter().map(|x| x * x).sum::<f64>() / values.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn minimum_phase() {
+        let linear = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let minimum = InterpolationParameters {
+            phase: FilterPhase::Minimum,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+            ..linear
+        };
+        let ratio = 1.5;
+        // clones share the table of sincs, which is slow to create for minimum phase
+        let fixed_in_lin = SincFixedIn::<f64>::new(ratio, linear, 256, 1);
+        let fixed_in_min = SincFixedIn::<f64>::new(ratio, minimum, 256, 1);
+
+        // less is held back, so the first chunk gives more output
+        let first_lin = tone(&mut fixed_in_lin.clone(), 0.01, 1).len();
+        let first_min = tone(&mut fixed_in_min.clone(), 0.01, 1).len();
+        assert!(first_min > first_lin + 40, "{} {}", first_min, first_lin);
+        let fixed_out_lin = SincFixedOut::<f64>::new(ratio, linear, 256, 1);
+        let fixed_out_min = SincFixedOut::<f64>::new(ratio, minimum, 256, 1);
+        assert!(fixed_out_min.nbr_frames_needed() < fixed_out_lin.nbr_frames_needed());
+
+        // low frequencies are aligned in the same way as for linear phase
+        assert_eq!(fixed_in_min.output_delay(), fixed_in_lin.output_delay());
+        let output_lin = tone(&mut fixed_in_lin.clone(), 0.01, 20);
+        let output_min = tone(&mut fixed_in_min.clone(), 0.01, 20);
+        for (n, (lin, min)) in output_lin
+            .iter()
+            .zip(output_min.iter())
+            .enumerate()
+            .skip(300)
+        {
+            assert!((lin - min).abs() < 0.001, "{}: {} != {}", n, min, lin);
+        }
+
+        // the magnitude response is the same within 1 dB
+        for freq in [0.05, 0.15, 0.25, 0.35, 0.45].iter() {
+            let rms_lin = rms(&tone(&mut fixed_in_lin.clone(), *freq, 20)[1000..]);
+            let rms_min = rms(&tone(&mut fixed_in_min.clone(), *freq, 20)[1000..]);
+            let diff_db = 20.0 * (rms_min / rms_lin).log10();
+            assert!(diff_db.abs() < 1.0, "{}: {} dB", freq, diff_db);
+        }
+    }
+
+    // The same windowed sinc as the resamplers make from the interpolation parameters,
+    // for a ratio above 1.
+    fn windowed_sinc_taps(params: &InterpolationParameters) -> Vec<f64> {
+        let totpoints = params.sinc_len * params.oversampling_factor;
+        make_window::<f64>(totpoints, params.window)
+            .iter()
+            .enumerate()
+            .map(|(x, w)| {
+                w * sinc(
+                    (x as f64 - (totpoints / 2) as f64) * params.f_cutoff as f64
+                        / params.oversampling_factor as f64,
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn user_supplied_filter() {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let taps = windowed_sinc_taps(&params);
+        let mut reference = SincFixedIn::<f64>::new(1.2, params, 256, 1);
+        let mut resampler = SincFixedIn::<f64>::new_with_filter(
+            1.2,
+            &taps,
+            params.oversampling_factor,
+            params.interpolation,
+            256,
+            1,
+        )
+        .unwrap();
+        assert_eq!(resampler.output_delay(), reference.output_delay());
+        let expected = tone(&mut reference, 0.1, 10);
+        let output = tone(&mut resampler, 0.1, 10);
+        assert_eq!(output.len(), expected.len());
+        for (n, (value, exp)) in output.iter().zip(expected.iter()).enumerate().skip(100) {
+            assert!((value - exp).abs() < 1.0e-3, "{}: {} != {}", n, value, exp);
+        }
+
+        let mut reference = SincFixedOut::<f64>::new(1.2, params, 256, 1);
+        let mut resampler = SincFixedOut::<f64>::new_with_filter(
+            1.2,
+            &taps,
+            params.oversampling_factor,
+            params.interpolation,
+            256,
+            1,
+        )
+        .unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), reference.nbr_frames_needed());
+        let expected = tone(&mut reference, 0.1, 10);
+        let output = tone(&mut resampler, 0.1, 10);
+        for (n, (value, exp)) in output.iter().zip(expected.iter()).enumerate().skip(100) {
+            assert!((value - exp).abs() < 1.0e-3, "{}: {} != {}", n, value, exp);
+        }
+    }
+
+    #[test]
+    fn user_supplied_filter_errors() {
+        let new = |taps: &[f64], factor| {
+            SincFixedIn::<f64>::new_with_filter(
+                1.2,
+                taps,
+                factor,
+                InterpolationType::Linear,
+                256,
+                1,
+            )
+        };
+        match new(&[1.0; 64], 0) {
+            Err(ResamplerConstructionError::InvalidOversamplingFactor { factor: 0 }) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        match new(&[1.0; 65], 8) {
+            Err(ResamplerConstructionError::InvalidFilterLength { length: 65, .. }) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        match new(&[1.0; 48], 8) {
+            Err(ResamplerConstructionError::InvalidFilterLength { length: 48, .. }) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        match new(&[], 8) {
+            Err(ResamplerConstructionError::InvalidFilterLength { length: 0, .. }) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        let mut taps = vec![1.0; 64];
+        taps[10] = std::f64::NAN;
+        match new(&taps, 8) {
+            Err(ResamplerConstructionError::InvalidFilterTaps) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        let taps: Vec<f64> = (0..64)
+            .map(|n| if n % 2 == 0 { 1.0 } else { -1.0 })
+            .collect();
+        match new(&taps, 8) {
+            Err(ResamplerConstructionError::InvalidFilterTaps) => {}
+            other => panic!("unexpected result {:?}", other.map(|_| ())),
+        }
+        assert!(new(&[1.0; 64], 8).is_ok());
+    }
+
+    #[test]
+    fn set_output_chunk_size() {
+        let chunk_sizes = [256, 64, 1000, 1, 300, 2000, 128, 512];
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(0.8, params, 256, 1);
+        let expected = process_sine_in_chunks(&mut resampler, &[256; 20], |_, _| {});
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(0.8, params, 256, 1);
+        let actual = process_sine_in_chunks(&mut resampler, &chunk_sizes, |r, size| {
+            r.set_output_chunk_size(size).unwrap();
+            assert_eq!(r.output_frames_max(), size);
+        });
+        assert_streams_equal(&expected, &actual);
+        assert!(resampler.set_output_chunk_size(0).is_err());
+    }
+
+    #[test]
+    fn make_resampler_fo_upsample() {
+        let params = InterpolationParameters {
+            sinc_len: 256,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 160,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedOut::<f64>::new(8.0, params, 1024, 2);
+        let frames = resampler.nbr_frames_needed();
+        println!("{}", frames);
+        assert!(
+            frames > 128 && frames < 300,
+            "expected {}..{} samples, got {}",
+            140,
+            200,
+            frames
+        );
+        let waves = vec![vec![0.0f64; frames]; 2];
+        let out = resampler.process(&waves).unwrap();
+        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+        assert_eq!(
+            out[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out[0].len()
+        );
+        let frames2 = resampler.nbr_frames_needed();
+        assert!(
+            frames2 > 125 && frames2 < 131,
+            "expected {}..{} samples, got {}",
+            125,
+            131,
+            frames2
+        );
+        let waves2 = vec![vec![0.0f64; frames2]; 2];
+        let out2 = resampler.process(&waves2).unwrap();
+        assert_eq!(
+            out2[0].len(),
+            1024,
+            "Expected {} frames, got {}",
+            1024,
+            out2[0].len()
+        );
     }
 
     #[test]
-    fn int_lin() {
+    fn debug_output() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let _resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let yvals = [1.0f64, 5.0f64];
-        let interp = interp_lin(0.25f64, &yvals);
-        assert_eq!(interp, 2.0f64);
+        let resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
+        let text = format!("{:?}", resampler);
+        assert!(text.starts_with("SincFixedIn {"), "{}", text);
+        for field in &[
+            "nbr_channels: 2",
+            "chunk_size: 1024",
+            "resample_ratio: 1.2",
+            "interpolation: Cubic",
+            "sinc_len: 64",
+            "oversampling_factor: 16",
+        ] {
+            assert!(text.contains(field), "{}", text);
+        }
+        assert!(text.len() < 300, "{}", text);
+    }
+
+    // The interpolator with the widest instructions available is selected,
+    // and fused multiply-add is preferred for AVX.
+    fn expected_kernel() -> &'static str {
+        #[cfg(all(target_arch = "x86_64", feature = "avx"))]
+        if is_x86_feature_detected!("avx") {
+            return if is_x86_feature_detected!("fma") {
+                "avx+fma"
+            } else {
+                "avx"
+            };
+        }
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("sse3") {
+            return "sse3";
+        }
+        #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+        if is_aarch64_feature_detected!("neon") {
+            return "neon";
+        }
+        #[cfg(target_arch = "wasm32")]
+        if cfg!(target_feature = "simd128") {
+            return "simd128";
+        }
+        "scalar"
     }
 
     #[test]
-    fn make_resampler_fi() {
+    fn interpolator_kernel() {
+        let params = state_params();
+        let resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
+        assert_eq!(resampler.interpolator_kernel(), expected_kernel());
+        let text = format!("{:?}", resampler);
+        assert!(text.contains(&format!("kernel: {:?}", expected_kernel())));
+        let resampler = SincFixedOut::<f64>::new(0.8, params, 1024, 2);
+        assert_eq!(resampler.interpolator_kernel(), expected_kernel());
+        let params = InterpolationParameters {
+            storage: SincStorage::OnTheFly,
+            ..params
+        };
+        let resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
+        assert_eq!(resampler.interpolator_kernel(), "scalar");
+    }
+
+    #[test]
+    fn construction_getters() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
-            interpolation: InterpolationType::Cubic,
+            interpolation: InterpolationType::Linear,
             oversampling_factor: 16,
-            window: WindowFunction::BlackmanHarris2,
+            window: WindowFunction::Blackman,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 1150 && out[0].len() < 1229,
-            "expected {} - {} samples, got {}",
-            1150,
-            1229,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 1226 && out2[0].len() < 1232,
-            "expected {} - {} samples, got {}",
-            1226,
-            1232,
-            out2[0].len()
+        let resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2);
+        assert_eq!(resampler.chunk_size(), 1024);
+        let stored = resampler.interpolation_parameters().unwrap();
+        assert_eq!(stored.sinc_len, 64);
+        assert_eq!(stored.oversampling_factor, 16);
+        assert_eq!(format!("{:?}", stored.interpolation), "Linear");
+        assert_eq!(format!("{:?}", stored.window), "Blackman");
+
+        let interpolator = Box::new(ScalarInterpolator::<f64>::new(
+            64,
+            16,
+            0.95,
+            WindowFunction::Blackman,
+        ));
+        let resampler = SincFixedIn::<f64>::new_with_interpolator(
+            1.2,
+            InterpolationType::Linear,
+            interpolator,
+            512,
+            2,
         );
+        assert_eq!(resampler.chunk_size(), 512);
+        assert!(resampler.interpolation_parameters().is_none());
     }
 
-    #[test]
-    fn make_resampler_fi_32() {
-        let params = InterpolationParameters {
+    fn state_params() -> InterpolationParameters {
+        InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
             oversampling_factor: 16,
             window: WindowFunction::BlackmanHarris2,
-        };
-        let mut resampler = SincFixedIn::<f32>::new(1.2, params, 1024, 2);
-        let waves = vec![vec![0.0f32; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 1150 && out[0].len() < 1229,
-            "expected {} - {} samples, got {}",
-            1150,
-            1229,
-            out[0].len()
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        }
+    }
+
+    // Run ten minutes of 44.1 kHz input with the ratio 160 / 147 given exactly, and as an f64.
+    // A chunk of 1470 frames is ten periods of the fraction, so with the exact ratio
+    // every chunk gives 1600 frames and ends at the same position.
+    #[test]
+    fn rational_ratio_does_not_drift() {
+        let mut exact = SincFixedIn::<f32>::new(1.0, state_params(), 1470, 1);
+        exact.set_max_resample_ratio_relative(2.0).unwrap();
+        let mut float = exact.clone();
+        exact.set_resample_ratio_rational(320, 294, false).unwrap();
+        assert_eq!(exact.resample_ratio(), 160.0 / 147.0);
+        float
+            .set_resample_ratio_at(160.0 / 147.0, 0, false)
+            .unwrap();
+        let input = exact.input_buffer_allocate(true);
+        let mut output = exact.output_buffer_allocate(true);
+        let mask = Some(&[false][..]);
+        exact
+            .process_into_buffer(&input, &mut output, mask)
+            .unwrap();
+        float
+            .process_into_buffer(&input, &mut output, mask)
+            .unwrap();
+        let start = exact.last_index;
+        let float_start = float.last_index;
+        let mut float_frames = 0;
+        for _ in 0..18000 {
+            let (_, frames) = exact
+                .process_into_buffer(&input, &mut output, mask)
+                .unwrap();
+            assert_eq!(frames, 1600);
+            assert_eq!(exact.last_index, start);
+            float_frames += float
+                .process_into_buffer(&input, &mut output, mask)
+                .unwrap()
+                .1;
+        }
+        // The position of the f64 stream, compared to where the ideal steps would have put it.
+        let float_drift = float.last_index
+            - float_start
+            - (float_frames as f64 - 1600.0 * 18000.0) * 147.0 / 160.0;
+        assert!(float_drift != 0.0);
+
+        // Any other ratio goes back to normal stepping, and a ramp to an exact ratio
+        // switches to exact stepping once it is done.
+        exact.set_resample_ratio(1.1).unwrap();
+        exact
+            .process_into_buffer(&input, &mut output, mask)
+            .unwrap();
+        assert!(exact.exact.is_none());
+        exact.set_ramp_duration(1000);
+        exact.set_resample_ratio_rational(160, 147, true).unwrap();
+        for _ in 0..3 {
+            exact
+                .process_into_buffer(&input, &mut output, mask)
+                .unwrap();
+        }
+        let start = exact.last_index;
+        exact
+            .process_into_buffer(&input, &mut output, mask)
+            .unwrap();
+        assert_eq!(exact.last_index, start);
+
+        for (num, den) in [(0, 1), (1, 0), (std::u64::MAX, 1), (1, 3)].iter() {
+            match exact.set_resample_ratio_rational(*num, *den, false) {
+                Err(ResampleError::BadRatioUpdate) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
+
+    // Save the state after a few chunks, and check that the output after restoring it
+    // is identical to the uninterrupted output.
+    fn check_state_round_trip<R: Resampler<f64>>(
+        resampler: &mut R,
+        save: impl Fn(&R) -> ResamplerState<f64>,
+        restore: impl Fn(&mut R, &ResamplerState<f64>) -> ResampleResult<()>,
+    ) {
+        let wave: Vec<f64> = (0..20000).map(|n| (0.01 * n as f64).sin()).collect();
+        let mut position = 0;
+        for _ in 0..3 {
+            let frames = resampler.nbr_frames_needed();
+            resampler
+                .process(&[&wave[position..position + frames]])
+                .unwrap();
+            position += frames;
+        }
+        resampler.set_resample_ratio_relative(1.05).unwrap();
+        let state = save(resampler);
+        let saved_position = position;
+        let mut expected = Vec::new();
+        for _ in 0..5 {
+            let frames = resampler.nbr_frames_needed();
+            expected.push(
+                resampler
+                    .process(&[&wave[position..position + frames]])
+                    .unwrap(),
+            );
+            position += frames;
+        }
+        restore(resampler, &state).unwrap();
+        let mut position = saved_position;
+        for chunk in expected.iter() {
+            let frames = resampler.nbr_frames_needed();
+            let output = resampler
+                .process(&[&wave[position..position + frames]])
+                .unwrap();
+            assert_eq!(&output, chunk);
+            position += frames;
+        }
+    }
+
+    #[test]
+    fn save_and_restore_state() {
+        let mut resampler = SincFixedIn::<f64>::new(1.2, state_params(), 512, 1);
+        resampler.set_ramp_duration(1000);
+        check_state_round_trip(
+            &mut resampler,
+            |r| r.save_state(),
+            |r, state| r.restore_state(state),
         );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 1226 && out2[0].len() < 1232,
-            "expected {} - {} samples, got {}",
-            1226,
-            1232,
-            out2[0].len()
+        let mut resampler = SincFixedOut::<f64>::new(0.8, state_params(), 512, 1);
+        resampler.set_ramp_duration(1000);
+        check_state_round_trip(
+            &mut resampler,
+            |r| r.save_state(),
+            |r, state| r.restore_state(state),
         );
     }
 
     #[test]
-    fn make_resampler_fi_skipped() {
+    fn restore_incompatible_state() {
+        let state = SincFixedIn::<f64>::new(1.2, state_params(), 512, 2).save_state();
+        let mut other = SincFixedIn::<f64>::new(1.2, state_params(), 512, 1);
+        match other.restore_state(&state) {
+            Err(ResampleError::IncompatibleState {
+                parameter: "number of channels",
+            }) => {}
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        let mut other = SincFixedIn::<f64>::new(1.2, state_params(), 256, 2);
+        match other.restore_state(&state) {
+            Err(ResampleError::IncompatibleState {
+                parameter: "chunk size",
+            }) => {}
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        let mut other = SincFixedOut::<f64>::new(1.2, state_params(), 512, 2);
+        match other.restore_state(&state) {
+            Err(ResampleError::IncompatibleState {
+                parameter: "resampler type",
+            }) => {}
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        let mut params = state_params();
+        params.sinc_len = 128;
+        let mut other = SincFixedIn::<f64>::new(1.2, params, 512, 2);
+        assert!(other.restore_state(&state).is_err());
+    }
+
+    // Widen the range of allowed ratios, and check that processing at the new limits works
+    // without losing the history, and that the range can't be narrowed past the ratio in use.
+    fn check_max_resample_ratio<R: Resampler<f64>>(mut resampler: R) {
+        assert_eq!(resampler.max_resample_ratio_relative(), 1.1);
+        assert!(resampler.set_resample_ratio_relative(1.1).is_ok());
+        assert!(resampler.set_resample_ratio_relative(4.0).is_err());
+        resampler.set_max_resample_ratio_relative(4.0).unwrap();
+        assert_eq!(resampler.max_resample_ratio_relative(), 4.0);
+        for rel_ratio in [0.25, 4.0].iter() {
+            resampler.set_resample_ratio_relative(*rel_ratio).unwrap();
+            for _ in 0..3 {
+                let waves = vec![vec![0.5; resampler.nbr_frames_needed()]; 2];
+                let output = resampler.process(&waves).unwrap();
+                assert!(output[0].len() <= resampler.output_frames_max());
+            }
+        }
+        match resampler.set_max_resample_ratio_relative(2.0) {
+            Err(ResampleError::BadMaxRatioUpdate) => {}
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        assert!(resampler.set_max_resample_ratio_relative(0.5).is_err());
+        assert_eq!(resampler.max_resample_ratio_relative(), 4.0);
+        resampler.set_resample_ratio_relative(1.5).unwrap();
+        resampler.set_max_resample_ratio_relative(2.0).unwrap();
+        assert!(resampler.set_resample_ratio_relative(3.0).is_err());
+    }
+
+    #[test]
+    fn max_resample_ratio_relative() {
+        check_max_resample_ratio(SincFixedIn::<f64>::new(1.2, state_params(), 512, 2));
+        check_max_resample_ratio(SincFixedOut::<f64>::new(0.8, state_params(), 512, 2));
+    }
+
+    // Narrow the working range, and check that the maxima shrink, that ratios outside
+    // the range are rejected, and that processing at the limits of the range works.
+    #[test]
+    fn working_ratio_range() {
+        let mut resampler = SincFixedOut::<f64>::new(0.8, state_params(), 512, 2);
+        resampler.set_max_resample_ratio_relative(2.0).unwrap();
+        let input_max_full = resampler.input_frames_max();
+        assert_eq!(resampler.working_ratio_range(), (0.4, 1.6));
+        resampler.set_working_ratio_range(0.79, 0.81).unwrap();
+        assert_eq!(resampler.working_ratio_range(), (0.79, 0.81));
+        assert!(resampler.input_frames_max() < 2 * input_max_full / 3);
+        assert!(resampler.set_resample_ratio(0.78).is_err());
+        assert!(resampler.set_resample_ratio_at(0.82, 100, false).is_err());
+        for ratio in [0.79, 0.81].iter() {
+            resampler.set_resample_ratio(*ratio).unwrap();
+            let mut waves = resampler.input_buffer_allocate(true);
+            for _ in 0..3 {
+                for wave in waves.iter_mut() {
+                    wave.resize(resampler.nbr_frames_needed(), 0.5);
+                }
+                resampler.process(&waves).unwrap();
+                assert!(resampler.nbr_frames_needed() <= resampler.input_frames_max());
+            }
+        }
+        assert!(resampler.set_max_resample_ratio_relative(1.01).is_err());
+        resampler.set_resample_ratio(0.8).unwrap();
+        for (min, max) in [(0.81, 0.79), (0.3, 0.9), (0.81, 0.82)].iter() {
+            match resampler.set_working_ratio_range(*min, *max) {
+                Err(ResampleError::BadWorkingRatioRange) => {}
+                other => panic!("unexpected result {:?}", other.err()),
+            }
+        }
+        resampler.set_working_ratio_range(0.4, 1.6).unwrap();
+        assert_eq!(resampler.input_frames_max(), input_max_full);
+
+        let mut resampler = SincFixedIn::<f64>::new(1.2, state_params(), 512, 2);
+        resampler.set_max_resample_ratio_relative(2.0).unwrap();
+        let output_max_full = resampler.output_frames_max();
+        resampler.set_working_ratio_range(1.19, 1.21).unwrap();
+        assert!(resampler.output_frames_max() <= output_max_full);
+        assert!(resampler.set_resample_ratio(1.3).is_err());
+        resampler.set_resample_ratio(1.21).unwrap();
+        let waves = resampler.input_buffer_allocate(true);
+        let output = resampler.process(&waves).unwrap();
+        assert!(output[0].len() <= resampler.output_frames_max());
+    }
+
+    fn process_sine_from<R: Resampler<f64>>(
+        resampler: &mut R,
+        position: &mut usize,
+        nbr_chunks: usize,
+        output: &mut Vec<f64>,
+    ) {
+        for _ in 0..nbr_chunks {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (*position..*position + frames)
+                .map(|n| (0.01 * n as f64).sin())
+                .collect();
+            *position += frames;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+    }
+
+    // Switch between fixed input and fixed output during a ramp,
+    // and compare with the output of a resampler that isn't converted.
+    #[test]
+    fn convert_between_fixed_in_and_fixed_out() {
+        let mut reference = SincFixedIn::<f64>::new(0.8, state_params(), 256, 1);
+        reference.set_ramp_duration(1000);
+        let mut resampler = reference.clone();
+        let mut expected = Vec::new();
+        let mut actual = Vec::new();
+        let mut position_expected = 0;
+        let mut position_actual = 0;
+        process_sine_from(&mut reference, &mut position_expected, 3, &mut expected);
+        process_sine_from(&mut resampler, &mut position_actual, 3, &mut actual);
+        reference.set_resample_ratio_relative(1.05).unwrap();
+        resampler.set_resample_ratio_relative(1.05).unwrap();
+        process_sine_from(&mut reference, &mut position_expected, 25, &mut expected);
+        process_sine_from(&mut resampler, &mut position_actual, 1, &mut actual);
+        let mut resampler = resampler.into_fixed_out(300);
+        assert_eq!(resampler.output_frames_max(), 300);
+        process_sine_from(&mut resampler, &mut position_actual, 10, &mut actual);
+        let mut resampler = resampler.into_fixed_in(200);
+        assert_eq!(resampler.nbr_frames_needed(), 200);
+        process_sine_from(&mut resampler, &mut position_actual, 10, &mut actual);
+        assert_streams_equal(&expected, &actual);
+    }
+
+    // Change the ratio through a control handle, and check that it's used from the next chunk,
+    // clamped to the allowed range.
+    fn check_ratio_control<R: Resampler<f64>>(mut resampler: R, control: RatioControl) {
+        let original = resampler.resample_ratio();
+        let waves = vec![vec![0.0; resampler.input_frames_max()]; 2];
+        control.set(original * 1.05, false);
+        assert!(control.is_pending());
+        let frames = resampler.nbr_frames_needed();
+        resampler
+            .process(&[&waves[0][..frames], &waves[1][..frames]])
+            .unwrap();
+        assert!(!control.is_pending());
+        assert!((resampler.resample_ratio() - original * 1.05).abs() < 1.0e-12);
+        assert!(!control.take_clamped());
+        control.set(original * 2.0, true);
+        let frames = resampler.nbr_frames_needed();
+        resampler
+            .process(&[&waves[0][..frames], &waves[1][..frames]])
+            .unwrap();
+        assert!((resampler.resample_ratio() - original * 1.1).abs() < 1.0e-12);
+        assert!(control.take_clamped());
+    }
+
+    #[test]
+    fn ratio_control() {
+        let resampler = SincFixedIn::<f64>::new(1.2, state_params(), 512, 2);
+        let control = resampler.ratio_control();
+        check_ratio_control(resampler, control);
+        let resampler = SincFixedOut::<f64>::new(0.8, state_params(), 512, 2);
+        let control = resampler.ratio_control();
+        check_ratio_control(resampler, control);
+
+        let resampler = SincFixedIn::<f64>::new(1.2, state_params(), 512, 2);
+        let control = resampler.ratio_control();
+        let mut clone = resampler.clone();
+        control.set(1.25, false);
+        let waves = clone.input_buffer_allocate(true);
+        clone.process(&waves).unwrap();
+        assert_eq!(clone.resample_ratio(), 1.2);
+        assert!(control.is_pending());
+    }
+
+    // Update the ratio from another thread while processing,
+    // with buffers allocated for the full range of ratios.
+    #[test]
+    fn ratio_control_stress() {
+        let mut resampler = SincFixedOut::<f64>::new(1.0, state_params(), 256, 2);
+        resampler.set_ramp_duration(300);
+        let control = resampler.ratio_control();
+        let done = Arc::new(AtomicBool::new(false));
+        let done_control = done.clone();
+        let thread = std::thread::spawn(move || {
+            let mut n = 0;
+            while !done_control.load(Ordering::Relaxed) {
+                control.set(0.85 + 0.3 * (n % 100) as f64 / 100.0, n % 3 == 0);
+                n += 1;
+                std::thread::yield_now();
+            }
+        });
+        let waves = resampler.input_buffer_allocate(true);
+        let mut wave_out = resampler.output_buffer_allocate(true);
+        for _ in 0..2000 {
+            let frames = resampler.nbr_frames_needed();
+            assert!(frames <= resampler.input_frames_max());
+            let (frames_in, frames_out) = resampler
+                .process_into_buffer(
+                    &[&waves[0][..frames], &waves[1][..frames]],
+                    &mut wave_out,
+                    None,
+                )
+                .unwrap();
+            assert_eq!((frames_in, frames_out), (frames, 256));
+            let ratio = resampler.resample_ratio();
+            assert!(ratio > 1.0 / 1.1 - 1.0e-9 && ratio < 1.1 + 1.0e-9);
+        }
+        done.store(true, Ordering::Relaxed);
+        thread.join().unwrap();
+    }
+
+    fn sine_chunk(chan: usize, position: usize, frames: usize) -> Vec<f64> {
+        (position..position + frames)
+            .map(|n| (0.01 * (chan + 1) as f64 * n as f64).sin())
+            .collect()
+    }
+
+    // Split a resampler during a ramp, and check that the mono resamplers together
+    // give the same output as the original, and that they can be combined again.
+    #[test]
+    fn split_into_mono_resamplers() {
+        let mut resampler = SincFixedOut::<f64>::new(0.8, state_params(), 256, 3);
+        resampler.set_ramp_duration(1000);
+        let mut position = 0;
+        for _ in 0..3 {
+            let frames = resampler.nbr_frames_needed();
+            let waves: Vec<Vec<f64>> = (0..3).map(|c| sine_chunk(c, position, frames)).collect();
+            resampler.process(&waves).unwrap();
+            position += frames;
+        }
+        resampler.set_resample_ratio_relative(1.05).unwrap();
+        let mut reference = resampler.clone();
+        let mut monos = resampler.into_mono_resamplers();
+        assert_eq!(monos.len(), 3);
+        assert!(Arc::ptr_eq(&monos[0].interpolator, &monos[2].interpolator));
+        let mut reference_position = position;
+        for _ in 0..10 {
+            let frames = reference.nbr_frames_needed();
+            let waves: Vec<Vec<f64>> = (0..3)
+                .map(|c| sine_chunk(c, reference_position, frames))
+                .collect();
+            let expected = reference.process(&waves).unwrap();
+            reference_position += frames;
+            for (chan, mono) in monos.iter_mut().enumerate() {
+                assert_eq!(mono.nbr_frames_needed(), frames);
+                let output = mono.process(&[sine_chunk(chan, position, frames)]).unwrap();
+                assert_eq!(output[0], expected[chan]);
+            }
+            position += frames;
+        }
+        let mut combined = SincFixedOut::from_mono_resamplers(monos).unwrap();
+        assert_eq!(combined.nbr_channels(), 3);
+        let frames = reference.nbr_frames_needed();
+        let waves: Vec<Vec<f64>> = (0..3).map(|c| sine_chunk(c, position, frames)).collect();
+        assert_eq!(
+            combined.process(&waves).unwrap(),
+            reference.process(&waves).unwrap()
+        );
+
+        let mut monos = SincFixedIn::<f64>::new(1.2, state_params(), 256, 2).into_mono_resamplers();
+        let waves = vec![sine_chunk(0, 0, 256)];
+        monos[1].process(&waves).unwrap();
+        match SincFixedIn::from_mono_resamplers(monos) {
+            Err(ResampleError::IncompatibleResamplers { parameter }) => {
+                assert_eq!(parameter, "processing state")
+            }
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        let other = SincFixedIn::<f64>::new(1.2, state_params(), 256, 1);
+        let mono = SincFixedIn::<f64>::new(1.2, state_params(), 256, 1);
+        assert!(SincFixedIn::from_mono_resamplers(vec![mono, other]).is_err());
+        assert!(SincFixedIn::<f64>::from_mono_resamplers(Vec::new()).is_err());
+    }
+
+    // Follow a linear ramp with a ratio curve, and compare with the ramp of a resampler.
+    #[test]
+    fn ratio_curve_follows_ramp() {
+        let mut reference = SincFixedOut::<f64>::new(1.0, state_params(), 256, 1);
+        reference.set_ramp_duration(1024);
+        reference.set_resample_ratio_relative(1.05).unwrap();
+        let mut expected = Vec::new();
+        process_sine_from(&mut reference, &mut 0, 20, &mut expected);
+
+        let mut resampler = SincFixedOut::<f64>::new(1.0, state_params(), 256, 1);
+        let mut actual = Vec::new();
+        let mut position = 0;
+        let mut wave_out = vec![vec![0.0; 256]];
+        for chunk in 0..20 {
+            let curve: Vec<f64> = (0..256)
+                .map(|n| 1.0 + (1.05 - 1.0) * ((chunk * 256 + n + 1) as f64 / 1024.0).min(1.0))
+                .collect();
+            let frames = resampler.nbr_frames_needed_for_ratio_curve(&curve).unwrap();
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (0.01 * n as f64).sin())
+                .collect();
+            position += frames;
+            let (frames_in, frames_out) = resampler
+                .process_with_ratio_curve(&[wave], &mut wave_out, &curve, None)
+                .unwrap();
+            assert_eq!((frames_in, frames_out), (frames, 256));
+            actual.extend_from_slice(&wave_out[0]);
+        }
+        assert_streams_equal(&expected, &actual);
+        assert!((resampler.resample_ratio() - 1.05).abs() < 1.0e-12);
+
+        let wave = vec![vec![0.0; resampler.input_frames_max()]];
+        match resampler.process_with_ratio_curve(&wave, &mut wave_out, &[1.0; 255], None) {
+            Err(ResampleError::WrongRatioCurveLength {
+                expected: 256,
+                actual: 255,
+            }) => {}
+            other => panic!("unexpected result {:?}", other.err()),
+        }
+        let mut curve = vec![1.0; 256];
+        curve[100] = 1.2;
+        match resampler.nbr_frames_needed_for_ratio_curve(&curve) {
+            Err(ResampleError::RatioOutOfBounds { value, .. }) => assert_eq!(value, 1.2),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    // Interpolate at time `t` in the same way as the resamplers do.
+    fn interpolate_at(
+        interpolator: &ScalarInterpolator<f64>,
+        interpolation: InterpolationType,
+        wave: &[f64],
+        t: f64,
+    ) -> f64 {
+        let factor = interpolator.nbr_sincs();
+        let frac = t * factor as f64 - (t * factor as f64).floor();
+        match interpolation {
+            InterpolationType::Cubic => {
+                let mut nearest = [(0isize, 0isize); 4];
+                get_nearest_times_4(t, factor as isize, &mut nearest);
+                let mut points = [0.0; 4];
+                for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                    *p = interpolator.get_sinc_interpolated(wave, n.0 as usize, n.1 as usize);
+                }
+                interp_cubic(frac, &points)
+            }
+            InterpolationType::Linear => {
+                let mut nearest = [(0isize, 0isize); 2];
+                get_nearest_times_2(t, factor as isize, &mut nearest);
+                let mut points = [0.0; 2];
+                for (n, p) in nearest.iter().zip(points.iter_mut()) {
+                    *p = interpolator.get_sinc_interpolated(wave, n.0 as usize, n.1 as usize);
+                }
+                interp_lin(frac, &points)
+            }
+            InterpolationType::Nearest => {
+                let n = get_nearest_time(t, factor as isize);
+                interpolator.get_sinc_interpolated(wave, n.0 as usize, n.1 as usize)
+            }
+        }
+    }
+
+    // Measure the signal to noise ratio of the interpolation between the intermediate points,
+    // compared to sincs computed directly at the interpolated positions.
+    fn interpolation_snr(interpolation: InterpolationType, oversampling_factor: usize) -> f64 {
+        let sinc_len = 64;
+        let f_cutoff = 0.95;
+        let window = WindowFunction::BlackmanHarris2;
+        let reference_factor = 16 * oversampling_factor;
+        let interpolator =
+            ScalarInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window);
+        let reference =
+            ScalarInterpolator::<f64>::new(sinc_len, reference_factor, f_cutoff, window);
+        // A full scale sine close to the cutoff.
+        let wave: Vec<f64> = (0..2 * sinc_len)
+            .map(|n| (0.9 * std::f64::consts::PI * n as f64 + 0.3).sin())
+            .collect();
+        let mut signal = 0.0;
+        let mut noise = 0.0;
+        let mut rng = rand::thread_rng();
+        for _ in 0..2000 {
+            let subindex = rng.gen_range(0..reference_factor);
+            // Subindex `n` of a table with oversampling factor `f` is at position `(n + 1) / f`.
+            let t = 2.0 + (subindex + 1) as f64 / reference_factor as f64
+                - 1.0 / oversampling_factor as f64;
+            let value = interpolate_at(&interpolator, interpolation, &wave, t);
+            let expected = reference.get_sinc_interpolated(&wave, 2, subindex);
+            signal += expected * expected;
+            noise += (value - expected) * (value - expected);
+        }
+        10.0 * (signal / noise).log10()
+    }
+
+    #[test]
+    fn recommended_oversampling() {
+        let cases = [
+            (InterpolationType::Nearest, 40.0),
+            (InterpolationType::Nearest, 60.0),
+            (InterpolationType::Linear, 60.0),
+            (InterpolationType::Linear, 90.0),
+            (InterpolationType::Cubic, 60.0),
+            (InterpolationType::Cubic, 90.0),
+            (InterpolationType::Cubic, 120.0),
+        ];
+        for (interpolation, target_snr) in cases.iter() {
+            let factor =
+                InterpolationParameters::recommended_oversampling(*interpolation, *target_snr);
+            let snr = interpolation_snr(*interpolation, factor);
+            assert!(
+                snr >= *target_snr,
+                "{:?} with factor {} gave {} dB, wanted {} dB",
+                interpolation,
+                factor,
+                snr,
+                target_snr
+            );
+        }
+    }
+
+    #[test]
+    fn automatic_oversampling() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 16,
+            oversampling_factor: 0,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let mut resampler = SincFixedIn::<f64>::new(1.2, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024], Vec::new()];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert!(out[0].len() > 1150 && out[0].len() < 1250);
-        assert!(out[1].is_empty());
-        let waves = vec![Vec::new(), vec![0.0f64; 1024]];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert!(out[1].len() > 1150 && out[0].len() < 1250);
-        assert!(out[0].is_empty());
+        let expected =
+            InterpolationParameters::recommended_oversampling(InterpolationType::Cubic, 100.0);
+        assert_eq!(expected, 22);
+        let resampler = SincFixedIn::<f64>::new(1.2, params, 256, 2);
+        assert_eq!(resampler.interpolator.nbr_sincs(), expected);
+        let resampler = SincFixedOut::<f64>::new(1.2, params, 256, 2);
+        assert_eq!(resampler.interpolator.nbr_sincs(), expected);
+    }
+
+    // Downsample a sine at `freq` relative to the output Nyquist frequency by a factor 2,
+    // and get the largest output value once the filter is filled.
+    fn downsampled_peak(params: InterpolationParameters, freq: f64) -> f64 {
+        let mut resampler = SincFixedIn::<f64>::new(0.5, params, 1024, 1);
+        let mut peak = 0.0;
+        let mut position = 0;
+        for chunk in 0..8 {
+            let wave: Vec<f64> = (position..position + 1024)
+                .map(|n| (std::f64::consts::PI * freq * n as f64 / 2.0).sin())
+                .collect();
+            position += 1024;
+            let output = resampler.process(&[wave]).unwrap();
+            if chunk >= 4 {
+                peak = output[0]
+                    .iter()
+                    .fold(peak, |peak: f64, v| peak.max(v.abs()));
+            }
+        }
+        peak
     }
 
     #[test]
-    fn make_resampler_fi_downsample() {
-        // Replicate settings from reported issue
-        let params = InterpolationParameters {
-            sinc_len: 256,
-            f_cutoff: 0.95,
-            interpolation: InterpolationType::Cubic,
-            oversampling_factor: 160,
-            window: WindowFunction::BlackmanHarris2,
-        };
-        let mut resampler = SincFixedIn::<f64>::new(16000 as f64 / 96000 as f64, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 140 && out[0].len() < 200,
-            "expected {} - {} samples, got {}",
-            140,
-            200,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 167 && out2[0].len() < 173,
-            "expected {} - {} samples, got {}",
-            167,
-            173,
-            out2[0].len()
-        );
+    fn from_spec() {
+        let specs = [
+            (40.0, 0.1, InterpolationType::Linear),
+            (70.0, 0.05, InterpolationType::Cubic),
+            (100.0, 0.1, InterpolationType::Linear),
+            (140.0, 0.05, InterpolationType::Cubic),
+        ];
+        for (stopband, width, interpolation) in specs.iter() {
+            let params =
+                InterpolationParameters::from_spec(*stopband, *width, *interpolation).unwrap();
+            assert_eq!(params.sinc_len % 8, 0);
+            // The sinc is stretched by the ratio of 0.5, and the transition band
+            // is twice as wide, from `1.5 * width` below to `width / 2` above the Nyquist frequency.
+            let mut freq = 1.0 + width / 2.0;
+            while freq < 1.95 {
+                let peak = downsampled_peak(params, freq);
+                let attenuation = -20.0 * peak.log10();
+                assert!(
+                    attenuation > *stopband,
+                    "{} dB at {} for {:?}",
+                    attenuation,
+                    freq,
+                    params
+                );
+                freq += 0.05;
+            }
+            let peak = downsampled_peak(params, 1.0 - 1.5 * width);
+            assert!((peak - 1.0).abs() < 0.01, "passband peak {}", peak);
+        }
+        let longer = InterpolationParameters::from_spec(100.0, 0.05, InterpolationType::Cubic)
+            .unwrap()
+            .sinc_len;
+        let shorter = InterpolationParameters::from_spec(100.0, 0.1, InterpolationType::Cubic)
+            .unwrap()
+            .sinc_len;
+        assert!(longer > shorter);
+
+        let unachievable = [
+            (300.0, 0.05, InterpolationType::Cubic),
+            (100.0, 0.0, InterpolationType::Cubic),
+            (100.0, 1.5, InterpolationType::Cubic),
+            (std::f64::NAN, 0.05, InterpolationType::Cubic),
+            (100.0, 0.0001, InterpolationType::Cubic),
+            (120.0, 0.05, InterpolationType::Nearest),
+        ];
+        for (stopband, width, interpolation) in unachievable.iter() {
+            assert_eq!(
+                InterpolationParameters::from_spec(*stopband, *width, *interpolation).unwrap_err(),
+                ResamplerConstructionError::UnachievableFilterSpec
+            );
+        }
+    }
+
+    // A Hann window calculated from the distance to the center, like the built in one.
+    fn custom_hann(x: usize, npoints: usize) -> f64 {
+        let twice_distance = (2 * x).max(npoints) - (2 * x).min(npoints);
+        0.5 + 0.5 * (std::f64::consts::PI * twice_distance as f64 / npoints as f64).cos()
     }
 
     #[test]
-    fn make_resampler_fi_upsample() {
-        // Replicate settings from reported issue
-        let params = InterpolationParameters {
-            sinc_len: 256,
-            f_cutoff: 0.95,
+    fn custom_window() {
+        let builtin = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.9,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 160,
-            window: WindowFunction::BlackmanHarris2,
+            oversampling_factor: 32,
+            window: WindowFunction::Hann,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let mut resampler = SincFixedIn::<f64>::new(192000 as f64 / 44100 as f64, params, 1024, 2);
-        let waves = vec![vec![0.0f64; 1024]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert!(
-            out[0].len() > 3800 && out[0].len() < 4458,
-            "expected {} - {} samples, got {}",
-            3800,
-            4458,
-            out[0].len()
-        );
-        let out2 = resampler.process(&waves).unwrap();
-        assert_eq!(out2.len(), 2, "Expected {} channels, got {}", 2, out2.len());
-        assert!(
-            out2[0].len() > 4455 && out2[0].len() < 4461,
-            "expected {} - {} samples, got {}",
-            4455,
-            4461,
-            out2[0].len()
+        let custom = InterpolationParameters::with_custom_window(
+            64,
+            0.9,
+            32,
+            InterpolationType::Cubic,
+            custom_hann,
+        )
+        .unwrap();
+        let wave: Vec<f64> = (0..1024).map(|n| (0.03 * n as f64).sin()).collect();
+        for storage in [SincStorage::Table, SincStorage::OnTheFly].iter() {
+            let builtin = InterpolationParameters {
+                storage: *storage,
+                ..builtin
+            };
+            let custom = InterpolationParameters {
+                storage: *storage,
+                ..custom
+            };
+            let mut reference = SincFixedIn::<f64>::new(0.8, builtin, 1024, 1);
+            let mut resampler = SincFixedIn::<f64>::new(0.8, custom, 1024, 1);
+            assert_eq!(
+                resampler.process(&[&wave]).unwrap(),
+                reference.process(&[&wave]).unwrap()
+            );
+        }
+
+        fn not_finite(_x: usize, _npoints: usize) -> f64 {
+            std::f64::NAN
+        }
+        fn zero(_x: usize, _npoints: usize) -> f64 {
+            0.0
+        }
+        fn make(
+            sinc_len: usize,
+            factor: usize,
+            window: fn(usize, usize) -> f64,
+        ) -> Result<InterpolationParameters, ResamplerConstructionError> {
+            InterpolationParameters::with_custom_window(
+                sinc_len,
+                0.9,
+                factor,
+                InterpolationType::Cubic,
+                window,
+            )
+        }
+        match make(60, 32, custom_hann) {
+            Err(ResamplerConstructionError::InvalidFilterLength {
+                length: 1920,
+                factor: 32,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match make(64, 0, custom_hann) {
+            Ok(InterpolationParameters {
+                oversampling_factor,
+                ..
+            }) if oversampling_factor > 0 => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(
+            make(64, 32, not_finite).unwrap_err(),
+            ResamplerConstructionError::InvalidWindow
+        );
+        assert_eq!(
+            make(64, 32, zero).unwrap_err(),
+            ResamplerConstructionError::InvalidWindow
         );
     }
 
+    // Resample a tone of 0.05 cycles per frame, and measure its frequency in the output
+    // from the rising zero crossings of the last chunks.
+    fn output_tone_frequency<R: Resampler<f64>>(resampler: &mut R) -> f64 {
+        let mut position = 0;
+        let mut output = Vec::new();
+        for chunk in 0..10 {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (2.0 * std::f64::consts::PI * 0.05 * n as f64).sin())
+                .collect();
+            position += frames;
+            let out = resampler.process(&[wave]).unwrap();
+            if chunk >= 4 {
+                output.extend_from_slice(&out[0]);
+            }
+        }
+        let crossings: Vec<f64> = output
+            .windows(2)
+            .enumerate()
+            .filter(|(_, pair)| pair[0] < 0.0 && pair[1] >= 0.0)
+            .map(|(n, pair)| n as f64 + pair[0] / (pair[0] - pair[1]))
+            .collect();
+        let periods = crossings.len() - 1;
+        periods as f64 / (crossings[periods] - crossings[0])
+    }
+
     #[test]
-    fn make_resampler_fo() {
+    fn pitch_and_speed() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 16,
+            oversampling_factor: 64,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2);
-        let frames = resampler.nbr_frames_needed();
-        println!("{}", frames);
-        assert!(frames > 800 && frames < 900);
-        let waves = vec![vec![0.0f64; frames]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[0].len(), 1024);
+        let mut resampler = SincFixedIn::<f64>::new(1.0, params, 256, 1);
+        resampler.set_max_resample_ratio_relative(2.0).unwrap();
+        resampler.set_pitch_semitones(12.0, false).unwrap();
+        assert!((resampler.resample_ratio() - 0.5).abs() < 1e-12);
+        let freq = output_tone_frequency(&mut resampler);
+        assert!((freq - 0.1).abs() < 1e-4, "frequency {}", freq);
+
+        let mut resampler = SincFixedOut::<f64>::new(1.0, params, 256, 1);
+        resampler.set_max_resample_ratio_relative(2.0).unwrap();
+        resampler.set_pitch_semitones(-12.0, false).unwrap();
+        assert!((resampler.resample_ratio() - 2.0).abs() < 1e-12);
+        let freq = output_tone_frequency(&mut resampler);
+        assert!((freq - 0.025).abs() < 1e-4, "frequency {}", freq);
+
+        resampler.set_speed_percent(200.0, false).unwrap();
+        assert!((resampler.resample_ratio() - 0.5).abs() < 1e-12);
+        resampler.set_speed_percent(100.0, true).unwrap();
+        assert!((resampler.resample_ratio() - 1.0).abs() < 1e-12);
+
+        match resampler.set_pitch_semitones(13.0, false) {
+            Err(ResampleError::RatioOutOfBounds { value, min, max }) => {
+                assert_eq!(value, 13.0);
+                assert!((min + 12.0).abs() < 1e-9 && (max - 12.0).abs() < 1e-9);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resampler.set_speed_percent(40.0, false) {
+            Err(ResampleError::RatioOutOfBounds { value, min, max }) => {
+                assert_eq!(value, 40.0);
+                assert!((min - 50.0).abs() < 1e-9 && (max - 200.0).abs() < 1e-9);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(resampler.set_pitch_semitones(std::f64::NAN, false).is_err());
+        assert!((resampler.resample_ratio() - 1.0).abs() < 1e-12);
+    }
+
+    // Process `chunks` chunks of a two tone signal, starting at input frame `position`.
+    // Returns the output, and updates the position.
+    fn process_two_tones<R: Resampler<f64>>(
+        resampler: &mut R,
+        position: &mut usize,
+        chunks: usize,
+    ) -> Vec<f64> {
+        let mut output = Vec::new();
+        for _ in 0..chunks {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (*position..*position + frames)
+                .map(|n| (0.11 * n as f64).sin() + 0.5 * (0.37 * n as f64).cos())
+                .collect();
+            *position += frames;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+        output
+    }
+
+    // Get the smallest largest difference between the outputs, when shifting them
+    // against each other. The beginning of the late output is skipped,
+    // since it starts without any history.
+    fn smallest_difference(early: &[f64], late: &[f64]) -> f64 {
+        (0..256)
+            .map(|start| {
+                late[128..late.len() - 8]
+                    .iter()
+                    .zip(early[start..].iter())
+                    .fold(0.0, |acc: f64, (a, b)| acc.max((a - b).abs()))
+            })
+            .fold(std::f64::MAX, f64::min)
     }
 
     #[test]
-    fn make_resampler_fo_32() {
+    fn phase_alignment() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 16,
+            oversampling_factor: 256,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let mut resampler = SincFixedOut::<f32>::new(1.2, params, 1024, 2);
-        let frames = resampler.nbr_frames_needed();
-        println!("{}", frames);
-        assert!(frames > 800 && frames < 900);
-        let waves = vec![vec![0.0f32; frames]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[0].len(), 1024);
+        let ratio = 48000.0 / 44100.0;
+
+        let mut first = SincFixedIn::<f64>::new(ratio, params, 1000, 1);
+        let mut position = 0;
+        process_two_tones(&mut first, &mut position, 5);
+        let mut start = position;
+        let phase = first.phase();
+        let early = process_two_tones(&mut first, &mut position, 4);
+        let mut late_default = SincFixedIn::<f64>::new(ratio, params, 1000, 1);
+        let default = process_two_tones(&mut late_default, &mut start.clone(), 4);
+        let mut late = SincFixedIn::<f64>::new(ratio, params, 1000, 1);
+        assert_eq!(late.phase(), 0.0);
+        late.set_phase(phase).unwrap();
+        let aligned = process_two_tones(&mut late, &mut start, 4);
+        assert!(smallest_difference(&early, &default) > 0.01);
+        assert!(smallest_difference(&early, &aligned) < 1.0e-6);
+
+        let mut first = SincFixedOut::<f64>::new(ratio, params, 1000, 1);
+        let mut position = 0;
+        process_two_tones(&mut first, &mut position, 5);
+        let mut start = position;
+        let phase = first.phase();
+        let early = process_two_tones(&mut first, &mut position, 4);
+        let mut late = SincFixedOut::<f64>::new(ratio, params, 1000, 1);
+        late.set_phase(phase).unwrap();
+        assert!((late.phase() - phase).abs() < 1.0e-9);
+        let aligned = process_two_tones(&mut late, &mut start, 4);
+        assert!(smallest_difference(&early, &aligned) < 1.0e-6);
+
+        for bad in [1.0, -0.1, std::f64::NAN].iter() {
+            match late.set_phase(*bad) {
+                Err(ResampleError::BadPhaseUpdate) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        late.reset();
+        assert_eq!(late.phase(), 0.0);
+    }
+
+    // Compare a resampler primed with the frames before `seek`, and started there,
+    // with one that processes the whole signal.
+    fn check_primed<R: Resampler<f64>>(continuous: &mut R, primed: &mut R, seek: usize) {
+        let mut position = 0;
+        let whole = process_two_tones(continuous, &mut position, 8);
+        let mut position = seek;
+        let started = process_two_tones(primed, &mut position, 3);
+        // The output frame at the seek point.
+        let first = (seek as f64 * continuous.resample_ratio()).round() as usize;
+        for (n, (a, b)) in started.iter().zip(whole[first..].iter()).enumerate() {
+            assert!((a - b).abs() < 1.0e-5, "frame {}: {} != {}", n, a, b);
+        }
     }
 
     #[test]
-    fn make_resampler_fo_skipped() {
+    fn prime_with_history() {
         let params = InterpolationParameters {
             sinc_len: 64,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 16,
+            oversampling_factor: 256,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let mut resampler = SincFixedOut::<f64>::new(1.2, params, 1024, 2);
-        let frames = resampler.nbr_frames_needed();
-        println!("{}", frames);
-        assert!(frames > 800 && frames < 900);
-        let mut waves = vec![vec![0.0f64; frames], Vec::new()];
-        waves[0][100] = 3.0;
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[0].len(), 1024);
-        assert!(out[1].is_empty());
-        println!("{:?}", out[0]);
-        let summed = out[0].iter().sum::<f64>();
-        println!("sum: {}", summed);
-        assert!(summed < 4.0);
-        assert!(summed > 2.0);
+        let ratio = 48000.0 / 44100.0;
+        // A new resampler places output frame k at input frame (k + 1) / ratio - 1,
+        // so the continuous output has a frame exactly at the seek point.
+        let seek = 2940 - 1;
+        let history: Vec<f64> = (0..seek)
+            .map(|n| (0.11 * n as f64).sin() + 0.5 * (0.37 * n as f64).cos())
+            .collect();
 
-        let frames = resampler.nbr_frames_needed();
-        let mut waves = vec![Vec::new(), vec![0.0f64; frames]];
-        waves[1][10] = 3.0;
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2);
-        assert_eq!(out[1].len(), 1024);
-        assert!(out[0].is_empty());
-        let summed = out[1].iter().sum::<f64>();
-        assert!(summed < 4.0);
-        assert!(summed > 2.0);
+        let mut continuous = SincFixedIn::<f64>::new(ratio, params, 1000, 1);
+        let mut primed = SincFixedIn::<f64>::new(ratio, params, 1000, 1);
+        assert_eq!(primed.history_frames_needed(), 128);
+        primed.prime_with_history(&[&history]).unwrap();
+        check_primed(&mut continuous, &mut primed, seek);
+
+        let mut continuous = SincFixedOut::<f64>::new(ratio, params, 1000, 1);
+        let mut primed = SincFixedOut::<f64>::new(ratio, params, 1000, 1);
+        primed.prime_with_history(&[&history]).unwrap();
+        check_primed(&mut continuous, &mut primed, seek);
+
+        match primed.prime_with_history(&[&history[..127]]) {
+            Err(ResampleError::InsufficientInputBufferSize {
+                channel: 0,
+                expected: 128,
+                actual: 127,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match primed.prime_with_history(&[&history, &history]) {
+            Err(ResampleError::WrongNumberOfChannels {
+                expected: 1,
+                actual: 2,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
     }
 
-    #[test]
-    fn make_resampler_fo_downsample() {
+    // Resample a sum of tones in f32 with a long sinc, and return the rms difference
+    // from resampling the same input in f64.
+    fn f32_error(accumulation: Accumulation) -> f64 {
         let params = InterpolationParameters {
-            sinc_len: 256,
+            sinc_len: 512,
             f_cutoff: 0.95,
-            interpolation: InterpolationType::Cubic,
-            oversampling_factor: 160,
+            interpolation: InterpolationType::Linear,
+            oversampling_factor: 256,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation,
         };
-        let mut resampler = SincFixedOut::<f64>::new(0.125, params, 1024, 2);
-        let frames = resampler.nbr_frames_needed();
-        println!("{}", frames);
+        let ratio = 48000.0 / 44100.0;
+        let wave: Vec<f32> = (0..8192)
+            .map(|n| {
+                let sum: f64 = (1..20).map(|k| (0.0137 * (k * k * n) as f64).sin()).sum();
+                (sum / 20.0) as f32
+            })
+            .collect();
+        let wave_f64: Vec<f64> = wave.iter().map(|x| *x as f64).collect();
+        let mut resampler = SincFixedIn::<f32>::new(ratio, params, 8192, 1);
+        let mut reference = SincFixedIn::<f64>::new(ratio, params, 8192, 1);
+        let output = resampler.process(&[wave]).unwrap();
+        let expected = reference.process(&[wave_f64]).unwrap();
+        // Skip the start, where the output is still fading in.
+        let squares: Vec<f64> = output[0]
+            .iter()
+            .zip(expected[0].iter())
+            .skip(512)
+            .map(|(a, b)| (*a as f64 - b).powi(2))
+            .collect();
+        (squares.iter().sum::<f64>() / squares.len() as f64).sqrt()
+    }
+
+    #[test]
+    fn wide_accumulation() {
+        let native = f32_error(Accumulation::Native);
+        let double = f32_error(Accumulation::Double);
+        // The remaining error is mostly from rounding the sincs and the output to f32.
         assert!(
-            frames > 8192 && frames < 9000,
-            "expected {}..{} samples, got {}",
-            8192,
-            9000,
-            frames
+            double < 0.5 * native,
+            "native {}, double {}",
+            native,
+            double
         );
-        let waves = vec![vec![0.0f64; frames]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
+    }
+
+    // A full table of sincs, where the mirrored ones are exact reversed copies of the stored ones.
+    fn mirrored_table<T: Copy>(sincs: &[Vec<T>]) -> Vec<Vec<T>> {
+        let factor = sincs.len();
+        (0..factor)
+            .map(|subindex| match mirrored_subindex(subindex, factor) {
+                Some(stored) => sincs[stored].iter().rev().copied().collect(),
+                None => sincs[subindex].clone(),
+            })
+            .collect()
+    }
+
+    // Compare an interpolator that stores half the table
+    // with one that stores the full table, for all subindices.
+    fn check_mirrored<T: Sample>(half: &dyn SincInterpolator<T>, full: &dyn SincInterpolator<T>) {
+        let mut rng = rand::thread_rng();
+        let wave: Vec<T> = (0..200).map(|_| T::coerce(rng.gen::<f64>())).collect();
+        for subindex in 0..full.nbr_sincs() {
+            for index in [0, 17, 100].iter() {
+                assert_eq!(
+                    half.get_sinc_interpolated(&wave, *index, subindex),
+                    full.get_sinc_interpolated(&wave, *index, subindex),
+                    "subindex {}",
+                    subindex
+                );
+            }
+        }
+    }
+
+    fn check_mirrored_interpolators<T: Sample>(factor: usize) {
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = mirrored_table(&make_sincs::<T>(64, factor, 0.9, window));
+        let half = ScalarInterpolator::<T>::new(64, factor, 0.9, window);
+        let full = ScalarInterpolator::<T>::from_sincs(sincs.clone(), 32, false);
+        check_mirrored(&half, &full);
+        #[cfg(target_arch = "x86_64")]
+        if let Ok(half) = SseInterpolator::<T>::new(64, factor, 0.9, window) {
+            let full = SseInterpolator::<T>::from_sincs(sincs.clone(), 32, false).unwrap();
+            check_mirrored(&half, &full);
+        }
+        #[cfg(all(target_arch = "x86_64", feature = "avx"))]
+        if let Ok(half) = AvxInterpolator::<T>::new(64, factor, 0.9, window) {
+            let full = AvxInterpolator::<T>::from_sincs(sincs.clone(), 32, false).unwrap();
+            check_mirrored(&half, &full);
+        }
+        #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+        if let Ok(half) = NeonInterpolator::<T>::new(64, factor, 0.9, window) {
+            let full = NeonInterpolator::<T>::from_sincs(sincs.clone(), 32, false).unwrap();
+            check_mirrored(&half, &full);
+        }
+        #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+        if let Ok(half) = WasmInterpolator::<T>::new(64, factor, 0.9, window) {
+            let full = WasmInterpolator::<T>::from_sincs(sincs, 32, false).unwrap();
+            check_mirrored(&half, &full);
+        }
+    }
+
+    #[test]
+    fn mirrored_sincs() {
+        for factor in [1, 2, 15, 16].iter() {
+            check_mirrored_interpolators::<f32>(*factor);
+            check_mirrored_interpolators::<f64>(*factor);
+        }
+    }
+
+    #[test]
+    fn on_the_fly_interpolator() {
+        let mut rng = rand::thread_rng();
+        let wave: Vec<f64> = (0..200).map(|_| rng.gen::<f64>() - 0.5).collect();
+        let window = WindowFunction::Blackman2;
+        let table = ScalarInterpolator::<f64>::new(64, 12, 0.9, window);
+        let on_the_fly = OnTheFlyInterpolator::<f64>::new(64, 12, 0.9, window);
+        assert_eq!(on_the_fly.len(), 64);
+        assert_eq!(on_the_fly.nbr_sincs(), 12);
+        assert_eq!(on_the_fly.center(), table.center());
+        for subindex in 0..12 {
+            for index in [0, 33, 100].iter() {
+                let expected = table.get_sinc_interpolated(&wave, *index, subindex);
+                let value = on_the_fly.get_sinc_interpolated(&wave, *index, subindex);
+                assert!((value - expected).abs() < 1.0e-12);
+            }
+        }
+    }
+
+    fn resample_with_storage(phase: FilterPhase, storage: SincStorage) -> Vec<f32> {
+        let params = InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 32,
+            window: WindowFunction::BlackmanHarris2,
+            phase,
+            storage,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<f32>::new(0.8, params, 256, 1);
+        let mut output = Vec::new();
+        for chunk in 0..4 {
+            let wave: Vec<f32> = (0..256)
+                .map(|n| ((chunk * 256 + n) as f32 * 0.05).sin())
+                .collect();
+            output.extend(resampler.process(&[wave]).unwrap().remove(0));
+        }
+        output
+    }
+
+    #[test]
+    fn on_the_fly_storage() {
+        let table = resample_with_storage(FilterPhase::Linear, SincStorage::Table);
+        let on_the_fly = resample_with_storage(FilterPhase::Linear, SincStorage::OnTheFly);
+        assert_eq!(table.len(), on_the_fly.len());
+        for (value, expected) in on_the_fly.iter().zip(table.iter()) {
+            assert!((value - expected).abs() < 1.0e-5);
+        }
+        // Minimum phase falls back to a table.
         assert_eq!(
-            out[0].len(),
-            1024,
-            "Expected {} frames, got {}",
-            1024,
-            out[0].len()
+            resample_with_storage(FilterPhase::Minimum, SincStorage::Table),
+            resample_with_storage(FilterPhase::Minimum, SincStorage::OnTheFly)
         );
-        let frames2 = resampler.nbr_frames_needed();
-        assert!(
-            frames2 > 8189 && frames2 < 8195,
-            "expected {}..{} samples, got {}",
-            8189,
-            8195,
-            frames2
+    }
+
+    // A custom interpolator, that passes the calls on to a built-in one and counts them.
+    struct CountingInterpolator {
+        inner: ScalarInterpolator<f64>,
+        calls: Arc<AtomicUsize>,
+    }
+
+    impl SincInterpolator<f64> for CountingInterpolator {
+        fn get_sinc_interpolated(&self, wave: &[f64], index: usize, subindex: usize) -> f64 {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            self.inner.get_sinc_interpolated(wave, index, subindex)
+        }
+
+        fn len(&self) -> usize {
+            self.inner.len()
+        }
+
+        fn nbr_sincs(&self) -> usize {
+            self.inner.nbr_sincs()
+        }
+    }
+
+    #[test]
+    fn custom_interpolator() {
+        let window = WindowFunction::BlackmanHarris2;
+        let calls = Arc::new(AtomicUsize::new(0));
+        let custom = CountingInterpolator {
+            inner: ScalarInterpolator::new(64, 16, 0.95, window),
+            calls: calls.clone(),
+        };
+        let mut resampler_in = SincFixedIn::<f64>::new_with_interpolator(
+            1.2,
+            InterpolationType::Linear,
+            Box::new(custom),
+            256,
+            1,
         );
-        let waves2 = vec![vec![0.0f64; frames2]; 2];
-        let out2 = resampler.process(&waves2).unwrap();
-        assert_eq!(
-            out2[0].len(),
-            1024,
-            "Expected {} frames, got {}",
-            1024,
-            out2[0].len()
+        let mut reference_in = SincFixedIn::<f64>::new_with_interpolator(
+            1.2,
+            InterpolationType::Linear,
+            Box::new(ScalarInterpolator::new(64, 16, 0.95, window)),
+            256,
+            1,
+        );
+        let custom = CountingInterpolator {
+            inner: ScalarInterpolator::new(64, 16, 0.95, window),
+            calls: calls.clone(),
+        };
+        let mut resampler_out = SincFixedOut::<f64>::new_with_interpolator(
+            0.8,
+            InterpolationType::Linear,
+            Box::new(custom),
+            256,
+            1,
         );
+        let mut reference_out = SincFixedOut::<f64>::new_with_interpolator(
+            0.8,
+            InterpolationType::Linear,
+            Box::new(ScalarInterpolator::new(64, 16, 0.95, window)),
+            256,
+            1,
+        );
+        let wave: Vec<f64> = (0..512).map(|n| (n as f64 * 0.1).sin()).collect();
+        let mut output_frames = 0;
+        for _ in 0..3 {
+            let output = resampler_in.process(&[&wave[..256]]).unwrap();
+            assert_eq!(output, reference_in.process(&[&wave[..256]]).unwrap());
+            output_frames += output[0].len();
+            let frames = resampler_out.nbr_frames_needed();
+            let output = resampler_out.process(&[&wave[..frames]]).unwrap();
+            assert_eq!(output, reference_out.process(&[&wave[..frames]]).unwrap());
+            output_frames += output[0].len();
+        }
+        // Linear interpolation uses two points per output frame.
+        assert_eq!(calls.load(Ordering::Relaxed), 2 * output_frames);
+    }
+
+    // The level in dB of a full scale tone at `freq` relative to the input Nyquist frequency,
+    // after downsampling by a factor 2.
+    fn downsampled_level<T>(window: WindowFunction, freq: f64) -> f64
+    where
+        T: Sample + Into<f64>,
+    {
+        let params = InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler = SincFixedIn::<T>::new(0.5, params, 1024, 1);
+        let mut output = Vec::new();
+        for chunk in 0..4 {
+            let wave: Vec<T> = (0..1024)
+                .map(|n| T::coerce((std::f64::consts::PI * freq * (chunk * 1024 + n) as f64).sin()))
+                .collect();
+            output.extend(resampler.process(&[wave]).unwrap().remove(0));
+        }
+        // Skip the start, where the output ramps up.
+        let tail = &output[200..];
+        let mean_square = tail
+            .iter()
+            .map(|value| (*value).into().powi(2))
+            .sum::<f64>()
+            / tail.len() as f64;
+        10.0 * (2.0 * mean_square).log10()
     }
 
     #[test]
-    fn make_resampler_fo_upsample() {
+    fn stopband_f32() {
+        // The f32 stopband should be as good as the f64 one, as long as it is above
+        // the noise floor of f32 processing.
+        let windows = [
+            WindowFunction::Blackman,
+            WindowFunction::BlackmanHarris,
+            WindowFunction::Hann,
+        ];
+        for window in windows.iter() {
+            for freq in [0.6, 0.9].iter() {
+                let level_32 = downsampled_level::<f32>(*window, *freq);
+                let level_64 = downsampled_level::<f64>(*window, *freq);
+                assert!(level_64 < -75.0);
+                assert!((level_32 - level_64).abs() < 2.0);
+            }
+        }
+    }
+
+    // The output for a constant input of 0.5, after the start where the output ramps up.
+    // The filter is short with few sincs, where the gain at DC would differ between
+    // the sincs if they were not normalized one by one.
+    fn resample_dc<T>(interpolation: InterpolationType, ratio: f64, fixed_out: bool) -> Vec<f64>
+    where
+        T: Sample + Into<f64>,
+    {
+        let params = InterpolationParameters {
+            sinc_len: 32,
+            f_cutoff: 0.95,
+            interpolation,
+            oversampling_factor: 16,
+            window: WindowFunction::Hann,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        };
+        let mut resampler: Box<dyn crate::VecResampler<T>> = if fixed_out {
+            Box::new(SincFixedOut::<T>::new(ratio, params, 512, 1))
+        } else {
+            Box::new(SincFixedIn::<T>::new(ratio, params, 512, 1))
+        };
+        let mut output = Vec::new();
+        for _ in 0..4 {
+            let wave = vec![T::coerce(0.5); resampler.nbr_frames_needed()];
+            output.extend(resampler.process(&[wave]).unwrap().remove(0));
+        }
+        output
+            .iter()
+            .skip(128)
+            .map(|value| (*value).into())
+            .collect()
+    }
+
+    #[test]
+    fn unity_dc_gain() {
+        let interpolations = [
+            InterpolationType::Nearest,
+            InterpolationType::Linear,
+            InterpolationType::Cubic,
+        ];
+        for interpolation in interpolations.iter() {
+            for ratio in [0.5, 1.2, 48000.0 / 44100.0, 44100.0 / 48000.0].iter() {
+                for fixed_out in [false, true].iter() {
+                    let output_64 = resample_dc::<f64>(*interpolation, *ratio, *fixed_out);
+                    let output_32 = resample_dc::<f32>(*interpolation, *ratio, *fixed_out);
+                    for value in output_64.iter().chain(output_32.iter()) {
+                        assert!((value - 0.5).abs() < 1.0e-6);
+                    }
+                }
+            }
+        }
+    }
+
+    // Resample the next chunk of a continuous tone at 0.88 times the input Nyquist frequency.
+    fn next_tone_chunk<R: Resampler<f64>>(resampler: &mut R, position: &mut usize) -> Vec<f64> {
+        let frames = resampler.nbr_frames_needed();
+        let wave: Vec<f64> = (*position..*position + frames)
+            .map(|n| (2.0 * std::f64::consts::PI * 0.44 * n as f64).sin())
+            .collect();
+        *position += frames;
+        resampler.process(&[wave]).unwrap().remove(0)
+    }
+
+    #[test]
+    fn set_cutoff() {
         let params = InterpolationParameters {
             sinc_len: 256,
             f_cutoff: 0.95,
             interpolation: InterpolationType::Cubic,
-            oversampling_factor: 160,
+            oversampling_factor: 256,
             window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
         };
-        let mut resampler = SincFixedOut::<f64>::new(8.0, params, 1024, 2);
-        let frames = resampler.nbr_frames_needed();
-        println!("{}", frames);
-        assert!(
-            frames > 128 && frames < 300,
-            "expected {}..{} samples, got {}",
-            140,
-            200,
-            frames
-        );
-        let waves = vec![vec![0.0f64; frames]; 2];
-        let out = resampler.process(&waves).unwrap();
-        assert_eq!(out.len(), 2, "Expected {} channels, got {}", 2, out.len());
-        assert_eq!(
-            out[0].len(),
-            1024,
-            "Expected {} frames, got {}",
-            1024,
-            out[0].len()
-        );
-        let frames2 = resampler.nbr_frames_needed();
-        assert!(
-            frames2 > 125 && frames2 < 131,
-            "expected {}..{} samples, got {}",
-            125,
-            131,
-            frames2
-        );
-        let waves2 = vec![vec![0.0f64; frames2]; 2];
-        let out2 = resampler.process(&waves2).unwrap();
-        assert_eq!(
-            out2[0].len(),
-            1024,
-            "Expected {} frames, got {}",
+        let mut resampler = SincFixedIn::<f64>::new(1.1, params, 1024, 1);
+        let original = resampler.interpolator.clone();
+        let mut position = 0;
+        for _ in 0..3 {
+            next_tone_chunk(&mut resampler, &mut position);
+        }
+        assert!(rms(&next_tone_chunk(&mut resampler, &mut position)) > 0.6);
+
+        resampler.set_cutoff(0.8).unwrap();
+        // The first chunk fades over to the new filter.
+        let fading = next_tone_chunk(&mut resampler, &mut position);
+        let tenth = fading.len() / 10;
+        assert!(rms(&fading[..tenth]) > 0.6);
+        assert!(rms(&fading[fading.len() - tenth..]) < 0.1);
+        assert!(rms(&next_tone_chunk(&mut resampler, &mut position)) < 0.001);
+
+        // Switching back reuses the table for the original cutoff.
+        resampler.set_cutoff(0.95).unwrap();
+        assert!(Arc::ptr_eq(&resampler.interpolator, &original));
+        next_tone_chunk(&mut resampler, &mut position);
+        assert!(rms(&next_tone_chunk(&mut resampler, &mut position)) > 0.6);
+
+        let mut resampler = SincFixedOut::<f64>::new(1.1, params, 1024, 1);
+        resampler.set_cutoff(0.8).unwrap();
+        let mut position = 0;
+        for _ in 0..3 {
+            next_tone_chunk(&mut resampler, &mut position);
+        }
+        assert!(rms(&next_tone_chunk(&mut resampler, &mut position)) < 0.001);
+        for cutoff in [0.0, -0.5, 1.01, std::f32::NAN].iter() {
+            match resampler.set_cutoff(*cutoff) {
+                Err(ResampleError::BadCutoffUpdate) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+
+        let interpolator = Box::new(ScalarInterpolator::<f64>::new(
+            64,
+            16,
+            0.95,
+            WindowFunction::Hann2,
+        ));
+        let mut resampler = SincFixedIn::<f64>::new_with_interpolator(
+            1.1,
+            InterpolationType::Linear,
+            interpolator,
             1024,
-            out2[0].len()
+            1,
         );
+        match resampler.set_cutoff(0.8) {
+            Err(ResampleError::NoInterpolationParameters) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
     }
 }
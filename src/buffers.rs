@@ -0,0 +1,744 @@
+use crate::error::{ResampleError, ResampleResult};
+use crate::{InactiveOutput, Sample};
+
+/// A source of input frames for one processing call.
+pub(crate) trait InputBuffer<T> {
+    /// Copy the frames of channel `chan`, starting at frame `start`, into `dest`.
+    /// Any part of `dest` that extends past the end of the input is filled with zeros.
+    fn read_into(&self, chan: usize, start: usize, dest: &mut [T]);
+
+    /// Find the first frame of channel `chan` that holds a NaN or an infinite value.
+    fn find_non_finite(&self, chan: usize) -> Option<usize>;
+}
+
+/// A destination for output frames of one processing call.
+pub(crate) trait OutputBuffer<T> {
+    /// Write a single sample to channel `chan` at frame `frame`.
+    fn write(&mut self, chan: usize, frame: usize, value: T);
+
+    /// Write a number of consecutive samples to channel `chan`, starting at frame `start`.
+    fn write_slice(&mut self, chan: usize, start: usize, values: &[T]);
+
+    /// Write `frames` zeros to channel `chan`, starting at frame `start`.
+    fn write_zeros(&mut self, chan: usize, start: usize, frames: usize);
+}
+
+/// Input stored as one slice per channel.
+pub(crate) struct SequentialInput<'a, V> {
+    waves: &'a [V],
+    offset: usize,
+    frames: usize,
+}
+
+impl<'a, V> SequentialInput<'a, V> {
+    /// Read `frames` frames of each channel, starting at frame `offset`.
+    pub fn new(waves: &'a [V], offset: usize, frames: usize) -> Self {
+        SequentialInput {
+            waves,
+            offset,
+            frames,
+        }
+    }
+}
+
+impl<'a, T, V> InputBuffer<T> for SequentialInput<'a, V>
+where
+    T: Sample,
+    V: AsRef<[T]>,
+{
+    fn read_into(&self, chan: usize, start: usize, dest: &mut [T]) {
+        let available = self.frames.saturating_sub(start).min(dest.len());
+        if available > 0 {
+            let first = self.offset + start;
+            let wave = self.waves[chan].as_ref();
+            dest[..available].copy_from_slice(&wave[first..first + available]);
+        }
+        for value in dest[available..].iter_mut() {
+            *value = T::zero();
+        }
+    }
+
+    fn find_non_finite(&self, chan: usize) -> Option<usize> {
+        if self.frames == 0 {
+            return None;
+        }
+        let wave = self.waves[chan].as_ref();
+        find_non_finite(&wave[self.offset..self.offset + self.frames])
+    }
+}
+
+/// Input stored as interleaved frames in a single slice.
+pub(crate) struct InterleavedInput<'a, T> {
+    wave: &'a [T],
+    nbr_channels: usize,
+    frames: usize,
+}
+
+impl<'a, T> InterleavedInput<'a, T> {
+    /// Read `frames` frames from an interleaved slice with `nbr_channels` channels.
+    pub fn new(wave: &'a [T], nbr_channels: usize, frames: usize) -> Self {
+        InterleavedInput {
+            wave,
+            nbr_channels,
+            frames,
+        }
+    }
+}
+
+impl<'a, T> InputBuffer<T> for InterleavedInput<'a, T>
+where
+    T: Sample,
+{
+    fn read_into(&self, chan: usize, start: usize, dest: &mut [T]) {
+        let available = self.frames.saturating_sub(start).min(dest.len());
+        for (value, sample) in dest.iter_mut().zip(
+            self.wave[start * self.nbr_channels + chan..]
+                .iter()
+                .step_by(self.nbr_channels)
+                .take(available),
+        ) {
+            *value = *sample;
+        }
+        for value in dest[available..].iter_mut() {
+            *value = T::zero();
+        }
+    }
+
+    fn find_non_finite(&self, chan: usize) -> Option<usize> {
+        let frames = &self.wave[..self.frames * self.nbr_channels];
+        // Scan all channels at once, and only look for the position when needed.
+        find_non_finite(frames)?;
+        frames[chan..]
+            .iter()
+            .step_by(self.nbr_channels)
+            .position(|value| !value.is_finite())
+    }
+}
+
+/// Find the first NaN or infinite value in a slice.
+/// The values are checked in blocks, without branching for every value,
+/// so that the compiler can vectorize the loop.
+fn find_non_finite<T>(values: &[T]) -> Option<usize>
+where
+    T: Sample,
+{
+    const BLOCK: usize = 64;
+    for (block_nbr, block) in values.chunks(BLOCK).enumerate() {
+        let finite = block
+            .iter()
+            .fold(true, |finite, value| finite & value.is_finite());
+        if !finite {
+            let pos = block.iter().position(|value| !value.is_finite());
+            return pos.map(|pos| block_nbr * BLOCK + pos);
+        }
+    }
+    None
+}
+
+/// Check that the active channels of an input hold only finite values.
+pub(crate) fn validate_finite<T, I>(wave_in: &I, mask: &[bool]) -> ResampleResult<()>
+where
+    I: InputBuffer<T>,
+{
+    for (channel, active) in mask.iter().enumerate() {
+        if *active {
+            if let Some(frame) = wave_in.find_non_finite(channel) {
+                return Err(ResampleError::InvalidInput { channel, frame });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Output stored as one slice per channel.
+pub(crate) struct SequentialOutput<'a, U> {
+    waves: &'a mut [U],
+    offset: usize,
+}
+
+impl<'a, U> SequentialOutput<'a, U> {
+    /// Write to each channel, starting at frame `offset`.
+    pub fn new(waves: &'a mut [U], offset: usize) -> Self {
+        SequentialOutput { waves, offset }
+    }
+}
+
+impl<'a, T, U> OutputBuffer<T> for SequentialOutput<'a, U>
+where
+    T: Sample,
+    U: AsMut<[T]>,
+{
+    fn write(&mut self, chan: usize, frame: usize, value: T) {
+        self.waves[chan].as_mut()[self.offset + frame] = value;
+    }
+
+    fn write_slice(&mut self, chan: usize, start: usize, values: &[T]) {
+        let first = self.offset + start;
+        self.waves[chan].as_mut()[first..first + values.len()].copy_from_slice(values);
+    }
+
+    fn write_zeros(&mut self, chan: usize, start: usize, frames: usize) {
+        let first = self.offset + start;
+        for sample in self.waves[chan].as_mut()[first..first + frames].iter_mut() {
+            *sample = T::zero();
+        }
+    }
+}
+
+/// Output stored as interleaved frames in a single slice.
+pub(crate) struct InterleavedOutput<'a, T> {
+    wave: &'a mut [T],
+    nbr_channels: usize,
+}
+
+impl<'a, T> InterleavedOutput<'a, T> {
+    /// Write to an interleaved slice with `nbr_channels` channels.
+    pub fn new(wave: &'a mut [T], nbr_channels: usize) -> Self {
+        InterleavedOutput { wave, nbr_channels }
+    }
+}
+
+impl<'a, T> OutputBuffer<T> for InterleavedOutput<'a, T>
+where
+    T: Sample,
+{
+    fn write(&mut self, chan: usize, frame: usize, value: T) {
+        self.wave[frame * self.nbr_channels + chan] = value;
+    }
+
+    fn write_slice(&mut self, chan: usize, start: usize, values: &[T]) {
+        for (sample, value) in self.wave[start * self.nbr_channels + chan..]
+            .iter_mut()
+            .step_by(self.nbr_channels)
+            .zip(values.iter())
+        {
+            *sample = *value;
+        }
+    }
+
+    fn write_zeros(&mut self, chan: usize, start: usize, frames: usize) {
+        for sample in self.wave[start * self.nbr_channels + chan..]
+            .iter_mut()
+            .step_by(self.nbr_channels)
+            .take(frames)
+        {
+            *sample = T::zero();
+        }
+    }
+}
+
+/// An output that drops the first `skip` frames written to it,
+/// and moves the following frames to the start of the wrapped output.
+pub(crate) struct TrimmedOutput<'a, O> {
+    output: &'a mut O,
+    skip: usize,
+}
+
+impl<'a, O> TrimmedOutput<'a, O> {
+    /// Wrap `output`, dropping the first `skip` frames.
+    pub fn new(output: &'a mut O, skip: usize) -> Self {
+        TrimmedOutput { output, skip }
+    }
+}
+
+impl<'a, T, O> OutputBuffer<T> for TrimmedOutput<'a, O>
+where
+    O: OutputBuffer<T>,
+{
+    fn write(&mut self, chan: usize, frame: usize, value: T) {
+        if frame >= self.skip {
+            self.output.write(chan, frame - self.skip, value);
+        }
+    }
+
+    fn write_slice(&mut self, chan: usize, start: usize, values: &[T]) {
+        let dropped = self.skip.saturating_sub(start).min(values.len());
+        if dropped < values.len() {
+            self.output
+                .write_slice(chan, start + dropped - self.skip, &values[dropped..]);
+        }
+    }
+
+    fn write_zeros(&mut self, chan: usize, start: usize, frames: usize) {
+        let dropped = self.skip.saturating_sub(start).min(frames);
+        if dropped < frames {
+            self.output
+                .write_zeros(chan, start + dropped - self.skip, frames - dropped);
+        }
+    }
+}
+
+/// The number of output frames to discard at the start of a stream,
+/// when trimming of the output delay is enabled.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct DelayTrim {
+    enabled: bool,
+    frames: usize,
+    remaining: usize,
+}
+
+impl DelayTrim {
+    /// Enable or disable trimming.
+    /// A stream that hasn't started yet is trimmed by `delay` output frames,
+    /// otherwise the change applies from the next [restart](DelayTrim::restart).
+    pub fn set_enabled(&mut self, enabled: bool, delay: f64, started: bool) {
+        self.enabled = enabled;
+        if !started {
+            self.restart(delay);
+        }
+    }
+
+    /// Start a new stream, which is trimmed by `delay` output frames,
+    /// rounded to the nearest frame, if trimming is enabled.
+    pub fn restart(&mut self, delay: f64) {
+        self.frames = if self.enabled && delay > 0.0 {
+            delay.round() as usize
+        } else {
+            0
+        };
+        self.remaining = self.frames;
+    }
+
+    /// Get the number of frames trimmed from the start of the current stream.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Get the number of frames that are still to be discarded.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Get the number of frames that have been discarded so far.
+    pub fn skipped(&self) -> usize {
+        self.frames - self.remaining
+    }
+
+    /// Discard the first remaining frames of `produced` new frames,
+    /// and return the number of frames that are kept.
+    pub fn consume(&mut self, produced: usize) -> usize {
+        let skipped = produced.min(self.remaining);
+        self.remaining -= skipped;
+        produced - skipped
+    }
+}
+
+/// Check the channels of a sequential input, and mark the non-empty ones as active,
+/// unless they are disabled by the `stored` mask.
+/// Each non-empty channel must contain exactly `frames` frames.
+/// When no frames are needed, an empty channel can't be told apart from a skipped one,
+/// and all enabled channels are marked as active.
+/// `clear_history` is called for each channel that was inactive and becomes active.
+pub(crate) fn update_mask_from_buffers<T, V, F>(
+    mask: &mut [bool],
+    stored: &[bool],
+    wave_in: &[V],
+    frames: usize,
+    mut clear_history: F,
+) -> ResampleResult<()>
+where
+    V: AsRef<[T]>,
+    F: FnMut(usize),
+{
+    if wave_in.len() != mask.len() {
+        return Err(ResampleError::WrongNumberOfChannels {
+            expected: mask.len(),
+            actual: wave_in.len(),
+        });
+    }
+    for (chan, wave) in wave_in.iter().enumerate() {
+        let wave = wave.as_ref();
+        if !wave.is_empty() && wave.len() != frames {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: chan,
+                expected: frames,
+                actual: wave.len(),
+            });
+        }
+    }
+    for (chan, wave) in wave_in.iter().enumerate() {
+        let active = stored[chan] && (frames == 0 || !wave.as_ref().is_empty());
+        if active && !mask[chan] {
+            clear_history(chan);
+        }
+        mask[chan] = active;
+    }
+    Ok(())
+}
+
+/// Check the length of an active channels mask, and copy it to `dest`.
+/// All channels are active when no mask is given.
+pub(crate) fn store_mask(dest: &mut [bool], mask: Option<&[bool]>) -> ResampleResult<()> {
+    if let Some(active) = mask {
+        if active.len() != dest.len() {
+            return Err(ResampleError::WrongNumberOfMaskChannels {
+                expected: dest.len(),
+                actual: active.len(),
+            });
+        }
+        dest.copy_from_slice(active);
+    } else {
+        dest.iter_mut().for_each(|active| *active = true);
+    }
+    Ok(())
+}
+
+/// Store the active channels mask for the next processing call.
+/// The `stored` mask is used when no mask is given for the call.
+/// `clear_history` is called for each channel that was inactive and becomes active.
+pub(crate) fn update_mask<F>(
+    mask: &mut [bool],
+    stored: &[bool],
+    active_channels_mask: Option<&[bool]>,
+    mut clear_history: F,
+) -> ResampleResult<()>
+where
+    F: FnMut(usize),
+{
+    let active_channels_mask = active_channels_mask.unwrap_or(stored);
+    if active_channels_mask.len() != mask.len() {
+        return Err(ResampleError::WrongNumberOfMaskChannels {
+            expected: mask.len(),
+            actual: active_channels_mask.len(),
+        });
+    }
+    for (chan, active) in active_channels_mask.iter().enumerate() {
+        if *active && !mask[chan] {
+            clear_history(chan);
+        }
+        mask[chan] = *active;
+    }
+    Ok(())
+}
+
+/// Change the number of channels of a set of per-channel buffers.
+/// Removed channels are dropped. Added channels get zeroed buffers with the same length
+/// as the existing ones, or `frames` long if there are none.
+pub(crate) fn resize_channels<T: Sample>(
+    buffers: &mut Vec<Vec<T>>,
+    nbr_channels: usize,
+    frames: usize,
+) {
+    let frames = buffers.first().map_or(frames, |buf| buf.len());
+    buffers.resize(nbr_channels, vec![T::zero(); frames]);
+}
+
+/// Change the number of channels of a channel mask. Added channels are active.
+pub(crate) fn resize_mask(mask: &mut Vec<bool>, nbr_channels: usize) {
+    mask.resize(nbr_channels, true);
+}
+
+/// Write zeros to the first `frames` frames of the inactive channels,
+/// if requested by `inactive_output`.
+pub(crate) fn fill_inactive<T, O>(
+    wave_out: &mut O,
+    mask: &[bool],
+    inactive_output: InactiveOutput,
+    frames: usize,
+) where
+    O: OutputBuffer<T>,
+{
+    if inactive_output == InactiveOutput::Zero {
+        for (chan, active) in mask.iter().enumerate() {
+            if !active {
+                wave_out.write_zeros(chan, 0, frames);
+            }
+        }
+    }
+}
+
+/// Check that an interleaved input holds exactly `frames` frames,
+/// and that an interleaved output has room for at least `frames_out_max` frames.
+pub(crate) fn validate_interleaved<T>(
+    wave_in: &[T],
+    wave_out: &[T],
+    nbr_channels: usize,
+    frames_in: usize,
+    frames_out_max: usize,
+) -> ResampleResult<()> {
+    let frames = wave_in.len() / nbr_channels;
+    if frames * nbr_channels != wave_in.len() {
+        return Err(ResampleError::WrongInterleavedLength {
+            nbr_channels,
+            length: wave_in.len(),
+        });
+    }
+    if frames != frames_in {
+        return Err(ResampleError::WrongNumberOfFrames {
+            channel: 0,
+            expected: frames_in,
+            actual: frames,
+        });
+    }
+    validate_interleaved_output(wave_out, nbr_channels, frames_out_max)
+}
+
+/// Check that an interleaved output has room for at least `frames_out_max` frames.
+fn validate_interleaved_output<T>(
+    wave_out: &[T],
+    nbr_channels: usize,
+    frames_out_max: usize,
+) -> ResampleResult<()> {
+    if wave_out.len() / nbr_channels < frames_out_max {
+        return Err(ResampleError::InsufficientOutputBufferSize {
+            expected: frames_out_max,
+            actual: wave_out.len() / nbr_channels,
+        });
+    }
+    Ok(())
+}
+
+/// Check that sequential input and output buffers have room for one processing call,
+/// reading `frames_in` frames from `input_offset` and writing up to `frames_out_max` frames
+/// from `output_offset`. Only the channels marked as active in `mask` are checked.
+pub(crate) fn validate_sequential<T, V, U>(
+    wave_in: &[V],
+    input_offset: usize,
+    wave_out: &mut [U],
+    output_offset: usize,
+    mask: &[bool],
+    frames_in: usize,
+    frames_out_max: usize,
+) -> ResampleResult<()>
+where
+    V: AsRef<[T]>,
+    U: AsMut<[T]>,
+{
+    validate_input(wave_in, input_offset, mask, frames_in)?;
+    validate_output(wave_out, output_offset, mask, frames_out_max)
+}
+
+/// Check that sequential input buffers hold `frames_in` frames, and that an interleaved
+/// output with `mask.len()` channels has room for at least `frames_out_max` frames.
+pub(crate) fn validate_sequential_to_interleaved<T, V>(
+    wave_in: &[V],
+    wave_out: &[T],
+    mask: &[bool],
+    frames_in: usize,
+    frames_out_max: usize,
+) -> ResampleResult<()>
+where
+    V: AsRef<[T]>,
+{
+    validate_input(wave_in, 0, mask, frames_in)?;
+    validate_interleaved_output(wave_out, mask.len(), frames_out_max)
+}
+
+/// Check that the active channels of a sequential input hold at least `frames_in` frames
+/// from `input_offset`.
+fn validate_input<T, V>(
+    wave_in: &[V],
+    input_offset: usize,
+    mask: &[bool],
+    frames_in: usize,
+) -> ResampleResult<()>
+where
+    V: AsRef<[T]>,
+{
+    validate_channels(wave_in.len(), mask.len())?;
+    for (chan, wave) in wave_in.iter().enumerate() {
+        let len_in = wave.as_ref().len();
+        if mask[chan] && len_in < input_offset + frames_in {
+            return Err(ResampleError::InsufficientInputBufferSize {
+                channel: chan,
+                expected: input_offset + frames_in,
+                actual: len_in,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Check the buffers for a partial processing call, where the input may hold fewer than
+/// `frames_in` frames, or be missing. All active input channels must have the same length.
+/// Returns the number of input frames available.
+pub(crate) fn validate_partial<T, V, U>(
+    wave_in: Option<&[V]>,
+    wave_out: &mut [U],
+    mask: &[bool],
+    frames_in: usize,
+    frames_out_max: usize,
+) -> ResampleResult<usize>
+where
+    V: AsRef<[T]>,
+    U: AsMut<[T]>,
+{
+    let mut frames = 0;
+    if let Some(wave_in) = wave_in {
+        validate_channels(wave_in.len(), mask.len())?;
+        let mut lengths = wave_in
+            .iter()
+            .enumerate()
+            .filter(|(chan, _)| mask[*chan])
+            .map(|(chan, wave)| (chan, wave.as_ref().len()));
+        if let Some((chan, len)) = lengths.next() {
+            if len > frames_in {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: frames_in,
+                    actual: len,
+                });
+            }
+            frames = len;
+        }
+        if let Some((chan, len)) = lengths.find(|(_, len)| *len != frames) {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: chan,
+                expected: frames,
+                actual: len,
+            });
+        }
+    }
+    validate_output(wave_out, 0, mask, frames_out_max)?;
+    Ok(frames)
+}
+
+/// Check the number of channels of a buffer.
+fn validate_channels(actual: usize, expected: usize) -> ResampleResult<()> {
+    if actual != expected {
+        return Err(ResampleError::WrongNumberOfChannels { expected, actual });
+    }
+    Ok(())
+}
+
+/// Check that sequential output buffers have room for `frames_out_max` frames
+/// from `output_offset`, for the channels marked as active in `mask`.
+fn validate_output<T, U>(
+    wave_out: &mut [U],
+    output_offset: usize,
+    mask: &[bool],
+    frames_out_max: usize,
+) -> ResampleResult<()>
+where
+    U: AsMut<[T]>,
+{
+    validate_channels(wave_out.len(), mask.len())?;
+    for (chan, wave) in wave_out.iter_mut().enumerate() {
+        let len_out = wave.as_mut().len();
+        if mask[chan] && len_out < output_offset + frames_out_max {
+            return Err(ResampleError::InsufficientOutputBufferSize {
+                expected: output_offset + frames_out_max,
+                actual: len_out,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::buffers::{fill_inactive, update_mask, SequentialInput, SequentialOutput};
+    use crate::buffers::{DelayTrim, TrimmedOutput};
+    use crate::buffers::{InputBuffer, InterleavedInput, InterleavedOutput, OutputBuffer};
+    use crate::InactiveOutput;
+
+    #[test]
+    fn read_interleaved() {
+        let wave = vec![1.0, 10.0, 2.0, 20.0, 3.0, 30.0];
+        let input = InterleavedInput::new(&wave, 2, 3);
+        let mut dest = vec![0.5; 4];
+        input.read_into(1, 1, &mut dest);
+        assert_eq!(dest, vec![20.0, 30.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn find_non_finite_values() {
+        let mut waves = vec![vec![1.0; 200]; 2];
+        waves[1][150] = std::f64::NAN;
+        waves[1][190] = std::f64::INFINITY;
+        let input = SequentialInput::new(&waves, 100, 60);
+        assert_eq!(input.find_non_finite(0), None);
+        assert_eq!(input.find_non_finite(1), Some(50));
+        let input = SequentialInput::new(&waves, 0, 150);
+        assert_eq!(input.find_non_finite(1), None);
+
+        let mut wave = vec![1.0; 2 * 100];
+        wave[2 * 70 + 1] = std::f64::NEG_INFINITY;
+        let input = InterleavedInput::new(&wave, 2, 100);
+        assert_eq!(input.find_non_finite(0), None);
+        assert_eq!(input.find_non_finite(1), Some(70));
+        let input = InterleavedInput::new(&wave, 2, 70);
+        assert_eq!(input.find_non_finite(1), None);
+    }
+
+    #[test]
+    fn read_sequential_with_offset() {
+        let waves = vec![vec![1.0, 2.0, 3.0, 4.0], vec![10.0, 20.0, 30.0, 40.0]];
+        let input = SequentialInput::new(&waves, 1, 2);
+        let mut dest = vec![0.5; 3];
+        input.read_into(1, 0, &mut dest);
+        assert_eq!(dest, vec![20.0, 30.0, 0.0]);
+    }
+
+    #[test]
+    fn write_interleaved_and_sequential() {
+        let mut wave = vec![0.0; 6];
+        let mut output = InterleavedOutput::new(&mut wave, 2);
+        output.write_slice(1, 1, &[2.0, 3.0]);
+        output.write(0, 0, 1.0);
+        assert_eq!(wave, vec![1.0, 0.0, 0.0, 2.0, 0.0, 3.0]);
+
+        let mut waves = vec![vec![0.0; 3], vec![0.0; 3]];
+        let mut output = SequentialOutput::new(&mut waves, 1);
+        output.write_slice(1, 0, &[2.0, 3.0]);
+        output.write(0, 1, 1.0);
+        assert_eq!(waves, vec![vec![0.0, 0.0, 1.0], vec![0.0, 2.0, 3.0]]);
+    }
+
+    #[test]
+    fn write_trimmed() {
+        let mut waves = vec![vec![0.0; 4]];
+        let mut sequential = SequentialOutput::new(&mut waves, 0);
+        let mut output = TrimmedOutput::new(&mut sequential, 3);
+        output.write_slice(0, 0, &[1.0, 2.0]);
+        output.write(0, 2, 3.0);
+        output.write_slice(0, 2, &[3.0, 4.0, 5.0]);
+        output.write(0, 5, 6.0);
+        assert_eq!(waves, vec![vec![4.0, 5.0, 6.0, 0.0]]);
+        let mut waves = vec![vec![1.0; 4]];
+        let mut sequential = SequentialOutput::new(&mut waves, 0);
+        TrimmedOutput::new(&mut sequential, 3).write_zeros(0, 1, 3);
+        assert_eq!(waves, vec![vec![0.0, 1.0, 1.0, 1.0]]);
+
+        let mut trim = DelayTrim::default();
+        trim.set_enabled(true, 2.6, false);
+        assert_eq!(trim.consume(2), 0);
+        assert_eq!(trim.consume(5), 4);
+        assert_eq!((trim.frames(), trim.remaining(), trim.skipped()), (3, 0, 3));
+        trim.set_enabled(false, 2.6, true);
+        assert_eq!(trim.frames(), 3);
+        trim.restart(2.6);
+        assert_eq!(trim.consume(5), 5);
+    }
+
+    #[test]
+    fn fill_inactive_channels() {
+        let mut wave = vec![1.0; 6];
+        let mut output = InterleavedOutput::new(&mut wave, 2);
+        fill_inactive(&mut output, &[true, false], InactiveOutput::Skip, 2);
+        fill_inactive(&mut output, &[false, true], InactiveOutput::Zero, 2);
+        assert_eq!(wave, vec![0.0, 1.0, 0.0, 1.0, 1.0, 1.0]);
+
+        let mut waves = vec![vec![1.0; 3], vec![1.0; 3]];
+        let mut output = SequentialOutput::new(&mut waves, 1);
+        fill_inactive(&mut output, &[true, false], InactiveOutput::Zero, 2);
+        assert_eq!(waves, vec![vec![1.0; 3], vec![1.0, 0.0, 0.0]]);
+    }
+
+    #[test]
+    fn clear_history_of_reactivated_channels() {
+        let mut mask = vec![true, false, false];
+        let stored = vec![false, true, false];
+        let mut cleared = Vec::new();
+        update_mask(&mut mask, &stored, None, |chan| cleared.push(chan)).unwrap();
+        assert_eq!(mask, stored);
+        assert_eq!(cleared, vec![1]);
+        cleared.clear();
+        update_mask(&mut mask, &stored, Some(&[true, true, true]), |chan| {
+            cleared.push(chan)
+        })
+        .unwrap();
+        assert_eq!(mask, vec![true, true, true]);
+        assert_eq!(cleared, vec![0, 2]);
+    }
+}
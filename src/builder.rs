@@ -0,0 +1,298 @@
+use crate::error::ResamplerConstructionError;
+use crate::oneshot::fft_suitable;
+use crate::{Accumulation, FilterPhase, InterpolationParameters, InterpolationType, SincStorage};
+use crate::{FftFixedIn, FftFixedInOut, FftFixedOut, SincFixedIn, SincFixedOut};
+use crate::{Sample, VecResampler, WindowFunction};
+
+/// The chunk size used when neither an input nor an output chunk size is given.
+const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// The number of sub chunks used for the FFT resamplers.
+const FFT_SUB_CHUNKS: usize = 2;
+
+/// Quality settings for the resamplers created by [ResamplerBuilder].
+///
+/// This selects the sinc interpolation parameters of the asynchronous resamplers.
+/// The synchronous FFT resamplers always give the best quality, and are not affected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    /// Short sinc filters and linear interpolation, for the lowest cpu load.
+    Low,
+    /// A balance between quality and cpu load.
+    Medium,
+    /// Long sinc filters and cubic interpolation, for the best quality.
+    High,
+}
+
+impl Quality {
+    /// Get the interpolation parameters for this quality.
+    pub fn interpolation_parameters(&self) -> InterpolationParameters {
+        match self {
+            Quality::Low => InterpolationParameters {
+                sinc_len: 64,
+                f_cutoff: 0.91,
+                interpolation: InterpolationType::Linear,
+                oversampling_factor: 128,
+                window: WindowFunction::Hann2,
+                phase: FilterPhase::Linear,
+                storage: SincStorage::Table,
+                accumulation: Accumulation::Native,
+            },
+            Quality::Medium => InterpolationParameters {
+                sinc_len: 128,
+                f_cutoff: 0.93,
+                interpolation: InterpolationType::Cubic,
+                oversampling_factor: 128,
+                window: WindowFunction::Blackman2,
+                phase: FilterPhase::Linear,
+                storage: SincStorage::Table,
+                accumulation: Accumulation::Native,
+            },
+            Quality::High => InterpolationParameters {
+                sinc_len: 256,
+                f_cutoff: 0.95,
+                interpolation: InterpolationType::Cubic,
+                oversampling_factor: 256,
+                window: WindowFunction::BlackmanHarris2,
+                phase: FilterPhase::Linear,
+                storage: SincStorage::Table,
+                accumulation: Accumulation::Native,
+            },
+        }
+    }
+}
+
+/// A builder that picks a suitable resampler for a pair of sample rates.
+///
+/// When the resampler needs to be adjustable, an asynchronous sinc resampler is used,
+/// otherwise a synchronous FFT resampler.
+/// Sample rates that only reduce to a fraction with large numbers, like 44100 and 44101 Hz,
+/// would need very long FFTs, and also use a sinc resampler.
+/// Setting the input chunk size gives a resampler with a fixed input size,
+/// [SincFixedIn] or [FftFixedIn], and setting the output chunk size gives
+/// [SincFixedOut] or [FftFixedOut].
+/// When neither is set, a fixed input size of 1024 frames is used for sinc resamplers,
+/// and an [FftFixedInOut] with chunks of about 1024 frames otherwise.
+///
+/// ```
+/// use rubato::{Quality, ResamplerBuilder};
+/// let mut resampler = ResamplerBuilder::new()
+///     .sample_rates(44100, 48000)
+///     .chunk_size_in(1024)
+///     .quality(Quality::High)
+///     .adjustable(true)
+///     .channels(2)
+///     .build::<f32>()
+///     .unwrap();
+/// let waves_in = vec![vec![0.0f32; 1024]; 2];
+/// let waves_out = resampler.process(&waves_in).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResamplerBuilder {
+    fs_in: usize,
+    fs_out: usize,
+    chunk_size_in: Option<usize>,
+    chunk_size_out: Option<usize>,
+    quality: Quality,
+    adjustable: bool,
+    nbr_channels: usize,
+}
+
+impl Default for ResamplerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResamplerBuilder {
+    /// Create a new builder, for a single channel with medium quality.
+    /// The sample rates must be set before building.
+    pub fn new() -> Self {
+        ResamplerBuilder {
+            fs_in: 0,
+            fs_out: 0,
+            chunk_size_in: None,
+            chunk_size_out: None,
+            quality: Quality::Medium,
+            adjustable: false,
+            nbr_channels: 1,
+        }
+    }
+
+    /// Set the input and output sample rates.
+    pub fn sample_rates(mut self, fs_in: usize, fs_out: usize) -> Self {
+        self.fs_in = fs_in;
+        self.fs_out = fs_out;
+        self
+    }
+
+    /// Use a fixed number of input frames for each call.
+    pub fn chunk_size_in(mut self, chunk_size: usize) -> Self {
+        self.chunk_size_in = Some(chunk_size);
+        self
+    }
+
+    /// Use a fixed number of output frames for each call.
+    pub fn chunk_size_out(mut self, chunk_size: usize) -> Self {
+        self.chunk_size_out = Some(chunk_size);
+        self
+    }
+
+    /// Set the quality, see [Quality].
+    pub fn quality(mut self, quality: Quality) -> Self {
+        self.quality = quality;
+        self
+    }
+
+    /// Select if the resample ratio must be adjustable after creation.
+    pub fn adjustable(mut self, adjustable: bool) -> Self {
+        self.adjustable = adjustable;
+        self
+    }
+
+    /// Set the number of channels.
+    pub fn channels(mut self, nbr_channels: usize) -> Self {
+        self.nbr_channels = nbr_channels;
+        self
+    }
+
+    /// Create the resampler.
+    /// # Errors
+    ///
+    /// The function returns an error if a sample rate, the number of channels
+    /// or a chunk size is zero, or if both an input and an output chunk size are given.
+    pub fn build<T: Sample>(&self) -> Result<Box<dyn VecResampler<T>>, ResamplerConstructionError> {
+        if self.fs_in == 0 || self.fs_out == 0 {
+            return Err(ResamplerConstructionError::InvalidSampleRate {
+                fs_in: self.fs_in,
+                fs_out: self.fs_out,
+            });
+        }
+        if self.nbr_channels == 0 {
+            return Err(ResamplerConstructionError::InvalidNbrChannels);
+        }
+        if self.chunk_size_in == Some(0) || self.chunk_size_out == Some(0) {
+            return Err(ResamplerConstructionError::InvalidChunkSize);
+        }
+        let ratio = self.fs_out as f64 / self.fs_in as f64;
+        let asynchronous = self.adjustable || !fft_suitable(self.fs_in, self.fs_out);
+        let resampler: Box<dyn VecResampler<T>> =
+            match (self.chunk_size_in, self.chunk_size_out, asynchronous) {
+                (Some(_), Some(_), _) => {
+                    return Err(ResamplerConstructionError::ConflictingChunkSizes);
+                }
+                (None, Some(chunk_size), true) => Box::new(SincFixedOut::<T>::new(
+                    ratio,
+                    self.quality.interpolation_parameters(),
+                    chunk_size,
+                    self.nbr_channels,
+                )),
+                (chunk_size, None, true) => Box::new(SincFixedIn::<T>::new(
+                    ratio,
+                    self.quality.interpolation_parameters(),
+                    chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE),
+                    self.nbr_channels,
+                )),
+                (None, Some(chunk_size), false) => Box::new(FftFixedOut::<T>::new(
+                    self.fs_in,
+                    self.fs_out,
+                    chunk_size,
+                    FFT_SUB_CHUNKS,
+                    self.nbr_channels,
+                )),
+                (Some(chunk_size), None, false) => Box::new(FftFixedIn::<T>::new(
+                    self.fs_in,
+                    self.fs_out,
+                    chunk_size,
+                    FFT_SUB_CHUNKS,
+                    self.nbr_channels,
+                )),
+                (None, None, false) => Box::new(FftFixedInOut::<T>::new(
+                    self.fs_in,
+                    self.fs_out,
+                    DEFAULT_CHUNK_SIZE,
+                    self.nbr_channels,
+                )),
+            };
+        Ok(resampler)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::builder::{Quality, ResamplerBuilder};
+    use crate::error::ResamplerConstructionError;
+
+    #[test]
+    fn build_fixed_in_and_out() {
+        let builder = ResamplerBuilder::new()
+            .sample_rates(44100, 48000)
+            .channels(2);
+        let resampler = builder.clone().chunk_size_in(1000).build::<f64>().unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), 1000);
+        assert_eq!(resampler.nbr_channels(), 2);
+        let mut resampler = builder
+            .clone()
+            .chunk_size_in(1000)
+            .adjustable(true)
+            .quality(Quality::Low)
+            .build::<f64>()
+            .unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), 1000);
+        assert!(resampler.set_resample_ratio_relative(1.05).is_ok());
+        let mut resampler = builder.clone().chunk_size_out(1000).build::<f32>().unwrap();
+        assert_eq!(resampler.output_frames_max(), 1000);
+        assert!(resampler.set_resample_ratio_relative(1.05).is_err());
+        let resampler = builder
+            .clone()
+            .chunk_size_out(1000)
+            .adjustable(true)
+            .build::<f32>()
+            .unwrap();
+        assert_eq!(resampler.output_frames_max(), 1000);
+        let resampler = builder.build::<f64>().unwrap();
+        assert!((resampler.resample_ratio() - 48000.0 / 44100.0).abs() < 1.0e-9);
+    }
+
+    // Rates that don't reduce to small numbers give a sinc resampler instead of a huge FFT.
+    #[test]
+    fn build_large_reduced_rates() {
+        let builder = ResamplerBuilder::new().sample_rates(44100, 44101);
+        let mut resampler = builder.clone().chunk_size_in(1000).build::<f64>().unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), 1000);
+        assert!(resampler.set_resample_ratio_relative(1.05).is_ok());
+        let resampler = builder.clone().chunk_size_out(1000).build::<f64>().unwrap();
+        assert_eq!(resampler.output_frames_max(), 1000);
+        let mut resampler = builder.build::<f64>().unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), 1024);
+        assert!((resampler.resample_ratio() - 44101.0 / 44100.0).abs() < 1.0e-9);
+        let waves_in = vec![vec![0.0; 1024]];
+        assert!(resampler.process(&waves_in).is_ok());
+    }
+
+    #[test]
+    fn build_errors() {
+        match ResamplerBuilder::new().build::<f64>() {
+            Err(ResamplerConstructionError::InvalidSampleRate { .. }) => {}
+            _ => panic!("expected an error"),
+        }
+        let builder = ResamplerBuilder::new().sample_rates(44100, 48000);
+        match builder.clone().channels(0).build::<f64>() {
+            Err(ResamplerConstructionError::InvalidNbrChannels) => {}
+            _ => panic!("expected an error"),
+        }
+        match builder.clone().chunk_size_out(0).build::<f64>() {
+            Err(ResamplerConstructionError::InvalidChunkSize) => {}
+            _ => panic!("expected an error"),
+        }
+        match builder
+            .chunk_size_in(1024)
+            .chunk_size_out(1024)
+            .adjustable(true)
+            .build::<f64>()
+        {
+            Err(ResamplerConstructionError::ConflictingChunkSizes) => {}
+            _ => panic!("expected an error"),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The bit of a stored ratio that holds the ramp flag.
+/// Resample ratios are positive, so the sign bit of the f64 is free.
+const RAMP_BIT: u64 = 1 << 63;
+
+/// The stored value when no update is pending. This is the bit pattern of 0.0,
+/// which is never a valid resample ratio.
+const NO_UPDATE: u64 = 0;
+
+/// The state shared between a resampler and its control handles.
+#[derive(Debug, Default)]
+struct Shared {
+    /// The pending update, a bit-cast f64 ratio with the ramp flag in the sign bit.
+    update: AtomicU64,
+    /// Set when an update was outside the allowed range and had to be clamped.
+    clamped: AtomicBool,
+}
+
+/// A handle for changing the resample ratio of an asynchronous resampler from another thread,
+/// without locks and without access to the resampler itself.
+///
+/// A handle is obtained from the `ratio_control` method of the resampler,
+/// for example [SincFixedIn::ratio_control](crate::SincFixedIn::ratio_control).
+/// It is cheap to clone, and all clones control the same resampler.
+///
+/// The resampler checks for a new ratio at the end of each processing call,
+/// so that [nbr_frames_needed](crate::Resampler::nbr_frames_needed) and
+/// [output_frames_max](crate::Resampler::output_frames_max) stay valid between calls.
+/// The new ratio is then used from the next chunk, either immediately or with a ramp.
+///
+/// A ratio outside the range allowed by the resampler is clamped to that range,
+/// since there is no way to return an error to the caller of [set](RatioControl::set).
+/// The clamping can be detected with [take_clamped](RatioControl::take_clamped).
+///
+/// # Memory ordering
+///
+/// The ratio and the ramp flag are stored together in a single `AtomicU64`,
+/// so an update is never seen half written.
+/// Setting a ratio is a release store, and the resampler takes the update with an acquire swap,
+/// so everything written by the control thread before setting the ratio is visible to the
+/// processing thread once it has used the new ratio.
+/// Only the most recent update is kept. When several updates are made between
+/// two processing calls, the last one wins and the earlier ones are never applied.
+/// An update made while a chunk is being processed is applied at the end of that chunk,
+/// or at the end of the next one.
+///
+/// ```
+/// use rubato::{Accumulation, FilterPhase, SincStorage, WindowFunction};
+/// use rubato::{InterpolationParameters, InterpolationType, Resampler, SincFixedIn};
+///
+/// let params = InterpolationParameters {
+///     sinc_len: 64,
+///     f_cutoff: 0.95,
+///     interpolation: InterpolationType::Linear,
+///     oversampling_factor: 64,
+///     window: WindowFunction::BlackmanHarris2,
+///     phase: FilterPhase::Linear,
+///     storage: SincStorage::Table,
+///     accumulation: Accumulation::Native,
+/// };
+/// let mut resampler = SincFixedIn::<f64>::new(1.0, params, 256, 1);
+/// let control = resampler.ratio_control();
+/// std::thread::spawn(move || control.set(1.05, false)).join().unwrap();
+/// let waves_in = resampler.input_buffer_allocate(true);
+/// resampler.process(&waves_in).unwrap();
+/// assert_eq!(resampler.resample_ratio(), 1.05);
+/// ```
+#[derive(Clone)]
+pub struct RatioControl {
+    shared: Arc<Shared>,
+}
+
+impl RatioControl {
+    /// Set a new resample ratio, used by the resampler from the next chunk.
+    /// With `ramp`, the ratio changes gradually over the ramp duration of the resampler,
+    /// otherwise the change is immediate.
+    /// A value that is not a positive finite number is ignored, and counts as clamped.
+    pub fn set(&self, new_ratio: f64, ramp: bool) {
+        if !(new_ratio > 0.0 && new_ratio.is_finite()) {
+            self.shared.clamped.store(true, Ordering::Relaxed);
+            return;
+        }
+        let mut bits = new_ratio.to_bits();
+        if ramp {
+            bits |= RAMP_BIT;
+        }
+        self.shared.update.store(bits, Ordering::Release);
+    }
+
+    /// Check if a ratio has been clamped to the allowed range since the last call,
+    /// and clear the flag.
+    pub fn take_clamped(&self) -> bool {
+        self.shared.clamped.swap(false, Ordering::Relaxed)
+    }
+
+    /// Check if an update is waiting to be picked up by the resampler.
+    pub fn is_pending(&self) -> bool {
+        self.shared.update.load(Ordering::Relaxed) != NO_UPDATE
+    }
+}
+
+impl fmt::Debug for RatioControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RatioControl")
+            .field("pending", &self.is_pending())
+            .finish()
+    }
+}
+
+/// The side of the shared state that is owned by the resampler.
+/// A clone gets new state of its own, so that a cloned resampler
+/// isn't controlled by the handles of the original.
+pub(crate) struct RatioReceiver {
+    shared: Arc<Shared>,
+}
+
+impl RatioReceiver {
+    pub fn new() -> Self {
+        RatioReceiver {
+            shared: Arc::new(Shared::default()),
+        }
+    }
+
+    /// Get a new handle for this receiver.
+    pub fn control(&self) -> RatioControl {
+        RatioControl {
+            shared: self.shared.clone(),
+        }
+    }
+
+    /// Take the pending update, if any, as a ratio and a ramp flag.
+    pub fn take(&self) -> Option<(f64, bool)> {
+        let bits = self.shared.update.swap(NO_UPDATE, Ordering::Acquire);
+        if bits == NO_UPDATE {
+            return None;
+        }
+        Some((f64::from_bits(bits & !RAMP_BIT), bits & RAMP_BIT != 0))
+    }
+
+    /// Record that a ratio was outside the allowed range.
+    pub fn set_clamped(&self) {
+        self.shared.clamped.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Clone for RatioReceiver {
+    fn clone(&self) -> Self {
+        RatioReceiver::new()
+    }
+}
+
+impl fmt::Debug for RatioReceiver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RatioReceiver").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RatioReceiver;
+
+    #[test]
+    fn store_and_take() {
+        let receiver = RatioReceiver::new();
+        let control = receiver.control();
+        assert_eq!(receiver.take(), None);
+        control.set(1.5, false);
+        control.set(0.75, true);
+        assert!(control.is_pending());
+        assert_eq!(receiver.take(), Some((0.75, true)));
+        assert_eq!(receiver.take(), None);
+        assert!(!control.is_pending());
+        control.set(std::f64::NAN, false);
+        control.set(-1.0, false);
+        assert_eq!(receiver.take(), None);
+        assert!(control.take_clamped());
+        assert!(!control.take_clamped());
+        let clone = receiver.clone();
+        control.set(1.25, false);
+        assert_eq!(clone.take(), None);
+        assert_eq!(receiver.take(), Some((1.25, false)));
+    }
+
+    // Update from several threads while taking the updates on this one,
+    // and check that every value taken is one that was set, in one piece.
+    #[test]
+    fn concurrent_updates() {
+        let receiver = RatioReceiver::new();
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let control = receiver.control();
+                std::thread::spawn(move || {
+                    for n in 0..10000 {
+                        control.set(1.0 + (t * 10000 + n) as f64 * 1.0e-6, t % 2 == 0);
+                    }
+                })
+            })
+            .collect();
+        for _ in 0..100000 {
+            if let Some((ratio, ramp)) = receiver.take() {
+                let value = ((ratio - 1.0) * 1.0e6).round() as usize;
+                assert!((ratio - 1.0 - value as f64 * 1.0e-6).abs() < 1.0e-12);
+                assert!(value < 40000);
+                assert_eq!(ramp, (value / 10000) % 2 == 0);
+            }
+        }
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        receiver.control().set(2.0, false);
+        assert_eq!(receiver.take(), Some((2.0, false)));
+    }
+}
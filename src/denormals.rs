@@ -0,0 +1,83 @@
+#[cfg(target_arch = "x86_64")]
+#[allow(deprecated)]
+use core::arch::x86_64::{_mm_getcsr, _mm_setcsr};
+
+/// The flush-to-zero bit of the MXCSR register.
+#[cfg(target_arch = "x86_64")]
+const FLUSH_TO_ZERO: u32 = 0x8000;
+/// The denormals-are-zero bit of the MXCSR register.
+#[cfg(target_arch = "x86_64")]
+const DENORMALS_ARE_ZERO: u32 = 0x0040;
+
+/// A guard that makes the floating point unit of the current thread treat denormal numbers
+/// as zero, and restores the previous mode when it is dropped.
+/// This is only implemented for x86_64, where it sets the FTZ and DAZ flags of the MXCSR register.
+/// On other architectures the guard does nothing.
+pub(crate) struct DenormalGuard {
+    #[cfg(target_arch = "x86_64")]
+    previous: Option<u32>,
+}
+
+impl DenormalGuard {
+    /// Create a new guard. The mode is only changed if `enabled` is true.
+    #[allow(unused_variables)]
+    pub fn new(enabled: bool) -> Self {
+        #[cfg(target_arch = "x86_64")]
+        {
+            let previous = if enabled {
+                // SSE is always available on x86_64.
+                #[allow(deprecated)]
+                let previous = unsafe { _mm_getcsr() };
+                #[allow(deprecated)]
+                unsafe {
+                    _mm_setcsr(previous | FLUSH_TO_ZERO | DENORMALS_ARE_ZERO)
+                };
+                Some(previous)
+            } else {
+                None
+            };
+            DenormalGuard { previous }
+        }
+        #[cfg(not(target_arch = "x86_64"))]
+        DenormalGuard {}
+    }
+}
+
+impl Drop for DenormalGuard {
+    fn drop(&mut self) {
+        #[cfg(target_arch = "x86_64")]
+        if let Some(previous) = self.previous {
+            #[allow(deprecated)]
+            unsafe {
+                _mm_setcsr(previous)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::denormals::DenormalGuard;
+
+    // Divide at runtime, so that the compiler can't do it at compile time.
+    fn divide(value: f64, divisor: f64) -> f64 {
+        unsafe { std::ptr::read_volatile(&value) / std::ptr::read_volatile(&divisor) }
+    }
+
+    #[test]
+    fn flush_denormals() {
+        let tiny = std::f64::MIN_POSITIVE;
+        assert!(divide(tiny, 4.0) > 0.0);
+        {
+            let _guard = DenormalGuard::new(false);
+            assert!(divide(tiny, 4.0) > 0.0);
+        }
+        #[cfg(target_arch = "x86_64")]
+        {
+            let _guard = DenormalGuard::new(true);
+            assert_eq!(divide(tiny, 4.0), 0.0);
+            assert_eq!(divide(tiny / 4.0, 1.0), 0.0);
+        }
+        assert!(divide(tiny, 4.0) > 0.0);
+    }
+}
@@ -16,6 +16,9 @@ pub enum CpuFeature {
     /// aarc64 neon cpu feature.
     #[cfg(all(feature = "neon", target_arch = "aarch64"))]
     Neon,
+    /// wasm32 simd128 target feature.
+    #[cfg(target_arch = "wasm32")]
+    Simd128,
 }
 
 impl CpuFeature {
@@ -38,6 +41,9 @@ impl CpuFeature {
             CpuFeature::Neon => {
                 is_aarch64_feature_detected!("neon")
             }
+            // WebAssembly has no runtime detection, the feature is enabled when compiling.
+            #[cfg(target_arch = "wasm32")]
+            CpuFeature::Simd128 => cfg!(target_feature = "simd128"),
         }
     }
 }
@@ -62,6 +68,10 @@ impl fmt::Display for CpuFeature {
             CpuFeature::Neon => {
                 write!(f, "neon")
             }
+            #[cfg(target_arch = "wasm32")]
+            CpuFeature::Simd128 => {
+                write!(f, "simd128")
+            }
         }
     }
 }
@@ -82,10 +92,40 @@ impl error::Error for MissingCpuFeature {}
 #[derive(Debug)]
 pub enum ResampleError {
     /// Error raised when Resample::set_resample_ratio is called with a ratio
-    /// that deviates for more than 10% of the original.
+    /// outside the range given by the maximum relative resample ratio.
     BadRatioUpdate,
+    /// Error raised when a speed or pitch change gives a resample ratio outside the range
+    /// given by the maximum relative resample ratio.
+    /// The requested value and the allowed range are given in the units of the request.
+    RatioOutOfBounds { value: f64, min: f64, max: f64 },
+    /// Error raised when Resample::set_max_resample_ratio_relative is called with a value
+    /// smaller than 1.0, or with a value that doesn't allow the ratios in use
+    /// or the working range of ratios.
+    BadMaxRatioUpdate,
+    /// Error raised when trying to set a working range of resample ratios that is empty,
+    /// not within the range given by the maximum relative resample ratio,
+    /// or that excludes the original ratio or a ratio in use.
+    BadWorkingRatioRange,
+    /// Error raised when trying to set a chunk size of zero.
+    BadChunkSizeUpdate,
+    /// Error raised when trying to set the number of channels to zero.
+    BadNbrChannelsUpdate,
+    /// Error raised when trying to set a relative cutoff frequency outside the range (0, 1].
+    BadCutoffUpdate,
+    /// Error raised when trying to set a phase outside the range [0, 1).
+    BadPhaseUpdate,
+    /// Error raised when trying to set a gain that is negative or not finite.
+    BadGainUpdate,
     /// Error raised when trying to adjust a synchronous resampler.
     SyncNotAdjustable,
+    /// Error raised when an operation needs the interpolation parameters of a resampler
+    /// that was created with an existing interpolator.
+    NoInterpolationParameters,
+    /// Error raised when a sample rate given to [resample](crate::resample) is zero.
+    InvalidSampleRate { fs_in: usize, fs_out: usize },
+    /// Error raised when the input given to [resample](crate::resample)
+    /// has no channels or no frames.
+    EmptyInput,
     /// Error raised when the number of channels doesn't match expected.
     WrongNumberOfChannels { expected: usize, actual: usize },
     /// Error raised when the number of frames in a single channel doesn't match
@@ -95,6 +135,38 @@ pub enum ResampleError {
         expected: usize,
         actual: usize,
     },
+    /// Error raised when a curve of resample ratios doesn't hold
+    /// one value per output frame of the chunk.
+    WrongRatioCurveLength { expected: usize, actual: usize },
+    /// Error raised when the length of an interleaved buffer
+    /// is not a multiple of the number of channels.
+    WrongInterleavedLength { nbr_channels: usize, length: usize },
+    /// Error raised when an input channel doesn't hold the number of frames
+    /// needed for the next processing call.
+    InsufficientInputBufferSize {
+        channel: usize,
+        expected: usize,
+        actual: usize,
+    },
+    /// Error raised when the output buffer can't hold the number of frames
+    /// that may be produced.
+    InsufficientOutputBufferSize { expected: usize, actual: usize },
+    /// Error raised when the length of the active channels mask doesn't match
+    /// the number of channels.
+    WrongNumberOfMaskChannels { expected: usize, actual: usize },
+    /// Error raised when a stream id doesn't refer to a stream of a
+    /// [MultiResampler](crate::MultiResampler).
+    InvalidStream { stream_id: usize },
+    /// Error raised when a channel index is not smaller than the number of channels.
+    InvalidChannel { channel: usize, nbr_channels: usize },
+    /// Error raised when restoring a saved state into a resampler
+    /// that is configured differently from the one that saved it.
+    IncompatibleState { parameter: &'static str },
+    /// Error raised when combining resamplers that differ in the given parameter.
+    IncompatibleResamplers { parameter: &'static str },
+    /// Error raised when input validation is enabled, and an active input channel
+    /// holds a NaN or an infinite value.
+    InvalidInput { channel: usize, frame: usize },
 }
 
 impl fmt::Display for ResampleError {
@@ -103,9 +175,62 @@ impl fmt::Display for ResampleError {
             Self::BadRatioUpdate => {
                 write!(f, "New resample ratio is too far off from original")
             }
+            Self::RatioOutOfBounds { value, min, max } => {
+                write!(
+                    f,
+                    "Value {} is outside the allowed range {} to {}",
+                    value, min, max
+                )
+            }
+            Self::BadMaxRatioUpdate => {
+                write!(
+                    f,
+                    "Maximum relative resample ratio must be at least 1.0 and allow the ratios in use"
+                )
+            }
+            Self::BadWorkingRatioRange => {
+                write!(
+                    f,
+                    "Working range of resample ratios must be within the allowed range and include the ratios in use"
+                )
+            }
+            Self::BadChunkSizeUpdate => {
+                write!(f, "Chunk size must be larger than zero")
+            }
+            Self::BadNbrChannelsUpdate => {
+                write!(f, "Number of channels must be larger than zero")
+            }
+            Self::BadCutoffUpdate => {
+                write!(
+                    f,
+                    "Relative cutoff frequency must be larger than zero and at most 1.0"
+                )
+            }
+            Self::BadPhaseUpdate => {
+                write!(f, "Phase must be at least 0.0 and smaller than 1.0")
+            }
+            Self::BadGainUpdate => {
+                write!(f, "Gain must be a finite number of at least 0.0")
+            }
             Self::SyncNotAdjustable { .. } => {
                 write!(f, "Not possible to adjust a synchronous resampler")
             }
+            Self::NoInterpolationParameters => {
+                write!(
+                    f,
+                    "The interpolation parameters of the resampler are not known"
+                )
+            }
+            Self::InvalidSampleRate { fs_in, fs_out } => {
+                write!(
+                    f,
+                    "Invalid sample rates {} and {}, both must be larger than zero",
+                    fs_in, fs_out
+                )
+            }
+            Self::EmptyInput => {
+                write!(f, "The input has no channels or no frames")
+            }
             Self::WrongNumberOfChannels { expected, actual } => {
                 write!(
                     f,
@@ -124,11 +249,193 @@ impl fmt::Display for ResampleError {
                     actual, channel, expected
                 )
             }
+            Self::WrongRatioCurveLength { expected, actual } => {
+                write!(
+                    f,
+                    "Wrong number of values {} in ratio curve, expected {}",
+                    actual, expected
+                )
+            }
+            Self::WrongInterleavedLength {
+                nbr_channels,
+                length,
+            } => {
+                write!(
+                    f,
+                    "Interleaved buffer length {} is not a multiple of the number of channels {}",
+                    length, nbr_channels
+                )
+            }
+            Self::InsufficientInputBufferSize {
+                channel,
+                expected,
+                actual,
+            } => {
+                write!(
+                    f,
+                    "Insufficient input buffer size {} frames in channel {}, expected at least {}",
+                    actual, channel, expected
+                )
+            }
+            Self::InsufficientOutputBufferSize { expected, actual } => {
+                write!(
+                    f,
+                    "Insufficient output buffer size {} frames, expected at least {}",
+                    actual, expected
+                )
+            }
+            Self::WrongNumberOfMaskChannels { expected, actual } => {
+                write!(
+                    f,
+                    "Wrong number of channels {} in active channels mask, expected {}",
+                    actual, expected
+                )
+            }
+            Self::InvalidStream { stream_id } => {
+                write!(f, "There is no stream with id {}", stream_id)
+            }
+            Self::InvalidChannel {
+                channel,
+                nbr_channels,
+            } => {
+                write!(
+                    f,
+                    "Invalid channel index {}, the resampler has {} channels",
+                    channel, nbr_channels
+                )
+            }
+            Self::IncompatibleState { parameter } => {
+                write!(
+                    f,
+                    "Saved state doesn't match the resampler, it has a different {}",
+                    parameter
+                )
+            }
+            Self::IncompatibleResamplers { parameter } => {
+                write!(
+                    f,
+                    "The resamplers can't be combined, they have a different {}",
+                    parameter
+                )
+            }
+            Self::InvalidInput { channel, frame } => {
+                write!(
+                    f,
+                    "Invalid input value in channel {} at frame {}, NaN or infinite",
+                    channel, frame
+                )
+            }
         }
     }
 }
 
 impl error::Error for ResampleError {}
 
+/// The error type returned when a [ResamplerBuilder](crate::ResamplerBuilder)
+/// can't create a resampler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResamplerConstructionError {
+    /// Error raised when a sample rate is zero or was not given.
+    InvalidSampleRate { fs_in: usize, fs_out: usize },
+    /// Error raised when the number of channels is zero.
+    InvalidNbrChannels,
+    /// Error raised when a chunk size is zero.
+    InvalidChunkSize,
+    /// Error raised when both an input and an output chunk size are given.
+    ConflictingChunkSizes,
+    /// Error raised when the oversampling factor of a filter is zero or too large.
+    InvalidOversamplingFactor { factor: usize },
+    /// Error raised when the length of a filter is not a multiple of 8 times
+    /// the oversampling factor, or is zero or too large.
+    InvalidFilterLength { length: usize, factor: usize },
+    /// Error raised when a filter contains values that are not finite,
+    /// or has zero gain at low frequencies.
+    InvalidFilterTaps,
+    /// Error raised when no filter meets a specification, or the specification is invalid.
+    UnachievableFilterSpec,
+    /// Error raised when a custom window has values that are not finite, or is zero everywhere.
+    InvalidWindow,
+    /// Error raised when the maximum relative resample ratio is smaller than 1.0.
+    InvalidMaxRatio,
+    /// Error raised when no chunk size gives a latency within the requested maximum.
+    /// Both latencies are in output frames.
+    LatencyTooLow {
+        max_latency: usize,
+        min_latency: usize,
+    },
+}
+
+impl fmt::Display for ResamplerConstructionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSampleRate { fs_in, fs_out } => {
+                write!(
+                    f,
+                    "Invalid sample rates {} and {}, both must be larger than zero",
+                    fs_in, fs_out
+                )
+            }
+            Self::InvalidNbrChannels => {
+                write!(f, "The number of channels must be larger than zero")
+            }
+            Self::InvalidChunkSize => {
+                write!(f, "The chunk size must be larger than zero")
+            }
+            Self::ConflictingChunkSizes => {
+                write!(
+                    f,
+                    "Only one of the input and output chunk sizes can be fixed"
+                )
+            }
+            Self::InvalidOversamplingFactor { factor } => {
+                write!(f, "Invalid oversampling factor {}", factor)
+            }
+            Self::InvalidFilterLength { length, factor } => {
+                write!(
+                    f,
+                    "Invalid filter length {}, must be a multiple of 8 times the oversampling factor {}",
+                    length, factor
+                )
+            }
+            Self::InvalidFilterTaps => {
+                write!(
+                    f,
+                    "The filter must have finite values and a non-zero gain at low frequencies"
+                )
+            }
+            Self::UnachievableFilterSpec => {
+                write!(
+                    f,
+                    "No sinc filter meets the specified attenuation and transition band"
+                )
+            }
+            Self::InvalidWindow => {
+                write!(
+                    f,
+                    "The window must have finite values that are not all zero"
+                )
+            }
+            Self::InvalidMaxRatio => {
+                write!(
+                    f,
+                    "The maximum relative resample ratio must be at least 1.0"
+                )
+            }
+            Self::LatencyTooLow {
+                max_latency,
+                min_latency,
+            } => {
+                write!(
+                    f,
+                    "A latency of {} frames can't be reached, the smallest possible latency for these sample rates is {} frames",
+                    max_latency, min_latency
+                )
+            }
+        }
+    }
+}
+
+impl error::Error for ResamplerConstructionError {}
+
 /// A result alias for the error type used by `rubato`.
 pub type ResampleResult<T> = ::std::result::Result<T, ResampleError>;
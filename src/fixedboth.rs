@@ -0,0 +1,746 @@
+use crate::buffers::validate_sequential_to_interleaved;
+use crate::buffers::{fill_inactive, validate_finite, validate_partial, validate_sequential};
+use crate::buffers::{resize_channels, resize_mask, InputBuffer, OutputBuffer};
+use crate::buffers::{store_mask, update_mask, update_mask_from_buffers, validate_interleaved};
+use crate::buffers::{DelayTrim, TrimmedOutput};
+use crate::buffers::{InterleavedInput, InterleavedOutput, SequentialInput, SequentialOutput};
+use crate::denormals::DenormalGuard;
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::{process_final, InactiveOutput, InterpolationParameters, Resampler, Sample};
+use crate::{RatioControl, SincFixedIn};
+use std::fmt;
+
+/// An asynchronous resampler that accepts a fixed number of audio frames for input
+/// and returns a fixed number of frames.
+///
+/// This is meant for bridging two devices that both process audio in fixed chunks,
+/// for example a capture device that delivers 480 frames per period, and a playback device
+/// that wants 441 frames per period. The average ratio between the chunk sizes must
+/// match the resample ratio.
+///
+/// The resampling is done by a [SincFixedIn], that writes its output to a FIFO.
+/// Each call to "process" takes `chunk_size_in` frames, and returns the oldest
+/// `chunk_size_out` frames from the FIFO. The FIFO starts out holding
+/// [fifo_target](SincFixedBoth::fifo_target) frames of silence,
+/// which leaves room for the number of frames produced to vary from call to call.
+/// It also holds the frames that the first chunk of the [SincFixedIn] is short,
+/// so that the fill level settles at the target.
+/// Any difference between the resample ratio and the real ratio between the chunk rates
+/// makes the FIFO slowly fill up or run empty. The fill level is given by
+/// [fifo_fill](SincFixedBoth::fifo_fill), and a [RateAdjuster](crate::RateAdjuster)
+/// can use it to adjust the resample ratio so that the fill level stays at the target.
+///
+/// If the FIFO runs empty, the missing output frames are filled with zeros.
+/// If it holds more than twice the target after a call, the oldest frames are dropped.
+/// Both cause an audible glitch, but keep the chunk sizes fixed.
+///
+/// All buffers are allocated when the resampler is created,
+/// except when the range of ratios is changed with
+/// [set_max_resample_ratio_relative](Resampler::set_max_resample_ratio_relative)
+/// or the number of channels is changed.
+///
+/// A clone starts from the current processing state of the original,
+/// and shares the table of sincs with it.
+#[derive(Clone)]
+pub struct SincFixedBoth<T> {
+    nbr_channels: usize,
+    chunk_size_in: usize,
+    chunk_size_out: usize,
+    sinc: SincFixedIn<T>,
+    buffer_in: Vec<Vec<T>>,
+    fifo: Vec<Vec<T>>,
+    fifo_fill: usize,
+    fifo_target: usize,
+    fifo_start: usize,
+    channel_mask: Vec<bool>,
+    active_channels_mask: Vec<bool>,
+    inactive_output: InactiveOutput,
+    validate_input: bool,
+    flush_denormals: bool,
+    trim: DelayTrim,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
+    output_frames_total: u64,
+}
+
+impl<T> SincFixedBoth<T>
+where
+    T: Sample,
+{
+    /// Create a new SincFixedBoth
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `max_resample_ratio_relative`: How much the ratio can be adjusted,
+    ///   see [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size_in`: size of input data in frames
+    /// - `chunk_size_out`: size of output data in frames
+    /// - `nbr_channels`: number of channels in input/output
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a chunk size or the number of channels is zero,
+    /// or if the maximum relative ratio is smaller than 1.0.
+    pub fn new(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: InterpolationParameters,
+        chunk_size_in: usize,
+        chunk_size_out: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new SincFixedBoth, ratio: {}, chunk_size_in: {}, chunk_size_out: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size_in, chunk_size_out, nbr_channels, parameters
+        );
+        if chunk_size_in == 0 || chunk_size_out == 0 {
+            return Err(ResamplerConstructionError::InvalidChunkSize);
+        }
+        if nbr_channels == 0 {
+            return Err(ResamplerConstructionError::InvalidNbrChannels);
+        }
+        let mut sinc = SincFixedIn::new(resample_ratio, parameters, chunk_size_in, nbr_channels);
+        sinc.set_max_resample_ratio_relative(max_resample_ratio_relative)
+            .map_err(|_| ResamplerConstructionError::InvalidMaxRatio)?;
+        let mut resampler = SincFixedBoth {
+            nbr_channels,
+            chunk_size_in,
+            chunk_size_out,
+            sinc,
+            buffer_in: vec![vec![T::zero(); chunk_size_in]; nbr_channels],
+            fifo: vec![Vec::new(); nbr_channels],
+            fifo_fill: chunk_size_out,
+            fifo_target: chunk_size_out,
+            fifo_start: chunk_size_out,
+            channel_mask: vec![true; nbr_channels],
+            active_channels_mask: vec![true; nbr_channels],
+            inactive_output: InactiveOutput::Skip,
+            validate_input: false,
+            flush_denormals: false,
+            trim: DelayTrim::default(),
+            input_frames_total: 0,
+            output_frames_final: None,
+            output_frames_total: 0,
+        };
+        resampler.fifo_start = chunk_size_out + resampler.sinc.output_frames_held_back();
+        resampler.fifo_fill = resampler.fifo_start;
+        resampler.resize_fifo();
+        Ok(resampler)
+    }
+
+    /// Get the number of input frames expected by each call to "process".
+    pub fn chunk_size_in(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    /// Get the number of output frames returned by each call to "process".
+    pub fn chunk_size_out(&self) -> usize {
+        self.chunk_size_out
+    }
+
+    /// Get a handle for changing the resample ratio from another thread, see [RatioControl].
+    /// The FIFO takes up the difference in the number of frames produced.
+    pub fn ratio_control(&self) -> RatioControl {
+        self.sinc.ratio_control()
+    }
+
+    /// Get the number of frames in the FIFO after the most recent call to "process".
+    /// Before the first call, this also includes the frames that the first chunk is short.
+    pub fn fifo_fill(&self) -> usize {
+        self.fifo_fill
+    }
+
+    /// Get the number of frames the FIFO holds after each call when the ratio matches.
+    /// This is one output chunk, and is the fill level to aim for when adjusting the ratio.
+    pub fn fifo_target(&self) -> usize {
+        self.fifo_target
+    }
+
+    /// Make the FIFO large enough for the largest fill level plus the output of one call
+    /// at the highest allowed ratio.
+    fn resize_fifo(&mut self) {
+        let fifo_max = (2 * self.fifo_target).max(self.fifo_start);
+        let fifo_len = fifo_max + self.sinc.output_frames_max_allowed();
+        for chan in self.fifo.iter_mut() {
+            if chan.len() < fifo_len {
+                chan.resize(fifo_len, T::zero());
+            }
+        }
+    }
+
+    /// Get the delay of the sinc resampler plus the initial fill level, in output frames.
+    fn untrimmed_delay(&self) -> f64 {
+        self.sinc.output_delay_fractional() + self.fifo_start as f64
+    }
+
+    /// Resample one chunk from `wave_in` into the FIFO, and move one chunk from the FIFO
+    /// into `wave_out`, for the channels marked as active in the channel mask.
+    /// Returns the number of output frames.
+    fn process_buffers<I, O>(&mut self, wave_in: &I, wave_out: &mut O) -> ResampleResult<usize>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        if self.validate_input {
+            validate_finite(wave_in, &self.channel_mask)?;
+        }
+        let _denormals = DenormalGuard::new(self.flush_denormals);
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_in.read_into(chan, 0, &mut self.buffer_in[chan]);
+            }
+        }
+        let (_, frames_new) = self.sinc.process_into_buffer_at(
+            &self.buffer_in,
+            0,
+            &mut self.fifo,
+            self.fifo_fill,
+            Some(&self.channel_mask),
+        )?;
+        self.fifo_fill += frames_new;
+
+        let frames_available = self.fifo_fill.min(self.chunk_size_out);
+        if frames_available < self.chunk_size_out {
+            debug!(
+                "FIFO underrun, {} frames missing",
+                self.chunk_size_out - frames_available
+            );
+        }
+        // Drop the oldest frames if the FIFO holds too many after this call.
+        let fifo_max = 2 * self.fifo_target;
+        let frames_used = if self.fifo_fill - frames_available > fifo_max {
+            debug!(
+                "FIFO overflow, {} frames dropped",
+                self.fifo_fill - frames_available - fifo_max
+            );
+            self.fifo_fill - fifo_max
+        } else {
+            frames_available
+        };
+        let mut wave_out = TrimmedOutput::new(wave_out, self.trim.remaining());
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                let fifo = &mut self.fifo[chan];
+                wave_out.write_slice(chan, 0, &fifo[frames_used - frames_available..frames_used]);
+                wave_out.write_zeros(
+                    chan,
+                    frames_available,
+                    self.chunk_size_out - frames_available,
+                );
+                fifo.copy_within(frames_used..self.fifo_fill, 0);
+            }
+        }
+        self.fifo_fill -= frames_used;
+        let frames_out = self.trim.consume(self.chunk_size_out);
+        self.input_frames_total += self.chunk_size_in as u64;
+        self.output_frames_total += frames_out as u64;
+        Ok(frames_out)
+    }
+}
+
+impl<T> fmt::Debug for SincFixedBoth<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SincFixedBoth")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size_in", &self.chunk_size_in)
+            .field("chunk_size_out", &self.chunk_size_out)
+            .field("fifo_fill", &self.fifo_fill)
+            .field("sinc", &self.sinc)
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for SincFixedBoth<T>
+where
+    T: Sample,
+{
+    /// Query for the number of frames needed for the next call to "process",
+    /// which is always the input chunk size.
+    fn nbr_frames_needed(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    /// Get the maximum number of input frames, which is always the input chunk size.
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    /// Get the maximum number of output frames, which is always the output chunk size.
+    fn output_frames_max(&self) -> usize {
+        self.chunk_size_out
+    }
+
+    /// Reset the resampler state, go back to the original resample ratio,
+    /// and fill the FIFO with silence up to the initial level.
+    fn reset(&mut self) {
+        self.sinc.reset();
+        for chan in self.fifo.iter_mut() {
+            chan.iter_mut().for_each(|x| *x = T::zero());
+        }
+        self.fifo_start = self.fifo_target + self.sinc.output_frames_held_back();
+        self.resize_fifo();
+        self.fifo_fill = self.fifo_start;
+        self.input_frames_total = 0;
+        self.output_frames_final = None;
+        self.output_frames_total = 0;
+        self.trim.restart(self.untrimmed_delay());
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        self.sinc.reset_channel(channel)?;
+        self.fifo[channel].iter_mut().for_each(|x| *x = T::zero());
+        Ok(())
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        if nbr_channels == 0 {
+            return Err(ResampleError::BadNbrChannelsUpdate);
+        }
+        trace!("Change number of channels to {}", nbr_channels);
+        self.sinc.set_nbr_channels(nbr_channels)?;
+        resize_channels(&mut self.buffer_in, nbr_channels, self.chunk_size_in);
+        resize_channels(&mut self.fifo, nbr_channels, 0);
+        resize_mask(&mut self.channel_mask, nbr_channels);
+        resize_mask(&mut self.active_channels_mask, nbr_channels);
+        self.nbr_channels = nbr_channels;
+        self.resize_fifo();
+        Ok(())
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.sinc.resample_ratio()
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
+
+    fn output_frames_total(&self) -> u64 {
+        self.output_frames_total
+    }
+
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        store_mask(&mut self.active_channels_mask, mask)
+    }
+
+    fn active_channels_mask(&self) -> &[bool] {
+        &self.active_channels_mask
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        self.inactive_output = inactive_output;
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        self.validate_input = enabled;
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        let started = self.input_frames_total > 0;
+        self.trim
+            .set_enabled(enabled, self.untrimmed_delay(), started);
+    }
+
+    /// Resample a chunk of audio. The input and output lengths are fixed.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the size of the input data is not equal
+    /// to the number of channels and input size defined when creating the instance.
+    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
+        let fifo = &mut self.fifo;
+        update_mask_from_buffers(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            wave_in,
+            self.chunk_size_in,
+            |chan| {
+                fifo[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan] = vec![T::zero(); self.chunk_size_out];
+            }
+        }
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, 0, self.chunk_size_in),
+            &mut SequentialOutput::new(&mut wave_out, 0),
+        )?;
+        for chan in wave_out.iter_mut() {
+            chan.truncate(frames_out);
+        }
+        Ok(wave_out)
+    }
+
+    /// Resample a chunk of interleaved audio. The input and output lengths are fixed.
+    /// # Errors
+    ///
+    /// The function returns an error if the input doesn't contain the number of frames
+    /// given by "nbr_frames_needed", if the output buffer is shorter than "output_frames_max",
+    /// or if the mask doesn't match the number of channels.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.chunk_size_in;
+        validate_interleaved(
+            wave_in,
+            wave_out,
+            self.nbr_channels,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let fifo = &mut self.fifo;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                fifo[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out = self.process_buffers(
+            &InterleavedInput::new(wave_in, self.nbr_channels, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// `input_offset` plus "nbr_frames_needed" frames, if an active output channel holds fewer
+    /// than `output_offset` plus "output_frames_max" frames, or if the number of channels
+    /// of the buffers or the mask is wrong.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.chunk_size_in;
+        let fifo = &mut self.fifo;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                fifo[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential(
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = SequentialOutput::new(wave_out, output_offset);
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, input_offset, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers,
+    /// and write the result to an interleaved output buffer.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// "nbr_frames_needed" frames, if the output buffer is shorter than "output_frames_max" frames,
+    /// or if the number of channels of the input or the mask is wrong.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.chunk_size_in;
+        let fifo = &mut self.fifo;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                fifo[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential_to_interleaved(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the last frames of a stream, when fewer frames than needed remain.
+    /// The missing input frames are replaced by zeros, and the input may also be `None`,
+    /// which processes only zeros.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds more than
+    /// "nbr_frames_needed" frames, if the active input channels have different lengths,
+    /// if an active output channel holds fewer than "output_frames_max" frames,
+    /// or if the number of channels of the buffers or the mask is wrong.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let fifo = &mut self.fifo;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                fifo[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_in = validate_partial(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            self.chunk_size_in,
+            self.output_frames_max(),
+        )?;
+        let wave_in = wave_in.unwrap_or(&[]);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    /// Only the output frames that correspond to real input are counted,
+    /// and zero frames are returned once the stream has been flushed.
+    /// # Errors
+    ///
+    /// The function returns an error under the same conditions as "process_partial_into_buffer".
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mut output_frames_final = self.output_frames_final;
+        let result = process_final(
+            self,
+            &mut output_frames_final,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+        );
+        self.output_frames_final = output_frames_final;
+        result
+    }
+
+    /// Update the resample ratio, within the range allowed by "max_resample_ratio_relative".
+    /// Changing the ratio makes the FIFO fill up or run empty.
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        self.sinc.set_resample_ratio(new_ratio)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        self.sinc.set_resample_ratio_relative(rel_ratio)
+    }
+
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()> {
+        self.sinc.set_sample_rates(fs_in, fs_out, ramp)
+    }
+
+    fn ramp_progress(&self) -> Option<f64> {
+        self.sinc.ramp_progress()
+    }
+
+    fn max_resample_ratio_relative(&self) -> f64 {
+        self.sinc.max_resample_ratio_relative()
+    }
+
+    /// Update the range of allowed resample ratios.
+    /// The FIFO is reallocated if needed.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()> {
+        self.sinc.set_max_resample_ratio_relative(max_relative)?;
+        self.resize_fifo();
+        Ok(())
+    }
+
+    /// Get the delay in output frames, including the target fill level of the FIFO.
+    fn output_delay_fractional(&self) -> f64 {
+        self.untrimmed_delay() - self.trim.frames() as f64
+    }
+
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        self.sinc.frequency_response(n_points)
+    }
+
+    fn group_delay(&self) -> f64 {
+        self.sinc.group_delay()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WindowFunction;
+    use crate::{
+        Accumulation, FilterPhase, InterpolationParameters, InterpolationType, SincStorage,
+    };
+    use crate::{RateAdjuster, ResampleError, ResamplerConstructionError};
+    use crate::{Resampler, SincFixedBoth, SincFixedIn};
+    use std::time::Duration;
+
+    fn params() -> InterpolationParameters {
+        InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        }
+    }
+
+    #[test]
+    fn invalid_parameters() {
+        let result = SincFixedBoth::<f64>::new(0.91875, 0.9, params(), 480, 441, 2);
+        match result {
+            Err(ResamplerConstructionError::InvalidMaxRatio) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let result = SincFixedBoth::<f64>::new(0.91875, 1.1, params(), 480, 0, 2);
+        match result {
+            Err(ResamplerConstructionError::InvalidChunkSize) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    // With a matching ratio, the output is the output of a SincFixedIn,
+    // delayed by the initial fill level of the FIFO.
+    #[test]
+    fn same_as_fixed_in() {
+        let mut resampler = SincFixedBoth::<f64>::new(0.91875, 1.1, params(), 480, 441, 1).unwrap();
+        let mut reference = SincFixedIn::<f64>::new(0.91875, params(), 480, 1);
+        let target = resampler.fifo_target();
+        assert_eq!(target, 441);
+        let mut output = Vec::new();
+        let mut expected = vec![0.0; resampler.fifo_fill()];
+        for n in 0..100 {
+            let input = vec![(0..480)
+                .map(|m| ((n * 480 + m) as f64 * 0.05).sin())
+                .collect::<Vec<f64>>()];
+            let out = resampler.process(&input).unwrap();
+            assert_eq!(out[0].len(), 441);
+            assert!(resampler.fifo_fill() <= 2 * target);
+            assert!(resampler.fifo_fill() + 10 >= target);
+            output.extend_from_slice(&out[0]);
+            expected.extend_from_slice(&reference.process(&input).unwrap()[0]);
+        }
+        for (a, b) in output.iter().zip(expected.iter()) {
+            assert_eq!(a, b);
+        }
+    }
+
+    // A higher ratio makes the FIFO fill up, until the oldest frames are dropped.
+    #[test]
+    fn fifo_drift() {
+        let mut resampler = SincFixedBoth::<f64>::new(0.91875, 1.1, params(), 480, 441, 1).unwrap();
+        let input = resampler.input_buffer_allocate(true);
+        resampler.set_resample_ratio_relative(1.05).unwrap();
+        let mut previous = resampler.fifo_target();
+        for _ in 0..10 {
+            resampler.process(&input).unwrap();
+            assert!(resampler.fifo_fill() > previous);
+            previous = resampler.fifo_fill();
+        }
+        for _ in 0..100 {
+            let output = resampler.process(&input).unwrap();
+            assert_eq!(output[0].len(), 441);
+            assert!(resampler.fifo_fill() <= 2 * resampler.fifo_target());
+        }
+        match resampler.set_resample_ratio_relative(1.2) {
+            Err(ResampleError::BadRatioUpdate) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    // Run a few million frames with a nominal ratio that is slightly wrong and drifts,
+    // with the ratio corrected by a RateAdjuster watching the FIFO.
+    // The FIFO must never run empty or overflow.
+    #[test]
+    fn fifo_stress() {
+        let mut resampler = SincFixedBoth::<f32>::new(0.91875, 1.1, params(), 480, 441, 1).unwrap();
+        let target = resampler.fifo_target();
+        let mut adjuster = RateAdjuster::new(44100.0, target, 0.5);
+        let input = resampler.input_buffer_allocate(true);
+        let mut output = resampler.output_buffer_allocate(true);
+        let elapsed = Duration::from_millis(10);
+        for n in 0..5000 {
+            let drift = 2.0e-4 + 1.0e-4 * (n as f64 * 0.002).sin();
+            let relative = adjuster.update(resampler.fifo_fill(), elapsed);
+            resampler
+                .set_resample_ratio(0.91875 * (1.0 + drift) * relative)
+                .unwrap();
+            let (nbr_in, nbr_out) = resampler
+                .process_into_buffer(&input, &mut output, None)
+                .unwrap();
+            assert_eq!(nbr_in, 480);
+            assert_eq!(nbr_out, 441);
+            assert!(resampler.fifo_fill() > 0);
+            assert!(resampler.fifo_fill() < 2 * target);
+        }
+        assert!((resampler.fifo_fill() as f64 - target as f64).abs() < 20.0);
+    }
+}
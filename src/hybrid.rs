@@ -0,0 +1,739 @@
+use crate::buffers::validate_sequential_to_interleaved;
+use crate::buffers::{fill_inactive, validate_finite, validate_partial, validate_sequential};
+use crate::buffers::{resize_channels, resize_mask, InputBuffer, OutputBuffer};
+use crate::buffers::{store_mask, update_mask, update_mask_from_buffers, validate_interleaved};
+use crate::buffers::{DelayTrim, TrimmedOutput};
+use crate::buffers::{InterleavedInput, InterleavedOutput, SequentialInput, SequentialOutput};
+use crate::denormals::DenormalGuard;
+use crate::error::{ResampleError, ResampleResult};
+use crate::response::frequency_response;
+use crate::{process_final, InactiveOutput, InterpolationParameters, Resampler, Sample};
+use crate::{FftFilter, FftFixedInOut, SincFixedIn};
+use realfft::RealFftPlanner;
+use std::fmt;
+
+/// The largest number of frames in the smallest chunk of the synchronous stage,
+/// at either sample rate. The ratio of the synchronous stage is the closest rational
+/// approximation of the resample ratio that gives chunks no larger than this.
+const MAX_SYNC_BLOCK: usize = 1024;
+
+/// An asynchronous resampler for any resample ratio, that accepts a fixed number
+/// of audio frames for input and returns a variable number of frames.
+///
+/// The conversion is split in two stages. A synchronous FFT resampler does the bulk
+/// of the conversion, with a rational ratio that is close to the resample ratio,
+/// for example 61/56 for 44.1 kHz to 48.037 kHz.
+/// A [SincFixedIn] then corrects the small remaining difference.
+/// The rational ratio is chosen automatically as the closest one whose smallest chunk
+/// is at most 1024 frames at both sample rates.
+///
+/// Since the ratio of the sinc stage stays close to 1.0, the cutoff of its filters
+/// isn't scaled down when downsampling, and the antialiasing is mostly done by the
+/// long filter of the FFT stage. The sinc stage runs at the output sample rate.
+/// When the ratio is exactly rational with small terms, an [FftFixedInOut] alone
+/// is both faster and better.
+///
+/// The resample ratio can be adjusted like for [SincFixedIn], within the range given by
+/// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+/// Only the sinc stage is adjusted, and the FFT stage keeps its ratio.
+///
+/// A clone starts from the current processing state of the original,
+/// and shares the tables of sincs and the FFT plans with it.
+#[derive(Clone)]
+pub struct HybridFixedIn<T> {
+    nbr_channels: usize,
+    chunk_size: usize,
+    fft_blocks: (usize, usize),
+    sinc_ratio_original: f64,
+    sinc: SincFixedIn<T>,
+    fft: FftFixedInOut<T>,
+    buffer_in: Vec<Vec<T>>,
+    buffer_mid: Vec<Vec<T>>,
+    buffer_out: Vec<Vec<T>>,
+    channel_mask: Vec<bool>,
+    active_channels_mask: Vec<bool>,
+    inactive_output: InactiveOutput,
+    validate_input: bool,
+    flush_denormals: bool,
+    trim: DelayTrim,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
+    output_frames_total: u64,
+}
+
+impl<T> HybridFixedIn<T>
+where
+    T: Sample,
+{
+    /// Create a new HybridFixedIn
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates.
+    /// - `parameters`: Parameters for the sinc stage, see `InterpolationParameters`
+    /// - `chunk_size`: desired size of input data in frames. The actual value is
+    ///   rounded up to a multiple of the smallest input chunk of the FFT stage.
+    /// - `nbr_channels`: number of channels in input/output
+    pub fn new(
+        resample_ratio: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        debug!(
+            "Create new HybridFixedIn, ratio: {}, chunk_size: {}, channels: {}, parameters: {:?}",
+            resample_ratio, chunk_size, nbr_channels, parameters
+        );
+        let (block_in, block_out) = rational_split(resample_ratio);
+        let fft_chunks = ((chunk_size + block_in - 1) / block_in).max(1);
+        let fft = FftFixedInOut::<T>::with_fft_chunks(
+            block_in,
+            block_out,
+            fft_chunks,
+            nbr_channels,
+            FftFilter::default(),
+            &mut RealFftPlanner::new(),
+        );
+        let sinc = SincFixedIn::<T>::new(
+            resample_ratio * block_in as f64 / block_out as f64,
+            parameters,
+            fft.output_frames_max(),
+            nbr_channels,
+        );
+        let chunk_size = fft.nbr_frames_needed();
+
+        let mut resampler = HybridFixedIn {
+            nbr_channels,
+            chunk_size,
+            fft_blocks: (block_in, block_out),
+            sinc_ratio_original: sinc.resample_ratio(),
+            sinc,
+            fft,
+            buffer_in: vec![vec![T::zero(); chunk_size]; nbr_channels],
+            buffer_mid: vec![Vec::new(); nbr_channels],
+            buffer_out: vec![Vec::new(); nbr_channels],
+            channel_mask: vec![true; nbr_channels],
+            active_channels_mask: vec![true; nbr_channels],
+            inactive_output: InactiveOutput::Skip,
+            validate_input: false,
+            flush_denormals: false,
+            trim: DelayTrim::default(),
+            input_frames_total: 0,
+            output_frames_final: None,
+            output_frames_total: 0,
+        };
+        resampler.resize_buffers();
+        resampler
+    }
+
+    /// Get the ratio of the FFT stage, as the number of input and output frames
+    /// of its smallest chunk.
+    pub fn fft_blocks(&self) -> (usize, usize) {
+        self.fft_blocks
+    }
+
+    /// Get the resample ratio of the FFT stage.
+    fn fft_ratio(&self) -> f64 {
+        self.fft_blocks.1 as f64 / self.fft_blocks.0 as f64
+    }
+
+    /// Get the largest number of frames that the sinc stage can produce,
+    /// for any resample ratio within the allowed range.
+    fn sinc_frames_max(&self) -> usize {
+        let max_relative = self.sinc.max_resample_ratio_relative();
+        let lowest = self.sinc_ratio_original / max_relative;
+        let highest = self.sinc_ratio_original * max_relative;
+        let chunk = self.sinc.nbr_frames_needed() as f64;
+        ((chunk + (1.0 / lowest).ceil()) * highest + 10.0) as usize
+    }
+
+    /// Make the buffers between the stages large enough for the allowed range of ratios.
+    fn resize_buffers(&mut self) {
+        let mid_len = self.fft.output_frames_max();
+        let out_len = self.sinc_frames_max();
+        for chan in self.buffer_mid.iter_mut() {
+            if chan.len() < mid_len {
+                chan.resize(mid_len, T::zero());
+            }
+        }
+        for chan in self.buffer_out.iter_mut() {
+            if chan.len() < out_len {
+                chan.resize(out_len, T::zero());
+            }
+        }
+    }
+
+    /// Get the combined delay of the two stages, in output frames.
+    fn untrimmed_delay(&self) -> f64 {
+        self.fft.output_delay_fractional() * self.sinc.resample_ratio()
+            + self.sinc.output_delay_fractional()
+    }
+
+    /// Resample one chunk from `wave_in` into `wave_out`, for the channels marked as active
+    /// in the channel mask. Returns the number of output frames.
+    fn process_buffers<I, O>(&mut self, wave_in: &I, wave_out: &mut O) -> ResampleResult<usize>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        if self.validate_input {
+            validate_finite(wave_in, &self.channel_mask)?;
+        }
+        let _denormals = DenormalGuard::new(self.flush_denormals);
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_in.read_into(chan, 0, &mut self.buffer_in[chan]);
+            }
+        }
+        let mask = Some(&self.channel_mask[..]);
+        self.fft
+            .process_into_buffer(&self.buffer_in, &mut self.buffer_mid, mask)?;
+        let (_, frames_out) =
+            self.sinc
+                .process_into_buffer(&self.buffer_mid, &mut self.buffer_out, mask)?;
+        let mut wave_out = TrimmedOutput::new(wave_out, self.trim.remaining());
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out.write_slice(chan, 0, &self.buffer_out[chan][..frames_out]);
+            }
+        }
+        let frames_out = self.trim.consume(frames_out);
+        self.input_frames_total += self.chunk_size as u64;
+        self.output_frames_total += frames_out as u64;
+        Ok(frames_out)
+    }
+}
+
+/// Find the closest rational approximation of the resample ratio, with an input and
+/// an output block of at most [MAX_SYNC_BLOCK] frames, from the continued fraction of the ratio.
+/// The first approximation is always used, even when it has a larger block.
+/// Returns the input and output blocks.
+fn rational_split(resample_ratio: f64) -> (usize, usize) {
+    // Work with a value of at least one, so that the first approximation isn't zero.
+    let value = if resample_ratio >= 1.0 {
+        resample_ratio
+    } else {
+        1.0 / resample_ratio
+    };
+    let mut whole = value.floor();
+    let mut fraction = value - whole;
+    let (mut num_prev, mut num) = (1, whole as usize);
+    let (mut den_prev, mut den) = (0, 1);
+    while fraction > 1.0e-9 {
+        let inverse = 1.0 / fraction;
+        whole = inverse.floor();
+        fraction = inverse - whole;
+        let next_num = whole as usize * num + num_prev;
+        let next_den = whole as usize * den + den_prev;
+        if next_num.max(next_den) > MAX_SYNC_BLOCK {
+            break;
+        }
+        num_prev = num;
+        num = next_num;
+        den_prev = den;
+        den = next_den;
+    }
+    if resample_ratio >= 1.0 {
+        (den, num)
+    } else {
+        (num, den)
+    }
+}
+
+impl<T> fmt::Debug for HybridFixedIn<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HybridFixedIn")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size", &self.chunk_size)
+            .field("fft_blocks", &self.fft_blocks)
+            .field("sinc", &self.sinc)
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for HybridFixedIn<T>
+where
+    T: Sample,
+{
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the maximum number of input frames, which is always the number of frames needed.
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// Get the maximum number of output frames that the next call can produce.
+    fn output_frames_max(&self) -> usize {
+        self.sinc.output_frames_max()
+    }
+
+    /// Reset the resampler state, and go back to the original resample ratio.
+    fn reset(&mut self) {
+        self.sinc.reset();
+        self.fft.reset();
+        self.input_frames_total = 0;
+        self.output_frames_final = None;
+        self.output_frames_total = 0;
+        self.trim.restart(self.untrimmed_delay());
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        self.sinc.reset_channel(channel)?;
+        self.fft.reset_channel(channel)
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        if nbr_channels == 0 {
+            return Err(ResampleError::BadNbrChannelsUpdate);
+        }
+        trace!("Change number of channels to {}", nbr_channels);
+        self.sinc.set_nbr_channels(nbr_channels)?;
+        self.fft.set_nbr_channels(nbr_channels)?;
+        resize_channels(&mut self.buffer_in, nbr_channels, self.chunk_size);
+        resize_channels(&mut self.buffer_mid, nbr_channels, 0);
+        resize_channels(&mut self.buffer_out, nbr_channels, 0);
+        resize_mask(&mut self.channel_mask, nbr_channels);
+        resize_mask(&mut self.active_channels_mask, nbr_channels);
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        self.sinc.resample_ratio() * self.fft_ratio()
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
+
+    fn output_frames_total(&self) -> u64 {
+        self.output_frames_total
+    }
+
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        store_mask(&mut self.active_channels_mask, mask)
+    }
+
+    fn active_channels_mask(&self) -> &[bool] {
+        &self.active_channels_mask
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        self.inactive_output = inactive_output;
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        self.validate_input = enabled;
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        let started = self.input_frames_total > 0;
+        self.trim
+            .set_enabled(enabled, self.untrimmed_delay(), started);
+    }
+
+    /// Resample a chunk of audio. The input length is fixed, and the output varies in length.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the size of the input data is not equal
+    /// to the number of channels and input size defined when creating the instance.
+    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
+        let buffer_mid = &mut self.buffer_mid;
+        update_mask_from_buffers(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            wave_in,
+            self.chunk_size,
+            |chan| {
+                buffer_mid[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_out_max = self.output_frames_max();
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan] = vec![T::zero(); frames_out_max];
+            }
+        }
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, 0, self.chunk_size),
+            &mut SequentialOutput::new(&mut wave_out, 0),
+        )?;
+        for chan in wave_out.iter_mut() {
+            chan.truncate(frames_out);
+        }
+        Ok(wave_out)
+    }
+
+    /// Resample a chunk of interleaved audio. The input length is fixed,
+    /// and the output varies in length.
+    /// # Errors
+    ///
+    /// The function returns an error if the input doesn't contain the number of frames
+    /// given by "nbr_frames_needed", if the output buffer is shorter than "output_frames_max",
+    /// or if the mask doesn't match the number of channels.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.chunk_size;
+        validate_interleaved(
+            wave_in,
+            wave_out,
+            self.nbr_channels,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let buffer_mid = &mut self.buffer_mid;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer_mid[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out = self.process_buffers(
+            &InterleavedInput::new(wave_in, self.nbr_channels, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// `input_offset` plus "nbr_frames_needed" frames, if an active output channel holds fewer
+    /// than `output_offset` plus "output_frames_max" frames, or if the number of channels
+    /// of the buffers or the mask is wrong.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.chunk_size;
+        let buffer_mid = &mut self.buffer_mid;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer_mid[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential(
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = SequentialOutput::new(wave_out, output_offset);
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, input_offset, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers,
+    /// and write the result to an interleaved output buffer.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// "nbr_frames_needed" frames, if the output buffer is shorter than "output_frames_max" frames,
+    /// or if the number of channels of the input or the mask is wrong.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.chunk_size;
+        let buffer_mid = &mut self.buffer_mid;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer_mid[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential_to_interleaved(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the last frames of a stream, when fewer frames than needed remain.
+    /// The missing input frames are replaced by zeros, and the input may also be `None`,
+    /// which processes only zeros.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds more than
+    /// "nbr_frames_needed" frames, if the active input channels have different lengths,
+    /// if an active output channel holds fewer than "output_frames_max" frames,
+    /// or if the number of channels of the buffers or the mask is wrong.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let buffer_mid = &mut self.buffer_mid;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                buffer_mid[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_in = validate_partial(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            self.chunk_size,
+            self.output_frames_max(),
+        )?;
+        let wave_in = wave_in.unwrap_or(&[]);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    /// Only the output frames that correspond to real input are counted,
+    /// and zero frames are returned once the stream has been flushed.
+    /// # Errors
+    ///
+    /// The function returns an error under the same conditions as "process_partial_into_buffer".
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mut output_frames_final = self.output_frames_final;
+        let result = process_final(
+            self,
+            &mut output_frames_final,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+        );
+        self.output_frames_final = output_frames_final;
+        result
+    }
+
+    /// Update the resample ratio. The sinc stage takes care of the change,
+    /// and the new value must be within the range allowed by "max_resample_ratio_relative".
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        self.sinc.set_resample_ratio(new_ratio / self.fft_ratio())
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        self.sinc.set_resample_ratio_relative(rel_ratio)
+    }
+
+    /// Update the resample ratio from a pair of sample rates,
+    /// with a ramp if enabled for the sinc stage.
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()> {
+        self.sinc
+            .set_sample_rates(fs_in * self.fft_ratio(), fs_out, ramp)
+    }
+
+    fn ramp_progress(&self) -> Option<f64> {
+        self.sinc.ramp_progress()
+    }
+
+    fn max_resample_ratio_relative(&self) -> f64 {
+        self.sinc.max_resample_ratio_relative()
+    }
+
+    /// Update the range of allowed resample ratios.
+    /// The buffers between the stages are reallocated if needed.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()> {
+        self.sinc.set_max_resample_ratio_relative(max_relative)?;
+        self.resize_buffers();
+        Ok(())
+    }
+
+    /// Get the delay of both stages, in output frames.
+    fn output_delay_fractional(&self) -> f64 {
+        self.untrimmed_delay() - self.trim.frames() as f64
+    }
+
+    /// Get the combined response of both stages.
+    /// The frequencies seen by the second stage are scaled by the ratio of the first.
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        let sinc = self.sinc.filter_response();
+        let fft = self.fft.filter_response();
+        let fft_ratio = self.fft_ratio();
+        frequency_response(self.resample_ratio(), n_points, |freq| {
+            fft.magnitude(freq) * sinc.magnitude(freq / fft_ratio)
+        })
+    }
+
+    /// Get the delay of both stages, in input frames.
+    fn group_delay(&self) -> f64 {
+        let sinc = self.sinc.filter_response().delay();
+        let fft = self.fft.filter_response().delay();
+        fft + sinc / self.fft_ratio()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rational_split;
+    use crate::WindowFunction;
+    use crate::{
+        Accumulation, FilterPhase, InterpolationParameters, InterpolationType, SincStorage,
+    };
+    use crate::{HybridFixedIn, Resampler};
+    use std::f64::consts::PI;
+
+    fn params() -> InterpolationParameters {
+        InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 256,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        }
+    }
+
+    #[test]
+    fn rational_splits() {
+        assert_eq!(rational_split(48037.0 / 44100.0), (56, 61));
+        assert_eq!(rational_split(48000.0 / 44100.0), (147, 160));
+        assert_eq!(rational_split(44100.0 / 48000.0), (160, 147));
+        assert_eq!(rational_split(32000.0 / 44100.0), (441, 320));
+        assert_eq!(rational_split(2.0), (1, 2));
+        assert_eq!(rational_split(0.5), (2, 1));
+        assert_eq!(rational_split(6.0), (1, 6));
+        assert_eq!(rational_split(1.0 / 3.0), (3, 1));
+        assert_eq!(rational_split(PI), (113, 355));
+        assert_eq!(rational_split(44100.0 / 8000.0), (80, 441));
+    }
+
+    // Resample a sine and fit a sine and a cosine of the expected frequency to the output,
+    // away from the start and the end. Returns the amplitude and the rms of the residual.
+    fn fit_sine(resampler: &mut HybridFixedIn<f64>, freq: f64, frames: usize) -> (f64, f64) {
+        let ratio = resampler.resample_ratio();
+        let input = vec![(0..frames)
+            .map(|n| (2.0 * PI * freq * n as f64).sin())
+            .collect::<Vec<f64>>()];
+        let output = resampler.process_all(&input).unwrap();
+        let freq_out = 2.0 * PI * freq / ratio;
+        let range = 2000..output[0].len() - 2000;
+        let (mut ss, mut sc, mut cc, mut ys, mut yc) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for n in range.clone() {
+            let (s, c) = (freq_out * n as f64).sin_cos();
+            let y = output[0][n];
+            ss += s * s;
+            sc += s * c;
+            cc += c * c;
+            ys += y * s;
+            yc += y * c;
+        }
+        let det = ss * cc - sc * sc;
+        let a = (ys * cc - yc * sc) / det;
+        let b = (yc * ss - ys * sc) / det;
+        let residual = range
+            .clone()
+            .map(|n| {
+                let (s, c) = (freq_out * n as f64).sin_cos();
+                let diff = output[0][n] - a * s - b * c;
+                diff * diff
+            })
+            .sum::<f64>();
+        (
+            (a * a + b * b).sqrt(),
+            (residual / range.len() as f64).sqrt(),
+        )
+    }
+
+    #[test]
+    fn sine_quality() {
+        let mut resampler = HybridFixedIn::<f64>::new(48037.0 / 44100.0, params(), 1024, 1);
+        assert_eq!(resampler.fft_blocks(), (56, 61));
+        for freq in [0.01, 0.1, 0.3].iter() {
+            resampler.reset();
+            let (amplitude, residual) = fit_sine(&mut resampler, *freq, 50000);
+            assert!((amplitude - 1.0).abs() < 1.0e-3, "amplitude {}", amplitude);
+            assert!(residual < 1.0e-3, "residual {}", residual);
+        }
+    }
+
+    #[test]
+    fn set_ratio_adjusts_sinc_stage() {
+        let ratio = 48037.0 / 44100.0;
+        let mut resampler = HybridFixedIn::<f64>::new(ratio, params(), 1024, 1);
+        resampler.set_resample_ratio(ratio * 1.05).unwrap();
+        assert_eq!(resampler.fft_blocks(), (56, 61));
+        assert!((resampler.resample_ratio() - ratio * 1.05).abs() < 1.0e-9);
+        assert!(resampler.set_resample_ratio(ratio * 1.2).is_err());
+        resampler.set_resample_ratio_relative(1.0).unwrap();
+        assert!((resampler.resample_ratio() - ratio).abs() < 1.0e-9);
+        let (_, residual) = fit_sine(&mut resampler, 0.1, 20000);
+        assert!(residual < 1.0e-3, "residual {}", residual);
+    }
+}
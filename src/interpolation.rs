@@ -1,45 +1,25 @@
 /// Get the two nearest time points for time t in format (index, subindex)
 pub fn get_nearest_times_2(t: f64, factor: isize, points: &mut [(isize, isize); 2]) {
-    let mut index = t.floor() as isize;
-    let mut subindex = ((t - t.floor()) * (factor as f64)).floor() as isize;
-    points[0] = (index, subindex);
-    subindex += 1;
-    if subindex >= factor {
-        subindex -= factor;
-        index += 1;
+    // Find the points from `t * factor`, the same value that the fraction
+    // used for interpolating between them is calculated from.
+    let start = (t * factor as f64).floor() as isize;
+    for (idx, point) in (start..start + 2).enumerate() {
+        points[idx] = (point.div_euclid(factor), point.rem_euclid(factor));
     }
-    points[1] = (index, subindex);
 }
 
 /// Get the four nearest time points for time t in format (index, subindex).
 pub fn get_nearest_times_4(t: f64, factor: isize, points: &mut [(isize, isize); 4]) {
-    let start = t.floor() as isize;
-    let frac = ((t - t.floor()) * (factor as f64)).floor() as isize;
-    let mut index;
-    let mut subindex;
-    for (idx, sub) in (-1..3).enumerate() {
-        index = start;
-        subindex = frac + sub;
-        if subindex < 0 {
-            subindex += factor;
-            index -= 1;
-        } else if subindex >= factor {
-            subindex -= factor;
-            index += 1;
-        }
-        points[idx] = (index, subindex);
+    let start = (t * factor as f64).floor() as isize;
+    for (idx, point) in (start - 1..start + 3).enumerate() {
+        points[idx] = (point.div_euclid(factor), point.rem_euclid(factor));
     }
 }
 
 /// Get the nearest time point for time t in format (index, subindex).
 pub fn get_nearest_time(t: f64, factor: isize) -> (isize, isize) {
-    let mut index = t.floor() as isize;
-    let mut subindex = ((t - t.floor()) * (factor as f64)).round() as isize;
-    if subindex >= factor {
-        subindex -= factor;
-        index += 1;
-    }
-    (index, subindex)
+    let point = (t * factor as f64).round() as isize;
+    (point.div_euclid(factor), point.rem_euclid(factor))
 }
 
 #[cfg(test)]
@@ -96,4 +76,21 @@ mod tests {
         let time = get_nearest_time(t, 8);
         assert_eq!(time, (5, 4));
     }
+
+    // The points must agree with the fraction `t * factor - floor(t * factor)`,
+    // also for times that are not exactly representable.
+    #[test]
+    fn get_nearest_consistent() {
+        let factor = 39;
+        let mut times = [(0isize, 0isize); 4];
+        for n in 0..2000 {
+            let t = 2.0 + n as f64 / 624.0 - 1.0 / factor as f64;
+            let point = (t * factor as f64).floor() as isize;
+            get_nearest_times_4(t, factor, &mut times);
+            assert_eq!(times[1].0 * factor + times[1].1, point);
+            let mut times_2 = [(0isize, 0isize); 2];
+            get_nearest_times_2(t, factor, &mut times_2);
+            assert_eq!(times_2[0].0 * factor + times_2[0].1, point);
+        }
+    }
 }
@@ -1,8 +1,10 @@
 use crate::asynchro::SincInterpolator;
 use crate::error::{CpuFeature, MissingCpuFeature};
-use crate::sinc::make_sincs;
+use crate::sinc::{drop_mirrored_sincs, make_sincs_with_phase, mirrored_subindex};
+use crate::sinc::{Accumulation, FilterPhase};
 use crate::windows::WindowFunction;
 use crate::Sample;
+use core::arch::x86_64::_mm256_cvtps_pd;
 use core::arch::x86_64::{
     __m256, __m256d, _mm256_castpd256_pd128, _mm256_castps256_ps128, _mm256_extractf128_pd,
     _mm256_extractf128_ps,
@@ -11,16 +13,26 @@ use core::arch::x86_64::{
     _mm256_add_pd, _mm256_fmadd_pd, _mm256_loadu_pd, _mm256_setzero_pd, _mm_add_pd, _mm_hadd_pd,
     _mm_store_sd,
 };
+use core::arch::x86_64::{_mm256_add_ps, _mm256_mul_pd, _mm256_mul_ps};
+use core::arch::x86_64::{
+    _mm256_castpd128_pd256, _mm256_castps128_ps256, _mm256_insertf128_pd, _mm256_insertf128_ps,
+    _mm_loadu_pd, _mm_loadu_ps,
+};
 use core::arch::x86_64::{
     _mm256_fmadd_ps, _mm256_loadu_ps, _mm256_setzero_ps, _mm_add_ps, _mm_hadd_ps, _mm_store_ss,
 };
+use core::arch::x86_64::{
+    _mm256_permute2f128_pd, _mm256_permute2f128_ps, _mm256_permute_pd, _mm256_permute_ps,
+};
+use std::fmt;
 
 /// Collection of cpu features required for this interpolator.
-static FEATURES: &[CpuFeature] = &[CpuFeature::Avx, CpuFeature::Fma];
+/// Fused multiply-add is used when the cpu also has [CpuFeature::Fma].
+static FEATURES: &[CpuFeature] = &[CpuFeature::Avx];
 
 /// Trait governing what can be done with an AvxSample.
-pub trait AvxSample: Sized + Send {
-    type Sinc: Send;
+pub trait AvxSample: Sized + Send + Sync {
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -43,12 +55,226 @@ pub trait AvxSample: Sized + Send {
         sincs: &[Vec<Self::Sinc>],
         length: usize,
     ) -> Self;
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Interpolate a sinc sample, with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+    }
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order,
+    /// with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_reversed_unsafe(wave, index, subindex, sincs, length)
+    }
+
+    /// Interpolate a sinc sample, without fused multiply-add.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_no_fma_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order,
+    /// without fused multiply-add.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_no_fma_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Interpolate a sinc sample, with the products summed in f64,
+    /// without fused multiply-add.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_wide_no_fma_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_no_fma_unsafe(wave, index, subindex, sincs, length)
+    }
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order,
+    /// with the products summed in f64, without fused multiply-add.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_wide_no_fma_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_reversed_no_fma_unsafe(wave, index, subindex, sincs, length)
+    }
+}
+
+/// Reverse the order of the elements of a vector.
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn reverse_ps(values: __m256) -> __m256 {
+    let reversed_halves = _mm256_permute_ps(values, 0x1b);
+    _mm256_permute2f128_ps(reversed_halves, reversed_halves, 0x01)
+}
+
+/// Reverse the order of the elements of a vector.
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn reverse_pd(values: __m256d) -> __m256d {
+    let reversed_halves = _mm256_permute_pd(values, 0b0101);
+    _mm256_permute2f128_pd(reversed_halves, reversed_halves, 0x01)
+}
+
+/// Load 8 values in reversed order.
+/// The halves are loaded separately, since a lane crossing shuffle in the inner loop
+/// ends up with a false dependency on the accumulator.
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn loadu_reversed_ps(values: &f32) -> __m256 {
+    let ptr = values as *const f32;
+    let high = _mm256_castps128_ps256(_mm_loadu_ps(ptr.add(4)));
+    let swapped = _mm256_insertf128_ps(high, _mm_loadu_ps(ptr), 1);
+    _mm256_permute_ps(swapped, 0x1b)
+}
+
+/// Load 4 values in reversed order.
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn loadu_reversed_pd(values: &f64) -> __m256d {
+    let ptr = values as *const f64;
+    let high = _mm256_castpd128_pd256(_mm_loadu_pd(ptr.add(2)));
+    let swapped = _mm256_insertf128_pd(high, _mm_loadu_pd(ptr), 1);
+    _mm256_permute_pd(swapped, 0b0101)
+}
+
+/// Multiply two vectors of f32 in f64, and add the products of the low and high halves
+/// to the two accumulators.
+#[inline]
+#[target_feature(enable = "avx", enable = "fma")]
+unsafe fn fmadd_wide(
+    wave: __m256,
+    sinc: __m256,
+    acc_low: __m256d,
+    acc_high: __m256d,
+) -> (__m256d, __m256d) {
+    let wave_low = _mm256_cvtps_pd(_mm256_castps256_ps128(wave));
+    let wave_high = _mm256_cvtps_pd(_mm256_extractf128_ps(wave, 1));
+    let sinc_low = _mm256_cvtps_pd(_mm256_castps256_ps128(sinc));
+    let sinc_high = _mm256_cvtps_pd(_mm256_extractf128_ps(sinc, 1));
+    (
+        _mm256_fmadd_pd(wave_low, sinc_low, acc_low),
+        _mm256_fmadd_pd(wave_high, sinc_high, acc_high),
+    )
+}
+
+/// Multiply two vectors of f32 in f64, and add the products of the low and high halves
+/// to the two accumulators, with separate multiply and add instructions.
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn mul_add_wide(
+    wave: __m256,
+    sinc: __m256,
+    acc_low: __m256d,
+    acc_high: __m256d,
+) -> (__m256d, __m256d) {
+    let wave_low = _mm256_cvtps_pd(_mm256_castps256_ps128(wave));
+    let wave_high = _mm256_cvtps_pd(_mm256_extractf128_ps(wave, 1));
+    let sinc_low = _mm256_cvtps_pd(_mm256_castps256_ps128(sinc));
+    let sinc_high = _mm256_cvtps_pd(_mm256_extractf128_ps(sinc, 1));
+    (
+        _mm256_add_pd(_mm256_mul_pd(wave_low, sinc_low), acc_low),
+        _mm256_add_pd(_mm256_mul_pd(wave_high, sinc_high), acc_high),
+    )
+}
+
+/// Sum the elements of an accumulator.
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn sum_ps(acc: __m256) -> f32 {
+    let acc_high = _mm256_extractf128_ps(acc, 1);
+    let acc_low = _mm_add_ps(acc_high, _mm256_castps256_ps128(acc));
+    let temp2 = _mm_hadd_ps(acc_low, acc_low);
+    let temp1 = _mm_hadd_ps(temp2, temp2);
+    let mut result = 0.0;
+    _mm_store_ss(&mut result, temp1);
+    result
+}
+
+/// Sum the elements of two accumulators.
+#[inline]
+#[target_feature(enable = "avx")]
+unsafe fn sum_wide(acc0: __m256d, acc1: __m256d) -> f64 {
+    let acc_all = _mm256_add_pd(acc0, acc1);
+    let acc_high = _mm256_extractf128_pd(acc_all, 1);
+    let temp2 = _mm_add_pd(acc_high, _mm256_castpd256_pd128(acc_all));
+    let temp1 = _mm_hadd_pd(temp2, temp2);
+    let mut result = 0.0;
+    _mm_store_sd(&mut result, temp1);
+    result
 }
 
 impl AvxSample for f32 {
     type Sinc = __m256;
 
-    #[target_feature(enable = "avx", enable = "fma")]
+    #[target_feature(enable = "avx")]
     unsafe fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>> {
         let mut packed_sincs = Vec::new();
         for sinc in sincs.iter() {
@@ -79,20 +305,168 @@ impl AvxSample for f32 {
             acc = _mm256_fmadd_ps(w, *sinc.get_unchecked(s_idx), acc);
             w_idx += 8;
         }
-        let acc_high = _mm256_extractf128_ps(acc, 1);
-        let acc_low = _mm_add_ps(acc_high, _mm256_castps256_ps128(acc));
-        let temp2 = _mm_hadd_ps(acc_low, acc_low);
-        let temp1 = _mm_hadd_ps(temp2, temp2);
-        let mut result = 0.0;
-        _mm_store_ss(&mut result, temp1);
-        result
+        sum_ps(acc)
+    }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc = _mm256_setzero_ps();
+        let mut w_idx = 0;
+        // Pair the reversed wave with the stored sinc, and reverse the sums at the end.
+        // This gives the same products and sums as the forward direction.
+        for s_idx in (0..length / 8).rev() {
+            let w = loadu_reversed_ps(wave_cut.get_unchecked(w_idx));
+            acc = _mm256_fmadd_ps(w, *sinc.get_unchecked(s_idx), acc);
+            w_idx += 8;
+        }
+        sum_ps(reverse_ps(acc))
+    }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm256_setzero_pd();
+        let mut acc1 = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        for s_idx in 0..length / 8 {
+            let w = _mm256_loadu_ps(wave_cut.get_unchecked(w_idx));
+            let (a0, a1) = fmadd_wide(w, *sinc.get_unchecked(s_idx), acc0, acc1);
+            acc0 = a0;
+            acc1 = a1;
+            w_idx += 8;
+        }
+        sum_wide(acc0, acc1) as f32
+    }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm256_setzero_pd();
+        let mut acc1 = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        for s_idx in (0..length / 8).rev() {
+            let w = loadu_reversed_ps(wave_cut.get_unchecked(w_idx));
+            let (a0, a1) = fmadd_wide(w, *sinc.get_unchecked(s_idx), acc0, acc1);
+            acc0 = a0;
+            acc1 = a1;
+            w_idx += 8;
+        }
+        sum_wide(acc0, acc1) as f32
+    }
+
+    #[target_feature(enable = "avx")]
+    unsafe fn get_sinc_interpolated_no_fma_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc = _mm256_setzero_ps();
+        let mut w_idx = 0;
+        for s_idx in 0..length / 8 {
+            let w = _mm256_loadu_ps(wave_cut.get_unchecked(w_idx));
+            acc = _mm256_add_ps(_mm256_mul_ps(w, *sinc.get_unchecked(s_idx)), acc);
+            w_idx += 8;
+        }
+        sum_ps(acc)
+    }
+
+    #[target_feature(enable = "avx")]
+    unsafe fn get_sinc_interpolated_reversed_no_fma_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc = _mm256_setzero_ps();
+        let mut w_idx = 0;
+        for s_idx in (0..length / 8).rev() {
+            let w = loadu_reversed_ps(wave_cut.get_unchecked(w_idx));
+            acc = _mm256_add_ps(_mm256_mul_ps(w, *sinc.get_unchecked(s_idx)), acc);
+            w_idx += 8;
+        }
+        sum_ps(reverse_ps(acc))
+    }
+
+    #[target_feature(enable = "avx")]
+    unsafe fn get_sinc_interpolated_wide_no_fma_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm256_setzero_pd();
+        let mut acc1 = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        for s_idx in 0..length / 8 {
+            let w = _mm256_loadu_ps(wave_cut.get_unchecked(w_idx));
+            let (a0, a1) = mul_add_wide(w, *sinc.get_unchecked(s_idx), acc0, acc1);
+            acc0 = a0;
+            acc1 = a1;
+            w_idx += 8;
+        }
+        sum_wide(acc0, acc1) as f32
+    }
+
+    #[target_feature(enable = "avx")]
+    unsafe fn get_sinc_interpolated_reversed_wide_no_fma_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm256_setzero_pd();
+        let mut acc1 = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        for s_idx in (0..length / 8).rev() {
+            let w = loadu_reversed_ps(wave_cut.get_unchecked(w_idx));
+            let (a0, a1) = mul_add_wide(w, *sinc.get_unchecked(s_idx), acc0, acc1);
+            acc0 = a0;
+            acc1 = a1;
+            w_idx += 8;
+        }
+        sum_wide(acc0, acc1) as f32
     }
 }
 
 impl AvxSample for f64 {
     type Sinc = __m256d;
 
-    #[target_feature(enable = "avx", enable = "fma")]
+    #[target_feature(enable = "avx")]
     unsafe fn pack_sincs(sincs: Vec<Vec<f64>>) -> Vec<Vec<Self::Sinc>> {
         let mut packed_sincs = Vec::new();
         for sinc in sincs.iter() {
@@ -128,17 +502,89 @@ impl AvxSample for f64 {
             w_idx += 8;
             s_idx += 2;
         }
-        let acc_all = _mm256_add_pd(acc0, acc1);
-        let acc_high = _mm256_extractf128_pd(acc_all, 1);
-        let temp2 = _mm_add_pd(acc_high, _mm256_castpd256_pd128(acc_all));
-        let temp1 = _mm_hadd_pd(temp2, temp2);
-        let mut result = 0.0;
-        _mm_store_sd(&mut result, temp1);
-        result
+        sum_wide(acc0, acc1)
+    }
+
+    #[target_feature(enable = "avx", enable = "fma")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm256_setzero_pd();
+        let mut acc1 = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        // Pair the reversed wave with the stored sinc, and reverse the sums at the end.
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = loadu_reversed_pd(wave_cut.get_unchecked(w_idx));
+            let w1 = loadu_reversed_pd(wave_cut.get_unchecked(w_idx + 4));
+            acc0 = _mm256_fmadd_pd(w0, *sinc.get_unchecked(s_idx - 1), acc0);
+            acc1 = _mm256_fmadd_pd(w1, *sinc.get_unchecked(s_idx - 2), acc1);
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        sum_wide(reverse_pd(acc0), reverse_pd(acc1))
+    }
+
+    #[target_feature(enable = "avx")]
+    unsafe fn get_sinc_interpolated_no_fma_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm256_setzero_pd();
+        let mut acc1 = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = _mm256_loadu_pd(wave_cut.get_unchecked(w_idx));
+            let w1 = _mm256_loadu_pd(wave_cut.get_unchecked(w_idx + 4));
+            acc0 = _mm256_add_pd(_mm256_mul_pd(w0, *sinc.get_unchecked(s_idx)), acc0);
+            acc1 = _mm256_add_pd(_mm256_mul_pd(w1, *sinc.get_unchecked(s_idx + 1)), acc1);
+            w_idx += 8;
+            s_idx += 2;
+        }
+        sum_wide(acc0, acc1)
+    }
+
+    #[target_feature(enable = "avx")]
+    unsafe fn get_sinc_interpolated_reversed_no_fma_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm256_setzero_pd();
+        let mut acc1 = _mm256_setzero_pd();
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = loadu_reversed_pd(wave_cut.get_unchecked(w_idx));
+            let w1 = loadu_reversed_pd(wave_cut.get_unchecked(w_idx + 4));
+            acc0 = _mm256_add_pd(_mm256_mul_pd(w0, *sinc.get_unchecked(s_idx - 1)), acc0);
+            acc1 = _mm256_add_pd(_mm256_mul_pd(w1, *sinc.get_unchecked(s_idx - 2)), acc1);
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        sum_wide(reverse_pd(acc0), reverse_pd(acc1))
     }
 }
 
-/// An AVX accelerated interpolator
+/// An AVX accelerated interpolator.
+/// It uses fused multiply-add instructions when the cpu supports them,
+/// and separate multiply and add instructions otherwise.
 pub struct AvxInterpolator<T>
 where
     T: AvxSample,
@@ -146,6 +592,25 @@ where
     sincs: Vec<Vec<T::Sinc>>,
     length: usize,
     nbr_sincs: usize,
+    center: usize,
+    mirrored: bool,
+    wide: bool,
+    fma: bool,
+}
+
+impl<T> fmt::Debug for AvxInterpolator<T>
+where
+    T: AvxSample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AvxInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .field("mirrored", &self.mirrored)
+            .field("wide", &self.wide)
+            .field("fma", &self.fma)
+            .finish()
+    }
 }
 
 impl<T> SincInterpolator<T> for AvxInterpolator<T>
@@ -166,7 +631,43 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+        let sincs = &self.sincs;
+        let length = self.length;
+        let stored = if self.mirrored {
+            mirrored_subindex(subindex, self.nbr_sincs)
+        } else {
+            None
+        };
+        unsafe {
+            match (stored, self.wide, self.fma) {
+                (Some(stored), false, true) => {
+                    T::get_sinc_interpolated_reversed_unsafe(wave, index, stored, sincs, length)
+                }
+                (Some(stored), true, true) => {
+                    T::get_sinc_interpolated_reversed_wide_unsafe(wave, index, stored, sincs, length)
+                }
+                (Some(stored), false, false) => T::get_sinc_interpolated_reversed_no_fma_unsafe(
+                    wave, index, stored, sincs, length,
+                ),
+                (Some(stored), true, false) => {
+                    T::get_sinc_interpolated_reversed_wide_no_fma_unsafe(
+                        wave, index, stored, sincs, length,
+                    )
+                }
+                (None, false, true) => {
+                    T::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+                }
+                (None, true, true) => {
+                    T::get_sinc_interpolated_wide_unsafe(wave, index, subindex, sincs, length)
+                }
+                (None, false, false) => {
+                    T::get_sinc_interpolated_no_fma_unsafe(wave, index, subindex, sincs, length)
+                }
+                (None, true, false) => {
+                    T::get_sinc_interpolated_wide_no_fma_unsafe(wave, index, subindex, sincs, length)
+                }
+            }
+        }
     }
 
     fn len(&self) -> usize {
@@ -176,6 +677,18 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn center(&self) -> usize {
+        self.center
+    }
+
+    fn kernel(&self) -> &'static str {
+        if self.fma {
+            "avx+fma"
+        } else {
+            "avx"
+        }
+    }
 }
 
 impl<T> AvxInterpolator<T>
@@ -194,28 +707,81 @@ where
         oversampling_factor: usize,
         f_cutoff: f32,
         window: WindowFunction,
+    ) -> Result<Self, MissingCpuFeature> {
+        Self::new_with_phase(
+            sinc_len,
+            oversampling_factor,
+            f_cutoff,
+            window,
+            FilterPhase::Linear,
+        )
+    }
+
+    /// Create a new AvxInterpolator with the given phase response of the sincs.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `phase`: Phase response of the sincs, see `FilterPhase`.
+    pub fn new_with_phase(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+        phase: FilterPhase,
     ) -> Result<Self, MissingCpuFeature> {
         if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
             return Err(MissingCpuFeature(*feature));
         }
 
         assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
-        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        let (sincs, center) =
+            make_sincs_with_phase(sinc_len, oversampling_factor, f_cutoff, window, phase);
+        Self::from_sincs(sincs, center, phase == FilterPhase::Linear)
+    }
+
+    /// Create a new AvxInterpolator from an already calculated set of sincs,
+    /// where the point at `center` in each sinc lines up with the interpolated sample.
+    /// If `mirrored` is set, the table must be symmetric,
+    /// and only the half that isn't mirror images of the other half is kept.
+    pub(crate) fn from_sincs(
+        mut sincs: Vec<Vec<T>>,
+        center: usize,
+        mirrored: bool,
+    ) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let length = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(length % 8 == 0, "Sinc length must be a multiple of 8.");
+        if mirrored {
+            drop_mirrored_sincs(&mut sincs);
+        }
         let sincs = unsafe { <T as AvxSample>::pack_sincs(sincs) };
 
         Ok(Self {
             sincs,
-            length: sinc_len,
-            nbr_sincs: oversampling_factor,
+            length,
+            nbr_sincs,
+            center,
+            mirrored,
+            wide: false,
+            fma: CpuFeature::Fma.is_detected(),
         })
     }
+
+    /// Choose how the products are summed, see `Accumulation`.
+    pub(crate) fn with_accumulation(mut self, accumulation: Accumulation) -> Self {
+        self.wide = accumulation == Accumulation::Double;
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::asynchro::SincInterpolator;
     use crate::interpolator_avx::AvxInterpolator;
-    use crate::sinc::make_sincs;
+    use crate::sinc::{make_sincs, Accumulation};
     use crate::WindowFunction;
     use num_traits::Float;
     use rand::Rng;
@@ -245,7 +811,7 @@ mod tests {
             match AvxInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window) {
                 Ok(interpolator) => interpolator,
                 Err(..) => {
-                    assert!(!(is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma")));
+                    assert!(!is_x86_feature_detected!("avx"));
                     return;
                 }
             };
@@ -272,7 +838,7 @@ mod tests {
             match AvxInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window) {
                 Ok(interpolator) => interpolator,
                 Err(..) => {
-                    assert!(!(is_x86_feature_detected!("avx") && is_x86_feature_detected!("fma")));
+                    assert!(!is_x86_feature_detected!("avx"));
                     return;
                 }
             };
@@ -281,4 +847,82 @@ mod tests {
         let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
         assert!((value - check).abs() < 1.0e-5);
     }
+
+    #[test]
+    fn test_avx_interpolator_32_wide() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f32>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let interpolator =
+            match AvxInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window) {
+                Ok(interpolator) => interpolator.with_accumulation(Accumulation::Double),
+                Err(..) => {
+                    assert!(!is_x86_feature_detected!("avx"));
+                    return;
+                }
+            };
+        // Both the stored and the mirrored sincs, compared with a sum in f64.
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator.get_sinc_interpolated(&wave, 333, *subindex);
+            let check = wave[333..333 + sinc_len]
+                .iter()
+                .zip(sincs[*subindex].iter())
+                .fold(0.0, |acc, (x, y)| acc + *x as f64 * *y as f64);
+            assert!((value as f64 - check).abs() < 1.0e-6);
+        }
+    }
+
+    // Fused multiply-add rounds differently, so the kernels with and without it
+    // are compared with the scalar sum within a tolerance, for both the stored and
+    // the mirrored sincs.
+    #[test]
+    fn test_avx_interpolator_no_fma() {
+        let mut rng = rand::thread_rng();
+        let wave_64: Vec<f64> = (0..2048).map(|_| rng.gen::<f64>()).collect();
+        let wave_32: Vec<f32> = wave_64.iter().map(|x| *x as f32).collect();
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs_64 = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window);
+        let sincs_32 = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let (mut interpolator_64, mut interpolator_32) = match (
+            AvxInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window),
+            AvxInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window),
+        ) {
+            (Ok(interpolator_64), Ok(interpolator_32)) => (interpolator_64, interpolator_32),
+            _ => {
+                assert!(!is_x86_feature_detected!("avx"));
+                return;
+            }
+        };
+        assert_eq!(interpolator_64.fma, is_x86_feature_detected!("fma"));
+        interpolator_64.fma = false;
+        interpolator_32.fma = false;
+        assert_eq!(interpolator_64.kernel(), "avx");
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator_64.get_sinc_interpolated(&wave_64, 333, *subindex);
+            let check = get_sinc_interpolated(&wave_64, 333, &sincs_64[*subindex]);
+            assert!((value - check).abs() < 1.0e-9);
+            let value = interpolator_32.get_sinc_interpolated(&wave_32, 333, *subindex);
+            let check = get_sinc_interpolated(&wave_32, 333, &sincs_32[*subindex]);
+            assert!((value - check).abs() < 1.0e-5);
+        }
+        let interpolator_32 = interpolator_32.with_accumulation(Accumulation::Double);
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator_32.get_sinc_interpolated(&wave_32, 333, *subindex);
+            let check = wave_32[333..333 + sinc_len]
+                .iter()
+                .zip(sincs_32[*subindex].iter())
+                .fold(0.0, |acc, (x, y)| acc + *x as f64 * *y as f64);
+            assert!((value as f64 - check).abs() < 1.0e-6);
+        }
+    }
 }
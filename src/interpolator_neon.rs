@@ -1,18 +1,22 @@
 use crate::asynchro::SincInterpolator;
 use crate::error::{CpuFeature, MissingCpuFeature};
-use crate::sinc::make_sincs;
+use crate::sinc::{drop_mirrored_sincs, make_sincs_with_phase, mirrored_subindex};
+use crate::sinc::{Accumulation, FilterPhase};
 use crate::windows::WindowFunction;
 use core::arch::aarch64::{float32x4_t, float64x2_t};
 use core::arch::aarch64::{vadd_f32, vaddq_f32, vfmaq_f32, vld1q_f32, vmovq_n_f32, vst1_f32, vget_high_f32, vget_low_f32};
 use core::arch::aarch64::{vaddq_f64, vfmaq_f64, vld1q_f64, vmovq_n_f64, vst1q_f64};
+use core::arch::aarch64::{vextq_f32, vextq_f64, vrev64q_f32};
+use core::arch::aarch64::{vcvt_f64_f32, vcvt_high_f64_f32};
 use crate::Sample;
+use std::fmt;
 
 /// Collection of cpu features required for this interpolator.
 static FEATURES: &[CpuFeature] = &[CpuFeature::Neon];
 
 /// Trait governing what can be done with an NeonSample.
-pub trait NeonSample: Sized + Send {
-    type Sinc: Send;
+pub trait NeonSample: Sized + Send + Sync {
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -35,6 +39,103 @@ pub trait NeonSample: Sized + Send {
         sincs: &[Vec<Self::Sinc>],
         length: usize,
     ) -> Self;
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Interpolate a sinc sample, with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+    }
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order,
+    /// with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_reversed_unsafe(wave, index, subindex, sincs, length)
+    }
+}
+
+/// Reverse the order of the elements of a vector.
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn reverse_f32(values: float32x4_t) -> float32x4_t {
+    let reversed_pairs = vrev64q_f32(values);
+    vextq_f32(reversed_pairs, reversed_pairs, 2)
+}
+
+/// Reverse the order of the elements of a vector.
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn reverse_f64(values: float64x2_t) -> float64x2_t {
+    vextq_f64(values, values, 1)
+}
+
+/// Multiply two vectors of f32 in f64, and add the products of the low and high halves
+/// to the two accumulators.
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn fma_wide(
+    acc_low: float64x2_t,
+    acc_high: float64x2_t,
+    wave: float32x4_t,
+    sinc: float32x4_t,
+) -> (float64x2_t, float64x2_t) {
+    let wave_low = vcvt_f64_f32(vget_low_f32(wave));
+    let sinc_low = vcvt_f64_f32(vget_low_f32(sinc));
+    (
+        vfmaq_f64(acc_low, wave_low, sinc_low),
+        vfmaq_f64(acc_high, vcvt_high_f64_f32(wave), vcvt_high_f64_f32(sinc)),
+    )
+}
+
+/// Sum the elements of four accumulators.
+#[inline]
+#[target_feature(enable = "neon")]
+unsafe fn sum_wide(
+    acc0: float64x2_t,
+    acc1: float64x2_t,
+    acc2: float64x2_t,
+    acc3: float64x2_t,
+) -> f64 {
+    let packedsum0 = vaddq_f64(acc0, acc1);
+    let packedsum1 = vaddq_f64(acc2, acc3);
+    let packedsum2 = vaddq_f64(packedsum0, packedsum1);
+    let mut values = [0.0, 0.0];
+    vst1q_f64(values.as_mut_ptr(), packedsum2);
+    values[0] + values[1]
 }
 
 impl NeonSample for f32 {
@@ -84,6 +185,101 @@ impl NeonSample for f32 {
         vst1_f32(array.as_mut_ptr(), sum2);
         array[0] + array[1]
     }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = vmovq_n_f32(0.0);
+        let mut acc1 = vmovq_n_f32(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = vld1q_f32(wave_cut.get_unchecked(w_idx));
+            let w1 = vld1q_f32(wave_cut.get_unchecked(w_idx + 4));
+            acc0 = vfmaq_f32(acc0, w0, reverse_f32(*sinc.get_unchecked(s_idx - 1)));
+            acc1 = vfmaq_f32(acc1, w1, reverse_f32(*sinc.get_unchecked(s_idx - 2)));
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        let sum4 = vaddq_f32(acc0, acc1);
+        let high = vget_high_f32(sum4);
+        let low = vget_low_f32(sum4);
+        let sum2 = vadd_f32(high, low);
+        let mut array = [0.0, 0.0];
+        vst1_f32(array.as_mut_ptr(), sum2);
+        array[0] + array[1]
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = vmovq_n_f64(0.0);
+        let mut acc1 = vmovq_n_f64(0.0);
+        let mut acc2 = vmovq_n_f64(0.0);
+        let mut acc3 = vmovq_n_f64(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = vld1q_f32(wave_cut.get_unchecked(w_idx));
+            let w1 = vld1q_f32(wave_cut.get_unchecked(w_idx + 4));
+            let (a0, a1) = fma_wide(acc0, acc1, w0, *sinc.get_unchecked(s_idx));
+            let (a2, a3) = fma_wide(acc2, acc3, w1, *sinc.get_unchecked(s_idx + 1));
+            acc0 = a0;
+            acc1 = a1;
+            acc2 = a2;
+            acc3 = a3;
+            w_idx += 8;
+            s_idx += 2;
+        }
+        sum_wide(acc0, acc1, acc2, acc3) as f32
+    }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = vmovq_n_f64(0.0);
+        let mut acc1 = vmovq_n_f64(0.0);
+        let mut acc2 = vmovq_n_f64(0.0);
+        let mut acc3 = vmovq_n_f64(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = vld1q_f32(wave_cut.get_unchecked(w_idx));
+            let w1 = vld1q_f32(wave_cut.get_unchecked(w_idx + 4));
+            let s0 = reverse_f32(*sinc.get_unchecked(s_idx - 1));
+            let s1 = reverse_f32(*sinc.get_unchecked(s_idx - 2));
+            let (a0, a1) = fma_wide(acc0, acc1, w0, s0);
+            let (a2, a3) = fma_wide(acc2, acc3, w1, s1);
+            acc0 = a0;
+            acc1 = a1;
+            acc2 = a2;
+            acc3 = a3;
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        sum_wide(acc0, acc1, acc2, acc3) as f32
+    }
 }
 
 impl NeonSample for f64 {
@@ -138,6 +334,42 @@ impl NeonSample for f64 {
         vst1q_f64(values.as_mut_ptr(), packedsum2);
         values[0] + values[1]
     }
+
+    #[target_feature(enable = "neon")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = vmovq_n_f64(0.0);
+        let mut acc1 = vmovq_n_f64(0.0);
+        let mut acc2 = vmovq_n_f64(0.0);
+        let mut acc3 = vmovq_n_f64(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = vld1q_f64(wave_cut.get_unchecked(w_idx));
+            let w1 = vld1q_f64(wave_cut.get_unchecked(w_idx+2));
+            let w2 = vld1q_f64(wave_cut.get_unchecked(w_idx+4));
+            let w3 = vld1q_f64(wave_cut.get_unchecked(w_idx+6));
+            acc0 = vfmaq_f64(acc0, w0, reverse_f64(*sinc.get_unchecked(s_idx - 1)));
+            acc1 = vfmaq_f64(acc1, w1, reverse_f64(*sinc.get_unchecked(s_idx - 2)));
+            acc2 = vfmaq_f64(acc2, w2, reverse_f64(*sinc.get_unchecked(s_idx - 3)));
+            acc3 = vfmaq_f64(acc3, w3, reverse_f64(*sinc.get_unchecked(s_idx - 4)));
+            w_idx += 8;
+            s_idx -= 4;
+        }
+        let packedsum0 = vaddq_f64(acc0, acc1);
+        let packedsum1 = vaddq_f64(acc2, acc3);
+        let packedsum2 = vaddq_f64(packedsum0, packedsum1);
+        let mut values = [0.0, 0.0];
+        vst1q_f64(values.as_mut_ptr(), packedsum2);
+        values[0] + values[1]
+    }
 }
 
 /// A SSE accelerated interpolator
@@ -148,6 +380,23 @@ where
     sincs: Vec<Vec<T::Sinc>>,
     length: usize,
     nbr_sincs: usize,
+    center: usize,
+    mirrored: bool,
+    wide: bool,
+}
+
+impl<T> fmt::Debug for NeonInterpolator<T>
+where
+    T: NeonSample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NeonInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .field("mirrored", &self.mirrored)
+            .field("wide", &self.wide)
+            .finish()
+    }
 }
 
 impl<T> SincInterpolator<T> for NeonInterpolator<T>
@@ -168,7 +417,28 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+        let sincs = &self.sincs;
+        let length = self.length;
+        if self.mirrored {
+            if let Some(stored) = mirrored_subindex(subindex, self.nbr_sincs) {
+                return unsafe {
+                    if self.wide {
+                        T::get_sinc_interpolated_reversed_wide_unsafe(
+                            wave, index, stored, sincs, length,
+                        )
+                    } else {
+                        T::get_sinc_interpolated_reversed_unsafe(wave, index, stored, sincs, length)
+                    }
+                };
+            }
+        }
+        unsafe {
+            if self.wide {
+                T::get_sinc_interpolated_wide_unsafe(wave, index, subindex, sincs, length)
+            } else {
+                T::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+            }
+        }
     }
 
     fn len(&self) -> usize {
@@ -178,6 +448,14 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn center(&self) -> usize {
+        self.center
+    }
+
+    fn kernel(&self) -> &'static str {
+        "neon"
+    }
 }
 
 impl<T> NeonInterpolator<T>
@@ -196,28 +474,80 @@ where
         oversampling_factor: usize,
         f_cutoff: f32,
         window: WindowFunction,
+    ) -> Result<Self, MissingCpuFeature> {
+        Self::new_with_phase(
+            sinc_len,
+            oversampling_factor,
+            f_cutoff,
+            window,
+            FilterPhase::Linear,
+        )
+    }
+
+    /// Create a new NeonInterpolator with the given phase response of the sincs.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `phase`: Phase response of the sincs, see `FilterPhase`.
+    pub fn new_with_phase(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+        phase: FilterPhase,
     ) -> Result<Self, MissingCpuFeature> {
         if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
             return Err(MissingCpuFeature(*feature));
         }
 
         assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
-        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        let (sincs, center) =
+            make_sincs_with_phase(sinc_len, oversampling_factor, f_cutoff, window, phase);
+        Self::from_sincs(sincs, center, phase == FilterPhase::Linear)
+    }
+
+    /// Create a new NeonInterpolator from an already calculated set of sincs,
+    /// where the point at `center` in each sinc lines up with the interpolated sample.
+    /// If `mirrored` is set, the table must be symmetric,
+    /// and only the half that isn't mirror images of the other half is kept.
+    pub(crate) fn from_sincs(
+        mut sincs: Vec<Vec<T>>,
+        center: usize,
+        mirrored: bool,
+    ) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let length = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(length % 8 == 0, "Sinc length must be a multiple of 8.");
+        if mirrored {
+            drop_mirrored_sincs(&mut sincs);
+        }
         let sincs = unsafe { <T as NeonSample>::pack_sincs(sincs) };
 
         Ok(Self {
             sincs,
-            length: sinc_len,
-            nbr_sincs: oversampling_factor,
+            length,
+            nbr_sincs,
+            center,
+            mirrored,
+            wide: false,
         })
     }
+
+    /// Choose how the products are summed, see `Accumulation`.
+    pub(crate) fn with_accumulation(mut self, accumulation: Accumulation) -> Self {
+        self.wide = accumulation == Accumulation::Double;
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::asynchro::SincInterpolator;
     use crate::interpolator_neon::NeonInterpolator;
-    use crate::sinc::make_sincs;
+    use crate::sinc::{make_sincs, Accumulation};
     use crate::WindowFunction;
     use num_traits::Float;
     use rand::Rng;
@@ -267,4 +597,31 @@ mod tests {
         let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
         assert!((value - check).abs() < 1.0e-5);
     }
+
+    #[test]
+    fn test_neon_interpolator_32_wide() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f32>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let interpolator =
+            NeonInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window)
+                .unwrap()
+                .with_accumulation(Accumulation::Double);
+        // Both the stored and the mirrored sincs, compared with a sum in f64.
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator.get_sinc_interpolated(&wave, 333, *subindex);
+            let check = wave[333..333 + sinc_len]
+                .iter()
+                .zip(sincs[*subindex].iter())
+                .fold(0.0, |acc, (x, y)| acc + *x as f64 * *y as f64);
+            assert!((value as f64 - check).abs() < 1.0e-6);
+        }
+    }
 }
@@ -1,22 +1,25 @@
 use crate::asynchro::SincInterpolator;
 use crate::error::{CpuFeature, MissingCpuFeature};
-use crate::sinc::make_sincs;
+use crate::sinc::{drop_mirrored_sincs, make_sincs_with_phase, mirrored_subindex};
+use crate::sinc::{Accumulation, FilterPhase};
 use crate::windows::WindowFunction;
 use crate::Sample;
 use core::arch::x86_64::{__m128, __m128d};
 use core::arch::x86_64::{
-    _mm_add_pd, _mm_hadd_pd, _mm_loadu_pd, _mm_mul_pd, _mm_setzero_pd, _mm_store_sd,
+    _mm_add_pd, _mm_hadd_pd, _mm_loadu_pd, _mm_mul_pd, _mm_setzero_pd, _mm_shuffle_pd, _mm_store_sd,
 };
 use core::arch::x86_64::{
-    _mm_add_ps, _mm_hadd_ps, _mm_loadu_ps, _mm_mul_ps, _mm_setzero_ps, _mm_store_ss,
+    _mm_add_ps, _mm_hadd_ps, _mm_loadu_ps, _mm_mul_ps, _mm_setzero_ps, _mm_shuffle_ps, _mm_store_ss,
 };
+use core::arch::x86_64::{_mm_cvtps_pd, _mm_movehl_ps};
+use std::fmt;
 
 /// Collection of cpu features required for this interpolator.
 static FEATURES: &[CpuFeature] = &[CpuFeature::Sse3];
 
 /// Trait governing what can be done with an SseSample.
-pub trait SseSample: Sized + Send {
-    type Sinc: Send;
+pub trait SseSample: Sized + Send + Sync {
+    type Sinc: Send + Sync;
 
     /// Pack sincs into a vector.
     ///
@@ -39,6 +42,98 @@ pub trait SseSample: Sized + Send {
         sincs: &[Vec<Self::Sinc>],
         length: usize,
     ) -> Self;
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Interpolate a sinc sample, with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+    }
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order,
+    /// with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_reversed_unsafe(wave, index, subindex, sincs, length)
+    }
+}
+
+/// Reverse the order of the elements of a vector.
+#[inline]
+#[target_feature(enable = "sse3")]
+unsafe fn reverse_ps(values: __m128) -> __m128 {
+    _mm_shuffle_ps(values, values, 0x1b)
+}
+
+/// Reverse the order of the elements of a vector.
+#[inline]
+#[target_feature(enable = "sse3")]
+unsafe fn reverse_pd(values: __m128d) -> __m128d {
+    _mm_shuffle_pd(values, values, 0b01)
+}
+
+/// Multiply two vectors of f32 in f64, and add the products of the low and high halves
+/// to the two accumulators.
+#[inline]
+#[target_feature(enable = "sse3")]
+unsafe fn mul_add_wide(
+    wave: __m128,
+    sinc: __m128,
+    acc_low: __m128d,
+    acc_high: __m128d,
+) -> (__m128d, __m128d) {
+    let low = _mm_mul_pd(_mm_cvtps_pd(wave), _mm_cvtps_pd(sinc));
+    let high = _mm_mul_pd(
+        _mm_cvtps_pd(_mm_movehl_ps(wave, wave)),
+        _mm_cvtps_pd(_mm_movehl_ps(sinc, sinc)),
+    );
+    (_mm_add_pd(acc_low, low), _mm_add_pd(acc_high, high))
+}
+
+/// Sum the elements of four accumulators.
+#[inline]
+#[target_feature(enable = "sse3")]
+unsafe fn sum_wide(acc0: __m128d, acc1: __m128d, acc2: __m128d, acc3: __m128d) -> f64 {
+    let temp2_0 = _mm_add_pd(acc0, acc1);
+    let temp2_1 = _mm_add_pd(acc2, acc3);
+    let temp2 = _mm_hadd_pd(temp2_0, temp2_1);
+    let temp1 = _mm_hadd_pd(temp2, temp2);
+    let mut result = 0.0;
+    _mm_store_sd(&mut result, temp1);
+    result
 }
 
 impl SseSample for f32 {
@@ -89,6 +184,102 @@ impl SseSample for f32 {
         _mm_store_ss(&mut result, temp1);
         result
     }
+
+    #[target_feature(enable = "sse3")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm_setzero_ps();
+        let mut acc1 = _mm_setzero_ps();
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx));
+            let w1 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx + 4));
+            let s0 = _mm_mul_ps(w0, reverse_ps(*sinc.get_unchecked(s_idx - 1)));
+            let s1 = _mm_mul_ps(w1, reverse_ps(*sinc.get_unchecked(s_idx - 2)));
+            acc0 = _mm_add_ps(acc0, s0);
+            acc1 = _mm_add_ps(acc1, s1);
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        let temp4 = _mm_add_ps(acc0, acc1);
+        let temp2 = _mm_hadd_ps(temp4, temp4);
+        let temp1 = _mm_hadd_ps(temp2, temp2);
+        let mut result = 0.0;
+        _mm_store_ss(&mut result, temp1);
+        result
+    }
+
+    #[target_feature(enable = "sse3")]
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm_setzero_pd();
+        let mut acc1 = _mm_setzero_pd();
+        let mut acc2 = _mm_setzero_pd();
+        let mut acc3 = _mm_setzero_pd();
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx));
+            let w1 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx + 4));
+            let (a0, a1) = mul_add_wide(w0, *sinc.get_unchecked(s_idx), acc0, acc1);
+            let (a2, a3) = mul_add_wide(w1, *sinc.get_unchecked(s_idx + 1), acc2, acc3);
+            acc0 = a0;
+            acc1 = a1;
+            acc2 = a2;
+            acc3 = a3;
+            w_idx += 8;
+            s_idx += 2;
+        }
+        sum_wide(acc0, acc1, acc2, acc3) as f32
+    }
+
+    #[target_feature(enable = "sse3")]
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm_setzero_pd();
+        let mut acc1 = _mm_setzero_pd();
+        let mut acc2 = _mm_setzero_pd();
+        let mut acc3 = _mm_setzero_pd();
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx));
+            let w1 = _mm_loadu_ps(wave_cut.get_unchecked(w_idx + 4));
+            let s0 = reverse_ps(*sinc.get_unchecked(s_idx - 1));
+            let s1 = reverse_ps(*sinc.get_unchecked(s_idx - 2));
+            let (a0, a1) = mul_add_wide(w0, s0, acc0, acc1);
+            let (a2, a3) = mul_add_wide(w1, s1, acc2, acc3);
+            acc0 = a0;
+            acc1 = a1;
+            acc2 = a2;
+            acc3 = a3;
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        sum_wide(acc0, acc1, acc2, acc3) as f32
+    }
 }
 
 impl SseSample for f64 {
@@ -148,6 +339,47 @@ impl SseSample for f64 {
         _mm_store_sd(&mut result, temp1);
         result
     }
+
+    #[target_feature(enable = "sse3")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = _mm_setzero_pd();
+        let mut acc1 = _mm_setzero_pd();
+        let mut acc2 = _mm_setzero_pd();
+        let mut acc3 = _mm_setzero_pd();
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = _mm_loadu_pd(wave_cut.get_unchecked(w_idx));
+            let w1 = _mm_loadu_pd(wave_cut.get_unchecked(w_idx + 2));
+            let w2 = _mm_loadu_pd(wave_cut.get_unchecked(w_idx + 4));
+            let w3 = _mm_loadu_pd(wave_cut.get_unchecked(w_idx + 6));
+            let s0 = _mm_mul_pd(w0, reverse_pd(*sinc.get_unchecked(s_idx - 1)));
+            let s1 = _mm_mul_pd(w1, reverse_pd(*sinc.get_unchecked(s_idx - 2)));
+            let s2 = _mm_mul_pd(w2, reverse_pd(*sinc.get_unchecked(s_idx - 3)));
+            let s3 = _mm_mul_pd(w3, reverse_pd(*sinc.get_unchecked(s_idx - 4)));
+            acc0 = _mm_add_pd(acc0, s0);
+            acc1 = _mm_add_pd(acc1, s1);
+            acc2 = _mm_add_pd(acc2, s2);
+            acc3 = _mm_add_pd(acc3, s3);
+            w_idx += 8;
+            s_idx -= 4;
+        }
+        let temp2_0 = _mm_add_pd(acc0, acc1);
+        let temp2_1 = _mm_add_pd(acc2, acc3);
+        let temp2 = _mm_hadd_pd(temp2_0, temp2_1);
+        let temp1 = _mm_hadd_pd(temp2, temp2);
+        let mut result = 0.0;
+        _mm_store_sd(&mut result, temp1);
+        result
+    }
 }
 
 /// A SSE accelerated interpolator
@@ -158,6 +390,23 @@ where
     sincs: Vec<Vec<T::Sinc>>,
     length: usize,
     nbr_sincs: usize,
+    center: usize,
+    mirrored: bool,
+    wide: bool,
+}
+
+impl<T> fmt::Debug for SseInterpolator<T>
+where
+    T: SseSample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SseInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .field("mirrored", &self.mirrored)
+            .field("wide", &self.wide)
+            .finish()
+    }
 }
 
 impl<T> SincInterpolator<T> for SseInterpolator<T>
@@ -178,7 +427,28 @@ where
             subindex,
             self.nbr_sincs - 1
         );
-        unsafe { T::get_sinc_interpolated_unsafe(wave, index, subindex, &self.sincs, self.length) }
+        let sincs = &self.sincs;
+        let length = self.length;
+        if self.mirrored {
+            if let Some(stored) = mirrored_subindex(subindex, self.nbr_sincs) {
+                return unsafe {
+                    if self.wide {
+                        T::get_sinc_interpolated_reversed_wide_unsafe(
+                            wave, index, stored, sincs, length,
+                        )
+                    } else {
+                        T::get_sinc_interpolated_reversed_unsafe(wave, index, stored, sincs, length)
+                    }
+                };
+            }
+        }
+        unsafe {
+            if self.wide {
+                T::get_sinc_interpolated_wide_unsafe(wave, index, subindex, sincs, length)
+            } else {
+                T::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+            }
+        }
     }
 
     fn len(&self) -> usize {
@@ -188,6 +458,14 @@ where
     fn nbr_sincs(&self) -> usize {
         self.nbr_sincs
     }
+
+    fn center(&self) -> usize {
+        self.center
+    }
+
+    fn kernel(&self) -> &'static str {
+        "sse3"
+    }
 }
 
 impl<T> SseInterpolator<T>
@@ -206,28 +484,80 @@ where
         oversampling_factor: usize,
         f_cutoff: f32,
         window: WindowFunction,
+    ) -> Result<Self, MissingCpuFeature> {
+        Self::new_with_phase(
+            sinc_len,
+            oversampling_factor,
+            f_cutoff,
+            window,
+            FilterPhase::Linear,
+        )
+    }
+
+    /// Create a new SseInterpolator with the given phase response of the sincs.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `phase`: Phase response of the sincs, see `FilterPhase`.
+    pub fn new_with_phase(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+        phase: FilterPhase,
     ) -> Result<Self, MissingCpuFeature> {
         if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
             return Err(MissingCpuFeature(*feature));
         }
 
         assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
-        let sincs = make_sincs(sinc_len, oversampling_factor, f_cutoff, window);
+        let (sincs, center) =
+            make_sincs_with_phase(sinc_len, oversampling_factor, f_cutoff, window, phase);
+        Self::from_sincs(sincs, center, phase == FilterPhase::Linear)
+    }
+
+    /// Create a new SseInterpolator from an already calculated set of sincs,
+    /// where the point at `center` in each sinc lines up with the interpolated sample.
+    /// If `mirrored` is set, the table must be symmetric,
+    /// and only the half that isn't mirror images of the other half is kept.
+    pub(crate) fn from_sincs(
+        mut sincs: Vec<Vec<T>>,
+        center: usize,
+        mirrored: bool,
+    ) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let length = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(length % 8 == 0, "Sinc length must be a multiple of 8.");
+        if mirrored {
+            drop_mirrored_sincs(&mut sincs);
+        }
         let sincs = unsafe { <T as SseSample>::pack_sincs(sincs) };
 
         Ok(Self {
             sincs,
-            length: sinc_len,
-            nbr_sincs: oversampling_factor,
+            length,
+            nbr_sincs,
+            center,
+            mirrored,
+            wide: false,
         })
     }
+
+    /// Choose how the products are summed, see `Accumulation`.
+    pub(crate) fn with_accumulation(mut self, accumulation: Accumulation) -> Self {
+        self.wide = accumulation == Accumulation::Double;
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::asynchro::SincInterpolator;
     use crate::interpolator_sse::SseInterpolator;
-    use crate::sinc::make_sincs;
+    use crate::sinc::{make_sincs, Accumulation};
     use crate::WindowFunction;
     use num_traits::Float;
     use rand::Rng;
@@ -277,4 +607,31 @@ mod tests {
         let check = get_sinc_interpolated(&wave, 333, &sincs[123]);
         assert!((value - check).abs() < 1.0e-5);
     }
+
+    #[test]
+    fn test_sse_interpolator_32_wide() {
+        let mut rng = rand::thread_rng();
+        let mut wave = Vec::new();
+        for _ in 0..2048 {
+            wave.push(rng.gen::<f32>());
+        }
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let interpolator =
+            SseInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window)
+                .unwrap()
+                .with_accumulation(Accumulation::Double);
+        // Both the stored and the mirrored sincs, compared with a sum in f64.
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator.get_sinc_interpolated(&wave, 333, *subindex);
+            let check = wave[333..333 + sinc_len]
+                .iter()
+                .zip(sincs[*subindex].iter())
+                .fold(0.0, |acc, (x, y)| acc + *x as f64 * *y as f64);
+            assert!((value as f64 - check).abs() < 1.0e-6);
+        }
+    }
 }
@@ -0,0 +1,621 @@
+use crate::asynchro::SincInterpolator;
+use crate::error::{CpuFeature, MissingCpuFeature};
+use crate::sinc::{drop_mirrored_sincs, make_sincs_with_phase, mirrored_subindex};
+use crate::sinc::{Accumulation, FilterPhase};
+use crate::windows::WindowFunction;
+use crate::Sample;
+use core::arch::wasm32::{f32x4_add, f32x4_extract_lane, f32x4_mul, f32x4_splat};
+use core::arch::wasm32::{f64x2_add, f64x2_extract_lane, f64x2_mul, f64x2_splat};
+use core::arch::wasm32::{f64x2_promote_low_f32x4, i32x4_shuffle, i64x2_shuffle};
+use core::arch::wasm32::{v128, v128_load};
+use std::fmt;
+
+/// Collection of cpu features required for this interpolator.
+/// WebAssembly has no runtime feature detection, so this is decided when compiling.
+static FEATURES: &[CpuFeature] = &[CpuFeature::Simd128];
+
+/// Trait governing what can be done with a WasmSample.
+pub trait WasmSample: Sized + Send + Sync {
+    type Sinc: Send + Sync;
+
+    /// Pack sincs into a vector.
+    ///
+    /// # Safety
+    ///
+    /// This is unsafe because it uses target_enable dispatching. There are no
+    /// special requirements from the caller.
+    unsafe fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>>;
+
+    /// Interpolate a sinc sample.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self;
+
+    /// Interpolate a sinc sample, with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+    }
+
+    /// Interpolate a sinc sample, using the sinc at `subindex` in reverse order,
+    /// with the products summed in f64.
+    ///
+    /// # Safety
+    ///
+    /// The caller must ensure that the various indexes are not out of bounds
+    /// in the collection of sincs.
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[Self],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> Self {
+        Self::get_sinc_interpolated_reversed_unsafe(wave, index, subindex, sincs, length)
+    }
+}
+
+/// Load 4 values of f32, or 2 of f64, starting at `value`.
+/// Wasm loads don't need to be aligned.
+#[inline]
+#[target_feature(enable = "simd128")]
+unsafe fn load<T>(value: &T) -> v128 {
+    v128_load(value as *const T as *const v128)
+}
+
+/// Reverse the order of the elements of a vector of f32.
+#[inline]
+#[target_feature(enable = "simd128")]
+unsafe fn reverse_f32(values: v128) -> v128 {
+    i32x4_shuffle::<3, 2, 1, 0>(values, values)
+}
+
+/// Reverse the order of the elements of a vector of f64.
+#[inline]
+#[target_feature(enable = "simd128")]
+unsafe fn reverse_f64(values: v128) -> v128 {
+    i64x2_shuffle::<1, 0>(values, values)
+}
+
+/// Multiply two vectors of f32 in f64, and add the products of the low and high halves
+/// to the two accumulators.
+#[inline]
+#[target_feature(enable = "simd128")]
+unsafe fn mul_add_wide(acc_low: v128, acc_high: v128, wave: v128, sinc: v128) -> (v128, v128) {
+    let wave_low = f64x2_promote_low_f32x4(wave);
+    let sinc_low = f64x2_promote_low_f32x4(sinc);
+    let wave_high = f64x2_promote_low_f32x4(i32x4_shuffle::<2, 3, 2, 3>(wave, wave));
+    let sinc_high = f64x2_promote_low_f32x4(i32x4_shuffle::<2, 3, 2, 3>(sinc, sinc));
+    (
+        f64x2_add(acc_low, f64x2_mul(wave_low, sinc_low)),
+        f64x2_add(acc_high, f64x2_mul(wave_high, sinc_high)),
+    )
+}
+
+/// Sum the elements of two accumulators of f32.
+#[inline]
+#[target_feature(enable = "simd128")]
+unsafe fn sum_f32(acc0: v128, acc1: v128) -> f32 {
+    let sum4 = f32x4_add(acc0, acc1);
+    (f32x4_extract_lane::<0>(sum4) + f32x4_extract_lane::<2>(sum4))
+        + (f32x4_extract_lane::<1>(sum4) + f32x4_extract_lane::<3>(sum4))
+}
+
+/// Sum the elements of four accumulators of f64.
+#[inline]
+#[target_feature(enable = "simd128")]
+unsafe fn sum_f64(acc0: v128, acc1: v128, acc2: v128, acc3: v128) -> f64 {
+    let packedsum0 = f64x2_add(acc0, acc1);
+    let packedsum1 = f64x2_add(acc2, acc3);
+    let packedsum2 = f64x2_add(packedsum0, packedsum1);
+    f64x2_extract_lane::<0>(packedsum2) + f64x2_extract_lane::<1>(packedsum2)
+}
+
+impl WasmSample for f32 {
+    type Sinc = v128;
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn pack_sincs(sincs: Vec<Vec<Self>>) -> Vec<Vec<Self::Sinc>> {
+        let mut packed_sincs = Vec::new();
+        for sinc in sincs.iter() {
+            let mut packed = Vec::new();
+            for elements in sinc.chunks(4) {
+                let packed_elems = load(&elements[0]);
+                packed.push(packed_elems);
+            }
+            packed_sincs.push(packed);
+        }
+        packed_sincs
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f32x4_splat(0.0);
+        let mut acc1 = f32x4_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = load(wave_cut.get_unchecked(w_idx));
+            let w1 = load(wave_cut.get_unchecked(w_idx + 4));
+            acc0 = f32x4_add(acc0, f32x4_mul(w0, *sinc.get_unchecked(s_idx)));
+            acc1 = f32x4_add(acc1, f32x4_mul(w1, *sinc.get_unchecked(s_idx + 1)));
+            w_idx += 8;
+            s_idx += 2;
+        }
+        sum_f32(acc0, acc1)
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f32x4_splat(0.0);
+        let mut acc1 = f32x4_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = load(wave_cut.get_unchecked(w_idx));
+            let w1 = load(wave_cut.get_unchecked(w_idx + 4));
+            let s0 = reverse_f32(*sinc.get_unchecked(s_idx - 1));
+            let s1 = reverse_f32(*sinc.get_unchecked(s_idx - 2));
+            acc0 = f32x4_add(acc0, f32x4_mul(w0, s0));
+            acc1 = f32x4_add(acc1, f32x4_mul(w1, s1));
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        sum_f32(acc0, acc1)
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f64x2_splat(0.0);
+        let mut acc1 = f64x2_splat(0.0);
+        let mut acc2 = f64x2_splat(0.0);
+        let mut acc3 = f64x2_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = load(wave_cut.get_unchecked(w_idx));
+            let w1 = load(wave_cut.get_unchecked(w_idx + 4));
+            let (a0, a1) = mul_add_wide(acc0, acc1, w0, *sinc.get_unchecked(s_idx));
+            let (a2, a3) = mul_add_wide(acc2, acc3, w1, *sinc.get_unchecked(s_idx + 1));
+            acc0 = a0;
+            acc1 = a1;
+            acc2 = a2;
+            acc3 = a3;
+            w_idx += 8;
+            s_idx += 2;
+        }
+        sum_f64(acc0, acc1, acc2, acc3) as f32
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_reversed_wide_unsafe(
+        wave: &[f32],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f32 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f64x2_splat(0.0);
+        let mut acc1 = f64x2_splat(0.0);
+        let mut acc2 = f64x2_splat(0.0);
+        let mut acc3 = f64x2_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = load(wave_cut.get_unchecked(w_idx));
+            let w1 = load(wave_cut.get_unchecked(w_idx + 4));
+            let s0 = reverse_f32(*sinc.get_unchecked(s_idx - 1));
+            let s1 = reverse_f32(*sinc.get_unchecked(s_idx - 2));
+            let (a0, a1) = mul_add_wide(acc0, acc1, w0, s0);
+            let (a2, a3) = mul_add_wide(acc2, acc3, w1, s1);
+            acc0 = a0;
+            acc1 = a1;
+            acc2 = a2;
+            acc3 = a3;
+            w_idx += 8;
+            s_idx -= 2;
+        }
+        sum_f64(acc0, acc1, acc2, acc3) as f32
+    }
+}
+
+impl WasmSample for f64 {
+    type Sinc = v128;
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn pack_sincs(sincs: Vec<Vec<f64>>) -> Vec<Vec<Self::Sinc>> {
+        let mut packed_sincs = Vec::new();
+        for sinc in sincs.iter() {
+            let mut packed = Vec::new();
+            for elements in sinc.chunks(2) {
+                let packed_elems = load(&elements[0]);
+                packed.push(packed_elems);
+            }
+            packed_sincs.push(packed);
+        }
+        packed_sincs
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f64x2_splat(0.0);
+        let mut acc1 = f64x2_splat(0.0);
+        let mut acc2 = f64x2_splat(0.0);
+        let mut acc3 = f64x2_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = 0;
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = load(wave_cut.get_unchecked(w_idx));
+            let w1 = load(wave_cut.get_unchecked(w_idx + 2));
+            let w2 = load(wave_cut.get_unchecked(w_idx + 4));
+            let w3 = load(wave_cut.get_unchecked(w_idx + 6));
+            acc0 = f64x2_add(acc0, f64x2_mul(w0, *sinc.get_unchecked(s_idx)));
+            acc1 = f64x2_add(acc1, f64x2_mul(w1, *sinc.get_unchecked(s_idx + 1)));
+            acc2 = f64x2_add(acc2, f64x2_mul(w2, *sinc.get_unchecked(s_idx + 2)));
+            acc3 = f64x2_add(acc3, f64x2_mul(w3, *sinc.get_unchecked(s_idx + 3)));
+            w_idx += 8;
+            s_idx += 4;
+        }
+        sum_f64(acc0, acc1, acc2, acc3)
+    }
+
+    #[target_feature(enable = "simd128")]
+    unsafe fn get_sinc_interpolated_reversed_unsafe(
+        wave: &[f64],
+        index: usize,
+        subindex: usize,
+        sincs: &[Vec<Self::Sinc>],
+        length: usize,
+    ) -> f64 {
+        let sinc = sincs.get_unchecked(subindex);
+        let wave_cut = &wave[index..(index + length)];
+        let mut acc0 = f64x2_splat(0.0);
+        let mut acc1 = f64x2_splat(0.0);
+        let mut acc2 = f64x2_splat(0.0);
+        let mut acc3 = f64x2_splat(0.0);
+        let mut w_idx = 0;
+        let mut s_idx = sinc.len();
+        for _ in 0..wave_cut.len() / 8 {
+            let w0 = load(wave_cut.get_unchecked(w_idx));
+            let w1 = load(wave_cut.get_unchecked(w_idx + 2));
+            let w2 = load(wave_cut.get_unchecked(w_idx + 4));
+            let w3 = load(wave_cut.get_unchecked(w_idx + 6));
+            let s0 = reverse_f64(*sinc.get_unchecked(s_idx - 1));
+            let s1 = reverse_f64(*sinc.get_unchecked(s_idx - 2));
+            let s2 = reverse_f64(*sinc.get_unchecked(s_idx - 3));
+            let s3 = reverse_f64(*sinc.get_unchecked(s_idx - 4));
+            acc0 = f64x2_add(acc0, f64x2_mul(w0, s0));
+            acc1 = f64x2_add(acc1, f64x2_mul(w1, s1));
+            acc2 = f64x2_add(acc2, f64x2_mul(w2, s2));
+            acc3 = f64x2_add(acc3, f64x2_mul(w3, s3));
+            w_idx += 8;
+            s_idx -= 4;
+        }
+        sum_f64(acc0, acc1, acc2, acc3)
+    }
+}
+
+/// A WebAssembly SIMD accelerated interpolator.
+/// It is used when compiling for wasm32 with the `simd128` target feature enabled,
+/// for example with `RUSTFLAGS="-C target-feature=+simd128"`.
+pub struct WasmInterpolator<T>
+where
+    T: WasmSample,
+{
+    sincs: Vec<Vec<T::Sinc>>,
+    length: usize,
+    nbr_sincs: usize,
+    center: usize,
+    mirrored: bool,
+    wide: bool,
+}
+
+impl<T> fmt::Debug for WasmInterpolator<T>
+where
+    T: WasmSample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WasmInterpolator")
+            .field("sinc_len", &self.length)
+            .field("nbr_sincs", &self.nbr_sincs)
+            .field("mirrored", &self.mirrored)
+            .field("wide", &self.wide)
+            .finish()
+    }
+}
+
+impl<T> SincInterpolator<T> for WasmInterpolator<T>
+where
+    T: Sample,
+{
+    /// Calculate the scalar produt of an input wave and the selected sinc filter
+    fn get_sinc_interpolated(&self, wave: &[T], index: usize, subindex: usize) -> T {
+        assert!(
+            (index + self.length) < wave.len(),
+            "Tried to interpolate for index {}, max for the given input is {}",
+            index,
+            wave.len() - self.length - 1
+        );
+        assert!(
+            subindex < self.nbr_sincs,
+            "Tried to use sinc subindex {}, max is {}",
+            subindex,
+            self.nbr_sincs - 1
+        );
+        let sincs = &self.sincs;
+        let length = self.length;
+        if self.mirrored {
+            if let Some(stored) = mirrored_subindex(subindex, self.nbr_sincs) {
+                return unsafe {
+                    if self.wide {
+                        T::get_sinc_interpolated_reversed_wide_unsafe(
+                            wave, index, stored, sincs, length,
+                        )
+                    } else {
+                        T::get_sinc_interpolated_reversed_unsafe(wave, index, stored, sincs, length)
+                    }
+                };
+            }
+        }
+        unsafe {
+            if self.wide {
+                T::get_sinc_interpolated_wide_unsafe(wave, index, subindex, sincs, length)
+            } else {
+                T::get_sinc_interpolated_unsafe(wave, index, subindex, sincs, length)
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.length
+    }
+
+    fn nbr_sincs(&self) -> usize {
+        self.nbr_sincs
+    }
+
+    fn center(&self) -> usize {
+        self.center
+    }
+
+    fn kernel(&self) -> &'static str {
+        "simd128"
+    }
+}
+
+impl<T> WasmInterpolator<T>
+where
+    T: Sample,
+{
+    /// Create a new WasmInterpolator
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: Length of sinc functions.
+    /// - `oversampling_factor`: Number of intermediate sincs (oversampling factor).
+    /// - `f_cutoff`: Relative cutoff frequency.
+    /// - `window`: Window function to use.
+    pub fn new(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+    ) -> Result<Self, MissingCpuFeature> {
+        Self::new_with_phase(
+            sinc_len,
+            oversampling_factor,
+            f_cutoff,
+            window,
+            FilterPhase::Linear,
+        )
+    }
+
+    /// Create a new WasmInterpolator with the given phase response of the sincs.
+    ///
+    /// Parameters are the same as for `new`, with the addition of:
+    /// - `phase`: Phase response of the sincs, see `FilterPhase`.
+    pub fn new_with_phase(
+        sinc_len: usize,
+        oversampling_factor: usize,
+        f_cutoff: f32,
+        window: WindowFunction,
+        phase: FilterPhase,
+    ) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        assert!(sinc_len % 8 == 0, "Sinc length must be a multiple of 8.");
+        let (sincs, center) =
+            make_sincs_with_phase(sinc_len, oversampling_factor, f_cutoff, window, phase);
+        Self::from_sincs(sincs, center, phase == FilterPhase::Linear)
+    }
+
+    /// Create a new WasmInterpolator from an already calculated set of sincs,
+    /// where the point at `center` in each sinc lines up with the interpolated sample.
+    /// If `mirrored` is set, the table must be symmetric,
+    /// and only the half that isn't mirror images of the other half is kept.
+    pub(crate) fn from_sincs(
+        mut sincs: Vec<Vec<T>>,
+        center: usize,
+        mirrored: bool,
+    ) -> Result<Self, MissingCpuFeature> {
+        if let Some(feature) = FEATURES.iter().find(|f| !f.is_detected()) {
+            return Err(MissingCpuFeature(*feature));
+        }
+
+        let length = sincs[0].len();
+        let nbr_sincs = sincs.len();
+        assert!(length % 8 == 0, "Sinc length must be a multiple of 8.");
+        if mirrored {
+            drop_mirrored_sincs(&mut sincs);
+        }
+        let sincs = unsafe { <T as WasmSample>::pack_sincs(sincs) };
+
+        Ok(Self {
+            sincs,
+            length,
+            nbr_sincs,
+            center,
+            mirrored,
+            wide: false,
+        })
+    }
+
+    /// Choose how the products are summed, see `Accumulation`.
+    pub(crate) fn with_accumulation(mut self, accumulation: Accumulation) -> Self {
+        self.wide = accumulation == Accumulation::Double;
+        self
+    }
+}
+
+// These tests only run on wasm32 with simd128, for example on wasm32-wasi with wasmtime:
+// `CARGO_TARGET_WASM32_WASI_RUNNER=wasmtime RUSTFLAGS="-C target-feature=+simd128"
+// cargo test --lib --target wasm32-wasi`.
+// The waves are made without rand, so that the tests don't need a source of entropy.
+#[cfg(test)]
+mod tests {
+    use crate::asynchro::SincInterpolator;
+    use crate::interpolator_wasm::WasmInterpolator;
+    use crate::sinc::{make_sincs, Accumulation};
+    use crate::WindowFunction;
+    use num_traits::Float;
+
+    fn get_sinc_interpolated<T: Float>(wave: &[T], index: usize, sinc: &[T]) -> T {
+        let wave_cut = &wave[index..(index + sinc.len())];
+        wave_cut
+            .iter()
+            .zip(sinc.iter())
+            .fold(T::zero(), |acc, (x, y)| acc + *x * *y)
+    }
+
+    fn make_wave() -> Vec<f64> {
+        (0..2048)
+            .map(|n| ((n * 7919) % 2048) as f64 / 1024.0 - 1.0)
+            .collect()
+    }
+
+    #[test]
+    fn test_wasm_interpolator_64() {
+        let wave = make_wave();
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f64>(sinc_len, oversampling_factor, f_cutoff, window);
+        let interpolator =
+            WasmInterpolator::<f64>::new(sinc_len, oversampling_factor, f_cutoff, window).unwrap();
+        // Both the stored and the mirrored sincs.
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator.get_sinc_interpolated(&wave, 333, *subindex);
+            let check = get_sinc_interpolated(&wave, 333, &sincs[*subindex]);
+            assert!((value - check).abs() < 1.0e-9);
+        }
+    }
+
+    #[test]
+    fn test_wasm_interpolator_32() {
+        let wave: Vec<f32> = make_wave().iter().map(|x| *x as f32).collect();
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let interpolator =
+            WasmInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window).unwrap();
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator.get_sinc_interpolated(&wave, 333, *subindex);
+            let check = get_sinc_interpolated(&wave, 333, &sincs[*subindex]);
+            assert!((value - check).abs() < 1.0e-5);
+        }
+    }
+
+    #[test]
+    fn test_wasm_interpolator_32_wide() {
+        let wave: Vec<f32> = make_wave().iter().map(|x| *x as f32).collect();
+        let sinc_len = 256;
+        let f_cutoff = 0.9473371669037001;
+        let oversampling_factor = 256;
+        let window = WindowFunction::BlackmanHarris2;
+        let sincs = make_sincs::<f32>(sinc_len, oversampling_factor, f_cutoff, window);
+        let interpolator =
+            WasmInterpolator::<f32>::new(sinc_len, oversampling_factor, f_cutoff, window)
+                .unwrap()
+                .with_accumulation(Accumulation::Double);
+        // Both the stored and the mirrored sincs, compared with a sum in f64.
+        for subindex in [10, 123, 200].iter() {
+            let value = interpolator.get_sinc_interpolated(&wave, 333, *subindex);
+            let check = wave[333..333 + sinc_len]
+                .iter()
+                .zip(sincs[*subindex].iter())
+                .fold(0.0, |acc, (x, y)| acc + *x as f64 * *y as f64);
+            assert!((value as f64 - check).abs() < 1.0e-6);
+        }
+    }
+}
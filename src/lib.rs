@@ -11,23 +11,53 @@
 //! interpolation filters. The sinc interpolation upsamples by an adjustable factor,
 //! and then the new sample points are calculated by interpolating between these points.
 //! The resampling ratio can be updated at any time.
+//! The sinc filters can optionally be made minimum phase, to reduce the latency
+//! for realtime use.
 //!
 //! ## Synchronous resampling
 //! Synchronous resampling is implemented via FFT. The data is FFT:ed, the spectrum modified,
 //! and then inverse FFT:ed to get the resampled data.
 //! This type of resampler is considerably faster but doesn't support changing the resampling ratio.
 //!
+//! ## Two-stage resampling
+//! For large resample ratios, the two methods can be combined in two stages.
+//! The synchronous stage changes the rate by a power of two,
+//! and the sinc interpolation takes care of the rest at the lower sample rate.
+//!
+//! ## Fixed input and output
+//! When both sides process audio in chunks of fixed size, for example when bridging two devices
+//! with independent clocks, a FIFO can take up the difference between the chunks.
+//! The resampler then always consumes and produces the same number of frames,
+//! and the fill level of the FIFO can be used to adjust the resample ratio.
+//!
 //! ## SIMD acceleration
 //! The asynchronous resampler is designed to benefit from auto-vectorization, meaning that the Rust compiler
 //! can recognize calculations that can be done in parallel. It will then use SIMD instructions for those.
 //! This works quite well, but there is still room for improvement.
 //! On x86_64 it will always use SSE3 if available. The speed benefit compared to auto-vectorization
 //! depends on the CPU, but tends to be in the range 20-30% for 64-bit data, and 50-100% for 32-bit data.
+//! The fastest available implementation is picked automatically. A specific one, or a custom
+//! implementation of the `SincInterpolator` trait, can be used with `new_with_interpolator`.
+//! For 32-bit data with long sincs, the SIMD implementations can sum the products in 64 bits,
+//! see `Accumulation::Double`. This lowers the rounding noise, but is slower.
+//!
+//! ## Realtime use
+//! All buffers are allocated when a resampler is created, so that the calls that process
+//! into buffers given by the caller never allocate. This covers the `process_*_into_buffer`
+//! methods of the [Resampler] trait, including the partial and final variants,
+//! as well as [reset](Resampler::reset), [reset_channel](Resampler::reset_channel)
+//! and changes of the active channels mask. These can be called from a realtime thread.
+//! The methods that return new vectors, like [process](Resampler::process),
+//! and the methods that change the sizes of a resampler, like
+//! [set_nbr_channels](Resampler::set_nbr_channels) or the `change_sample_rates` method
+//! of the FFT resamplers, may allocate.
 //!
 //! ## Cargo features
 //! #### `avx`: AVX on x86_64
 //! The `avx` feature is enabled by default, and enables the use of AVX when it's available.
 //! The speed increase compared to SSE depends on the CPU, and tends to range from zero to 50%.
+//! Fused multiply-add instructions are used when the CPU supports them,
+//! and the interpolator in use can be checked with `interpolator_kernel()` on the resampler.
 //! On other architectures than x86_64 the `avx` feature does nothing.
 //!
 //! #### `neon`: Experimental Neon support on aarch64
@@ -37,6 +67,12 @@
 //! compared to the auto-vectorized implementation.
 //! Note that this only works on a full 64-bit operating system.
 //!
+//! #### SIMD on wasm32
+//! WebAssembly has no runtime detection of cpu features, so the SIMD interpolator for wasm32
+//! is chosen when compiling. It is used when the `simd128` target feature is enabled,
+//! for example with `RUSTFLAGS="-C target-feature=+simd128"`, and otherwise the scalar interpolator is used.
+//! The wasm SIMD intrinsics require rustc 1.54 or newer. Only build with `simd128` for engines that support it.
+//!
 //! ## Documentation
 //!
 //! The full documentation can be generated by rustdoc. To generate and view it run:
@@ -48,13 +84,16 @@
 //! Resample a single chunk of a dummy audio file from 44100 to 48000 Hz.
 //! See also the "fixedin64" example that can be used to process a file from disk.
 //! ```
-//! use rubato::{Resampler, SincFixedIn, InterpolationType, InterpolationParameters, WindowFunction};
+//! use rubato::{Resampler, SincFixedIn, InterpolationType, InterpolationParameters, WindowFunction, FilterPhase, SincStorage, Accumulation};
 //! let params = InterpolationParameters {
 //!     sinc_len: 256,
 //!     f_cutoff: 0.95,
 //!     interpolation: InterpolationType::Linear,
 //!     oversampling_factor: 256,
 //!     window: WindowFunction::BlackmanHarris2,
+//!     phase: FilterPhase::Linear,
+//!     storage: SincStorage::Table,
+//!     accumulation: Accumulation::Native,
 //! };
 //! let mut resampler = SincFixedIn::<f64>::new(
 //!     48000 as f64 / 44100 as f64,
@@ -74,19 +113,46 @@
 #![cfg_attr(feature = "neon", feature(aarch64_target_feature))]
 #![cfg_attr(feature = "neon", feature(stdsimd))]
 
+mod adjuster;
 mod asynchro;
+mod buffers;
+mod builder;
+mod control;
+mod denormals;
 mod error;
+mod fixedboth;
+mod hybrid;
 mod interpolation;
+mod multi;
+mod oneshot;
+mod response;
 mod sample;
 mod sinc;
 mod synchro;
+mod twostage;
 mod windows;
 
-pub use crate::asynchro::{ScalarInterpolator, SincFixedIn, SincFixedOut};
+pub use crate::adjuster::RateAdjuster;
+pub use crate::asynchro::{OnTheFlyInterpolator, RampType, ResamplerState, ScalarInterpolator};
+pub use crate::asynchro::{SincFixedIn, SincFixedOut, SincInterpolator};
+pub use crate::builder::{Quality, ResamplerBuilder};
+pub use crate::control::RatioControl;
+pub use crate::error::ResamplerConstructionError;
 pub use crate::error::{CpuFeature, MissingCpuFeature, ResampleError, ResampleResult};
+pub use crate::fixedboth::SincFixedBoth;
+pub use crate::hybrid::HybridFixedIn;
+pub use crate::multi::MultiResampler;
+pub use crate::oneshot::resample;
 pub use crate::sample::Sample;
-pub use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut};
-pub use crate::windows::WindowFunction;
+pub use crate::sinc::{Accumulation, FilterPhase, SincStorage};
+pub use crate::synchro::{FftFilter, FftFixedIn, FftFixedInOut, FftFixedOut, FftOverlap};
+pub use crate::twostage::TwoStageSincFixedIn;
+pub use crate::windows::{calculate_cutoff, calculate_sinc_len, kaiser_design};
+pub use crate::windows::{window_enbw, window_sidelobe_level, WindowFunction};
+pub use realfft::RealFftPlanner;
+
+use crate::sinc::{MAX_OVERSAMPLING_FACTOR, MAX_SINC_LEN};
+use crate::windows::WINDOW_FUNCTIONS;
 
 /// Helper macro to define a dummy implementation of the sample trait if a
 /// feature is not supported.
@@ -132,11 +198,17 @@ interpolator! {
     trait NeonSample;
 }
 
+interpolator! {
+    #[cfg(all(target_arch = "wasm32", target_feature = "simd128"))]
+    mod interpolator_wasm;
+    trait WasmSample;
+}
+
 #[macro_use]
 extern crate log;
 
 /// A struct holding the parameters for interpolation.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub struct InterpolationParameters {
     /// Length of the windowed sinc interpolation filter.
     /// Higher values can allow a higher cut-off frequency leading to less high frequency roll-off
@@ -151,11 +223,239 @@ pub struct InterpolationParameters {
     /// Only the points actually needed are calculated dusing processing
     /// so a larger number does not directly lead to higher cpu usage.
     /// But keeping it down helps in keeping the sincs in the cpu cache. Start at 128.
+    /// With `FilterPhase::Linear` the sincs are symmetric, and only half of them are stored.
+    /// Set to 0 to let the resampler choose a value for a signal to noise ratio of 100 dB,
+    /// see [recommended_oversampling](InterpolationParameters::recommended_oversampling).
     pub oversampling_factor: usize,
     /// Interpolation type, see `InterpolationType`
     pub interpolation: InterpolationType,
     /// Window function to use.
     pub window: WindowFunction,
+    /// Phase response of the sinc interpolation filter, see `FilterPhase`.
+    /// Use `FilterPhase::Linear` unless the latency of the resampler is a problem.
+    pub phase: FilterPhase,
+    /// How the sinc interpolation filters are stored, see `SincStorage`.
+    /// Use `SincStorage::Table` unless the memory used by the table is a problem.
+    pub storage: SincStorage,
+    /// How the products of the interpolation are summed, see `Accumulation`.
+    /// Use `Accumulation::Native` unless the rounding noise of f32 processing
+    /// with a long sinc is a problem.
+    pub accumulation: Accumulation,
+}
+
+/// The signal to noise ratio used when the oversampling factor is chosen automatically.
+const AUTOMATIC_OVERSAMPLING_SNR_DB: f64 = 100.0;
+
+impl InterpolationParameters {
+    /// Get the smallest oversampling factor that keeps the error of interpolating
+    /// between the intermediate points at least `target_snr_db` dB below the signal.
+    ///
+    /// The estimate uses the error bounds of the interpolation methods,
+    /// for a full scale signal at the Nyquist frequency.
+    /// With `h = pi / oversampling_factor`, the largest relative errors are
+    /// `h / 2` for nearest, `h^2 / 8` for linear and `3 * h^4 / 128` for cubic interpolation.
+    /// A target of 100 dB gives a factor of 22 for cubic and 352 for linear interpolation.
+    /// Nearest needs a very large table for any useful signal to noise ratio,
+    /// and is better used with an oversampling factor that gives exact points as described for
+    /// [InterpolationType::Nearest].
+    pub fn recommended_oversampling(interpolation: InterpolationType, target_snr_db: f64) -> usize {
+        let max_error = 10.0f64.powf(-target_snr_db / 20.0);
+        let pi = std::f64::consts::PI;
+        let factor = match interpolation {
+            InterpolationType::Nearest => pi / (2.0 * max_error),
+            InterpolationType::Linear => pi * (1.0 / (8.0 * max_error)).sqrt(),
+            InterpolationType::Cubic => pi * (3.0 / (128.0 * max_error)).sqrt().sqrt(),
+        };
+        (factor.ceil() as usize).max(1)
+    }
+
+    /// Design the parameters from a specification of the filter, instead of choosing
+    /// the sinc length, cutoff and window by hand.
+    ///
+    /// Parameters are:
+    /// - `stopband_db`: the smallest attenuation of the stopband, in dB.
+    /// - `transition_width`: the largest width of the transition band,
+    ///   relative to the lower Nyquist frequency, like `f_cutoff`.
+    /// - `interpolation`: the interpolation between the intermediate points.
+    ///
+    /// The window is the one that gives the shortest sinc among those with enough attenuation,
+    /// and the sinc is made long enough for the transition band, rounded up to a multiple of 8.
+    /// The cutoff is then placed so that the stopband starts at the Nyquist frequency.
+    /// The oversampling factor is chosen with
+    /// [recommended_oversampling](InterpolationParameters::recommended_oversampling),
+    /// to keep the interpolation error below the stopband.
+    /// The filter has linear phase, and the sincs are stored in a table.
+    ///
+    /// The transition band has the given width when upsampling.
+    /// When downsampling, the sinc is stretched by the resample ratio, and the transition band
+    /// is wider by the inverse of the ratio. Multiply `transition_width` by the ratio
+    /// to get the given width also when downsampling.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `stopband_db` is larger than what the best window gives,
+    /// about 140 dB, if `transition_width` isn't between 0 and 1, or if the sinc
+    /// or the oversampling factor would be larger than the resamplers accept.
+    /// Nearest interpolation needs very large oversampling factors,
+    /// and is only possible for low attenuations.
+    pub fn from_spec(
+        stopband_db: f64,
+        transition_width: f64,
+        interpolation: InterpolationType,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if !(stopband_db > 0.0 && transition_width > 0.0 && transition_width < 1.0) {
+            return Err(ResamplerConstructionError::UnachievableFilterSpec);
+        }
+        let window = WINDOW_FUNCTIONS
+            .iter()
+            .filter(|window| window.stopband_attenuation() >= stopband_db)
+            .min_by(|a, b| {
+                a.transition_width()
+                    .partial_cmp(&b.transition_width())
+                    .unwrap()
+            })
+            .copied()
+            .ok_or(ResamplerConstructionError::UnachievableFilterSpec)?;
+        let sinc_len = 8 * ((window.transition_width() / transition_width / 8.0).ceil() as usize);
+        Self::with_window(
+            stopband_db,
+            transition_width,
+            window,
+            sinc_len,
+            interpolation,
+        )
+    }
+
+    /// Design the parameters for a sinc with a Kaiser window from a specification of the filter,
+    /// like [from_spec](InterpolationParameters::from_spec).
+    /// The window and the sinc length are found with [kaiser_design],
+    /// which gives a shorter sinc than the fixed windows for most specifications,
+    /// and allows any attenuation.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `stopband_db` isn't positive,
+    /// if `transition_width` isn't between 0 and 1, or if the sinc
+    /// or the oversampling factor would be larger than the resamplers accept.
+    pub fn kaiser(
+        stopband_db: f64,
+        transition_width: f64,
+        interpolation: InterpolationType,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if !(stopband_db > 0.0 && transition_width > 0.0 && transition_width < 1.0) {
+            return Err(ResamplerConstructionError::UnachievableFilterSpec);
+        }
+        let (sinc_len, beta) = kaiser_design(stopband_db, transition_width);
+        Self::with_window(
+            stopband_db,
+            transition_width,
+            WindowFunction::Kaiser(beta),
+            sinc_len,
+            interpolation,
+        )
+    }
+
+    /// Make parameters for a sinc windowed by a custom window, see [WindowFunction::Custom].
+    /// The attenuation and transition band of a custom window are not known,
+    /// so the cutoff must be given, and the filter has linear phase
+    /// and is stored in a table.
+    ///
+    /// Parameters are:
+    /// - `sinc_len`: length of the windowed sinc, a multiple of 8.
+    /// - `f_cutoff`: relative cutoff frequency, like [f_cutoff](InterpolationParameters::f_cutoff).
+    /// - `oversampling_factor`: number of intermediate points, or 0 to let the resampler choose.
+    /// - `interpolation`: the interpolation between the intermediate points.
+    /// - `window`: the window function, called as `window(x, npoints)`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the sinc length or the oversampling factor is zero,
+    /// too large or not a multiple of 8, or if the window has values that are not finite
+    /// or is zero everywhere for the `sinc_len * oversampling_factor` points of the sinc.
+    pub fn with_custom_window(
+        sinc_len: usize,
+        f_cutoff: f32,
+        oversampling_factor: usize,
+        interpolation: InterpolationType,
+        window: fn(usize, usize) -> f64,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let parameters = InterpolationParameters {
+            sinc_len,
+            f_cutoff,
+            oversampling_factor,
+            interpolation,
+            window: WindowFunction::Custom(window),
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        }
+        .with_automatic_oversampling();
+        let factor = parameters.oversampling_factor;
+        if factor > MAX_OVERSAMPLING_FACTOR {
+            return Err(ResamplerConstructionError::InvalidOversamplingFactor { factor });
+        }
+        if sinc_len == 0 || sinc_len % 8 != 0 || sinc_len > MAX_SINC_LEN {
+            return Err(ResamplerConstructionError::InvalidFilterLength {
+                length: sinc_len * factor,
+                factor,
+            });
+        }
+        let npoints = sinc_len * factor;
+        let mut nonzero = false;
+        for x in 0..npoints {
+            let value = window(x, npoints);
+            if !value.is_finite() {
+                return Err(ResamplerConstructionError::InvalidWindow);
+            }
+            nonzero |= value != 0.0;
+        }
+        if !nonzero {
+            return Err(ResamplerConstructionError::InvalidWindow);
+        }
+        Ok(parameters)
+    }
+
+    /// Finish a design from a specification, for the chosen window and sinc length.
+    fn with_window(
+        stopband_db: f64,
+        transition_width: f64,
+        window: WindowFunction,
+        sinc_len: usize,
+        interpolation: InterpolationType,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let oversampling_factor = Self::recommended_oversampling(interpolation, stopband_db);
+        if sinc_len > MAX_SINC_LEN || oversampling_factor > MAX_OVERSAMPLING_FACTOR {
+            return Err(ResamplerConstructionError::UnachievableFilterSpec);
+        }
+        let f_cutoff = calculate_cutoff(sinc_len, window);
+        debug!(
+            "Designed filter for {} dB and transition width {}: window {:?}, sinc_len {}, f_cutoff {}, oversampling {}",
+            stopband_db, transition_width, window, sinc_len, f_cutoff, oversampling_factor
+        );
+        Ok(InterpolationParameters {
+            sinc_len,
+            f_cutoff,
+            oversampling_factor,
+            interpolation,
+            window,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        })
+    }
+
+    /// Fill in the oversampling factor if it was left at 0.
+    pub(crate) fn with_automatic_oversampling(mut self) -> Self {
+        if self.oversampling_factor == 0 {
+            self.oversampling_factor =
+                Self::recommended_oversampling(self.interpolation, AUTOMATIC_OVERSAMPLING_SNR_DB);
+            debug!(
+                "Use automatic oversampling factor {}",
+                self.oversampling_factor
+            );
+        }
+        self
+    }
 }
 
 /// Interpolation methods that can be selected. For asynchronous interpolation where the
@@ -166,7 +466,7 @@ pub struct InterpolationParameters {
 /// It's more efficient to combine the sinc filters with some other interpolation technique.
 /// Then sinc filters are used to provide a fixed number of interpolated points between input samples,
 /// and then the new value is calculated by interpolation between those points.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum InterpolationType {
     /// For cubic interpolation, the four nearest intermediate points are calculated
     /// using sinc interpolation.
@@ -191,9 +491,39 @@ pub enum InterpolationType {
     Nearest,
 }
 
+/// What to write to the output of inactive channels,
+/// see [set_inactive_channel_output](Resampler::set_inactive_channel_output).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InactiveOutput {
+    /// Leave the output of inactive channels untouched.
+    Skip,
+    /// Fill the output of inactive channels with zeros.
+    Zero,
+}
+
+/// The result of a processing call, see [process_into_buffer_ext](Resampler::process_into_buffer_ext).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProcessResult {
+    /// The number of input frames consumed by the call.
+    pub input_frames_used: usize,
+    /// The number of output frames written by the call.
+    pub output_frames_written: usize,
+    /// The number of input frames needed for the next call,
+    /// see [nbr_frames_needed](Resampler::nbr_frames_needed).
+    pub input_frames_next: usize,
+    /// The number of output frames the next call may write, which is the room
+    /// needed in the output buffer, see [output_frames_max](Resampler::output_frames_max).
+    /// For resamplers with a fixed output size, this is exactly the number of frames
+    /// that will be written.
+    pub output_frames_next: usize,
+}
+
 /// A resampler that us used to resample a chunk of audio to a new sample rate.
 /// The rate can be adjusted as required.
-pub trait Resampler<T>: Send {
+///
+/// Resamplers are both `Send` and `Sync`, so they can be moved to another thread,
+/// or shared between threads behind a lock such as a `Mutex`.
+pub trait Resampler<T>: Send + Sync {
     /// Resample a chunk of audio.
     ///
     /// The input data is a slice, where each element of the slice is itself referenceable as a slice
@@ -202,14 +532,543 @@ pub trait Resampler<T>: Send {
     /// of the vector is itself a vector which contains the samples for a single channel.
     fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>>;
 
+    /// Resample a chunk of interleaved audio, and write the result to an interleaved output buffer.
+    ///
+    /// The input must contain exactly [nbr_frames_needed](Resampler::nbr_frames_needed) frames,
+    /// meaning that its length is that number multiplied by the number of channels.
+    /// The output buffer must have room for at least [output_frames_max](Resampler::output_frames_max) frames.
+    ///
+    /// The optional `active_channels_mask` selects which channels to process.
+    /// Inactive channels are skipped, and their samples in the output buffer are left untouched.
+    /// All channels are processed when the mask is `None`.
+    ///
+    /// This method does not allocate, which makes it suitable for use in realtime threads.
+    /// It returns the number of input frames consumed and the number of output frames written.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential input buffers, and write the result
+    /// to sequential output buffers.
+    ///
+    /// This works like [process_into_buffer_at](Resampler::process_into_buffer_at),
+    /// with both offsets set to zero.
+    fn process_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        self.process_into_buffer_at(wave_in, 0, wave_out, 0, active_channels_mask)
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    ///
+    /// This makes it possible to step through long buffers without copying each chunk
+    /// to a separate buffer.
+    /// Each active input channel must hold at least `input_offset` plus
+    /// [nbr_frames_needed](Resampler::nbr_frames_needed) frames, and each active output channel
+    /// must hold at least `output_offset` plus [output_frames_max](Resampler::output_frames_max) frames.
+    ///
+    /// The optional `active_channels_mask` selects which channels to process.
+    /// Inactive channels are skipped, and their buffers may have any length.
+    /// All channels are processed when the mask is `None`.
+    ///
+    /// This method does not allocate, which makes it suitable for use in realtime threads.
+    /// It returns the number of input frames consumed and the number of output frames written.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential input buffers, and write the result
+    /// to sequential output buffers.
+    ///
+    /// This works like [process_into_buffer](Resampler::process_into_buffer),
+    /// but returns a [ProcessResult] that also tells how many frames
+    /// the next call will need and produce.
+    /// This saves querying the resampler again when scheduling the next call.
+    fn process_into_buffer_ext<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<ProcessResult> {
+        let (input_frames_used, output_frames_written) =
+            self.process_into_buffer(wave_in, wave_out, active_channels_mask)?;
+        Ok(ProcessResult {
+            input_frames_used,
+            output_frames_written,
+            input_frames_next: self.nbr_frames_needed(),
+            output_frames_next: self.output_frames_max(),
+        })
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, and append the result
+    /// to sequential output buffers.
+    ///
+    /// The output is written starting at frame `output_position`, which is then advanced
+    /// by the number of frames written. This makes it easy to collect the output of many calls
+    /// in one large buffer. The input is read like for
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    ///
+    /// Each active output channel must have room for [output_frames_max](Resampler::output_frames_max)
+    /// frames after `output_position`. If not, an
+    /// [InsufficientOutputBufferSize](ResampleError::InsufficientOutputBufferSize) error
+    /// gives the length the buffers need to have, and `output_position` is left unchanged.
+    ///
+    /// This method does not allocate, which makes it suitable for use in realtime threads.
+    /// It returns the number of input frames consumed and the number of output frames written.
+    fn process_append_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [U],
+        output_position: &mut usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let (frames_in, frames_out) = self.process_into_buffer_at(
+            wave_in,
+            0,
+            wave_out,
+            *output_position,
+            active_channels_mask,
+        )?;
+        *output_position += frames_out;
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, and write the result
+    /// directly to an interleaved output buffer.
+    ///
+    /// Each active input channel must hold at least [nbr_frames_needed](Resampler::nbr_frames_needed)
+    /// frames, and the output buffer must have room for at least
+    /// [output_frames_max](Resampler::output_frames_max) frames.
+    /// The output frames are written in place, so there is no need for an intermediate
+    /// sequential buffer that is interleaved afterwards.
+    ///
+    /// The optional `active_channels_mask` works like for
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    /// Inactive channels are skipped, and their samples in the output buffer are left untouched.
+    ///
+    /// This method does not allocate, which makes it suitable for use in realtime threads.
+    /// It returns the number of input frames consumed and the number of output frames written.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample the last frames of a stream, when fewer than
+    /// [nbr_frames_needed](Resampler::nbr_frames_needed) frames remain.
+    ///
+    /// This is equivalent to padding each active input channel with zeros to the needed length,
+    /// and calling [process_into_buffer](Resampler::process_into_buffer),
+    /// but the padding is done while reading the input, so nothing is allocated or copied.
+    /// The input may also be `None`, which processes only zeros.
+    /// All active input channels must have the same length.
+    ///
+    /// This method does not allocate, which makes it suitable for use in realtime threads.
+    /// It returns the number of input frames used, not counting the padding,
+    /// and the number of output frames written.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    ///
+    /// The first call takes the remaining input, which may be shorter than
+    /// [nbr_frames_needed](Resampler::nbr_frames_needed) or `None`, and works like
+    /// [process_partial_into_buffer](Resampler::process_partial_into_buffer).
+    /// Keep calling with `None` until it returns zero output frames.
+    /// Any input given to these later calls is ignored.
+    ///
+    /// The returned number of output frames only counts the frames that correspond to real input,
+    /// and not the resampled zero padding.
+    /// If the resample ratio was the same for the whole stream, the output holds exactly
+    /// `ceil(total_input_frames * ratio)` frames after the initial
+    /// [output_delay](Resampler::output_delay) frames.
+    /// Call [reset](Resampler::reset) to start a new stream.
+    /// Like the other processing calls into buffers, this method does not allocate.
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
     /// Query for the number of frames needed for the next call to "process".
     fn nbr_frames_needed(&self) -> usize;
 
+    /// Get the maximum number of input frames that a single call to "process" can need.
+    /// For the resamplers where [nbr_frames_needed](Resampler::nbr_frames_needed) varies,
+    /// this covers all the ratios allowed by
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    fn input_frames_max(&self) -> usize;
+
+    /// Get the maximum number of output frames that a single call to "process" can produce.
+    /// Use this to size the output buffer for
+    /// [process_interleaved_into_buffer](Resampler::process_interleaved_into_buffer).
+    fn output_frames_max(&self) -> usize;
+
+    /// Allocate a buffer with one vector per channel for the input of the processing calls.
+    ///
+    /// With `filled`, each vector holds [input_frames_max](Resampler::input_frames_max) zeros,
+    /// and the buffer can be passed to the processing calls that read the first
+    /// [nbr_frames_needed](Resampler::nbr_frames_needed) frames, such as
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    /// Otherwise the vectors are empty, with room for that many frames,
+    /// which avoids writing the zeros.
+    fn input_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample,
+    {
+        allocate_buffer(self.nbr_channels(), self.input_frames_max(), filled)
+    }
+
+    /// Allocate a buffer with one vector per channel for the output of the processing calls.
+    ///
+    /// With `filled`, each vector holds [output_frames_max](Resampler::output_frames_max) zeros,
+    /// and the buffer can be passed directly to for example
+    /// [process_into_buffer](Resampler::process_into_buffer).
+    /// Otherwise the vectors are empty, with room for that many frames.
+    fn output_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample,
+    {
+        allocate_buffer(self.nbr_channels(), self.output_frames_max(), filled)
+    }
+
+    /// Resample a complete waveform in one go.
+    ///
+    /// The input is split into chunks internally, and the end is flushed out by padding with zeros.
+    /// The initial frames corresponding to the [output_delay](Resampler::output_delay)
+    /// are removed, so that the output is aligned with the input.
+    /// Each non-empty output channel contains `ceil(input_frames * ratio)` frames,
+    /// the same as a stream with a trimmed delay that is ended with
+    /// [process_final_into_buffer](Resampler::process_final_into_buffer),
+    /// and empty input channels give empty output channels.
+    /// Channels that are inactive in the stored
+    /// [active channels mask](Resampler::set_active_channels_mask) also give empty output channels.
+    /// All non-empty input channels must have the same length.
+    ///
+    /// The resampler is [reset](Resampler::reset) before and after processing.
+    fn process_all<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample,
+    {
+        let frames_in = wave_in
+            .iter()
+            .map(|wave| wave.as_ref().len())
+            .find(|len| *len > 0)
+            .unwrap_or(0);
+        for (chan, wave) in wave_in.iter().enumerate() {
+            let len = wave.as_ref().len();
+            if len > 0 && len != frames_in {
+                return Err(ResampleError::WrongNumberOfFrames {
+                    channel: chan,
+                    expected: frames_in,
+                    actual: len,
+                });
+            }
+        }
+        self.reset();
+        let frames_out = (frames_in as f64 * self.resample_ratio()).ceil() as usize;
+        let delay = self.output_delay();
+        let mut wave_out = vec![Vec::new(); wave_in.len()];
+        let mut chunk = vec![Vec::new(); wave_in.len()];
+        let mut position = 0;
+        let mut produced = 0;
+        while frames_in > 0 && produced < delay + frames_out {
+            let frames = self.nbr_frames_needed();
+            for (chunk_chan, wave) in chunk.iter_mut().zip(wave_in.iter()) {
+                let wave = wave.as_ref();
+                if !wave.is_empty() {
+                    chunk_chan.clear();
+                    let start = position.min(frames_in);
+                    let end = (position + frames).min(frames_in);
+                    chunk_chan.extend_from_slice(&wave[start..end]);
+                    chunk_chan.resize(frames, T::zero());
+                }
+            }
+            position += frames;
+            let chunk_out = self.process(&chunk)?;
+            for (out_chan, new_frames) in wave_out.iter_mut().zip(chunk_out.iter()) {
+                out_chan.extend_from_slice(new_frames);
+            }
+            produced += chunk_out.iter().map(|chan| chan.len()).max().unwrap_or(0);
+        }
+        let mask = self.active_channels_mask();
+        for (chan, (out_chan, wave)) in wave_out.iter_mut().zip(wave_in.iter()).enumerate() {
+            let active = mask.get(chan).copied().unwrap_or(true);
+            if wave.as_ref().is_empty() || !active {
+                out_chan.clear();
+            } else {
+                out_chan.drain(..delay);
+                out_chan.truncate(frames_out);
+            }
+        }
+        self.reset();
+        Ok(wave_out)
+    }
+
+    /// Reset the resampler state and clear all internal buffers.
+    /// A resampler with an adjustable ratio also goes back to the original resample ratio.
+    /// This method does not allocate.
+    fn reset(&mut self);
+
+    /// Clear the history of a single channel, for example when its source has been reconnected.
+    /// The other channels, the resample ratio and the position between input samples
+    /// are left untouched, so processing continues without interruption.
+    /// This method does not allocate.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the channel index is out of range.
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()>;
+
+    /// Get the number of channels.
+    fn nbr_channels(&self) -> usize;
+
+    /// Change the number of channels, keeping the filter tables and FFT plans.
+    ///
+    /// The history of the remaining channels is kept, removed channels are dropped,
+    /// and added channels start with an empty history. Added channels are active in the
+    /// stored [active channels mask](Resampler::set_active_channels_mask).
+    /// The buffers given to the following processing calls must match the new number of channels.
+    /// This call may allocate.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new number of channels is zero.
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()>;
+
+    /// Get the current resample ratio, defined as output sample rate divided by input sample rate.
+    fn resample_ratio(&self) -> f64;
+
+    /// Get the total number of input frames consumed since the resampler was created or reset.
+    /// Calling [reset](Resampler::reset) sets the counter to zero.
+    fn input_frames_total(&self) -> u64;
+
+    /// Get the total number of output frames produced since the resampler was created or reset.
+    /// Calling [reset](Resampler::reset) sets the counter to zero.
+    fn output_frames_total(&self) -> u64;
+
+    /// Store a mask that selects which channels to process.
+    ///
+    /// The stored mask is used by every processing call that doesn't provide its own
+    /// `active_channels_mask`, and by [process](Resampler::process), where empty input
+    /// channels are skipped as well. Passing `None` makes all channels active.
+    /// The mask is kept when the resampler is [reset](Resampler::reset).
+    ///
+    /// The history of a channel is not updated while it is inactive.
+    /// When an inactive channel becomes active again, its history is cleared,
+    /// so that old samples don't leak into the output.
+    /// Neither this method nor the following processing calls allocate when the mask changes.
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()>;
+
+    /// Get the stored active channels mask,
+    /// see [set_active_channels_mask](Resampler::set_active_channels_mask).
+    fn active_channels_mask(&self) -> &[bool];
+
+    /// Select what the processing calls that write to an output buffer do with
+    /// the output of inactive channels.
+    /// The default, [InactiveOutput::Skip], leaves the output buffer untouched.
+    /// With [InactiveOutput::Zero], the frames that would have been written are set to zero.
+    /// Inactive channels are skipped by the resampling in both cases.
+    /// This has no effect on [process](Resampler::process),
+    /// which returns empty vectors for inactive channels.
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput);
+
+    /// Enable or disable checking the input for NaN and infinite values.
+    ///
+    /// A single non-finite input value spreads through the filter history,
+    /// and spoils the output of the channel for a full filter length after it.
+    /// With validation enabled, every processing call scans the active input channels
+    /// before using them, and returns [ResampleError::InvalidInput] with the position
+    /// of the first bad value, before the history or the position in the stream is updated.
+    /// Validation is disabled by default, and then costs nothing.
+    ///
+    /// A resampler that has already processed non-finite values recovers fully
+    /// after [reset](Resampler::reset), or [reset_channel](Resampler::reset_channel)
+    /// for the affected channel.
+    fn set_validate_input(&mut self, enabled: bool);
+
+    /// Enable or disable flushing of denormal numbers to zero while processing.
+    ///
+    /// When a signal decays towards silence, the values in the filter history eventually
+    /// become denormal numbers, and calculations with these can be many times slower
+    /// than with normal numbers on some CPUs.
+    /// With flushing enabled, each processing call sets the flush-to-zero and
+    /// denormals-are-zero flags of the calling thread, and restores the previous flags
+    /// before returning. Since the flags are per thread, callbacks or other code running
+    /// on the same thread during the processing call are not affected.
+    /// This is only implemented for x86_64, and does nothing on other architectures.
+    /// Disabled by default.
+    fn set_flush_denormals(&mut self, enabled: bool);
+
+    /// Enable or disable trimming of the output delay.
+    ///
+    /// With trimming enabled, the resampler discards the first
+    /// [output_delay](Resampler::output_delay) frames of each stream, so that the output
+    /// is aligned with the input. The first processing calls consume input as usual,
+    /// but return fewer output frames, possibly none, until the delay has been discarded.
+    /// The delay reported by [output_delay_fractional](Resampler::output_delay_fractional)
+    /// and the frame counts given by [output_frames_for_input](Resampler::output_frames_for_input)
+    /// and [output_frames_total](Resampler::output_frames_total) no longer include
+    /// the discarded frames.
+    ///
+    /// The number of frames to discard is set at the start of each stream.
+    /// A change made after processing has started takes effect at the next
+    /// [reset](Resampler::reset). Disabled by default.
+    fn set_trim_delay(&mut self, enabled: bool);
+
+    /// Get the output frame, as a fractional value, where a feature at input frame `n` appears.
+    /// The output delay is included, and the current resample ratio is assumed for the whole stream.
+    fn output_time_for_input_frame(&self, n: u64) -> f64 {
+        n as f64 * self.resample_ratio() + self.output_delay_fractional()
+    }
+
+    /// Get the total number of output frames that a stream of `total_input_frames` frames gives,
+    /// when it is processed in chunks and ended with
+    /// [process_final_into_buffer](Resampler::process_final_into_buffer).
+    /// This includes the initial [output_delay](Resampler::output_delay) frames,
+    /// and assumes that the current resample ratio is used for the whole stream.
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64 {
+        self.output_delay() as u64
+            + (total_input_frames as f64 * self.resample_ratio()).ceil() as u64
+    }
+
+    /// Get the smallest number of input frames for a stream that gives at least
+    /// `total_output_frames` frames, see
+    /// [output_frames_for_input](Resampler::output_frames_for_input).
+    fn input_frames_for_output(&self, total_output_frames: u64) -> u64 {
+        let delay = self.output_delay() as u64;
+        if total_output_frames <= delay {
+            return 0;
+        }
+        let estimate = ((total_output_frames - delay) as f64 / self.resample_ratio()).ceil();
+        let mut frames = estimate as u64;
+        // The estimate can be off by a frame because of rounding.
+        while self.output_frames_for_input(frames) < total_output_frames {
+            frames += 1;
+        }
+        while frames > 0 && self.output_frames_for_input(frames - 1) >= total_output_frames {
+            frames -= 1;
+        }
+        frames
+    }
+
     /// Update the resample ratio.
     fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()>;
 
     /// Update the resample ratio relative to the original one.
     fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()>;
+
+    /// Get the maximum relative resample ratio.
+    /// The resample ratio can be adjusted from `1 / max` to `max` times the original ratio.
+    /// This is 1.1 for a new sinc resampler, and 1.0 for the resamplers that
+    /// can't change their ratio.
+    fn max_resample_ratio_relative(&self) -> f64;
+
+    /// Update the maximum relative resample ratio, see
+    /// [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    /// The resample ratio and the filter history are kept.
+    ///
+    /// Allowing lower ratios increases the number of input frames that a resampler
+    /// with fixed output may need, and its internal buffers are then reallocated.
+    /// This call may therefore allocate.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new value is smaller than 1.0,
+    /// if the new range doesn't include the ratios in use, including those of an ongoing ramp
+    /// or a scheduled change, or if the resampler can't change its ratio.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()>;
+
+    /// Update the resample ratio from a pair of input and output sample rates.
+    /// The new ratio is `fs_out / fs_in`, and must be within the same bounds as for
+    /// [set_resample_ratio](Resampler::set_resample_ratio).
+    /// If `ramp` is false, a resampler that supports ramping switches to the new ratio immediately.
+    /// The default implementation ignores `ramp`.
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()> {
+        let _ = ramp;
+        self.set_resample_ratio(fs_out / fs_in)
+    }
+
+    /// Check if the resample ratio is ramping towards a new value.
+    /// This reflects the state after the most recent call to "process",
+    /// and the ramp is finished when the last output frame produced used the new ratio.
+    /// A change scheduled for a later frame doesn't count as ramping until it starts.
+    fn is_ramping(&self) -> bool {
+        self.ramp_progress().is_some()
+    }
+
+    /// Get the progress of an ongoing ramp of the resample ratio,
+    /// as the fraction of the ramp duration that has been produced, from 0.0 up to 1.0.
+    /// Returns `None` when the ratio isn't ramping, see [is_ramping](Resampler::is_ramping).
+    /// The default implementation returns `None`, for resamplers that don't ramp.
+    fn ramp_progress(&self) -> Option<f64> {
+        None
+    }
+
+    /// Get the delay of the resampler, in output frames, rounded to the nearest whole frame.
+    /// See [output_delay_fractional](Resampler::output_delay_fractional) for details.
+    fn output_delay(&self) -> usize {
+        let delay = self.output_delay_fractional().round();
+        if delay > 0.0 {
+            delay as usize
+        } else {
+            0
+        }
+    }
+
+    /// Get the delay of the resampler, in output frames.
+    ///
+    /// This is the number of output frames that the resampled signal lags behind
+    /// the input signal, meaning that a feature at input frame `n` appears at output frame
+    /// `n * ratio + output_delay_fractional()`. The value reflects the current resample ratio.
+    ///
+    /// The FFT resamplers delay the signal by about half an FFT block.
+    /// The sinc resamplers instead keep back the input frames they can't yet process,
+    /// and their output is aligned with the input to within one frame.
+    /// The remaining offset can be slightly negative when downsampling.
+    fn output_delay_fractional(&self) -> f64;
+
+    /// Get the magnitude response of the antialiasing filter, at `n_points` evenly spaced
+    /// frequencies from zero up to the higher of the input and output Nyquist frequencies.
+    ///
+    /// Each point is a pair of a frequency and a gain in dB. The frequencies are given
+    /// relative to the lower of the two Nyquist frequencies, like
+    /// [f_cutoff](InterpolationParameters::f_cutoff), and the gain is relative to the gain at
+    /// zero frequency. Frequencies that the resampler removes entirely,
+    /// like those above the lower Nyquist frequency for the FFT resamplers,
+    /// give a gain of negative infinity.
+    ///
+    /// The response is calculated from the filter taps each time, and this call allocates.
+    /// It is meant for inspecting the filters, not for use during processing.
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)>;
+
+    /// Get the delay of the antialiasing filter at low frequencies, in input frames.
+    ///
+    /// This is about half the filter length for linear phase filters,
+    /// and shorter for minimum phase filters.
+    /// The FFT resamplers include this delay in the output, see
+    /// [output_delay_fractional](Resampler::output_delay_fractional).
+    /// The sinc resamplers instead compensate for it, by waiting for this many input frames
+    /// before producing the output for a given input frame, and it then shows up as latency.
+    fn group_delay(&self) -> f64;
 }
 
 /// This is a helper trait that can be used when a [Resampler] must be object safe.
@@ -221,19 +1080,182 @@ pub trait Resampler<T>: Send {
 /// let boxed: Box<dyn VecResampler<f64>> = Box::new(FftFixedIn::<f64>::new(44100, 88200, 1024, 2, 2));
 /// ```
 /// Use this implementation as an example if you need to fix the input type to something else.
-pub trait VecResampler<T>: Send {
+pub trait VecResampler<T>: Send + Sync {
     /// Resample a chunk of audio.
     /// Input and output data is stored in vectors, where each element contains a vector with all samples for a single channel.
     fn process(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>>;
 
+    /// Resample a chunk of interleaved audio, and write the result to an interleaved output buffer.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers.
+    fn process_into_buffer(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers, and return a [ProcessResult].
+    fn process_into_buffer_ext(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<ProcessResult>;
+
+    /// Resample a chunk of audio from sequential buffers, starting at the given frame offsets.
+    fn process_into_buffer_at(
+        &mut self,
+        wave_in: &[Vec<T>],
+        input_offset: usize,
+        wave_out: &mut [Vec<T>],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers, and append the result to the output buffers.
+    fn process_append_into_buffer(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [Vec<T>],
+        output_position: &mut usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers, and write the result to an interleaved output buffer.
+    fn process_into_interleaved_buffer(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample the last frames of a stream, padding the input with zeros.
+    fn process_partial_into_buffer(
+        &mut self,
+        wave_in: Option<&[Vec<T>]>,
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    fn process_final_into_buffer(
+        &mut self,
+        wave_in: Option<&[Vec<T>]>,
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
     /// Query for the number of frames needed for the next call to "process".
     fn nbr_frames_needed(&self) -> usize;
 
+    /// Get the maximum number of input frames that a single call to "process" can need.
+    fn input_frames_max(&self) -> usize;
+
+    /// Get the maximum number of output frames that a single call to "process" can produce.
+    fn output_frames_max(&self) -> usize;
+
+    /// Allocate a buffer for the input of the processing calls.
+    fn input_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample;
+
+    /// Allocate a buffer for the output of the processing calls.
+    fn output_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample;
+
+    /// Resample a complete waveform in one go.
+    fn process_all(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample;
+
+    /// Reset the resampler state and clear all internal buffers.
+    fn reset(&mut self);
+
+    /// Clear the history of a single channel.
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()>;
+
+    /// Get the number of channels.
+    fn nbr_channels(&self) -> usize;
+
+    /// Change the number of channels.
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()>;
+
+    /// Get the current resample ratio.
+    fn resample_ratio(&self) -> f64;
+
+    /// Get the total number of input frames consumed since the resampler was created or reset.
+    fn input_frames_total(&self) -> u64;
+
+    /// Get the total number of output frames produced since the resampler was created or reset.
+    fn output_frames_total(&self) -> u64;
+
+    /// Store a mask that selects which channels to process.
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()>;
+
+    /// Get the stored active channels mask.
+    fn active_channels_mask(&self) -> &[bool];
+
+    /// Select what to write to the output of inactive channels.
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput);
+
+    /// Enable or disable checking the input for NaN and infinite values.
+    fn set_validate_input(&mut self, enabled: bool);
+
+    /// Enable or disable flushing of denormal numbers to zero while processing.
+    fn set_flush_denormals(&mut self, enabled: bool);
+
+    /// Enable or disable trimming of the output delay.
+    fn set_trim_delay(&mut self, enabled: bool);
+
+    /// Get the output frame where a feature at input frame `n` appears.
+    fn output_time_for_input_frame(&self, n: u64) -> f64;
+
+    /// Get the total number of output frames for a stream of `total_input_frames` frames.
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64;
+
+    /// Get the smallest number of input frames that gives at least `total_output_frames` frames.
+    fn input_frames_for_output(&self, total_output_frames: u64) -> u64;
+
     /// Update the resample ratio.
     fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()>;
 
     /// Update the resample ratio relative to the original one.
     fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()>;
+
+    /// Get the maximum relative resample ratio.
+    fn max_resample_ratio_relative(&self) -> f64;
+
+    /// Update the maximum relative resample ratio.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()>;
+
+    /// Update the resample ratio from a pair of input and output sample rates.
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()>;
+
+    /// Check if the resample ratio is ramping towards a new value.
+    fn is_ramping(&self) -> bool;
+
+    /// Get the progress of an ongoing ramp of the resample ratio.
+    fn ramp_progress(&self) -> Option<f64>;
+
+    /// Get the delay of the resampler, in output frames, rounded to the nearest whole frame.
+    fn output_delay(&self) -> usize;
+
+    /// Get the delay of the resampler, in output frames.
+    fn output_delay_fractional(&self) -> f64;
+
+    /// Get the magnitude response of the antialiasing filter.
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)>;
+
+    /// Get the delay of the antialiasing filter at low frequencies, in input frames.
+    fn group_delay(&self) -> f64;
 }
 
 impl<T, U> VecResampler<T> for U
@@ -244,55 +1266,2185 @@ where
         Resampler::process(self, wave_in)
     }
 
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_interleaved_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_into_buffer(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_into_buffer_ext(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<ProcessResult> {
+        Resampler::process_into_buffer_ext(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_into_buffer_at(
+        &mut self,
+        wave_in: &[Vec<T>],
+        input_offset: usize,
+        wave_out: &mut [Vec<T>],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_into_buffer_at(
+            self,
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            active_channels_mask,
+        )
+    }
+
+    fn process_append_into_buffer(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [Vec<T>],
+        output_position: &mut usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_append_into_buffer(
+            self,
+            wave_in,
+            wave_out,
+            output_position,
+            active_channels_mask,
+        )
+    }
+
+    fn process_into_interleaved_buffer(
+        &mut self,
+        wave_in: &[Vec<T>],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_into_interleaved_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_partial_into_buffer(
+        &mut self,
+        wave_in: Option<&[Vec<T>]>,
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_partial_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_final_into_buffer(
+        &mut self,
+        wave_in: Option<&[Vec<T>]>,
+        wave_out: &mut [Vec<T>],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_final_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
     fn nbr_frames_needed(&self) -> usize {
         Resampler::nbr_frames_needed(self)
     }
 
-    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
-        Resampler::set_resample_ratio(self, new_ratio)
+    fn input_frames_max(&self) -> usize {
+        Resampler::input_frames_max(self)
     }
 
-    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
-        Resampler::set_resample_ratio_relative(self, rel_ratio)
+    fn output_frames_max(&self) -> usize {
+        Resampler::output_frames_max(self)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::VecResampler;
-    use crate::{FftFixedIn, FftFixedInOut, FftFixedOut};
-    use crate::{SincFixedIn, SincFixedOut};
+    fn input_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample,
+    {
+        Resampler::input_buffer_allocate(self, filled)
+    }
 
-    // This tests that a VecResampler can be boxed.
-    #[test]
-    fn boxed_resampler() {
-        let boxed: Box<dyn VecResampler<f64>> =
-            Box::new(FftFixedIn::<f64>::new(44100, 88200, 1024, 2, 2));
-        let result = process_with_boxed(boxed);
-        assert_eq!(result.len(), 2);
-        assert_eq!(result[0].len(), 2048);
-        assert_eq!(result[1].len(), 2048);
+    fn output_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample,
+    {
+        Resampler::output_buffer_allocate(self, filled)
     }
 
-    fn process_with_boxed(mut resampler: Box<dyn VecResampler<f64>>) -> Vec<Vec<f64>> {
-        let frames = resampler.nbr_frames_needed();
-        let waves = vec![vec![0.0f64; frames]; 2];
-        resampler.process(&waves).unwrap()
+    fn process_all(&mut self, wave_in: &[Vec<T>]) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample,
+    {
+        Resampler::process_all(self, wave_in)
     }
 
-    fn impl_send<T: Send>() {
-        fn is_send<T: Send>() {}
-        is_send::<SincFixedOut<T>>();
-        is_send::<SincFixedIn<T>>();
-        is_send::<FftFixedOut<T>>();
-        is_send::<FftFixedIn<T>>();
-        is_send::<FftFixedInOut<T>>();
+    fn reset(&mut self) {
+        Resampler::reset(self)
     }
 
-    // This tests that all resamplers are Send.
-    #[test]
-    fn test_impl_send() {
-        impl_send::<f32>();
-        impl_send::<f64>();
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        Resampler::reset_channel(self, channel)
+    }
+
+    fn nbr_channels(&self) -> usize {
+        Resampler::nbr_channels(self)
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        Resampler::set_nbr_channels(self, nbr_channels)
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        Resampler::resample_ratio(self)
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        Resampler::input_frames_total(self)
+    }
+
+    fn output_frames_total(&self) -> u64 {
+        Resampler::output_frames_total(self)
+    }
+
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        Resampler::set_active_channels_mask(self, mask)
+    }
+
+    fn active_channels_mask(&self) -> &[bool] {
+        Resampler::active_channels_mask(self)
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        Resampler::set_inactive_channel_output(self, inactive_output)
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        Resampler::set_validate_input(self, enabled)
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        Resampler::set_flush_denormals(self, enabled)
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        Resampler::set_trim_delay(self, enabled)
+    }
+
+    fn output_time_for_input_frame(&self, n: u64) -> f64 {
+        Resampler::output_time_for_input_frame(self, n)
+    }
+
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64 {
+        Resampler::output_frames_for_input(self, total_input_frames)
+    }
+
+    fn input_frames_for_output(&self, total_output_frames: u64) -> u64 {
+        Resampler::input_frames_for_output(self, total_output_frames)
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        Resampler::set_resample_ratio(self, new_ratio)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        Resampler::set_resample_ratio_relative(self, rel_ratio)
+    }
+
+    fn max_resample_ratio_relative(&self) -> f64 {
+        Resampler::max_resample_ratio_relative(self)
+    }
+
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()> {
+        Resampler::set_max_resample_ratio_relative(self, max_relative)
+    }
+
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()> {
+        Resampler::set_sample_rates(self, fs_in, fs_out, ramp)
+    }
+
+    fn is_ramping(&self) -> bool {
+        Resampler::is_ramping(self)
+    }
+
+    fn ramp_progress(&self) -> Option<f64> {
+        Resampler::ramp_progress(self)
+    }
+
+    fn output_delay(&self) -> usize {
+        Resampler::output_delay(self)
+    }
+
+    fn output_delay_fractional(&self) -> f64 {
+        Resampler::output_delay_fractional(self)
+    }
+
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        Resampler::frequency_response(self, n_points)
+    }
+
+    fn group_delay(&self) -> f64 {
+        Resampler::group_delay(self)
+    }
+}
+
+/// A helper trait like [VecResampler], for when the audio data is only available as
+/// one slice per channel.
+///
+/// The input is fixed to `&[&[T]]`, and the output buffers to `&mut [&mut [T]]`.
+/// This allows it to be made into a trait object like this:
+/// ```
+/// # use rubato::{FftFixedIn, SliceResampler};
+/// let mut boxed: Box<dyn SliceResampler<f32>> = Box::new(FftFixedIn::<f32>::new(44100, 88200, 1024, 2, 2));
+/// let left = vec![0.0; 1024];
+/// let right = vec![0.0; 1024];
+/// let mut out_left = vec![0.0; boxed.output_frames_max()];
+/// let mut out_right = vec![0.0; boxed.output_frames_max()];
+/// boxed
+///     .process_into_buffer(&[&left, &right], &mut [&mut out_left, &mut out_right], None)
+///     .unwrap();
+/// ```
+pub trait SliceResampler<T>: Send + Sync {
+    /// Resample a chunk of audio.
+    /// The input is a slice of slices, one per channel. The output is stored in vectors,
+    /// where each element contains a vector with all samples for a single channel.
+    fn process(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>>;
+
+    /// Resample a chunk of interleaved audio, and write the result to an interleaved output buffer.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers.
+    fn process_into_buffer(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers, and return a [ProcessResult].
+    fn process_into_buffer_ext(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<ProcessResult>;
+
+    /// Resample a chunk of audio from sequential buffers, starting at the given frame offsets.
+    fn process_into_buffer_at(
+        &mut self,
+        wave_in: &[&[T]],
+        input_offset: usize,
+        wave_out: &mut [&mut [T]],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers, and append the result to the output buffers.
+    fn process_append_into_buffer(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [&mut [T]],
+        output_position: &mut usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample a chunk of audio from sequential buffers, and write the result to an interleaved output buffer.
+    fn process_into_interleaved_buffer(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample the last frames of a stream, padding the input with zeros.
+    fn process_partial_into_buffer(
+        &mut self,
+        wave_in: Option<&[&[T]]>,
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    fn process_final_into_buffer(
+        &mut self,
+        wave_in: Option<&[&[T]]>,
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)>;
+
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize;
+
+    /// Get the maximum number of input frames that a single call to "process" can need.
+    fn input_frames_max(&self) -> usize;
+
+    /// Get the maximum number of output frames that a single call to "process" can produce.
+    fn output_frames_max(&self) -> usize;
+
+    /// Allocate a buffer for the input of the processing calls.
+    fn input_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample;
+
+    /// Allocate a buffer for the output of the processing calls.
+    fn output_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample;
+
+    /// Resample a complete waveform in one go.
+    fn process_all(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample;
+
+    /// Reset the resampler state and clear all internal buffers.
+    fn reset(&mut self);
+
+    /// Clear the history of a single channel.
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()>;
+
+    /// Get the number of channels.
+    fn nbr_channels(&self) -> usize;
+
+    /// Change the number of channels.
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()>;
+
+    /// Get the current resample ratio.
+    fn resample_ratio(&self) -> f64;
+
+    /// Get the total number of input frames consumed since the resampler was created or reset.
+    fn input_frames_total(&self) -> u64;
+
+    /// Get the total number of output frames produced since the resampler was created or reset.
+    fn output_frames_total(&self) -> u64;
+
+    /// Store a mask that selects which channels to process.
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()>;
+
+    /// Get the stored active channels mask.
+    fn active_channels_mask(&self) -> &[bool];
+
+    /// Select what to write to the output of inactive channels.
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput);
+
+    /// Enable or disable checking the input for NaN and infinite values.
+    fn set_validate_input(&mut self, enabled: bool);
+
+    /// Enable or disable flushing of denormal numbers to zero while processing.
+    fn set_flush_denormals(&mut self, enabled: bool);
+
+    /// Enable or disable trimming of the output delay.
+    fn set_trim_delay(&mut self, enabled: bool);
+
+    /// Get the output frame where a feature at input frame `n` appears.
+    fn output_time_for_input_frame(&self, n: u64) -> f64;
+
+    /// Get the total number of output frames for a stream of `total_input_frames` frames.
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64;
+
+    /// Get the smallest number of input frames that gives at least `total_output_frames` frames.
+    fn input_frames_for_output(&self, total_output_frames: u64) -> u64;
+
+    /// Update the resample ratio.
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()>;
+
+    /// Update the resample ratio relative to the original one.
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()>;
+
+    /// Get the maximum relative resample ratio.
+    fn max_resample_ratio_relative(&self) -> f64;
+
+    /// Update the maximum relative resample ratio.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()>;
+
+    /// Update the resample ratio from a pair of input and output sample rates.
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()>;
+
+    /// Check if the resample ratio is ramping towards a new value.
+    fn is_ramping(&self) -> bool;
+
+    /// Get the progress of an ongoing ramp of the resample ratio.
+    fn ramp_progress(&self) -> Option<f64>;
+
+    /// Get the delay of the resampler, in output frames, rounded to the nearest whole frame.
+    fn output_delay(&self) -> usize;
+
+    /// Get the delay of the resampler, in output frames.
+    fn output_delay_fractional(&self) -> f64;
+
+    /// Get the magnitude response of the antialiasing filter.
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)>;
+
+    /// Get the delay of the antialiasing filter at low frequencies, in input frames.
+    fn group_delay(&self) -> f64;
+}
+
+impl<T, U> SliceResampler<T> for U
+where
+    U: Resampler<T>,
+{
+    fn process(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>> {
+        Resampler::process(self, wave_in)
+    }
+
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_interleaved_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_into_buffer(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_into_buffer_ext(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<ProcessResult> {
+        Resampler::process_into_buffer_ext(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_into_buffer_at(
+        &mut self,
+        wave_in: &[&[T]],
+        input_offset: usize,
+        wave_out: &mut [&mut [T]],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_into_buffer_at(
+            self,
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            active_channels_mask,
+        )
+    }
+
+    fn process_append_into_buffer(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [&mut [T]],
+        output_position: &mut usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_append_into_buffer(
+            self,
+            wave_in,
+            wave_out,
+            output_position,
+            active_channels_mask,
+        )
+    }
+
+    fn process_into_interleaved_buffer(
+        &mut self,
+        wave_in: &[&[T]],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_into_interleaved_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_partial_into_buffer(
+        &mut self,
+        wave_in: Option<&[&[T]]>,
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_partial_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn process_final_into_buffer(
+        &mut self,
+        wave_in: Option<&[&[T]]>,
+        wave_out: &mut [&mut [T]],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        Resampler::process_final_into_buffer(self, wave_in, wave_out, active_channels_mask)
+    }
+
+    fn nbr_frames_needed(&self) -> usize {
+        Resampler::nbr_frames_needed(self)
+    }
+
+    fn input_frames_max(&self) -> usize {
+        Resampler::input_frames_max(self)
+    }
+
+    fn output_frames_max(&self) -> usize {
+        Resampler::output_frames_max(self)
+    }
+
+    fn input_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample,
+    {
+        Resampler::input_buffer_allocate(self, filled)
+    }
+
+    fn output_buffer_allocate(&self, filled: bool) -> Vec<Vec<T>>
+    where
+        T: Sample,
+    {
+        Resampler::output_buffer_allocate(self, filled)
+    }
+
+    fn process_all(&mut self, wave_in: &[&[T]]) -> ResampleResult<Vec<Vec<T>>>
+    where
+        T: Sample,
+    {
+        Resampler::process_all(self, wave_in)
+    }
+
+    fn reset(&mut self) {
+        Resampler::reset(self)
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        Resampler::reset_channel(self, channel)
+    }
+
+    fn nbr_channels(&self) -> usize {
+        Resampler::nbr_channels(self)
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        Resampler::set_nbr_channels(self, nbr_channels)
+    }
+
+    fn resample_ratio(&self) -> f64 {
+        Resampler::resample_ratio(self)
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        Resampler::input_frames_total(self)
+    }
+
+    fn output_frames_total(&self) -> u64 {
+        Resampler::output_frames_total(self)
+    }
+
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        Resampler::set_active_channels_mask(self, mask)
+    }
+
+    fn active_channels_mask(&self) -> &[bool] {
+        Resampler::active_channels_mask(self)
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        Resampler::set_inactive_channel_output(self, inactive_output)
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        Resampler::set_validate_input(self, enabled)
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        Resampler::set_flush_denormals(self, enabled)
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        Resampler::set_trim_delay(self, enabled)
+    }
+
+    fn output_time_for_input_frame(&self, n: u64) -> f64 {
+        Resampler::output_time_for_input_frame(self, n)
+    }
+
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64 {
+        Resampler::output_frames_for_input(self, total_input_frames)
+    }
+
+    fn input_frames_for_output(&self, total_output_frames: u64) -> u64 {
+        Resampler::input_frames_for_output(self, total_output_frames)
+    }
+
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        Resampler::set_resample_ratio(self, new_ratio)
+    }
+
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        Resampler::set_resample_ratio_relative(self, rel_ratio)
+    }
+
+    fn max_resample_ratio_relative(&self) -> f64 {
+        Resampler::max_resample_ratio_relative(self)
+    }
+
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()> {
+        Resampler::set_max_resample_ratio_relative(self, max_relative)
+    }
+
+    fn set_sample_rates(&mut self, fs_in: f64, fs_out: f64, ramp: bool) -> ResampleResult<()> {
+        Resampler::set_sample_rates(self, fs_in, fs_out, ramp)
+    }
+
+    fn is_ramping(&self) -> bool {
+        Resampler::is_ramping(self)
+    }
+
+    fn ramp_progress(&self) -> Option<f64> {
+        Resampler::ramp_progress(self)
+    }
+
+    fn output_delay(&self) -> usize {
+        Resampler::output_delay(self)
+    }
+
+    fn output_delay_fractional(&self) -> f64 {
+        Resampler::output_delay_fractional(self)
+    }
+
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        Resampler::frequency_response(self, n_points)
+    }
+
+    fn group_delay(&self) -> f64 {
+        Resampler::group_delay(self)
+    }
+}
+
+/// Allocate a buffer of `nbr_channels` vectors with room for `frames` frames,
+/// and fill them with zeros if requested.
+fn allocate_buffer<T: Sample>(nbr_channels: usize, frames: usize, filled: bool) -> Vec<Vec<T>> {
+    let mut buffer = Vec::with_capacity(nbr_channels);
+    for _ in 0..nbr_channels {
+        if filled {
+            buffer.push(vec![T::zero(); frames]);
+        } else {
+            buffer.push(Vec::with_capacity(frames));
+        }
+    }
+    buffer
+}
+
+/// Shared implementation of [Resampler::process_final_into_buffer].
+///
+/// `output_frames_final` is the value of [Resampler::output_frames_total] where the
+/// stream ends. It is `None` until the first call, which sets it from the total input length.
+pub(crate) fn process_final<T, R, V, U>(
+    resampler: &mut R,
+    output_frames_final: &mut Option<u64>,
+    wave_in: Option<&[V]>,
+    wave_out: &mut [U],
+    active_channels_mask: Option<&[bool]>,
+) -> ResampleResult<(usize, usize)>
+where
+    R: Resampler<T>,
+    V: AsRef<[T]>,
+    U: AsMut<[T]>,
+{
+    let frames_before = resampler.output_frames_total();
+    if let Some(frames_final) = *output_frames_final {
+        if frames_before >= frames_final {
+            return Ok((0, 0));
+        }
+        let (_, frames_out) =
+            resampler.process_partial_into_buffer(None::<&[V]>, wave_out, active_channels_mask)?;
+        let frames_valid = frames_out.min((frames_final - frames_before) as usize);
+        return Ok((0, frames_valid));
+    }
+    let input_before = resampler.input_frames_total();
+    let (frames_in, frames_out) =
+        resampler.process_partial_into_buffer(wave_in, wave_out, active_channels_mask)?;
+    let input_total = input_before + frames_in as u64;
+    let frames_final = resampler.output_frames_for_input(input_total);
+    *output_frames_final = Some(frames_final);
+    let frames_valid = frames_out.min(frames_final.saturating_sub(frames_before) as usize);
+    Ok((frames_in, frames_valid))
+}
+#[cfg(test)]
+mod tests {
+    use crate::WindowFunction;
+    use crate::{
+        Accumulation, FilterPhase, InterpolationParameters, InterpolationType, SincStorage,
+    };
+    use crate::{FftFixedIn, FftFixedInOut, FftFixedOut, HybridFixedIn};
+    use crate::{ProcessResult, ResampleError, Resampler};
+    use crate::{SincFixedBoth, SincFixedIn, SincFixedOut, TwoStageSincFixedIn};
+    use rand::Rng;
+
+    // This tests that a VecResampler can be boxed.
+    #[test]
+    fn boxed_resampler() {
+        let boxed: Box<dyn crate::VecResampler<f64>> =
+            Box::new(FftFixedIn::<f64>::new(44100, 88200, 1024, 2, 2));
+        let result = process_with_boxed(boxed);
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].len(), 2048);
+        assert_eq!(result[1].len(), 2048);
+    }
+
+    fn process_with_boxed(mut resampler: Box<dyn crate::VecResampler<f64>>) -> Vec<Vec<f64>> {
+        let frames = resampler.nbr_frames_needed();
+        let waves = vec![vec![0.0f64; frames]; 2];
+        resampler.process(&waves).unwrap()
+    }
+
+    // This tests that each resampler can be boxed as a SliceResampler.
+    #[test]
+    fn boxed_slice_resampler() {
+        let resamplers: Vec<Box<dyn crate::SliceResampler<f32>>> = vec![
+            Box::new(SincFixedIn::<f32>::new(1.2, sinc_parameters(), 256, 2)),
+            Box::new(SincFixedOut::<f32>::new(0.8, sinc_parameters(), 256, 2)),
+            Box::new(FftFixedInOut::<f32>::new(44100, 48000, 512, 2)),
+            Box::new(FftFixedIn::<f32>::new(44100, 48000, 512, 2, 2)),
+            Box::new(FftFixedOut::<f32>::new(48000, 44100, 512, 2, 2)),
+            Box::new(TwoStageSincFixedIn::<f32>::new(
+                6.0,
+                sinc_parameters(),
+                256,
+                2,
+            )),
+            Box::new(TwoStageSincFixedIn::<f32>::new(
+                1.0 / 6.0,
+                sinc_parameters(),
+                256,
+                2,
+            )),
+            Box::new(
+                SincFixedBoth::<f32>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+            ),
+            Box::new(HybridFixedIn::<f32>::new(
+                48037.0 / 44100.0,
+                sinc_parameters(),
+                256,
+                2,
+            )),
+        ];
+        for mut resampler in resamplers {
+            let frames = resampler.nbr_frames_needed();
+            let left = vec![0.0f32; frames + 10];
+            let right = vec![0.0f32; frames + 10];
+            let mut out_left = vec![0.0f32; resampler.output_frames_max()];
+            let mut out_right = vec![0.0f32; resampler.output_frames_max()];
+            let waves_in: Vec<&[f32]> = vec![&left[10..], &right[10..]];
+            let (frames_in, frames_out) = resampler
+                .process_into_buffer(&waves_in, &mut [&mut out_left, &mut out_right], None)
+                .unwrap();
+            assert_eq!(frames_in, frames);
+            assert_eq!(resampler.output_frames_total(), frames_out as u64);
+            let frames = resampler.nbr_frames_needed();
+            let result = resampler.process(&[&left[..frames], &[]]).unwrap();
+            assert!(result[1].is_empty());
+            resampler.reset();
+            assert_eq!(resampler.input_frames_total(), 0);
+        }
+    }
+
+    fn impl_send<T: Send>() {
+        fn is_send<T: Send>() {}
+        is_send::<SincFixedOut<T>>();
+        is_send::<SincFixedIn<T>>();
+        is_send::<FftFixedOut<T>>();
+        is_send::<FftFixedIn<T>>();
+        is_send::<FftFixedInOut<T>>();
+        is_send::<TwoStageSincFixedIn<T>>();
+        is_send::<SincFixedBoth<T>>();
+        is_send::<HybridFixedIn<T>>();
+    }
+
+    // This tests that all resamplers are Send.
+    #[test]
+    fn test_impl_send() {
+        impl_send::<f32>();
+        impl_send::<f64>();
+    }
+
+    fn impl_sync<T: Send + Sync>() {
+        fn is_sync<T: Sync>() {}
+        is_sync::<SincFixedOut<T>>();
+        is_sync::<SincFixedIn<T>>();
+        is_sync::<FftFixedOut<T>>();
+        is_sync::<FftFixedIn<T>>();
+        is_sync::<FftFixedInOut<T>>();
+        is_sync::<TwoStageSincFixedIn<T>>();
+        is_sync::<SincFixedBoth<T>>();
+        is_sync::<HybridFixedIn<T>>();
+    }
+
+    // This tests that all resamplers are Sync, so that they can be shared between threads.
+    #[test]
+    fn test_impl_sync() {
+        impl_sync::<f32>();
+        impl_sync::<f64>();
+    }
+
+    fn sinc_parameters() -> InterpolationParameters {
+        InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 32,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        }
+    }
+
+    // Process the same signal with "process", "process_interleaved_into_buffer"
+    // and "process_into_interleaved_buffer", and check that the results are identical.
+    // Channel 1 is skipped in the third chunk.
+    fn check_interleaved<R: Resampler<f64>>(mut sequential: R, mut interleaved: R, mut mixed: R) {
+        let mut phase: f64 = 0.0;
+        for chunk in 0..6 {
+            let frames = sequential.nbr_frames_needed();
+            assert_eq!(frames, interleaved.nbr_frames_needed());
+            let mut waves_in = vec![vec![0.0; frames]; 2];
+            let mut wave_in = vec![0.0; 2 * frames];
+            for n in 0..frames {
+                phase += 0.05;
+                waves_in[0][n] = phase.sin();
+                waves_in[1][n] = (0.7 * phase).cos();
+                wave_in[2 * n] = waves_in[0][n];
+                wave_in[2 * n + 1] = waves_in[1][n];
+            }
+            let skip = chunk == 2;
+            if skip {
+                waves_in[1] = Vec::new();
+            }
+            let mask = [true, !skip];
+            let waves_out = sequential.process(&waves_in).unwrap();
+
+            let mut wave_out = vec![10.0; 2 * interleaved.output_frames_max()];
+            let (frames_in, frames_out) = interleaved
+                .process_interleaved_into_buffer(&wave_in, &mut wave_out, Some(&mask))
+                .unwrap();
+            assert_eq!(frames_in, frames);
+            assert_eq!(frames_out, waves_out[0].len());
+            for n in 0..frames_out {
+                assert_eq!(wave_out[2 * n], waves_out[0][n]);
+                if skip {
+                    assert_eq!(wave_out[2 * n + 1], 10.0);
+                } else {
+                    assert_eq!(wave_out[2 * n + 1], waves_out[1][n]);
+                }
+            }
+
+            let mut mixed_out = vec![10.0; 2 * mixed.output_frames_max()];
+            let (frames_in, frames_out) = mixed
+                .process_into_interleaved_buffer(&waves_in, &mut mixed_out, Some(&mask))
+                .unwrap();
+            assert_eq!(frames_in, frames);
+            assert_eq!(frames_out, waves_out[0].len());
+            assert_eq!(mixed_out, wave_out);
+        }
+    }
+
+    #[test]
+    fn process_interleaved() {
+        check_interleaved(
+            SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2),
+            SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2),
+            SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2),
+        );
+        check_interleaved(
+            SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2),
+            SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2),
+            SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2),
+        );
+        check_interleaved(
+            FftFixedInOut::<f64>::new(44100, 48000, 512, 2),
+            FftFixedInOut::<f64>::new(44100, 48000, 512, 2),
+            FftFixedInOut::<f64>::new(44100, 48000, 512, 2),
+        );
+        check_interleaved(
+            FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2),
+            FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2),
+            FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2),
+        );
+        check_interleaved(
+            FftFixedOut::<f64>::new(48000, 44100, 500, 2, 2),
+            FftFixedOut::<f64>::new(48000, 44100, 500, 2, 2),
+            FftFixedOut::<f64>::new(48000, 44100, 500, 2, 2),
+        );
+        for ratio in [6.0, 1.0 / 6.0].iter() {
+            check_interleaved(
+                TwoStageSincFixedIn::<f64>::new(*ratio, sinc_parameters(), 256, 2),
+                TwoStageSincFixedIn::<f64>::new(*ratio, sinc_parameters(), 256, 2),
+                TwoStageSincFixedIn::<f64>::new(*ratio, sinc_parameters(), 256, 2),
+            );
+        }
+    }
+
+    #[test]
+    fn process_interleaved_errors() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2);
+        let frames_max = resampler.output_frames_max();
+        let wave_in = vec![0.0; 2 * 512];
+        let mut wave_out = vec![0.0; 2 * frames_max];
+        match resampler.process_interleaved_into_buffer(&wave_in[1..], &mut wave_out, None) {
+            Err(ResampleError::WrongInterleavedLength { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resampler.process_interleaved_into_buffer(&wave_in[2..], &mut wave_out, None) {
+            Err(ResampleError::WrongNumberOfFrames { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resampler.process_interleaved_into_buffer(&wave_in, &mut wave_out[2..], None) {
+            Err(ResampleError::InsufficientOutputBufferSize { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resampler.process_interleaved_into_buffer(&wave_in, &mut wave_out, Some(&[true])) {
+            Err(ResampleError::WrongNumberOfMaskChannels { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(resampler
+            .process_interleaved_into_buffer(&wave_in, &mut wave_out, None)
+            .is_ok());
+
+        let waves_in = vec![vec![0.0; 512], vec![0.0; 511]];
+        match resampler.process_into_interleaved_buffer(&waves_in, &mut wave_out, None) {
+            Err(ResampleError::InsufficientInputBufferSize { channel: 1, .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resampler.process_into_interleaved_buffer(
+            &waves_in,
+            &mut wave_out[2..],
+            Some(&[true, false]),
+        ) {
+            Err(ResampleError::InsufficientOutputBufferSize { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(resampler
+            .process_into_interleaved_buffer(&waves_in, &mut wave_out, Some(&[true, false]))
+            .is_ok());
+    }
+
+    // Step through long buffers with "process_into_buffer_at", and compare with "process".
+    fn check_process_at<R: Resampler<f64>>(mut chunked: R, mut offset: R) {
+        let wave: Vec<f64> = (0..20000).map(|n| (0.01 * n as f64).sin()).collect();
+        let waves_in = vec![wave.clone(), wave];
+        let mut waves_out = vec![vec![0.0; 30000]; 2];
+        let mut expected = vec![Vec::new(); 2];
+        let mut input_offset = 0;
+        let mut output_offset = 0;
+        for _ in 0..10 {
+            let frames = chunked.nbr_frames_needed();
+            let chunk: Vec<Vec<f64>> = waves_in
+                .iter()
+                .map(|chan| chan[input_offset..input_offset + frames].to_vec())
+                .collect();
+            let chunk_out = chunked.process(&chunk).unwrap();
+            for (exp, new) in expected.iter_mut().zip(chunk_out.iter()) {
+                exp.extend_from_slice(new);
+            }
+            let (frames_in, frames_out) = offset
+                .process_into_buffer_at(
+                    &waves_in,
+                    input_offset,
+                    &mut waves_out,
+                    output_offset,
+                    None,
+                )
+                .unwrap();
+            assert_eq!(frames_in, frames);
+            assert_eq!(frames_out, chunk_out[0].len());
+            input_offset += frames_in;
+            output_offset += frames_out;
+        }
+        for (out, exp) in waves_out.iter().zip(expected.iter()) {
+            assert_eq!(&out[..output_offset], &exp[..]);
+        }
+    }
+
+    #[test]
+    fn process_into_buffer_at() {
+        check_process_at(
+            SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2),
+            SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2),
+        );
+        check_process_at(
+            SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2),
+            SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2),
+        );
+        check_process_at(
+            FftFixedInOut::<f64>::new(44100, 48000, 512, 2),
+            FftFixedInOut::<f64>::new(44100, 48000, 512, 2),
+        );
+        check_process_at(
+            FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2),
+            FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2),
+        );
+        check_process_at(
+            FftFixedOut::<f64>::new(48000, 44100, 500, 2, 2),
+            FftFixedOut::<f64>::new(48000, 44100, 500, 2, 2),
+        );
+        for ratio in [6.0, 1.0 / 6.0].iter() {
+            check_process_at(
+                TwoStageSincFixedIn::<f64>::new(*ratio, sinc_parameters(), 256, 2),
+                TwoStageSincFixedIn::<f64>::new(*ratio, sinc_parameters(), 256, 2),
+            );
+        }
+    }
+
+    #[test]
+    fn process_into_buffer_at_errors() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2);
+        let frames_max = resampler.output_frames_max();
+        let waves_in = vec![vec![0.0; 600]; 2];
+        let mut waves_out = vec![vec![0.0; frames_max + 10]; 2];
+        match resampler.process_into_buffer_at(&waves_in, 100, &mut waves_out, 0, None) {
+            Err(ResampleError::InsufficientInputBufferSize {
+                channel: 0,
+                expected: 612,
+                actual: 600,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resampler.process_into_buffer_at(&waves_in, 88, &mut waves_out, 11, None) {
+            Err(ResampleError::InsufficientOutputBufferSize { expected, actual }) => {
+                assert_eq!(expected, frames_max + 11);
+                assert_eq!(actual, frames_max + 10);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resampler.process_into_buffer_at(&waves_in[..1], 0, &mut waves_out, 0, None) {
+            Err(ResampleError::WrongNumberOfChannels { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(resampler
+            .process_into_buffer_at(&waves_in, 88, &mut waves_out, 10, None)
+            .is_ok());
+
+        // inactive channels are not checked
+        let waves_in = vec![vec![0.0; 512], Vec::new()];
+        let mut waves_out = vec![vec![0.0; frames_max], Vec::new()];
+        assert!(resampler
+            .process_into_buffer(&waves_in, &mut waves_out, Some(&[true, false]))
+            .is_ok());
+    }
+
+    #[test]
+    fn process_into_buffer_ext() {
+        let mut resampler = SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2);
+        let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+        for _ in 0..5 {
+            let frames = resampler.nbr_frames_needed();
+            let waves_in = vec![vec![0.0; frames]; 2];
+            let result = resampler
+                .process_into_buffer_ext(&waves_in, &mut waves_out, None)
+                .unwrap();
+            assert_eq!(
+                result,
+                ProcessResult {
+                    input_frames_used: frames,
+                    output_frames_written: 256,
+                    input_frames_next: resampler.nbr_frames_needed(),
+                    output_frames_next: 256,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn process_append_into_buffer() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2);
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 500, 2, 2);
+        let frames_max = resampler.output_frames_max();
+        let mut waves_out = vec![vec![0.0; 3 * frames_max]; 2];
+        let mut expected = vec![Vec::new(); 2];
+        let mut position = 0;
+        for chunk in 0..3 {
+            let waves_in: Vec<Vec<f64>> = (0..2)
+                .map(|chan| {
+                    (0..500)
+                        .map(|n| (0.01 * (500 * chunk + n) as f64 + chan as f64).sin())
+                        .collect()
+                })
+                .collect();
+            for (exp, new) in expected
+                .iter_mut()
+                .zip(reference.process(&waves_in).unwrap().iter())
+            {
+                exp.extend_from_slice(new);
+            }
+            let before = position;
+            let (frames_in, frames_out) = resampler
+                .process_append_into_buffer(&waves_in, &mut waves_out, &mut position, None)
+                .unwrap();
+            assert_eq!(frames_in, 500);
+            assert_eq!(position, before + frames_out);
+        }
+        for (out, exp) in waves_out.iter().zip(expected.iter()) {
+            assert_eq!(&out[..position], &exp[..]);
+        }
+
+        // running out of space leaves the position unchanged
+        let waves_in = vec![vec![0.0; 500]; 2];
+        let before = position;
+        match resampler.process_append_into_buffer(&waves_in, &mut waves_out, &mut position, None) {
+            Err(ResampleError::InsufficientOutputBufferSize { expected, actual }) => {
+                assert_eq!(expected, position + frames_max);
+                assert_eq!(actual, 3 * frames_max);
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(position, before);
+    }
+
+    // Channels of different lengths are checked one by one,
+    // and the error tells which channel is too short.
+    #[test]
+    fn ragged_channels() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 3);
+        let frames_max = resampler.output_frames_max();
+        let waves_in = vec![vec![0.0; 512], vec![0.0; 300], vec![0.0; 700]];
+        let mut waves_out = vec![vec![0.0; frames_max]; 3];
+        match resampler.process_into_buffer(&waves_in, &mut waves_out, None) {
+            Err(ResampleError::InsufficientInputBufferSize {
+                channel: 1,
+                expected: 512,
+                actual: 300,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let (frames_in, _) = resampler
+            .process_into_buffer(&waves_in, &mut waves_out, Some(&[true, false, true]))
+            .unwrap();
+        assert_eq!(frames_in, 512);
+
+        let waves_in = [vec![0.0; 512], vec![0.0; 512], vec![0.0; 511]];
+        let waves_in: Vec<&[f64]> = waves_in.iter().map(|chan| &chan[..]).collect();
+        let mut waves_out = vec![vec![0.0; frames_max]; 3];
+        let mut waves_out: Vec<&mut [f64]> =
+            waves_out.iter_mut().map(|chan| &mut chan[..]).collect();
+        match crate::SliceResampler::process_into_buffer(
+            &mut resampler,
+            &waves_in,
+            &mut waves_out,
+            None,
+        ) {
+            Err(ResampleError::InsufficientInputBufferSize { channel: 2, .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut waves_out = vec![vec![0.0; frames_max], vec![0.0; 10], vec![0.0; frames_max]];
+        assert!(resampler
+            .process_into_buffer(&waves_in, &mut waves_out, Some(&[true, false, false]))
+            .is_ok());
+    }
+
+    // Process with a stored mask, and check that a reactivated channel starts from silence.
+    fn check_stored_mask<R: Resampler<f64>>(mut resampler: R) {
+        let nbr_frames_max = resampler.nbr_frames_needed() * 2;
+        let loud = vec![1.0; 2 * nbr_frames_max];
+        let silent = vec![0.0; 2 * nbr_frames_max];
+        let mut wave_out = vec![0.0; 2 * resampler.output_frames_max()];
+        for _ in 0..3 {
+            let frames = resampler.nbr_frames_needed();
+            resampler
+                .process_interleaved_into_buffer(&loud[..2 * frames], &mut wave_out, None)
+                .unwrap();
+        }
+        resampler
+            .set_active_channels_mask(Some(&[true, false]))
+            .unwrap();
+        assert_eq!(resampler.active_channels_mask(), &[true, false]);
+        for _ in 0..3 {
+            let frames = resampler.nbr_frames_needed();
+            wave_out.iter_mut().for_each(|x| *x = 5.0);
+            let (_, frames_out) = resampler
+                .process_interleaved_into_buffer(&silent[..2 * frames], &mut wave_out, None)
+                .unwrap();
+            assert!(wave_out[..2 * frames_out]
+                .iter()
+                .skip(1)
+                .step_by(2)
+                .all(|x| *x == 5.0));
+        }
+        resampler.set_active_channels_mask(None).unwrap();
+        assert_eq!(resampler.active_channels_mask(), &[true, true]);
+        let frames = resampler.nbr_frames_needed();
+        let (_, frames_out) = resampler
+            .process_interleaved_into_buffer(&silent[..2 * frames], &mut wave_out, None)
+            .unwrap();
+        assert!(wave_out[..2 * frames_out]
+            .iter()
+            .skip(1)
+            .step_by(2)
+            .all(|x| *x == 0.0));
+        match resampler.set_active_channels_mask(Some(&[true])) {
+            Err(ResampleError::WrongNumberOfMaskChannels { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn stored_mask() {
+        check_stored_mask(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_stored_mask(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_stored_mask(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_stored_mask(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+        check_stored_mask(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_stored_mask(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_stored_mask(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_stored_mask(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_stored_mask(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+        );
+        check_stored_mask(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    #[test]
+    fn stored_mask_with_process() {
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 512, 2);
+        resampler
+            .set_active_channels_mask(Some(&[false, true]))
+            .unwrap();
+        let waves_in = vec![vec![1.0; resampler.nbr_frames_needed()]; 2];
+        let waves_out = resampler.process(&waves_in).unwrap();
+        assert!(waves_out[0].is_empty());
+        assert_eq!(waves_out[1].len(), resampler.output_frames_max());
+    }
+
+    #[test]
+    fn inactive_output_zero() {
+        let mut resampler = SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2);
+        resampler.set_inactive_channel_output(crate::InactiveOutput::Zero);
+        let frames_max = resampler.output_frames_max();
+        let wave_in = vec![1.0; 2 * 256];
+        let mut wave_out = vec![5.0; 2 * frames_max];
+        let (_, frames_out) = resampler
+            .process_interleaved_into_buffer(&wave_in, &mut wave_out, Some(&[false, true]))
+            .unwrap();
+        assert!(frames_out < frames_max);
+        for (n, frame) in wave_out.chunks(2).enumerate() {
+            let expected = if n < frames_out { 0.0 } else { 5.0 };
+            assert_eq!(frame[0], expected);
+        }
+
+        let waves_in = vec![vec![1.0; 256]; 2];
+        let mut waves_out = vec![vec![5.0; frames_max + 2]; 2];
+        let (_, frames_out) = resampler
+            .process_into_buffer_at(&waves_in, 0, &mut waves_out, 2, Some(&[true, false]))
+            .unwrap();
+        assert!(waves_out[1][..2].iter().all(|x| *x == 5.0));
+        assert!(waves_out[1][2..2 + frames_out].iter().all(|x| *x == 0.0));
+        assert!(waves_out[1][2 + frames_out..].iter().all(|x| *x == 5.0));
+    }
+
+    // Clone a resampler in the middle of a stream, and check that both continue the same way.
+    fn check_clone<R: Resampler<f64> + Clone>(mut resampler: R) {
+        let wave: Vec<f64> = (0..10000).map(|n| (0.01 * n as f64).sin()).collect();
+        let mut position = 0;
+        let mut next_chunk = |resampler: &R| {
+            let frames = resampler.nbr_frames_needed();
+            let chunk = vec![wave[position..position + frames].to_vec(); 2];
+            position += frames;
+            chunk
+        };
+        for _ in 0..3 {
+            let chunk = next_chunk(&resampler);
+            resampler.process(&chunk).unwrap();
+        }
+        let mut cloned = resampler.clone();
+        for _ in 0..3 {
+            let chunk = next_chunk(&resampler);
+            assert_eq!(
+                resampler.process(&chunk).unwrap(),
+                cloned.process(&chunk).unwrap()
+            );
+        }
+        assert_eq!(resampler.input_frames_total(), cloned.input_frames_total());
+    }
+
+    #[test]
+    fn clone_resamplers() {
+        check_clone(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_clone(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_clone(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_clone(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+        check_clone(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_clone(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_clone(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_clone(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_clone(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+        );
+        check_clone(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    // Feed non-finite values to a resampler, with and without input validation.
+    fn check_validate_input<R: Resampler<f64> + Clone>(mut resampler: R) {
+        let wave: Vec<f64> = (0..20000).map(|n| (0.01 * n as f64).sin()).collect();
+        let mut position = 0;
+        let mut next_chunk = |resampler: &R| {
+            let frames = resampler.nbr_frames_needed();
+            let chunk = vec![wave[position..position + frames].to_vec(); 2];
+            position += frames;
+            chunk
+        };
+        for _ in 0..2 {
+            let chunk = next_chunk(&resampler);
+            resampler.process(&chunk).unwrap();
+        }
+        let mut poisoned = resampler.clone();
+        let mut reference = resampler.clone();
+        resampler.set_validate_input(true);
+
+        let mut bad_chunk = next_chunk(&resampler);
+        bad_chunk[1][5] = std::f64::NAN;
+        match resampler.process(&bad_chunk) {
+            Err(ResampleError::InvalidInput {
+                channel: 1,
+                frame: 5,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut interleaved: Vec<f64> = bad_chunk[0]
+            .iter()
+            .flat_map(|value| vec![*value, *value])
+            .collect();
+        interleaved[2 * 7] = std::f64::INFINITY;
+        let mut wave_out = vec![0.0; 2 * resampler.output_frames_max()];
+        match resampler.process_interleaved_into_buffer(&interleaved, &mut wave_out, None) {
+            Err(ResampleError::InvalidInput {
+                channel: 0,
+                frame: 7,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        // The rejected calls must not change the state.
+        let chunk = next_chunk(&resampler);
+        assert_eq!(
+            resampler.process(&chunk).unwrap(),
+            reference.process(&chunk).unwrap()
+        );
+        // Inactive channels are not checked.
+        let mut bad_chunk = next_chunk(&resampler);
+        bad_chunk[1][0] = std::f64::NAN;
+        let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+        resampler
+            .process_into_buffer(&bad_chunk, &mut waves_out, Some(&[true, false]))
+            .unwrap();
+
+        // Without validation, the bad value spoils the output, until the channel is reset.
+        let mut bad_chunk = next_chunk(&poisoned);
+        let last = bad_chunk[1].len() - 1;
+        bad_chunk[1][last] = std::f64::NAN;
+        poisoned.process(&bad_chunk).unwrap();
+        // A resampler with a FIFO may need another chunk before the bad value comes out.
+        let mut spoiled = false;
+        for _ in 0..2 {
+            let chunk = next_chunk(&poisoned);
+            let output = poisoned.process(&chunk).unwrap();
+            assert!(output[0].iter().all(|value| value.is_finite()));
+            spoiled |= output[1].iter().any(|value| value.is_nan());
+        }
+        assert!(spoiled);
+        poisoned.reset_channel(1).unwrap();
+        for _ in 0..3 {
+            let chunk = next_chunk(&poisoned);
+            let output = poisoned.process(&chunk).unwrap();
+            assert!(output.iter().flatten().all(|value| value.is_finite()));
+        }
+        let mut bad_chunk = next_chunk(&poisoned);
+        bad_chunk[0][0] = std::f64::NEG_INFINITY;
+        poisoned.process(&bad_chunk).unwrap();
+        poisoned.reset();
+        reference.reset();
+        for _ in 0..3 {
+            let chunk = next_chunk(&poisoned);
+            assert_eq!(
+                poisoned.process(&chunk).unwrap(),
+                reference.process(&chunk).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn validate_input() {
+        check_validate_input(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_validate_input(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_validate_input(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_validate_input(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_validate_input(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_validate_input(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_validate_input(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_validate_input(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+        );
+        check_validate_input(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    #[test]
+    fn flush_denormals() {
+        let mut resampler = SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 1);
+        let waves_in = vec![vec![1.0e-310; 256]];
+        let output = resampler.process(&waves_in).unwrap();
+        assert!(output[0].iter().any(|value| *value != 0.0));
+        #[cfg(target_arch = "x86_64")]
+        {
+            resampler.reset();
+            resampler.set_flush_denormals(true);
+            let output = resampler.process(&waves_in).unwrap();
+            assert!(output[0].iter().all(|value| *value == 0.0));
+        }
+    }
+
+    // Resample a single channel stream in chunks, and collect the frames reported as written.
+    fn resample_stream<R: Resampler<f64>>(resampler: &mut R, wave: &[f64]) -> Vec<f64> {
+        let mut output = Vec::new();
+        let mut position = 0;
+        while position + resampler.nbr_frames_needed() <= wave.len() {
+            let frames = resampler.nbr_frames_needed();
+            let mut wave_out = resampler.output_buffer_allocate(true);
+            let (_, frames_out) = resampler
+                .process_into_buffer(&[&wave[position..position + frames]], &mut wave_out, None)
+                .unwrap();
+            output.extend_from_slice(&wave_out[0][..frames_out]);
+            position += frames;
+        }
+        loop {
+            let mut wave_out = resampler.output_buffer_allocate(true);
+            let (_, frames_out) = resampler
+                .process_final_into_buffer(Some(&[&wave[position..]]), &mut wave_out, None)
+                .unwrap();
+            if frames_out == 0 {
+                break;
+            }
+            output.extend_from_slice(&wave_out[0][..frames_out]);
+        }
+        output
+    }
+
+    // Resample a stream in chunks with trimming of the delay, flush it,
+    // and compare with the same stream resampled with a single long FFT.
+    fn check_flush<R: Resampler<f64>>(mut resampler: R, wave: &[f64], reference: &[f64]) {
+        resampler.set_trim_delay(true);
+        let output = resample_stream(&mut resampler, wave);
+        assert_eq!(output.len(), reference.len());
+        for (n, (a, b)) in output.iter().zip(reference.iter()).enumerate() {
+            assert!((a - b).abs() < 1.0e-2, "frame {}: {} != {}", n, a, b);
+        }
+    }
+
+    #[test]
+    fn fft_flush_matches_long_fft() {
+        // Fade in and out, so that the filters of different lengths give the same output
+        // at the ends.
+        let wave: Vec<f64> = (0..20000)
+            .map(|n| (0.01 * n as f64).sin() * (std::f64::consts::PI * n as f64 / 20000.0).sin())
+            .collect();
+        let mut long = FftFixedIn::<f64>::new(44100, 48000, 8820, 1, 1);
+        long.set_trim_delay(true);
+        let reference = resample_stream(&mut long, &wave);
+        assert_eq!(
+            reference.len(),
+            (wave.len() as f64 * 48000.0 / 44100.0).ceil() as usize
+        );
+        check_flush(
+            FftFixedInOut::<f64>::new(44100, 48000, 512, 1),
+            &wave,
+            &reference,
+        );
+        check_flush(
+            FftFixedIn::<f64>::new(44100, 48000, 512, 2, 1),
+            &wave,
+            &reference,
+        );
+        check_flush(
+            FftFixedOut::<f64>::new(44100, 48000, 512, 2, 1),
+            &wave,
+            &reference,
+        );
+    }
+
+    // Resample an impulse with trimming of the delay, and check that the peak is aligned
+    // with the input, and that the output matches the untrimmed output without the delay.
+    fn check_trim_delay<R: Resampler<f64> + Clone>(mut resampler: R) {
+        let mut wave = vec![0.0; 8000];
+        wave[2000] = 1.0;
+        let mut untrimmed = resampler.clone();
+        let delay = untrimmed.output_delay();
+        assert!(delay > 0);
+        resampler.set_trim_delay(true);
+        assert_eq!(resampler.output_delay(), 0);
+        let ratio = resampler.resample_ratio();
+        let expected_len = (wave.len() as f64 * ratio).ceil() as usize;
+        assert_eq!(
+            resampler.output_frames_for_input(wave.len() as u64),
+            expected_len as u64
+        );
+
+        let reference = resample_stream(&mut untrimmed, &wave);
+        let output = resample_stream(&mut resampler, &wave);
+        assert_eq!(output.len(), expected_len);
+        assert_eq!(&reference[delay..], &output[..]);
+        let peak = output
+            .iter()
+            .enumerate()
+            .max_by(|a, b| a.1.abs().partial_cmp(&b.1.abs()).unwrap())
+            .unwrap()
+            .0;
+        let expected = 2000.0 * ratio;
+        assert!(
+            (peak as f64 - expected).abs() <= 1.0,
+            "peak at {}, expected {}",
+            peak,
+            expected
+        );
+        // The delay is trimmed again for the next stream.
+        resampler.reset();
+        assert_eq!(resample_stream(&mut resampler, &wave), output);
+    }
+
+    #[test]
+    fn trim_delay() {
+        check_trim_delay(SincFixedIn::<f64>::new(2.0, sinc_parameters(), 256, 1));
+        check_trim_delay(SincFixedOut::<f64>::new(3.0, sinc_parameters(), 256, 1));
+        check_trim_delay(FftFixedInOut::<f64>::new(44100, 48000, 512, 1));
+        check_trim_delay(FftFixedInOut::<f64>::new(44100, 32000, 512, 1));
+        check_trim_delay(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 1));
+        check_trim_delay(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 1));
+        check_trim_delay(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            1,
+        ));
+        check_trim_delay(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            1,
+        ));
+        check_trim_delay(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 1).unwrap(),
+        );
+        check_trim_delay(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            1,
+        ));
+    }
+
+    // Reset one channel in the middle of a stream, and compare with an untouched resampler.
+    fn check_reset_channel<R: Resampler<f64> + Clone>(mut resampler: R) {
+        let mut reference = resampler.clone();
+        for _ in 0..3 {
+            let waves = vec![vec![1.0; resampler.nbr_frames_needed()]; 2];
+            resampler.process(&waves).unwrap();
+            reference.process(&waves).unwrap();
+        }
+        resampler.reset_channel(1).unwrap();
+        for _ in 0..3 {
+            let waves = vec![vec![0.0; resampler.nbr_frames_needed()]; 2];
+            let output = resampler.process(&waves).unwrap();
+            let expected = reference.process(&waves).unwrap();
+            assert_eq!(output[0], expected[0]);
+            assert!(output[1].iter().all(|x| *x == 0.0));
+        }
+        match resampler.reset_channel(2) {
+            Err(ResampleError::InvalidChannel {
+                channel: 2,
+                nbr_channels: 2,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reset_channel() {
+        check_reset_channel(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_reset_channel(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_reset_channel(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_reset_channel(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+        check_reset_channel(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_reset_channel(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_reset_channel(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_reset_channel(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_reset_channel(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+        );
+        check_reset_channel(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    // Add and remove channels in the middle of a stream, and check that the remaining
+    // channels continue like in an untouched resampler, while added channels start from silence.
+    fn check_set_nbr_channels<R: Resampler<f64> + Clone>(mut resampler: R) {
+        let mut reference = resampler.clone();
+        for _ in 0..3 {
+            let waves = vec![vec![1.0; resampler.nbr_frames_needed()]; 2];
+            resampler.process(&waves).unwrap();
+            reference.process(&waves).unwrap();
+        }
+        resampler.set_nbr_channels(3).unwrap();
+        assert_eq!(resampler.nbr_channels(), 3);
+        assert_eq!(resampler.active_channels_mask(), &[true, true, true]);
+        let waves = vec![vec![0.0; resampler.nbr_frames_needed()]; 2];
+        assert!(resampler.process(&waves).is_err());
+        for _ in 0..3 {
+            let waves = vec![vec![0.0; resampler.nbr_frames_needed()]; 3];
+            let output = resampler.process(&waves).unwrap();
+            let expected = reference.process(&waves[..2]).unwrap();
+            assert_eq!(output[..2], expected[..]);
+            assert!(output[2].iter().all(|x| *x == 0.0));
+        }
+        resampler.set_nbr_channels(1).unwrap();
+        assert_eq!(resampler.nbr_channels(), 1);
+        let waves = vec![vec![0.0; resampler.nbr_frames_needed()]; 1];
+        let output = resampler.process(&waves).unwrap();
+        let expected = reference.process(&[&waves[0], &waves[0]]).unwrap();
+        assert_eq!(output[0], expected[0]);
+        match resampler.set_nbr_channels(0) {
+            Err(ResampleError::BadNbrChannelsUpdate) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn set_nbr_channels() {
+        check_set_nbr_channels(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_set_nbr_channels(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_set_nbr_channels(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_set_nbr_channels(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+        check_set_nbr_channels(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_set_nbr_channels(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_set_nbr_channels(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_set_nbr_channels(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_set_nbr_channels(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+        );
+        check_set_nbr_channels(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    // Process with buffers from the allocate helpers, also at the lowest allowed ratio.
+    fn check_buffer_allocate<R: Resampler<f64>>(mut resampler: R) {
+        let waves_in = resampler.input_buffer_allocate(true);
+        let mut waves_out = resampler.output_buffer_allocate(true);
+        assert_eq!(waves_in.len(), 2);
+        assert_eq!(waves_out.len(), 2);
+        assert!(waves_in
+            .iter()
+            .all(|chan| chan.len() == resampler.input_frames_max()));
+        assert!(waves_out
+            .iter()
+            .all(|chan| chan.len() == resampler.output_frames_max()));
+        let max_relative = resampler.max_resample_ratio_relative();
+        if max_relative > 1.0 {
+            resampler
+                .set_resample_ratio_relative(1.0 / max_relative)
+                .unwrap();
+        }
+        for _ in 0..5 {
+            assert!(resampler.nbr_frames_needed() <= resampler.input_frames_max());
+            resampler
+                .process_into_buffer(&waves_in, &mut waves_out, None)
+                .unwrap();
+        }
+        let waves_in = resampler.input_buffer_allocate(false);
+        assert_eq!(waves_in.len(), 2);
+        assert!(waves_in
+            .iter()
+            .all(|chan| chan.is_empty() && chan.capacity() >= resampler.input_frames_max()));
+        let waves_out = resampler.output_buffer_allocate(false);
+        assert!(waves_out
+            .iter()
+            .all(|chan| chan.is_empty() && chan.capacity() >= resampler.output_frames_max()));
+    }
+
+    #[test]
+    fn buffer_allocate() {
+        check_buffer_allocate(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_buffer_allocate(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_buffer_allocate(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_buffer_allocate(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_buffer_allocate(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_buffer_allocate(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_buffer_allocate(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_buffer_allocate(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+        );
+        check_buffer_allocate(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    // Compare "process_partial_into_buffer" with processing input padded with zeros.
+    fn check_partial<R: Resampler<f64> + Clone>(mut resampler: R) {
+        let mut padded = resampler.clone();
+        let frames = resampler.nbr_frames_needed();
+        let frames_max = resampler.output_frames_max();
+        let partial: Vec<Vec<f64>> = vec![(0..frames / 3).map(|n| n as f64).collect(); 2];
+        let mut waves_in = partial.clone();
+        waves_in
+            .iter_mut()
+            .for_each(|chan| chan.resize(frames, 0.0));
+        let mut waves_out = vec![vec![0.0; frames_max]; 2];
+        let mut expected = vec![vec![0.0; frames_max]; 2];
+        let (frames_in, frames_out) = resampler
+            .process_partial_into_buffer(Some(&partial), &mut waves_out, None)
+            .unwrap();
+        let (_, expected_frames) = padded
+            .process_into_buffer(&waves_in, &mut expected, None)
+            .unwrap();
+        assert_eq!(frames_in, frames / 3);
+        assert_eq!(frames_out, expected_frames);
+        assert_eq!(waves_out, expected);
+
+        let zeros = vec![vec![0.0; padded.nbr_frames_needed()]; 2];
+        let (frames_in, frames_out) = resampler
+            .process_partial_into_buffer(None::<&[Vec<f64>]>, &mut waves_out, None)
+            .unwrap();
+        let (_, expected_frames) = padded
+            .process_into_buffer(&zeros, &mut expected, None)
+            .unwrap();
+        assert_eq!(frames_in, 0);
+        assert_eq!(frames_out, expected_frames);
+        assert_eq!(waves_out, expected);
+    }
+
+    #[test]
+    fn process_partial_into_buffer() {
+        check_partial(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_partial(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_partial(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_partial(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+        check_partial(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_partial(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_partial(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_partial(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_partial(
+            SincFixedBoth::<f64>::new(0.91875, 1.1, sinc_parameters(), 480, 441, 2).unwrap(),
+        );
+        check_partial(HybridFixedIn::<f64>::new(
+            48037.0 / 44100.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    #[test]
+    fn process_partial_into_buffer_errors() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2);
+        let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+        let too_long = vec![vec![0.0; 513]; 2];
+        match resampler.process_partial_into_buffer(Some(&too_long), &mut waves_out, None) {
+            Err(ResampleError::WrongNumberOfFrames {
+                channel: 0,
+                expected: 512,
+                actual: 513,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let unequal = vec![vec![0.0; 100], vec![0.0; 101]];
+        match resampler.process_partial_into_buffer(Some(&unequal), &mut waves_out, None) {
+            Err(ResampleError::WrongNumberOfFrames {
+                channel: 1,
+                expected: 100,
+                actual: 101,
+            }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(resampler
+            .process_partial_into_buffer(Some(&unequal), &mut waves_out, Some(&[true, false]))
+            .is_ok());
+    }
+
+    // Process a stream in chunks, and end it with "process_final_into_buffer".
+    // Returns the total number of output frames.
+    fn process_stream<R: Resampler<f64>>(resampler: &mut R, input_frames: usize) -> usize {
+        let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+        let mut position = 0;
+        let mut total_out = 0;
+        while input_frames - position >= resampler.nbr_frames_needed() {
+            let waves_in = vec![vec![0.5; resampler.nbr_frames_needed()]; 2];
+            let (frames_in, frames_out) = resampler
+                .process_into_buffer(&waves_in, &mut waves_out, None)
+                .unwrap();
+            position += frames_in;
+            total_out += frames_out;
+        }
+        let remaining = vec![vec![0.5; input_frames - position]; 2];
+        let (frames_in, frames_out) = resampler
+            .process_final_into_buffer(Some(&remaining), &mut waves_out, None)
+            .unwrap();
+        assert_eq!(frames_in, input_frames - position);
+        total_out += frames_out;
+        loop {
+            let (frames_in, frames_out) = resampler
+                .process_final_into_buffer(None::<&[Vec<f64>]>, &mut waves_out, None)
+                .unwrap();
+            assert_eq!(frames_in, 0);
+            if frames_out == 0 {
+                break;
+            }
+            total_out += frames_out;
+        }
+        total_out
+    }
+
+    // Use a length that isn't a multiple of the chunk size,
+    // and check that the final flush gives the expected number of frames.
+    fn check_final<R: Resampler<f64>>(mut resampler: R, input_frames: usize) {
+        let total_out = process_stream(&mut resampler, input_frames);
+        let expected = (input_frames as f64 * resampler.resample_ratio()).ceil() as usize;
+        assert_eq!(total_out - resampler.output_delay(), expected);
+        let remaining = vec![vec![0.5; 10]; 2];
+        let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+        let (frames_in, frames_out) = resampler
+            .process_final_into_buffer(Some(&remaining), &mut waves_out, None)
+            .unwrap();
+        assert_eq!((frames_in, frames_out), (0, 0));
+    }
+
+    #[test]
+    fn process_final_into_buffer() {
+        for input_frames in [1000, 4000, 4321].iter() {
+            check_final(
+                SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2),
+                *input_frames,
+            );
+            check_final(
+                SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2),
+                *input_frames,
+            );
+            check_final(
+                FftFixedInOut::<f64>::new(44100, 48000, 512, 2),
+                *input_frames,
+            );
+            check_final(
+                FftFixedInOut::<f64>::new(44100, 32000, 512, 2),
+                *input_frames,
+            );
+            check_final(
+                FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2),
+                *input_frames,
+            );
+            check_final(
+                FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2),
+                *input_frames,
+            );
+            check_final(
+                TwoStageSincFixedIn::<f64>::new(6.0, sinc_parameters(), 256, 2),
+                *input_frames,
+            );
+            check_final(
+                TwoStageSincFixedIn::<f64>::new(1.0 / 6.0, sinc_parameters(), 256, 2),
+                *input_frames,
+            );
+            check_final(
+                HybridFixedIn::<f64>::new(48037.0 / 44100.0, sinc_parameters(), 256, 2),
+                *input_frames,
+            );
+        }
+    }
+
+    // Compare the predicted number of frames with the result of processing streams of random lengths.
+    fn check_frames_for_input<R: Resampler<f64>>(mut resampler: R) {
+        let mut rng = rand::thread_rng();
+        for _ in 0..5 {
+            let input_frames = rng.gen_range(0..5000);
+            resampler.reset();
+            let total_out = process_stream(&mut resampler, input_frames);
+            assert_eq!(
+                resampler.output_frames_for_input(input_frames as u64),
+                total_out as u64
+            );
+            let output_frames = rng.gen_range(0..5000);
+            let needed = resampler.input_frames_for_output(output_frames);
+            assert!(resampler.output_frames_for_input(needed) >= output_frames);
+            if needed > 0 {
+                assert!(resampler.output_frames_for_input(needed - 1) < output_frames);
+            }
+        }
+    }
+
+    #[test]
+    fn output_frames_for_input() {
+        check_frames_for_input(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_frames_for_input(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_frames_for_input(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_frames_for_input(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+        check_frames_for_input(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_frames_for_input(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_frames_for_input(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_frames_for_input(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    // Resample a sine with "process_all" and compare with the ideal result.
+    fn check_process_all<R: Resampler<f64>>(mut resampler: R) {
+        let ratio = resampler.resample_ratio();
+        let frames_in = 3000;
+        let wave: Vec<f64> = (0..frames_in).map(|n| (0.02 * n as f64).sin()).collect();
+        let waves_in = vec![wave, Vec::new()];
+        let waves_out = resampler.process_all(&waves_in).unwrap();
+        let frames_out = (frames_in as f64 * ratio).ceil() as usize;
+        assert_eq!(waves_out[0].len(), frames_out);
+        assert!(waves_out[1].is_empty());
+        for (n, value) in waves_out[0]
+            .iter()
+            .enumerate()
+            .take(frames_out - 300)
+            .skip(300)
+        {
+            let expected = (0.02 * n as f64 / ratio).sin();
+            assert!((value - expected).abs() < 0.05, "{}: {}", n, value);
+        }
+
+        // the resampler is reset, so processing again gives the same result
+        let again = resampler.process_all(&waves_in).unwrap();
+        assert_eq!(again, waves_out);
+
+        // inputs shorter than one chunk
+        let short = vec![vec![1.0; 10]; 2];
+        let short_out = resampler.process_all(&short).unwrap();
+        assert_eq!(short_out[0].len(), (10.0 * ratio).ceil() as usize);
+        assert_eq!(short_out[1].len(), (10.0 * ratio).ceil() as usize);
+    }
+
+    #[test]
+    fn process_all() {
+        check_process_all(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_process_all(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_process_all(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_process_all(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_process_all(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_process_all(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_process_all(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    #[test]
+    fn process_all_with_mask() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2);
+        resampler
+            .set_active_channels_mask(Some(&[true, false]))
+            .unwrap();
+        let waves_in = vec![vec![0.5; 1000]; 2];
+        let waves_out = resampler.process_all(&waves_in).unwrap();
+        assert_eq!(
+            waves_out[0].len(),
+            (1000.0 * 48000.0 / 44100.0_f64).ceil() as usize
+        );
+        assert!(waves_out[1].is_empty());
+        assert_eq!(resampler.active_channels_mask(), &[true, false]);
+    }
+
+    #[test]
+    fn process_all_unequal_channels() {
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2);
+        let waves_in = vec![vec![0.0; 100], vec![0.0; 101]];
+        match resampler.process_all(&waves_in) {
+            Err(ResampleError::WrongNumberOfFrames { channel: 1, .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    // Process a few chunks and compare the frame counters with the returned lengths.
+    fn check_frame_totals<R: Resampler<f64>>(mut resampler: R) {
+        let mut frames_in = 0;
+        let mut frames_out = 0;
+        for _ in 0..10 {
+            let frames = resampler.nbr_frames_needed();
+            let waves_in = vec![vec![0.0; frames]; 2];
+            let waves_out = resampler.process(&waves_in).unwrap();
+            frames_in += frames as u64;
+            frames_out += waves_out[0].len() as u64;
+            assert_eq!(resampler.input_frames_total(), frames_in);
+            assert_eq!(resampler.output_frames_total(), frames_out);
+        }
+        resampler.reset();
+        assert_eq!(resampler.input_frames_total(), 0);
+        assert_eq!(resampler.output_frames_total(), 0);
+    }
+
+    #[test]
+    fn frame_totals() {
+        check_frame_totals(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+        check_frame_totals(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+        check_frame_totals(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+        check_frame_totals(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+        check_frame_totals(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+        check_frame_totals(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+        check_frame_totals(TwoStageSincFixedIn::<f64>::new(
+            6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+        check_frame_totals(TwoStageSincFixedIn::<f64>::new(
+            1.0 / 6.0,
+            sinc_parameters(),
+            256,
+            2,
+        ));
+    }
+
+    #[test]
+    fn output_time_for_input_frame() {
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2);
+        let delay = resampler.output_delay_fractional();
+        assert!((resampler.output_time_for_input_frame(0) - delay).abs() < 1.0e-9);
+        let expected = 44100.0 * 48000.0 / 44100.0 + delay;
+        assert!((resampler.output_time_for_input_frame(44100) - expected).abs() < 1.0e-9);
+    }
+
+    #[test]
+    fn set_sample_rates() {
+        let mut resampler = SincFixedIn::<f64>::new(48000.0 / 44100.0, sinc_parameters(), 256, 2);
+        resampler.set_sample_rates(44099.7, 48000.0, false).unwrap();
+        assert!((resampler.resample_ratio() - 48000.0 / 44099.7).abs() < 1.0e-12);
+        resampler
+            .set_sample_rates(44100.0, 48000.0 * 1.01, true)
+            .unwrap();
+        assert!((resampler.resample_ratio() - 1.01 * 48000.0 / 44100.0).abs() < 1.0e-12);
+        match resampler.set_sample_rates(48000.0, 44100.0, false) {
+            Err(ResampleError::BadRatioUpdate) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(resampler.set_sample_rates(0.0, 48000.0, false).is_err());
+
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2);
+        match resampler.set_sample_rates(44100.0, 48000.0, false) {
+            Err(ResampleError::SyncNotAdjustable) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
     }
 }
@@ -0,0 +1,338 @@
+use crate::asynchro::StreamState;
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::{InterpolationParameters, Resampler, Sample, SincFixedIn};
+use std::fmt;
+
+/// An asynchronous resampler that processes many independent single channel streams
+/// with the same settings.
+///
+/// This is meant for applications that resample a large number of streams, for example
+/// hundreds of voice streams. Using a [SincFixedIn] per stream gives each stream
+/// its own table of sincs and its own buffers. The MultiResampler instead holds a single table
+/// and a single input buffer, and keeps only the filter history, the position between
+/// input frames and the resample ratio of each stream.
+/// Each call to [process_stream](MultiResampler::process_stream) loads the state of one stream,
+/// resamples a chunk of it like a [SincFixedIn] would, and stores the state again.
+///
+/// Streams are added with [add_stream](MultiResampler::add_stream), which returns an id
+/// for the stream, and removed with [remove_stream](MultiResampler::remove_stream).
+/// The id of a removed stream is reused by the next stream that is added.
+/// Each stream has its own resample ratio, within the range allowed by
+/// the maximum relative ratio given when creating the resampler.
+///
+/// Adding a stream allocates its history, unless it reuses the id of a removed stream.
+/// Processing doesn't allocate.
+#[derive(Clone)]
+pub struct MultiResampler<T> {
+    engine: SincFixedIn<T>,
+    streams: Vec<Option<StreamState<T>>>,
+}
+
+impl<T> MultiResampler<T>
+where
+    T: Sample,
+{
+    /// Create a new MultiResampler without any streams
+    ///
+    /// Parameters are:
+    /// - `resample_ratio`: Ratio between output and input sample rates,
+    ///   used by new streams and for computing the table of sincs.
+    /// - `max_resample_ratio_relative`: How much the ratio of a stream can be adjusted,
+    ///   see [max_resample_ratio_relative](Resampler::max_resample_ratio_relative).
+    /// - `parameters`: Parameters for interpolation, see `InterpolationParameters`
+    /// - `chunk_size`: size of input data in frames
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the chunk size is zero,
+    /// or if the maximum relative ratio is smaller than 1.0.
+    pub fn new(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        parameters: InterpolationParameters,
+        chunk_size: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        debug!(
+            "Create new MultiResampler, ratio: {}, chunk_size: {}, parameters: {:?}",
+            resample_ratio, chunk_size, parameters
+        );
+        if chunk_size == 0 {
+            return Err(ResamplerConstructionError::InvalidChunkSize);
+        }
+        let mut engine = SincFixedIn::new(resample_ratio, parameters, chunk_size, 1);
+        engine
+            .set_max_resample_ratio_relative(max_resample_ratio_relative)
+            .map_err(|_| ResamplerConstructionError::InvalidMaxRatio)?;
+        Ok(MultiResampler {
+            engine,
+            streams: Vec::new(),
+        })
+    }
+
+    /// Get the number of input frames expected by each call to "process_stream".
+    pub fn chunk_size(&self) -> usize {
+        self.engine.chunk_size()
+    }
+
+    /// Get the maximum number of output frames of a call to "process_stream",
+    /// for any resample ratio a stream may have.
+    pub fn output_frames_max(&self) -> usize {
+        self.engine.output_frames_max_allowed()
+    }
+
+    /// Get the number of streams.
+    pub fn nbr_streams(&self) -> usize {
+        self.streams
+            .iter()
+            .filter(|stream| stream.is_some())
+            .count()
+    }
+
+    /// Add a new stream, that starts from silence at the original resample ratio,
+    /// and return its id.
+    pub fn add_stream(&mut self) -> usize {
+        let stream = self.engine.new_stream();
+        match self.streams.iter().position(|stream| stream.is_none()) {
+            Some(stream_id) => {
+                self.streams[stream_id] = Some(stream);
+                stream_id
+            }
+            None => {
+                self.streams.push(Some(stream));
+                self.streams.len() - 1
+            }
+        }
+    }
+
+    /// Remove a stream. Its id may be reused by a stream that is added later.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there is no stream with the given id.
+    pub fn remove_stream(&mut self, stream_id: usize) -> ResampleResult<()> {
+        self.stream_mut(stream_id)?;
+        self.streams[stream_id] = None;
+        if self.streams.iter().all(|stream| stream.is_none()) {
+            self.streams.clear();
+        }
+        Ok(())
+    }
+
+    /// Reset a stream, so that it starts again from silence at the original resample ratio.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there is no stream with the given id.
+    pub fn reset_stream(&mut self, stream_id: usize) -> ResampleResult<()> {
+        let new_stream = self.engine.new_stream();
+        let stream = self.stream_mut(stream_id)?;
+        stream.clone_from(&new_stream);
+        Ok(())
+    }
+
+    /// Get the current resample ratio of a stream.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there is no stream with the given id.
+    pub fn resample_ratio(&self, stream_id: usize) -> ResampleResult<f64> {
+        self.stream(stream_id).map(|stream| stream.resample_ratio())
+    }
+
+    /// Get the number of input frames a stream has processed.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there is no stream with the given id.
+    pub fn input_frames_total(&self, stream_id: usize) -> ResampleResult<u64> {
+        self.stream(stream_id)
+            .map(|stream| stream.input_frames_total())
+    }
+
+    /// Update the resample ratio of a stream. The new value must be within the range allowed by
+    /// the maximum relative ratio. With `ramp`, the ratio changes over the duration set by
+    /// [set_ramp_duration](MultiResampler::set_ramp_duration), otherwise it changes immediately.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there is no stream with the given id,
+    /// or if the new ratio is outside the allowed range.
+    pub fn set_resample_ratio(
+        &mut self,
+        stream_id: usize,
+        new_ratio: f64,
+        ramp: bool,
+    ) -> ResampleResult<()> {
+        trace!(
+            "Change resample ratio of stream {} to {}",
+            stream_id,
+            new_ratio
+        );
+        let stream = self
+            .streams
+            .get_mut(stream_id)
+            .and_then(Option::as_mut)
+            .ok_or(ResampleError::InvalidStream { stream_id })?;
+        self.engine.set_stream_ratio(stream, new_ratio, ramp)
+    }
+
+    /// Set the duration of the ramp used when the resample ratio of a stream is changed,
+    /// in output frames, for all streams. See [SincFixedIn::set_ramp_duration].
+    pub fn set_ramp_duration(&mut self, frames: usize) {
+        self.engine.set_ramp_duration(frames);
+        for stream in self.streams.iter_mut().flatten() {
+            stream.set_ramp_duration(frames);
+        }
+    }
+
+    /// Resample a chunk of a stream.
+    /// The input must hold [chunk_size](MultiResampler::chunk_size) frames,
+    /// and the output must have room for [output_frames_max](MultiResampler::output_frames_max)
+    /// frames. Returns the number of input frames used and the number of output frames written.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if there is no stream with the given id,
+    /// or if the input or output has the wrong length.
+    pub fn process_stream(
+        &mut self,
+        stream_id: usize,
+        wave_in: &[T],
+        wave_out: &mut [T],
+    ) -> ResampleResult<(usize, usize)> {
+        let stream = self
+            .streams
+            .get_mut(stream_id)
+            .and_then(Option::as_mut)
+            .ok_or(ResampleError::InvalidStream { stream_id })?;
+        if wave_in.len() != self.engine.chunk_size() {
+            return Err(ResampleError::WrongNumberOfFrames {
+                channel: 0,
+                expected: self.engine.chunk_size(),
+                actual: wave_in.len(),
+            });
+        }
+        let frames_out_max = self.engine.output_frames_max_allowed();
+        if wave_out.len() < frames_out_max {
+            return Err(ResampleError::InsufficientOutputBufferSize {
+                expected: frames_out_max,
+                actual: wave_out.len(),
+            });
+        }
+        self.engine.load_stream(stream);
+        let result = self
+            .engine
+            .process_into_buffer(&[wave_in], &mut [wave_out], None);
+        self.engine.store_stream(stream);
+        result
+    }
+
+    fn stream(&self, stream_id: usize) -> ResampleResult<&StreamState<T>> {
+        self.streams
+            .get(stream_id)
+            .and_then(Option::as_ref)
+            .ok_or(ResampleError::InvalidStream { stream_id })
+    }
+
+    fn stream_mut(&mut self, stream_id: usize) -> ResampleResult<&mut StreamState<T>> {
+        self.streams
+            .get_mut(stream_id)
+            .and_then(Option::as_mut)
+            .ok_or(ResampleError::InvalidStream { stream_id })
+    }
+}
+
+impl<T> fmt::Debug for MultiResampler<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MultiResampler")
+            .field("nbr_streams", &self.streams.iter().flatten().count())
+            .field("engine", &self.engine)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::WindowFunction;
+    use crate::{
+        Accumulation, FilterPhase, InterpolationParameters, InterpolationType, SincStorage,
+    };
+    use crate::{MultiResampler, ResampleError, Resampler, SincFixedIn};
+
+    fn params() -> InterpolationParameters {
+        InterpolationParameters {
+            sinc_len: 64,
+            f_cutoff: 0.95,
+            interpolation: InterpolationType::Cubic,
+            oversampling_factor: 128,
+            window: WindowFunction::BlackmanHarris2,
+            phase: FilterPhase::Linear,
+            storage: SincStorage::Table,
+            accumulation: Accumulation::Native,
+        }
+    }
+
+    fn sine(stream: usize, position: usize, frames: usize) -> Vec<f64> {
+        (position..position + frames)
+            .map(|n| (0.01 * (stream + 1) as f64 * n as f64).sin())
+            .collect()
+    }
+
+    // Process interleaved chunks of three streams with different ratios,
+    // and compare with a separate SincFixedIn for each stream.
+    #[test]
+    fn same_as_separate_resamplers() {
+        let mut multi = MultiResampler::<f64>::new(1.0, 1.1, params(), 256).unwrap();
+        let mut references: Vec<SincFixedIn<f64>> = Vec::new();
+        let ratios = [1.0, 0.95, 1.08];
+        for ratio in ratios.iter() {
+            let stream_id = multi.add_stream();
+            multi.set_resample_ratio(stream_id, *ratio, false).unwrap();
+            let mut reference = SincFixedIn::<f64>::new(1.0, params(), 256, 1);
+            reference.set_resample_ratio(*ratio).unwrap();
+            references.push(reference);
+        }
+        assert_eq!(multi.nbr_streams(), 3);
+        let mut wave_out = vec![0.0; multi.output_frames_max()];
+        for chunk in 0..10 {
+            for (stream_id, reference) in references.iter_mut().enumerate() {
+                let wave_in = sine(stream_id, chunk * 256, 256);
+                let (frames_in, frames_out) = multi
+                    .process_stream(stream_id, &wave_in, &mut wave_out)
+                    .unwrap();
+                assert_eq!(frames_in, 256);
+                let expected = reference.process(&[&wave_in]).unwrap();
+                assert_eq!(&wave_out[..frames_out], &expected[0][..]);
+            }
+        }
+        assert_eq!(multi.resample_ratio(2).unwrap(), 1.08);
+        assert_eq!(multi.input_frames_total(1).unwrap(), 2560);
+    }
+
+    #[test]
+    fn add_and_remove_streams() {
+        let mut multi = MultiResampler::<f64>::new(1.0, 1.1, params(), 256).unwrap();
+        assert_eq!(multi.add_stream(), 0);
+        assert_eq!(multi.add_stream(), 1);
+        assert_eq!(multi.add_stream(), 2);
+        multi.remove_stream(1).unwrap();
+        assert_eq!(multi.nbr_streams(), 2);
+        let mut wave_out = vec![0.0; multi.output_frames_max()];
+        match multi.process_stream(1, &[0.0; 256], &mut wave_out) {
+            Err(ResampleError::InvalidStream { stream_id: 1 }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(multi.remove_stream(1).is_err());
+        assert!(multi.set_resample_ratio(0, 1.2, false).is_err());
+        assert!(multi.process_stream(0, &[0.0; 255], &mut wave_out).is_err());
+        assert_eq!(multi.add_stream(), 1);
+        multi.set_resample_ratio(1, 1.05, false).unwrap();
+        multi.reset_stream(1).unwrap();
+        assert_eq!(multi.resample_ratio(1).unwrap(), 1.0);
+        multi.remove_stream(0).unwrap();
+        multi.remove_stream(1).unwrap();
+        multi.remove_stream(2).unwrap();
+        assert_eq!(multi.nbr_streams(), 0);
+        assert_eq!(multi.add_stream(), 0);
+    }
+}
@@ -0,0 +1,148 @@
+use crate::builder::Quality;
+use crate::error::{ResampleError, ResampleResult};
+use crate::{FftFixedIn, Resampler, Sample, SincFixedIn};
+use num_integer as integer;
+
+/// The number of input frames processed per call.
+const CHUNK_SIZE: usize = 1024;
+
+/// The largest reduced sample rate for which the FFT resampler is used.
+/// The FFT sizes are multiples of the reduced rates, so larger values
+/// would give very long FFTs.
+const MAX_REDUCED_RATE: usize = 1024;
+
+/// Resample a complete clip of audio from `fs_in` to `fs_out`.
+///
+/// The input is given as one vector per channel, all with the same length.
+/// A synchronous FFT resampler is used when the ratio between the sample rates
+/// can be expressed as a fraction with small numbers, like 44100 to 48000 Hz,
+/// and an asynchronous sinc resampler with high quality settings otherwise.
+///
+/// The whole input is processed with [process_all](Resampler::process_all),
+/// including the frames held back by the resampler at the end.
+/// The delay of the resampler is removed, so that the output is aligned with
+/// the input, and the output holds exactly `ceil(input_length * fs_out / fs_in)` frames.
+///
+/// ```
+/// let waves_in = vec![vec![0.0f64; 10000]; 2];
+/// let waves_out = rubato::resample(&waves_in, 44100, 48000).unwrap();
+/// assert_eq!(waves_out[0].len(), 10885);
+/// ```
+/// # Errors
+///
+/// The function returns an error if a sample rate is zero, if the input has no channels
+/// or no frames, or if the channels have different lengths.
+pub fn resample<T: Sample>(
+    wave_in: &[Vec<T>],
+    fs_in: usize,
+    fs_out: usize,
+) -> ResampleResult<Vec<Vec<T>>> {
+    if fs_in == 0 || fs_out == 0 {
+        return Err(ResampleError::InvalidSampleRate { fs_in, fs_out });
+    }
+    let frames = wave_in.first().map_or(0, |chan| chan.len());
+    if frames == 0 {
+        return Err(ResampleError::EmptyInput);
+    }
+    if let Some((chan, wave)) = wave_in
+        .iter()
+        .enumerate()
+        .find(|(_, wave)| wave.len() != frames)
+    {
+        return Err(ResampleError::WrongNumberOfFrames {
+            channel: chan,
+            expected: frames,
+            actual: wave.len(),
+        });
+    }
+    let nbr_channels = wave_in.len();
+    let waves_out = if fft_suitable(fs_in, fs_out) {
+        FftFixedIn::<T>::new(fs_in, fs_out, CHUNK_SIZE, 2, nbr_channels).process_all(wave_in)?
+    } else {
+        SincFixedIn::<T>::new(
+            fs_out as f64 / fs_in as f64,
+            Quality::High.interpolation_parameters(),
+            CHUNK_SIZE,
+            nbr_channels,
+        )
+        .process_all(wave_in)?
+    };
+    let frames_out = ((frames as u64 * fs_out as u64 + fs_in as u64 - 1) / fs_in as u64) as usize;
+    debug_assert!(waves_out.iter().all(|chan| chan.len() == frames_out));
+    Ok(waves_out)
+}
+
+/// Check if the sample rates reduce to numbers small enough for the FFT resamplers.
+pub(crate) fn fft_suitable(fs_in: usize, fs_out: usize) -> bool {
+    let gcd = integer::gcd(fs_in, fs_out);
+    fs_in / gcd <= MAX_REDUCED_RATE && fs_out / gcd <= MAX_REDUCED_RATE
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{resample, FftFixedIn, ResampleError, Resampler};
+
+    fn check_sine(fs_in: usize, fs_out: usize) {
+        let frames = 20000;
+        let freq = 200.0;
+        let wave: Vec<f64> = (0..frames)
+            .map(|n| (2.0 * std::f64::consts::PI * freq * n as f64 / fs_in as f64).sin())
+            .collect();
+        let waves_out = resample(&[wave.clone(), wave], fs_in, fs_out).unwrap();
+        let expected_len = (frames * fs_out + fs_in - 1) / fs_in;
+        assert_eq!(waves_out.len(), 2);
+        for chan in waves_out.iter() {
+            assert_eq!(chan.len(), expected_len);
+            // skip the edges, where the filters see the start and end of the clip
+            for (n, value) in chan.iter().enumerate().take(expected_len - 500).skip(500) {
+                let expected = (2.0 * std::f64::consts::PI * freq * n as f64 / fs_out as f64).sin();
+                assert!(
+                    (value - expected).abs() < 0.02,
+                    "frame {}: got {}, expected {}",
+                    n,
+                    value,
+                    expected
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn resample_sine() {
+        check_sine(44100, 48000);
+        check_sine(48000, 44100);
+        check_sine(44100, 48001);
+        check_sine(96001, 44100);
+    }
+
+    // The output length is the same as from "process_all", also for very short clips.
+    #[test]
+    fn resample_short() {
+        let waves_in = vec![vec![1.0f64; 3]];
+        let waves_out = resample(&waves_in, 44100, 48000).unwrap();
+        assert_eq!(waves_out[0].len(), 4);
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1);
+        assert_eq!(resampler.process_all(&waves_in).unwrap(), waves_out);
+    }
+
+    #[test]
+    fn resample_errors() {
+        let waves_in = vec![vec![0.0f64; 100]; 2];
+        match resample(&waves_in, 0, 48000) {
+            Err(ResampleError::InvalidSampleRate { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resample::<f64>(&[], 44100, 48000) {
+            Err(ResampleError::EmptyInput) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resample(&[Vec::<f64>::new()], 44100, 48000) {
+            Err(ResampleError::EmptyInput) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        match resample(&[vec![0.0f64; 100], vec![0.0; 99]], 44100, 48000) {
+            Err(ResampleError::WrongNumberOfFrames { channel: 1, .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
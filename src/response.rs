@@ -0,0 +1,235 @@
+use crate::asynchro::SincInterpolator;
+use crate::Sample;
+use num_complex::Complex;
+use std::f64::consts::PI;
+
+/// The impulse response of the antialiasing filter of a resampler,
+/// used to calculate its frequency response and delay.
+pub(crate) struct FilterResponse {
+    /// The taps, in the order they are applied to the input, oldest frame first.
+    taps: Vec<f64>,
+    /// The number of taps per input frame.
+    taps_per_frame: usize,
+    /// Frequencies above this, in cycles per input frame, are removed entirely.
+    bandwidth: f64,
+}
+
+impl FilterResponse {
+    /// Create a response from taps given at `taps_per_frame` taps per input frame.
+    pub fn new(taps: Vec<f64>, taps_per_frame: usize, bandwidth: f64) -> Self {
+        FilterResponse {
+            taps,
+            taps_per_frame,
+            bandwidth,
+        }
+    }
+
+    /// Read the taps of a table of sincs, by interpolating unit impulses.
+    /// Sinc `n` gives the point `(n + 1) / nbr_sincs` frames after the center,
+    /// so the taps of the oversampled filter are found by interleaving the sincs in reverse order.
+    pub fn from_interpolator<T>(interpolator: &dyn SincInterpolator<T>) -> Self
+    where
+        T: Sample,
+    {
+        let sinc_len = interpolator.len();
+        let nbr_sincs = interpolator.nbr_sincs();
+        let mut taps = vec![0.0; sinc_len * nbr_sincs];
+        let mut wave = vec![T::zero(); sinc_len + 1];
+        for p in 0..sinc_len {
+            wave[p] = T::one();
+            for n in 0..nbr_sincs {
+                let value = interpolator.get_sinc_interpolated(&wave, 0, n);
+                taps[nbr_sincs * p + nbr_sincs - 1 - n] = value.to_f64();
+            }
+            wave[p] = T::zero();
+        }
+        FilterResponse {
+            taps,
+            taps_per_frame: nbr_sincs,
+            bandwidth: std::f64::INFINITY,
+        }
+    }
+
+    /// Get the magnitude of the response at `freq` cycles per input frame,
+    /// relative to the response at zero frequency.
+    pub fn magnitude(&self, freq: f64) -> f64 {
+        if freq > self.bandwidth {
+            return 0.0;
+        }
+        let angle = -2.0 * PI * freq / self.taps_per_frame as f64;
+        let step = Complex::new(angle.cos(), angle.sin());
+        let mut rotation = Complex::new(1.0, 0.0);
+        let mut sum = Complex::new(0.0, 0.0);
+        for (n, tap) in self.taps.iter().enumerate() {
+            // Start over from an exact value now and then, to limit the accumulated rounding errors.
+            if n % 1024 == 0 {
+                let phase = angle * n as f64;
+                rotation = Complex::new(phase.cos(), phase.sin());
+            }
+            sum += rotation * tap;
+            rotation *= step;
+        }
+        let dc: f64 = self.taps.iter().sum();
+        sum.norm() / dc.abs()
+    }
+
+    /// Get the delay of the filter at low frequencies, in input frames.
+    /// This is the centroid of the impulse response, measured from the newest input frame.
+    pub fn delay(&self) -> f64 {
+        let last = self.taps.len() as f64 - 1.0;
+        let (weighted, total) = self
+            .taps
+            .iter()
+            .enumerate()
+            .fold((0.0, 0.0), |(weighted, total), (n, tap)| {
+                (weighted + (last - n as f64) * tap, total + tap)
+            });
+        weighted / total / self.taps_per_frame as f64
+    }
+}
+
+/// Evaluate a magnitude response at `n_points` evenly spaced frequencies,
+/// from zero up to the higher of the input and output Nyquist frequencies.
+/// The `magnitude` function takes frequencies in cycles per input frame.
+/// The returned frequencies are relative to the lower of the two Nyquist frequencies,
+/// and the magnitudes are given in dB.
+pub(crate) fn frequency_response<F>(
+    resample_ratio: f64,
+    n_points: usize,
+    magnitude: F,
+) -> Vec<(f64, f64)>
+where
+    F: Fn(f64) -> f64,
+{
+    let lower_nyquist = 0.5 * resample_ratio.min(1.0);
+    let highest = resample_ratio.max(1.0 / resample_ratio);
+    let step = if n_points > 1 {
+        highest / (n_points - 1) as f64
+    } else {
+        0.0
+    };
+    (0..n_points)
+        .map(|n| {
+            let relative = step * n as f64;
+            let gain = magnitude(relative * lower_nyquist);
+            (relative, 20.0 * gain.log10())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        Accumulation, FilterPhase, InterpolationParameters, InterpolationType, SincStorage,
+    };
+    use crate::{FftFixedIn, FftFixedInOut, FftFixedOut, Resampler, TwoStageSincFixedIn};
+    use crate::{SincFixedIn, SincFixedOut, WindowFunction};
+
+    fn parameters(phase: FilterPhase, storage: SincStorage) -> InterpolationParameters {
+        InterpolationParameters {
+            sinc_len: 128,
+            f_cutoff: 0.9,
+            oversampling_factor: 64,
+            interpolation: InterpolationType::Linear,
+            window: WindowFunction::BlackmanHarris2,
+            phase,
+            storage,
+            accumulation: Accumulation::Native,
+        }
+    }
+
+    // Check that the passband is flat, and that the stopband starts above the cutoff.
+    fn check_response(response: &[(f64, f64)], passband: f64, stopband: f64, attenuation: f64) {
+        assert_eq!(response[0].0, 0.0);
+        assert!(response[0].1.abs() < 1e-9, "dc: {}", response[0].1);
+        for (freq, gain) in response.iter() {
+            if *freq < passband {
+                assert!(gain.abs() < 0.1, "gain {} dB at {}", gain, freq);
+            } else if *freq > stopband {
+                assert!(*gain < attenuation, "gain {} dB at {}", gain, freq);
+            }
+        }
+    }
+
+    #[test]
+    fn sinc_response() {
+        for ratio in [0.5, 1.2, 2.0].iter() {
+            let resampler = SincFixedIn::<f64>::new(
+                *ratio,
+                parameters(FilterPhase::Linear, SincStorage::Table),
+                1024,
+                1,
+            );
+            let response = resampler.frequency_response(101);
+            assert_eq!(response.len(), 101);
+            let last = response.last().unwrap().0;
+            assert!((last - ratio.max(1.0 / ratio)).abs() < 1e-12);
+            check_response(&response, 0.8, 1.1, -100.0);
+            assert!((resampler.group_delay() - 64.0).abs() < 0.1);
+
+            let resampler = SincFixedOut::<f32>::new(
+                *ratio,
+                parameters(FilterPhase::Linear, SincStorage::OnTheFly),
+                1024,
+                1,
+            );
+            check_response(&resampler.frequency_response(101), 0.8, 1.1, -100.0);
+            assert!((resampler.group_delay() - 64.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn minimum_phase_response() {
+        let linear = SincFixedIn::<f64>::new(
+            1.2,
+            parameters(FilterPhase::Linear, SincStorage::Table),
+            1024,
+            1,
+        );
+        let minimum = SincFixedIn::<f64>::new(
+            1.2,
+            parameters(FilterPhase::Minimum, SincStorage::Table),
+            1024,
+            1,
+        );
+        check_response(&minimum.frequency_response(101), 0.8, 1.1, -100.0);
+        assert!(minimum.group_delay() > 0.0);
+        assert!(minimum.group_delay() < linear.group_delay() / 2.0);
+    }
+
+    // The FFT resamplers include the whole delay of the filter in the output.
+    fn check_fft_response<T, R>(resampler: &R)
+    where
+        R: Resampler<T>,
+    {
+        let response = resampler.frequency_response(201);
+        check_response(&response, 0.8, 1.0, -100.0);
+        assert_eq!(response.last().unwrap().1, std::f64::NEG_INFINITY);
+        let delay = resampler.output_delay_fractional() / resampler.resample_ratio();
+        assert!((resampler.group_delay() - delay).abs() < 0.1);
+    }
+
+    #[test]
+    fn fft_response() {
+        check_fft_response(&FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1));
+        check_fft_response(&FftFixedOut::<f64>::new(48000, 44100, 1024, 2, 1));
+        check_fft_response(&FftFixedInOut::<f32>::new(44100, 48000, 1024, 1));
+        check_fft_response(&FftFixedInOut::<f64>::new(96000, 32000, 1024, 1));
+    }
+
+    #[test]
+    fn two_stage_response() {
+        for ratio in [1.0 / 6.5, 6.5].iter() {
+            let resampler = TwoStageSincFixedIn::<f64>::new(
+                *ratio,
+                parameters(FilterPhase::Linear, SincStorage::Table),
+                1024,
+                1,
+            );
+            let response = resampler.frequency_response(401);
+            check_response(&response, 0.7, 1.1, -100.0);
+            // The delay of the FFT stage adds to the 64 frames of the sinc stage.
+            assert!(resampler.group_delay() > 64.0);
+        }
+    }
+}
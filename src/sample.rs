@@ -1,4 +1,4 @@
-use crate::{AvxSample, NeonSample, SseSample};
+use crate::{AvxSample, NeonSample, SseSample, WasmSample};
 
 /// The trait governing a single sample.
 ///
@@ -24,7 +24,9 @@ where
         + AvxSample
         + SseSample
         + NeonSample
-        + Send,
+        + WasmSample
+        + Send
+        + Sync,
 {
     const PI: Self;
 
@@ -34,6 +36,12 @@ where
     /// Calculate the cosine of `self`.
     fn cos(self) -> Self;
 
+    /// Check that `self` is neither NaN nor infinite.
+    fn is_finite(self) -> bool;
+
+    /// Convert `self` to an f64.
+    fn to_f64(self) -> f64;
+
     /// Coerce `value` into the current type.
     ///
     /// Coercions are governed through the private `CoerceFrom` trait.
@@ -55,6 +63,14 @@ impl Sample for f32 {
     fn cos(self) -> Self {
         f32::cos(self)
     }
+
+    fn is_finite(self) -> bool {
+        f32::is_finite(self)
+    }
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
 }
 
 impl Sample for f64 {
@@ -67,6 +83,14 @@ impl Sample for f64 {
     fn cos(self) -> Self {
         f64::cos(self)
     }
+
+    fn is_finite(self) -> bool {
+        f64::is_finite(self)
+    }
+
+    fn to_f64(self) -> f64 {
+        self
+    }
 }
 
 /// The trait used to coerce a value infallibly from one type to another.
@@ -99,13 +123,13 @@ impl CoerceFrom<f64> for f32 {
 
 impl CoerceFrom<f64> for f64 {
     fn coerce_from(value: f64) -> Self {
-        value as f64
+        value
     }
 }
 
 impl CoerceFrom<f32> for f32 {
     fn coerce_from(value: f32) -> Self {
-        value as f32
+        value
     }
 }
 
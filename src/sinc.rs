@@ -1,5 +1,62 @@
-use crate::windows::{make_window, WindowFunction};
+use crate::error::ResamplerConstructionError;
+use crate::windows::{make_window, window_at, WindowFunction};
 use crate::Sample;
+use realfft::num_complex::Complex;
+use realfft::RealFftPlanner;
+
+/// The largest sinc length accepted for user supplied filters.
+pub const MAX_SINC_LEN: usize = 8192;
+
+/// The largest oversampling factor accepted for user supplied filters.
+pub const MAX_OVERSAMPLING_FACTOR: usize = 65536;
+
+/// The phase response of the sinc interpolation filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterPhase {
+    /// Linear phase, the usual symmetric windowed sinc.
+    /// All frequencies are delayed equally, by half the sinc length,
+    /// which the resampler needs to hold back as latency.
+    Linear,
+    /// Minimum phase, where the windowed sinc is converted to a filter with the same
+    /// magnitude response, but with most of its energy at the start.
+    /// This reduces the latency to a few frames, at the cost of a delay that varies
+    /// with frequency. Useful for live monitoring, where low latency matters most.
+    /// The first call to "process" then returns more frames, since fewer are held back.
+    Minimum,
+}
+
+impl Default for FilterPhase {
+    fn default() -> Self {
+        FilterPhase::Linear
+    }
+}
+
+/// How the sinc interpolation filters are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SincStorage {
+    /// Calculate all the oversampled sincs up front, and store them in a table.
+    /// This is the fastest option, but the table needs memory for
+    /// `sinc_len * oversampling_factor / 2` values, or twice that for minimum phase.
+    Table,
+    /// Calculate the values of the sincs when they are needed, instead of storing them.
+    /// This uses almost no memory, but is many times slower than using a table.
+    /// Useful on targets where memory is very limited.
+    /// Only linear phase sincs can be calculated this way,
+    /// minimum phase sincs are always stored in a table.
+    OnTheFly,
+}
+
+/// How the products of the sinc interpolation are summed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Accumulation {
+    /// Sum the products in the sample type. This is the fastest option.
+    Native,
+    /// Multiply and sum in f64, also for f32 samples.
+    /// With long sincs, this lowers the rounding noise of f32 processing,
+    /// at the cost of a slower interpolation.
+    /// It makes no difference for f64 samples.
+    Double,
+}
 
 /// Helper function: sinc(x) = sin(pi*x)/(pi*x)
 pub fn sinc<T>(value: T) -> T
@@ -13,7 +70,8 @@ where
     }
 }
 
-/// Helper function. Make a set of windowed sincs.  
+/// Helper function. Make a set of windowed sincs.
+/// The sincs are calculated in f64, and only rounded to the sample type at the end.
 pub fn make_sincs<T>(
     npoints: usize,
     factor: usize,
@@ -23,35 +81,308 @@ pub fn make_sincs<T>(
 where
     T: Sample,
 {
-    let totpoints = npoints * factor;
-    let mut y = Vec::with_capacity(totpoints);
+    let y = make_windowed_sinc::<f64>(npoints * factor, factor, f_cutoff, windowfunc);
+    split_sincs(&y, npoints, factor, true)
+}
+
+/// Helper function. Make a windowed sinc like `make_sincs` without oversampling,
+/// with a transition band shaped as a raised cosine of width `transition`, centered at the cutoff.
+/// The width is given relative to the Nyquist frequency, like the cutoff.
+/// A width of zero gives the same filter as `make_sincs`.
+pub fn make_sinc_with_transition<T>(
+    npoints: usize,
+    f_cutoff: f32,
+    transition: f32,
+    windowfunc: WindowFunction,
+) -> Vec<T>
+where
+    T: Sample,
+{
+    if transition == 0.0 {
+        return make_sincs(npoints, 1, f_cutoff, windowfunc).remove(0);
+    }
+    let y: Vec<f64> = make_windowed_sinc::<f64>(npoints, 1, f_cutoff, windowfunc)
+        .iter()
+        .enumerate()
+        .map(|(x, value)| {
+            let t = transition as f64 * (x as f64 - (npoints / 2) as f64);
+            // The limit of the shape where the denominator goes to zero.
+            let shape = if (1.0 - t * t).abs() < 1.0e-9 {
+                std::f64::consts::FRAC_PI_4
+            } else {
+                (std::f64::consts::FRAC_PI_2 * t).cos() / (1.0 - t * t)
+            };
+            value * shape
+        })
+        .collect();
+    split_sincs(&y, npoints, 1, true).remove(0)
+}
+
+/// Helper function. Make a set of windowed sincs with the given phase response.
+/// Returns the sincs, and the position of the center of the filter in frames,
+/// which is the point in the sincs that lines up with the interpolated sample.
+pub fn make_sincs_with_phase<T>(
+    npoints: usize,
+    factor: usize,
+    f_cutoff: f32,
+    windowfunc: WindowFunction,
+    phase: FilterPhase,
+) -> (Vec<Vec<T>>, usize)
+where
+    T: Sample,
+{
+    match phase {
+        FilterPhase::Linear => (
+            make_sincs(npoints, factor, f_cutoff, windowfunc),
+            npoints / 2,
+        ),
+        FilterPhase::Minimum => {
+            let y = make_windowed_sinc::<f64>(npoints * factor, factor, f_cutoff, windowfunc);
+            let y_min = minimum_phase(&y);
+            let (y_rev, center) = reverse_and_center(&y_min, factor);
+            debug!("Generate minimum phase sincs, center: {} frames", center);
+            (split_sincs(&y_rev, npoints, factor, true), center)
+        }
+    }
+}
+
+/// Helper function. Make a set of sincs from a user supplied filter.
+/// The filter is given as its impulse response, oversampled by `factor`,
+/// and its length must be a multiple of `8 * factor`.
+/// Returns the sincs, and the position of the center of the filter in frames.
+pub fn make_sincs_from_taps<T>(
+    taps: &[f64],
+    factor: usize,
+) -> Result<(Vec<Vec<T>>, usize), ResamplerConstructionError>
+where
+    T: Sample,
+{
+    if factor == 0 || factor > MAX_OVERSAMPLING_FACTOR {
+        return Err(ResamplerConstructionError::InvalidOversamplingFactor { factor });
+    }
+    let npoints = taps.len() / factor;
+    if taps.len() % factor != 0 || npoints % 8 != 0 || npoints == 0 || npoints > MAX_SINC_LEN {
+        return Err(ResamplerConstructionError::InvalidFilterLength {
+            length: taps.len(),
+            factor,
+        });
+    }
+    let sum: f64 = taps.iter().sum();
+    let abs_sum: f64 = taps.iter().map(|val| val.abs()).sum();
+    if !abs_sum.is_finite() || sum.abs() <= 1.0e-6 * abs_sum {
+        return Err(ResamplerConstructionError::InvalidFilterTaps);
+    }
+    let (y_rev, center) = reverse_and_center(taps, factor);
+    debug!("Generate sincs from taps, center: {} frames", center);
+    Ok((split_sincs(&y_rev, npoints, factor, false), center))
+}
+
+/// Reverse a filter in time, for storing as sincs, and shift it so that its center
+/// lands on a whole frame. The center is the delay of the filter at low frequencies.
+/// Returns the reversed filter, and the position of the center in frames.
+fn reverse_and_center(y: &[f64], factor: usize) -> (Vec<f64>, usize) {
+    let totpoints = y.len();
+    let sum: f64 = y.iter().sum();
+    let delay = y
+        .iter()
+        .enumerate()
+        .map(|(n, val)| n as f64 * val)
+        .sum::<f64>()
+        / sum;
+    let center = totpoints as f64 - 1.0 - delay.max(0.0).min(totpoints as f64 - 1.0);
+    let mut center_frames = (center / factor as f64).round() as usize;
+    let mut shift = (center - (center_frames * factor) as f64).round() as isize;
+    // Moving the center to a later frame drops the first points of the filter,
+    // only do that if they are negligible.
+    let limit = 1.0e-5 * y.iter().fold(0.0f64, |acc, val| acc.max(val.abs()));
+    if shift < 0 && y[..(-shift) as usize].iter().any(|val| val.abs() > limit) {
+        center_frames -= 1;
+        shift += factor as isize;
+    }
+    let mut y_rev = vec![0.0; totpoints];
+    for (n, val) in y_rev.iter_mut().enumerate() {
+        let idx = totpoints as isize - 1 - n as isize - shift;
+        if idx >= 0 && idx < totpoints as isize {
+            *val = y[idx as usize];
+        }
+    }
+    (y_rev, center_frames)
+}
+
+/// Make a single windowed sinc of `totpoints` points, with `factor` points per frame.
+/// The sinc is centered at point `totpoints / 2`, so the first point has no mirror image.
+/// It is set to zero, which makes the filter exactly symmetric, like a filter of
+/// `totpoints + 1` points with zeros at both ends.
+fn make_windowed_sinc<T>(
+    totpoints: usize,
+    factor: usize,
+    f_cutoff: f32,
+    windowfunc: WindowFunction,
+) -> Vec<T>
+where
+    T: Sample,
+{
     let window = make_window::<T>(totpoints, windowfunc);
-    let mut sum = T::zero();
-    for (x, w) in window.iter().enumerate().take(totpoints) {
-        let val = *w
-            * sinc(
+    window
+        .iter()
+        .enumerate()
+        .map(|(x, w)| {
+            if x == 0 {
+                return T::zero();
+            }
+            *w * sinc(
                 (T::coerce(x) - T::coerce(totpoints / 2)) * T::coerce(f_cutoff) / T::coerce(factor),
-            );
-        sum += val;
-        y.push(val);
+            )
+        })
+        .collect()
+}
+
+/// Helper function. Get point `x` of the windowed sinc made by `make_windowed_sinc`,
+/// without making the whole filter.
+pub fn windowed_sinc_at<T>(
+    x: usize,
+    totpoints: usize,
+    factor: usize,
+    f_cutoff: f32,
+    windowfunc: WindowFunction,
+) -> T
+where
+    T: Sample,
+{
+    if x == 0 {
+        return T::zero();
     }
-    sum /= T::coerce(factor);
+    window_at::<T>(x, totpoints, windowfunc)
+        * sinc((T::coerce(x) - T::coerce(totpoints / 2)) * T::coerce(f_cutoff) / T::coerce(factor))
+}
+
+/// Helper function. Get the sums of the points of each of the sincs made by `make_sincs`,
+/// before normalization. The sums are in the order of the points in the windowed sinc,
+/// meaning that the last one belongs to the first sinc.
+pub fn sinc_branch_sums(
+    npoints: usize,
+    factor: usize,
+    f_cutoff: f32,
+    windowfunc: WindowFunction,
+) -> Vec<f64> {
+    let totpoints = npoints * factor;
+    (0..factor)
+        .map(|n| {
+            (0..npoints).fold(0.0, |acc, p| {
+                acc + windowed_sinc_at::<f64>(
+                    factor * p + n,
+                    totpoints,
+                    factor,
+                    f_cutoff,
+                    windowfunc,
+                )
+            })
+        })
+        .collect()
+}
+
+/// Split an oversampled filter into `factor` sincs of `npoints` points,
+/// normalized to unity gain, and convert them to the sample type.
+/// With `each_branch`, every sinc is normalized separately so that its points sum to one.
+/// This gives exactly unity gain at DC for every position between the samples.
+/// Otherwise the filter is normalized as a whole.
+fn split_sincs<T>(y: &[f64], npoints: usize, factor: usize, each_branch: bool) -> Vec<Vec<T>>
+where
+    T: Sample,
+{
+    let total = y.iter().sum::<f64>() / factor as f64;
     debug!(
-        "Generate sincs, length: {}, oversampling: {}, normalized by: {:?}",
-        npoints, factor, sum
+        "Generate sincs, length: {}, oversampling: {}, normalized by: {:?}, per branch: {}",
+        npoints, factor, total, each_branch
     );
+    let sums: Vec<f64> = (0..factor)
+        .map(|n| {
+            if each_branch {
+                (0..npoints).fold(0.0, |acc, p| acc + y[factor * p + n])
+            } else {
+                total
+            }
+        })
+        .collect();
     let mut sincs = vec![vec![T::zero(); npoints]; factor];
     for p in 0..npoints {
         for n in 0..factor {
-            sincs[factor - n - 1][p] = y[factor * p + n] / sum;
+            sincs[factor - n - 1][p] = T::coerce(y[factor * p + n] / sums[n]);
         }
     }
     sincs
 }
 
+/// Get the subindex of the sinc that the given one is the mirror image of,
+/// or `None` if it isn't one of the mirrored ones.
+/// A linear phase table is symmetric, so that sinc `factor - 2 - n` is sinc `n` reversed,
+/// and only the sincs up to the middle, plus the last one, need to be stored.
+pub fn mirrored_subindex(subindex: usize, factor: usize) -> Option<usize> {
+    if subindex + 1 < factor && 2 * subindex + 2 > factor {
+        Some(factor - 2 - subindex)
+    } else {
+        None
+    }
+}
+
+/// Free the sincs of a symmetric table that are mirror images of others,
+/// see `mirrored_subindex`.
+pub fn drop_mirrored_sincs<T>(sincs: &mut [Vec<T>]) {
+    let factor = sincs.len();
+    for (subindex, sinc) in sincs.iter_mut().enumerate() {
+        if mirrored_subindex(subindex, factor).is_some() {
+            *sinc = Vec::new();
+        }
+    }
+}
+
+/// Convert a filter to minimum phase, keeping its magnitude response,
+/// using the real cepstrum.
+pub fn minimum_phase(y: &[f64]) -> Vec<f64> {
+    // Zero pad generously to limit the time aliasing of the cepstrum.
+    let fft_len = 8 * y.len().next_power_of_two();
+    let mut planner = RealFftPlanner::<f64>::new();
+    let fft = planner.plan_fft_forward(fft_len);
+    let ifft = planner.plan_fft_inverse(fft_len);
+    let mut time = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+
+    time[..y.len()].copy_from_slice(y);
+    fft.process(&mut time, &mut spectrum).unwrap();
+    let max_magnitude = spectrum.iter().fold(0.0f64, |acc, val| acc.max(val.norm()));
+    let floor = 1.0e-12 * max_magnitude;
+    for val in spectrum.iter_mut() {
+        *val = Complex::new(val.norm().max(floor).ln(), 0.0);
+    }
+    ifft.process(&mut spectrum, &mut time).unwrap();
+
+    // Fold the cepstrum onto positive times, which gives the minimum phase filter.
+    let scale = 1.0 / fft_len as f64;
+    for (n, val) in time.iter_mut().enumerate() {
+        if n == 0 || n == fft_len / 2 {
+            *val *= scale;
+        } else if n < fft_len / 2 {
+            *val *= 2.0 * scale;
+        } else {
+            *val = 0.0;
+        }
+    }
+    fft.process(&mut time, &mut spectrum).unwrap();
+    for val in spectrum.iter_mut() {
+        *val = val.exp();
+    }
+    ifft.process(&mut spectrum, &mut time).unwrap();
+    time.truncate(y.len());
+    for val in time.iter_mut() {
+        *val *= scale;
+    }
+    time
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::sinc::make_sincs;
+    use crate::sinc::{make_sincs, make_sincs_with_phase, mirrored_subindex, FilterPhase};
+    use crate::sinc::{sinc_branch_sums, windowed_sinc_at};
     use crate::WindowFunction;
 
     #[test]
@@ -61,4 +392,143 @@ mod tests {
         let sum: f64 = sincs.iter().map(|v| v.iter().sum::<f64>()).sum();
         assert!((sum - 8.0).abs() < 0.00001);
     }
+
+    #[test]
+    fn symmetric_sincs() {
+        for factor in [1, 2, 7, 16].iter() {
+            let sincs = make_sincs::<f64>(32, *factor, 0.9, WindowFunction::BlackmanHarris2);
+            let mut nbr_stored = 0;
+            for (subindex, sinc) in sincs.iter().enumerate() {
+                match mirrored_subindex(subindex, *factor) {
+                    Some(stored) => {
+                        assert!(mirrored_subindex(stored, *factor).is_none());
+                        for (val, mirrored) in sinc.iter().zip(sincs[stored].iter().rev()) {
+                            assert!((val - mirrored).abs() < 1.0e-12);
+                        }
+                    }
+                    None => nbr_stored += 1,
+                }
+            }
+            assert_eq!(nbr_stored, factor / 2 + 1);
+        }
+    }
+
+    // The points of a linear phase filter at the same distance on either side of the center
+    // are exactly equal. The first point has no mirror image, and must be zero.
+    #[test]
+    fn symmetric_taps() {
+        let windows = [
+            WindowFunction::Hann2,
+            WindowFunction::BlackmanHarris2,
+            WindowFunction::FlatTop,
+            WindowFunction::Kaiser(10.0),
+            WindowFunction::Tukey(0.5),
+        ];
+        for window in windows.iter() {
+            for factor in [1, 7, 16].iter() {
+                let totpoints = 64 * factor;
+                let center = totpoints / 2;
+                let first = windowed_sinc_at::<f64>(0, totpoints, *factor, 0.9, *window);
+                assert_eq!(first, 0.0);
+                let taps = make_sincs::<f64>(64, *factor, 0.9, *window);
+                assert_eq!(taps[factor - 1][0], 0.0);
+                for distance in 1..center {
+                    assert_eq!(
+                        windowed_sinc_at::<f64>(
+                            center - distance,
+                            totpoints,
+                            *factor,
+                            0.9,
+                            *window
+                        ),
+                        windowed_sinc_at::<f64>(
+                            center + distance,
+                            totpoints,
+                            *factor,
+                            0.9,
+                            *window
+                        )
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sincs_f32() {
+        for phase in [FilterPhase::Linear, FilterPhase::Minimum].iter() {
+            let window = WindowFunction::BlackmanHarris2;
+            let (sincs_32, _) = make_sincs_with_phase::<f32>(64, 16, 0.95, window, *phase);
+            let (sincs_64, _) = make_sincs_with_phase::<f64>(64, 16, 0.95, window, *phase);
+            for (sinc_32, sinc_64) in sincs_32.iter().zip(sincs_64.iter()) {
+                for (val_32, val_64) in sinc_32.iter().zip(sinc_64.iter()) {
+                    assert!(
+                        (*val_32 as f64 - val_64).abs() <= std::f32::EPSILON as f64 * val_64.abs()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sincs_at() {
+        let window = WindowFunction::Hann2;
+        let sincs = make_sincs::<f32>(16, 6, 0.9, window);
+        let sums = sinc_branch_sums(16, 6, 0.9, window);
+        for (subindex, sinc) in sincs.iter().enumerate() {
+            for (p, val) in sinc.iter().enumerate() {
+                let x = 6 * p + 5 - subindex;
+                let expected = windowed_sinc_at::<f64>(x, 96, 6, 0.9, window) / sums[5 - subindex];
+                assert_eq!(*val, expected as f32);
+            }
+        }
+    }
+
+    // The magnitude response at a few frequencies, from the sincs of the first subindex.
+    fn magnitudes(sinc: &[f64]) -> Vec<f64> {
+        [0.0, 0.1, 0.2, 0.3, 0.4]
+            .iter()
+            .map(|freq| {
+                let omega = 2.0 * std::f64::consts::PI * freq;
+                let (re, im) = sinc.iter().enumerate().fold((0.0, 0.0), |acc, (n, val)| {
+                    (
+                        acc.0 + val * (omega * n as f64).cos(),
+                        acc.1 + val * (omega * n as f64).sin(),
+                    )
+                });
+                (re * re + im * im).sqrt()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn minimum_phase_sincs() {
+        let (linear, linear_center) = make_sincs_with_phase::<f64>(
+            64,
+            16,
+            0.9,
+            WindowFunction::BlackmanHarris2,
+            FilterPhase::Linear,
+        );
+        let (minimum, minimum_center) = make_sincs_with_phase::<f64>(
+            64,
+            16,
+            0.9,
+            WindowFunction::BlackmanHarris2,
+            FilterPhase::Minimum,
+        );
+        assert_eq!(linear_center, 32);
+        // most of the filter is in the last frames, which are the most recent ones
+        assert!(minimum_center > 52);
+        let sum: f64 = minimum.iter().map(|v| v.iter().sum::<f64>()).sum();
+        assert!((sum - 16.0).abs() < 0.00001);
+        // the magnitude responses agree within 1 dB
+        for (lin, min) in magnitudes(&linear[8])
+            .iter()
+            .zip(magnitudes(&minimum[8]).iter())
+        {
+            let diff_db = 20.0 * (min / lin).log10();
+            assert!(diff_db.abs() < 1.0, "{} != {}", lin, min);
+        }
+    }
 }
@@ -1,18 +1,243 @@
-use crate::sinc::make_sincs;
+use crate::sinc::{make_sinc_with_transition, minimum_phase, FilterPhase};
 use crate::windows::WindowFunction;
 use num_complex::Complex;
 use num_integer as integer;
 use num_traits::Zero;
+use std::fmt;
+use std::mem;
 use std::sync::Arc;
 
-use crate::error::{ResampleError, ResampleResult};
-use crate::{Resampler, Sample};
+use crate::buffers::validate_sequential_to_interleaved;
+use crate::buffers::{fill_inactive, validate_finite, validate_partial, validate_sequential};
+use crate::buffers::{resize_channels, resize_mask, InputBuffer, OutputBuffer};
+use crate::buffers::{store_mask, update_mask, update_mask_from_buffers, validate_interleaved};
+use crate::buffers::{DelayTrim, TrimmedOutput};
+use crate::buffers::{InterleavedInput, InterleavedOutput, SequentialInput, SequentialOutput};
+use crate::builder::Quality;
+use crate::denormals::DenormalGuard;
+use crate::error::{ResampleError, ResampleResult, ResamplerConstructionError};
+use crate::response::{frequency_response, FilterResponse};
+use crate::{process_final, InactiveOutput, Resampler, Sample, SincFixedBoth};
 use realfft::{ComplexToReal, RealFftPlanner, RealToComplex};
 
+/// How the FFT resamplers split the input into blocks, and join the resampled blocks.
+///
+/// The chunk sizes are the same for both, but the delay differs, see
+/// [output_delay](Resampler::output_delay).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FftOverlap {
+    /// Each block is zero padded to twice its length and multiplied by the spectrum of the
+    /// antialiasing filter, and the outputs of consecutive blocks are added where they overlap.
+    /// This is a linear convolution with the filter, which is a sinc as long as a block.
+    /// The delay is half a block for a linear phase filter,
+    /// and only a few frames for a minimum phase filter, see [FftFilter::phase].
+    Filtered,
+    /// The blocks overlap by half, and are weighted by a square root Hann window
+    /// both before the FFT and after the inverse FFT. The two windows multiply to a Hann window,
+    /// and the overlapping Hann windows sum to one.
+    /// The spectrum is cut at the lower Nyquist frequency instead of being filtered,
+    /// so the cutoff and transition of the filter are not used.
+    /// Ringing around a transient is confined to the blocks it's in, but aliasing
+    /// is suppressed less than by the filter. At a ratio of 1:1 the input is reconstructed exactly.
+    /// The delay is a whole block, twice that of [Filtered](FftOverlap::Filtered)
+    /// with a linear phase filter.
+    SqrtHann,
+}
+
+impl Default for FftOverlap {
+    fn default() -> Self {
+        FftOverlap::Filtered
+    }
+}
+
+/// The antialiasing filter of the FFT resamplers.
+///
+/// The filter is a sinc, windowed over the length of the FFT.
+/// Both the cutoff and the transition width are given relative to the lower of the
+/// two Nyquist frequencies, which is the output Nyquist frequency when downsampling.
+/// The default places the cutoff as close to the Nyquist frequency as the filter length allows,
+/// with the sharpest transition the window gives, and uses a linear phase filter
+/// with [FftOverlap::Filtered].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct FftFilter {
+    /// The cutoff, or `None` for the default.
+    /// The response is down by 6 dB at the cutoff.
+    pub cutoff: Option<f32>,
+    /// The width of a raised cosine transition band centered at the cutoff.
+    /// A wider transition gives less ringing. Zero gives only the transition of the window.
+    pub transition: f32,
+    /// How the blocks overlap.
+    pub overlap: FftOverlap,
+    /// The phase response of the filter.
+    /// With [FilterPhase::Minimum], the sinc is converted to a minimum phase filter with
+    /// the same magnitude response. This reduces the delay from half a block to a few frames,
+    /// at the cost of a delay that varies with frequency.
+    /// It's only used with [FftOverlap::Filtered].
+    pub phase: FilterPhase,
+}
+
+impl FftFilter {
+    /// Check that the cutoff is in the range (0, 1],
+    /// and that the transition band doesn't extend below zero.
+    fn validate(&self) -> Result<(), ResamplerConstructionError> {
+        let cutoff = self.cutoff.unwrap_or(1.0);
+        if cutoff > 0.0
+            && cutoff <= 1.0
+            && self.transition >= 0.0
+            && self.transition <= 2.0 * cutoff
+        {
+            Ok(())
+        } else {
+            Err(ResamplerConstructionError::UnachievableFilterSpec)
+        }
+    }
+}
+
+/// Find the largest number of FFT blocks per FFT for which the latency stays within
+/// `max_latency` output frames. A block is the smallest chunk that the sample rates allow,
+/// and the latency is the duration of a chunk of `sub_chunks` FFTs plus the output delay.
+fn fft_chunks_for_latency(
+    fs_in: usize,
+    fs_out: usize,
+    sub_chunks: usize,
+    max_latency: usize,
+) -> Result<usize, ResamplerConstructionError> {
+    if fs_in == 0 || fs_out == 0 {
+        return Err(ResamplerConstructionError::InvalidSampleRate { fs_in, fs_out });
+    }
+    if sub_chunks == 0 {
+        return Err(ResamplerConstructionError::InvalidChunkSize);
+    }
+    let gcd = integer::gcd(fs_in, fs_out);
+    let block_in = fs_in / gcd;
+    let block_out = fs_out / gcd;
+    // The duration of a chunk plus the output delay, as given by the FftResampler.
+    let latency = |fft_chunks: usize| {
+        (sub_chunks * fft_chunks * block_out) as f64
+            + (fft_chunks * block_in / 2) as f64 * block_out as f64 / block_in as f64
+    };
+    if latency(1) > max_latency as f64 {
+        return Err(ResamplerConstructionError::LatencyTooLow {
+            max_latency,
+            min_latency: latency(1).ceil() as usize,
+        });
+    }
+    // The delay is at most half a block per FFT block, which gives a safe starting point.
+    let per_chunk = (sub_chunks as f64 + 0.5) * block_out as f64;
+    let mut fft_chunks = ((max_latency as f64 / per_chunk) as usize).max(1);
+    while latency(fft_chunks + 1) <= max_latency as f64 {
+        fft_chunks += 1;
+    }
+    Ok(fft_chunks)
+}
+
+/// Rate pairs where the smallest chunk is longer than this, at either rate,
+/// are converted in two stages by [FftFixedInOut::new],
+/// if both stages then have smallest chunks no longer than this.
+const MAX_SINGLE_STAGE_BLOCK: usize = 256;
+
+/// The shortest FFT input, in frames, that a stage of a two stage [FftFixedInOut] uses,
+/// unless the whole chunk is shorter. The filter of a shorter FFT would have a wider transition.
+const MIN_STAGE_FFT_SIZE: usize = 512;
+
+/// Get all the divisors of a value, in increasing order.
+fn divisors(value: usize) -> Vec<usize> {
+    (1..=value).filter(|n| value % n == 0).collect()
+}
+
+/// Split a conversion from `block_in` to `block_out` frames, where the two have no
+/// common factor, into two stages with smaller blocks.
+/// The intermediate rate is never lower than the lower of the two rates,
+/// so that no bandwidth is lost between the stages.
+/// Of the splits with the smallest largest block, the one with the lowest intermediate rate
+/// is used. Returns the input and output blocks of the two stages, or `None` if the blocks
+/// are small enough for a single stage, or if no split gives small enough blocks.
+fn split_blocks(block_in: usize, block_out: usize) -> Option<((usize, usize), (usize, usize))> {
+    if block_in.max(block_out) <= MAX_SINGLE_STAGE_BLOCK {
+        return None;
+    }
+    let mut best: Option<((usize, usize), (usize, usize))> = None;
+    let mut best_size = MAX_SINGLE_STAGE_BLOCK + 1;
+    let divisors_in = divisors(block_in);
+    for first_out in divisors(block_out) {
+        for first_in in divisors_in.iter().copied() {
+            let second_in = block_in / first_in;
+            let second_out = block_out / first_out;
+            // The blocks have no common factor, so a stage only keeps the rate when both are 1.
+            if first_in == first_out || second_in == second_out {
+                continue;
+            }
+            // The intermediate rate must be at least the input rate or the output rate.
+            if first_out < first_in && first_out * block_in < first_in * block_out {
+                continue;
+            }
+            let size = first_in.max(first_out).max(second_in).max(second_out);
+            let lower_rate = match best {
+                Some(((best_in, best_out), _)) => first_out * best_in < best_out * first_in,
+                None => true,
+            };
+            if size < best_size || (size == best_size && lower_rate) {
+                best = Some(((first_in, first_out), (second_in, second_out)));
+                best_size = size;
+            }
+        }
+    }
+    best
+}
+
+/// Find the FFT sizes of the two stages of a [FftFixedInOut] for chunks of `fft_chunks`
+/// of the smallest chunks that the rates allow, or `None` if a single stage is used.
+/// Each stage splits the chunk into the shortest FFTs that are at least [MIN_STAGE_FFT_SIZE]
+/// frames long at the input and fit a whole number of times in the chunk.
+fn two_stage_sizes(
+    block_in: usize,
+    block_out: usize,
+    fft_chunks: usize,
+) -> Option<((usize, usize), (usize, usize))> {
+    let ((first_in, first_out), (second_in, second_out)) = split_blocks(block_in, block_out)?;
+    let stage_blocks = |blocks_per_chunk: usize, stage_block_in: usize| {
+        divisors(blocks_per_chunk)
+            .into_iter()
+            .find(|n| n * stage_block_in >= MIN_STAGE_FFT_SIZE)
+            .unwrap_or(blocks_per_chunk)
+    };
+    // A chunk holds `fft_chunks * second_in` blocks of the first stage,
+    // which give `fft_chunks * first_out` blocks of the second stage.
+    let first_blocks = stage_blocks(fft_chunks * second_in, first_in);
+    let second_blocks = stage_blocks(fft_chunks * first_out, second_in);
+    Some((
+        (first_blocks * first_in, first_blocks * first_out),
+        (second_blocks * second_in, second_blocks * second_out),
+    ))
+}
+
+/// Make the taps of the antialiasing filter, a windowed sinc of `len` taps
+/// with the given phase response.
+fn make_filter_taps(len: usize, cutoff: f32, transition: f32, phase: FilterPhase) -> Vec<f64> {
+    let sinc =
+        make_sinc_with_transition::<f64>(len, cutoff, transition, WindowFunction::BlackmanHarris2);
+    match phase {
+        FilterPhase::Linear => sinc,
+        FilterPhase::Minimum => minimum_phase(&sinc),
+    }
+}
+
+/// Make a periodic square root Hann window of length `len`.
+/// Two of them overlapping by half multiply to Hann windows that sum to one.
+fn sqrt_hann<T: Sample>(len: usize) -> Vec<T> {
+    (0..len)
+        .map(|n| T::coerce((std::f64::consts::PI * n as f64 / len as f64).sin()))
+        .collect()
+}
+
 /// A helper for resampling a single chunk of data.
+#[derive(Clone)]
 struct FftResampler<T> {
     fft_size_in: usize,
     fft_size_out: usize,
+    filter_spec: FftFilter,
+    cutoff: f32,
+    transition: f32,
     filter_f: Vec<Complex<T>>,
     fft: Arc<dyn RealToComplex<T>>,
     ifft: Arc<dyn ComplexToReal<T>>,
@@ -22,6 +247,15 @@ struct FftResampler<T> {
     input_f: Vec<Complex<T>>,
     output_f: Vec<Complex<T>>,
     output_buf: Vec<T>,
+    /// The delay of the filter at low frequencies, in input frames.
+    delay: f64,
+    /// The analysis and synthesis windows for [FftOverlap::SqrtHann], otherwise empty.
+    window_in: Vec<T>,
+    window_out: Vec<T>,
+    /// The gain applied on top of the unity gain of the filter.
+    gain: f64,
+    /// The resampler for the FFT sizes used before the current ones.
+    previous: Option<Box<FftResampler<T>>>,
 }
 
 /// A synchronous resampler that needs a fixed number of audio frames for input
@@ -30,15 +264,30 @@ struct FftResampler<T> {
 /// The resampling is done by FFT:ing the input data. The spectrum is then extended or
 /// truncated as well as multiplied with an antialiasing filter
 /// before it's inverse transformed to get the resampled waveforms.
+///
+/// A clone starts from the current processing state of the original,
+/// and shares the FFT plans with it.
+#[derive(Clone)]
 pub struct FftFixedIn<T> {
     nbr_channels: usize,
     chunk_size_in: usize,
+    sub_chunks: usize,
     fft_size_in: usize,
     fft_size_out: usize,
+    frames_max_seen: (usize, usize),
     overlaps: Vec<Vec<T>>,
     input_buffers: Vec<Vec<T>>,
     saved_frames: usize,
     resampler: FftResampler<T>,
+    channel_mask: Vec<bool>,
+    active_channels_mask: Vec<bool>,
+    inactive_output: InactiveOutput,
+    validate_input: bool,
+    flush_denormals: bool,
+    trim: DelayTrim,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
+    output_frames_total: u64,
 }
 
 /// A synchronous resampler that needs a varying number of audio frames for input
@@ -47,16 +296,31 @@ pub struct FftFixedIn<T> {
 /// The resampling is done by FFT:ing the input data. The spectrum is then extended or
 /// truncated as well as multiplied with an antialiasing filter
 /// before it's inverse transformed to get the resampled waveforms.
+///
+/// A clone starts from the current processing state of the original,
+/// and shares the FFT plans with it.
+#[derive(Clone)]
 pub struct FftFixedOut<T> {
     nbr_channels: usize,
     chunk_size_out: usize,
+    sub_chunks: usize,
     fft_size_in: usize,
     fft_size_out: usize,
+    frames_max_seen: (usize, usize),
     overlaps: Vec<Vec<T>>,
     output_buffers: Vec<Vec<T>>,
     saved_frames: usize,
     frames_needed: usize,
     resampler: FftResampler<T>,
+    channel_mask: Vec<bool>,
+    active_channels_mask: Vec<bool>,
+    inactive_output: InactiveOutput,
+    validate_input: bool,
+    flush_denormals: bool,
+    trim: DelayTrim,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
+    output_frames_total: u64,
 }
 
 /// A synchronous resampler that accepts a fixed number of audio frames for input
@@ -65,52 +329,187 @@ pub struct FftFixedOut<T> {
 /// The resampling is done by FFT:ing the input data. The spectrum is then extended or
 /// truncated as well as multiplied with an antialiasing filter
 /// before it's inverse transformed to get the resampled waveforms.
+///
+/// A clone starts from the current processing state of the original,
+/// and shares the FFT plans with it.
+#[derive(Clone)]
 pub struct FftFixedInOut<T> {
     nbr_channels: usize,
+    chunk_size_wanted: usize,
     chunk_size_in: usize,
     chunk_size_out: usize,
-    fft_size_in: usize,
+    frames_max_seen: (usize, usize),
     overlaps: Vec<Vec<T>>,
     resampler: FftResampler<T>,
+    second: Option<SecondStage<T>>,
+    fine: Option<FineAdjust<T>>,
+    channel_mask: Vec<bool>,
+    active_channels_mask: Vec<bool>,
+    inactive_output: InactiveOutput,
+    validate_input: bool,
+    flush_denormals: bool,
+    trim: DelayTrim,
+    input_frames_total: u64,
+    output_frames_final: Option<u64>,
+    output_frames_total: u64,
+}
+
+/// The second stage of a [FftFixedInOut] that converts in two stages.
+#[derive(Clone)]
+struct SecondStage<T> {
+    resampler: FftResampler<T>,
+    overlaps: Vec<Vec<T>>,
+    /// The output of the first stage for one chunk of one channel.
+    buffer_mid: Vec<T>,
+}
+
+impl<T> SecondStage<T>
+where
+    T: Sample,
+{
+    fn new(
+        fft_size_in: usize,
+        fft_size_out: usize,
+        chunk_size_mid: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+        planner: &mut RealFftPlanner<T>,
+    ) -> Self {
+        let resampler = FftResampler::new_with_filter(fft_size_in, fft_size_out, filter, planner);
+        SecondStage {
+            overlaps: vec![vec![T::zero(); resampler.overlap_len()]; nbr_channels],
+            resampler,
+            buffer_mid: vec![T::zero(); chunk_size_mid],
+        }
+    }
+}
+
+/// Clear the overlaps of both stages of a [FftFixedInOut] for one channel.
+fn clear_overlaps<T: Sample>(
+    overlaps: &mut [Vec<T>],
+    second: &mut Option<SecondStage<T>>,
+    chan: usize,
+) {
+    overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+    if let Some(second) = second.as_mut() {
+        second.overlaps[chan]
+            .iter_mut()
+            .for_each(|x| *x = T::zero());
+    }
+}
+
+/// The stage that applies small adjustments of the ratio to the output of [FftFixedInOut].
+#[derive(Clone)]
+struct FineAdjust<T> {
+    resampler: SincFixedBoth<T>,
+    buffer_in: Vec<Vec<T>>,
+    buffer_out: Vec<Vec<T>>,
+}
+
+impl<T> FineAdjust<T>
+where
+    T: Sample,
+{
+    /// Create a fine adjustment stage for output chunks of `chunk_size` frames.
+    fn new(
+        resample_ratio: f64,
+        max_resample_ratio_relative: f64,
+        chunk_size: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let resampler = SincFixedBoth::new(
+            resample_ratio,
+            max_resample_ratio_relative,
+            Quality::High.interpolation_parameters(),
+            chunk_size,
+            chunk_size,
+            nbr_channels,
+        )?;
+        Ok(FineAdjust {
+            resampler,
+            buffer_in: vec![vec![T::zero(); chunk_size]; nbr_channels],
+            buffer_out: vec![vec![T::zero(); chunk_size]; nbr_channels],
+        })
+    }
 }
 
 impl<T> FftResampler<T>
 where
     T: Sample,
 {
-    //
-    pub fn new(fft_size_in: usize, fft_size_out: usize) -> Self {
+    /// Create a resampler with the given antialiasing filter,
+    /// using `planner` to plan the FFTs.
+    pub fn new_with_filter(
+        fft_size_in: usize,
+        fft_size_out: usize,
+        filter: FftFilter,
+        planner: &mut RealFftPlanner<T>,
+    ) -> Self {
+        let relative_cutoff = filter
+            .cutoff
+            .unwrap_or_else(|| 0.4f32.powf(16.0 / fft_size_in as f32));
         // calculate antialiasing cutoff
-        let cutoff = if fft_size_in > fft_size_out {
-            0.4f32.powf(16.0 / fft_size_in as f32) * fft_size_out as f32 / fft_size_in as f32
+        let (cutoff, transition) = if fft_size_in > fft_size_out {
+            let scale = fft_size_out as f32 / fft_size_in as f32;
+            (relative_cutoff * scale, filter.transition * scale)
         } else {
-            0.4f32.powf(16.0 / fft_size_in as f32)
+            (relative_cutoff, filter.transition)
         };
         debug!(
-            "Create new FftResampler, fft_size_in: {}, fft_size_out: {}, cutoff: {}",
-            fft_size_in, fft_size_out, cutoff
+            "Create new FftResampler, fft_size_in: {}, fft_size_out: {}, cutoff: {}, transition: {}",
+            fft_size_in, fft_size_out, cutoff, transition
         );
-        let sinc = make_sincs::<T>(fft_size_in, 1, cutoff, WindowFunction::BlackmanHarris2);
         let mut filter_t: Vec<T> = vec![T::zero(); 2 * fft_size_in];
         let mut filter_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_in + 1];
-        for (n, f) in filter_t.iter_mut().enumerate().take(fft_size_in) {
-            *f = sinc[0][n] / T::coerce(2 * fft_size_in);
-        }
+        let (delay, window_in, window_out) = match filter.overlap {
+            FftOverlap::Filtered => {
+                let taps = make_filter_taps(fft_size_in, cutoff, transition, filter.phase);
+                for (f, tap) in filter_t.iter_mut().zip(taps.iter()) {
+                    *f = T::coerce(*tap);
+                }
+                let delay = match filter.phase {
+                    FilterPhase::Linear => (fft_size_in / 2) as f64,
+                    FilterPhase::Minimum => {
+                        let weighted: f64 =
+                            taps.iter().enumerate().map(|(n, t)| n as f64 * t).sum();
+                        weighted / taps.iter().sum::<f64>()
+                    }
+                };
+                (delay, Vec::new(), Vec::new())
+            }
+            FftOverlap::SqrtHann => {
+                // A unit impulse gives a flat spectrum.
+                filter_t[0] = T::one();
+                (
+                    fft_size_in as f64,
+                    sqrt_hann(2 * fft_size_in),
+                    sqrt_hann(2 * fft_size_out),
+                )
+            }
+        };
 
         let input_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_in + 1];
         let input_buf: Vec<T> = vec![T::zero(); 2 * fft_size_in];
         let output_f: Vec<Complex<T>> = vec![Complex::zero(); fft_size_out + 1];
         let output_buf: Vec<T> = vec![T::zero(); 2 * fft_size_out];
-        let mut planner = RealFftPlanner::<T>::new();
         let fft = planner.plan_fft_forward(2 * fft_size_in);
         let ifft = planner.plan_fft_inverse(2 * fft_size_out);
         fft.process(&mut filter_t, &mut filter_f).unwrap();
+        // The forward and inverse FFTs are unnormalized, and together scale by `2 * fft_size_in`.
+        // Scale the filter to cancel that, and to give it exactly unity gain at DC.
+        let normalization = T::one() / (filter_f[0].re * T::coerce(2 * fft_size_in));
+        for f in filter_f.iter_mut() {
+            *f = f.scale(normalization);
+        }
         let scratch_fw = fft.make_scratch_vec();
         let scratch_inv = ifft.make_scratch_vec();
 
         FftResampler {
             fft_size_in,
             fft_size_out,
+            filter_spec: filter,
+            cutoff,
+            transition,
             filter_f,
             fft,
             ifft,
@@ -120,20 +519,130 @@ where
             input_f,
             output_f,
             output_buf,
+            delay,
+            window_in,
+            window_out,
+            gain: 1.0,
+            previous: None,
         }
     }
 
-    /// Resample a small chunk
-    fn resample_unit(&mut self, wave_in: &[T], wave_out: &mut [T], overlap: &mut [T]) {
-        // Copy to input buffer and clear padding area
-        self.input_buf[0..self.fft_size_in].copy_from_slice(wave_in);
-        for item in self
-            .input_buf
-            .iter_mut()
-            .skip(self.fft_size_in)
-            .take(self.fft_size_in)
-        {
-            *item = T::zero();
+    /// Change the FFT sizes, keeping the antialiasing filter specification.
+    /// The resampler for the current sizes is kept, and is swapped back in
+    /// without allocating if its sizes are asked for again.
+    fn set_sizes(&mut self, fft_size_in: usize, fft_size_out: usize) {
+        if fft_size_in == self.fft_size_in && fft_size_out == self.fft_size_out {
+            return;
+        }
+        let gain = self.gain;
+        match self.previous.take() {
+            Some(mut previous)
+                if previous.fft_size_in == fft_size_in && previous.fft_size_out == fft_size_out =>
+            {
+                mem::swap(self, &mut *previous);
+                self.previous = Some(previous);
+            }
+            previous => {
+                let next = FftResampler::new_with_filter(
+                    fft_size_in,
+                    fft_size_out,
+                    self.filter_spec,
+                    &mut RealFftPlanner::new(),
+                );
+                let current = mem::replace(self, next);
+                match previous {
+                    Some(mut previous) => {
+                        *previous = current;
+                        self.previous = Some(previous);
+                    }
+                    None => self.previous = Some(Box::new(current)),
+                }
+            }
+        }
+        self.gain = gain;
+    }
+
+    /// Set the gain, after checking that it's a finite number of at least zero.
+    fn set_gain(&mut self, gain: f64) -> ResampleResult<()> {
+        if !(gain >= 0.0 && gain.is_finite()) {
+            return Err(ResampleError::BadGainUpdate);
+        }
+        self.gain = gain;
+        Ok(())
+    }
+
+    /// Get the length of the state kept for each channel between blocks.
+    /// This is the overlapping part of the output, followed by the previous input block
+    /// when the input blocks overlap as well.
+    fn overlap_len(&self) -> usize {
+        match self.filter_spec.overlap {
+            FftOverlap::Filtered => self.fft_size_out,
+            FftOverlap::SqrtHann => self.fft_size_out + self.fft_size_in,
+        }
+    }
+
+    /// Get the delay in output frames, which is the delay of the filter scaled by the resample ratio.
+    /// A linear phase filter is a sinc centered at frame `fft_size_in / 2`, and the delay of
+    /// a minimum phase filter is the centroid of its taps.
+    /// With overlapping windows, a block is output once the next block has been added to it.
+    fn output_delay(&self) -> f64 {
+        self.delay * self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    /// Get the response of the antialiasing filter.
+    /// The filter is applied by multiplying the spectrum, so the taps are reversed to put
+    /// the oldest frame first. The bins above the lower Nyquist frequency are dropped.
+    /// With overlapping windows, the response is flat up to the lower Nyquist frequency,
+    /// with a delay of one block.
+    fn filter_response(&self) -> FilterResponse {
+        let taps = match self.filter_spec.overlap {
+            FftOverlap::Filtered => make_filter_taps(
+                self.fft_size_in,
+                self.cutoff,
+                self.transition,
+                self.filter_spec.phase,
+            )
+            .iter()
+            .rev()
+            .copied()
+            .collect(),
+            FftOverlap::SqrtHann => {
+                let mut taps = vec![0.0; self.fft_size_in + 1];
+                taps[0] = 1.0;
+                taps
+            }
+        };
+        let bandwidth = 0.5 * (self.fft_size_out as f64 / self.fft_size_in as f64).min(1.0);
+        FilterResponse::new(taps, 1, bandwidth)
+    }
+
+    /// Resample the small chunk stored in the first `fft_size_in` frames of the input buffer.
+    /// The result is left in the first `fft_size_out` frames of the output buffer.
+    /// The `overlap` holds the state of the channel, see [overlap_len](Self::overlap_len).
+    fn resample_input_buf(&mut self, overlap: &mut [T]) {
+        let (overlap, previous_in) = overlap.split_at_mut(self.fft_size_out);
+        match self.filter_spec.overlap {
+            FftOverlap::Filtered => {
+                // Clear padding area
+                for item in self
+                    .input_buf
+                    .iter_mut()
+                    .skip(self.fft_size_in)
+                    .take(self.fft_size_in)
+                {
+                    *item = T::zero();
+                }
+            }
+            FftOverlap::SqrtHann => {
+                // Put the new block after the previous one, and apply the analysis window
+                self.input_buf
+                    .copy_within(0..self.fft_size_in, self.fft_size_in);
+                self.input_buf[0..self.fft_size_in].copy_from_slice(previous_in);
+                previous_in.copy_from_slice(&self.input_buf[self.fft_size_in..]);
+                for (item, w) in self.input_buf.iter_mut().zip(self.window_in.iter()) {
+                    *item *= *w;
+                }
+            }
         }
 
         // FFT and store result in history, update index
@@ -142,12 +651,13 @@ where
             .unwrap();
 
         // multiply with filter FT
+        let gain = T::coerce(self.gain);
         self.input_f
             .iter_mut()
             .take(self.fft_size_in + 1)
             .zip(self.filter_f.iter())
-            .for_each(|(spec, filt)| *spec *= filt);
-        let new_len = if self.fft_size_in < self.fft_size_out {
+            .for_each(|(spec, filt)| *spec *= filt.scale(gain));
+        let new_len = if self.fft_size_in <= self.fft_size_out {
             self.fft_size_in + 1
         } else {
             self.fft_size_out
@@ -158,7 +668,16 @@ where
         for val in self.output_f[new_len..].iter_mut() {
             *val = Complex::zero();
         }
-        //self.output_f[self.fft_size_out] = self.input_f[self.fft_size_in];
+        // The input Nyquist bin stands for both the positive and the negative frequency,
+        // but the longer inverse FFT counts it once for each. Halve it to keep the gain.
+        if self.fft_size_in < self.fft_size_out {
+            self.output_f[self.fft_size_in] = self.output_f[self.fft_size_in].scale(T::coerce(0.5));
+        }
+        // The first and last bins of a real signal are real, but their imaginary parts
+        // are NaN if the input is not finite, which the inverse FFT rejects.
+        let last = self.output_f.len() - 1;
+        self.output_f[0].im = T::zero();
+        self.output_f[last].im = T::zero();
 
         // IFFT result, store result and overlap
         self.ifft
@@ -168,8 +687,11 @@ where
                 &mut self.scratch_inv,
             )
             .unwrap();
-        for (n, item) in wave_out.iter_mut().enumerate().take(self.fft_size_out) {
-            *item = self.output_buf[n] + overlap[n];
+        for (item, w) in self.output_buf.iter_mut().zip(self.window_out.iter()) {
+            *item *= *w;
+        }
+        for (item, ovl) in self.output_buf.iter_mut().zip(overlap.iter_mut()) {
+            *item += *ovl;
         }
         overlap.copy_from_slice(&self.output_buf[self.fft_size_out..]);
     }
@@ -186,7 +708,89 @@ where
     /// - `fs_out`: Output sample rate.
     /// - `chunk_size_in`: desired length of input data in frames, actual value may be different.
     /// - `nbr_channels`: number of channels in input/output.
+    ///
+    /// The chunk sizes are always multiples of the smallest chunks that the sample rates allow,
+    /// which are the rates divided by their greatest common divisor.
+    /// For some pairs of rates, like 44.1 kHz and 32 kHz with smallest chunks of 441 and 320
+    /// frames, these are long, and so are the FFTs of a single stage.
+    /// When the smallest chunks are longer than 256 frames, and the conversion can be split
+    /// into two stages with smallest chunks of at most 256 frames, two FFT stages are used instead.
+    /// For 44.1 to 32 kHz these convert to 33.6 kHz and then to 32 kHz,
+    /// with smallest chunks of 21 and 16 frames, and then 21 and 20 frames.
+    /// Each stage splits a chunk into several shorter FFTs, of at least 512 input frames.
+    /// The chunk sizes stay the same, while the output delay and the FFT buffers shrink.
+    /// See [nbr_stages](Self::nbr_stages) for checking which is used.
     pub fn new(fs_in: usize, fs_out: usize, chunk_size_in: usize, nbr_channels: usize) -> Self {
+        Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            nbr_channels,
+            FftFilter::default(),
+            &mut RealFftPlanner::new(),
+        )
+    }
+
+    /// Create a new FftFixedInOut with the given antialiasing filter, see [FftFilter].
+    /// The other parameters are the same as for [new](Self::new).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the cutoff is not in the range (0, 1],
+    /// or if the transition is negative or wider than twice the cutoff.
+    pub fn new_with_filter(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+    ) -> Result<Self, ResamplerConstructionError> {
+        filter.validate()?;
+        Ok(Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            nbr_channels,
+            filter,
+            &mut RealFftPlanner::new(),
+        ))
+    }
+
+    /// Create a new FftFixedInOut that plans its FFTs with the given planner.
+    /// The other parameters are the same as for [new](Self::new).
+    ///
+    /// A planner caches the FFTs it has planned, together with their twiddle factors.
+    /// When many resamplers with the same sample rates and chunk size are created,
+    /// sharing one planner between them makes creating all but the first one much faster,
+    /// and lets them share the memory used by the FFTs.
+    /// Run the `resamplers` benchmark to compare creating 100 FftFixedInOut
+    /// for 44.1 to 48 kHz with and without a shared planner.
+    pub fn new_with_planner(
+        planner: &mut RealFftPlanner<T>,
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            nbr_channels,
+            FftFilter::default(),
+            planner,
+        )
+    }
+
+    /// Create a new FftFixedInOut with an antialiasing filter that is known to be valid.
+    pub(crate) fn with_filter(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+        planner: &mut RealFftPlanner<T>,
+    ) -> Self {
         debug!(
             "Create new FftFixedInOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}",
             fs_in, fs_out, chunk_size_in, nbr_channels
@@ -196,151 +800,1376 @@ where
         let min_chunk_out = fs_out / gcd;
         let wanted = chunk_size_in;
         let fft_chunks = (wanted as f32 / min_chunk_out as f32).ceil() as usize;
+        let mut resampler = match two_stage_sizes(fs_in / gcd, fs_out / gcd, fft_chunks) {
+            Some((first, second)) => {
+                debug!(
+                    "Convert in two stages, FFT sizes: {:?} and {:?}",
+                    first, second
+                );
+                let chunk_size_in = fft_chunks * fs_in / gcd;
+                let chunk_size_out = fft_chunks * fs_out / gcd;
+                let second = SecondStage::new(
+                    second.0,
+                    second.1,
+                    chunk_size_in / first.0 * first.1,
+                    nbr_channels,
+                    filter,
+                    planner,
+                );
+                Self::with_stages(
+                    (chunk_size_in, chunk_size_out),
+                    first,
+                    Some(second),
+                    nbr_channels,
+                    filter,
+                    planner,
+                )
+            }
+            None => Self::with_fft_chunks(fs_in, fs_out, fft_chunks, nbr_channels, filter, planner),
+        };
+        resampler.chunk_size_wanted = wanted;
+        resampler
+    }
+
+    /// Create a new FftFixedInOut where the FFT holds `fft_chunks` of the smallest
+    /// chunks that the sample rates allow. This always uses a single stage.
+    pub(crate) fn with_fft_chunks(
+        fs_in: usize,
+        fs_out: usize,
+        fft_chunks: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+        planner: &mut RealFftPlanner<T>,
+    ) -> Self {
+        let gcd = integer::gcd(fs_in, fs_out);
         let fft_size_out = fft_chunks * fs_out / gcd;
         let fft_size_in = fft_chunks * fs_in / gcd;
+        let sizes = (fft_size_in, fft_size_out);
+        Self::with_stages(sizes, sizes, None, nbr_channels, filter, planner)
+    }
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+    /// Create a new FftFixedInOut for the given input and output chunk sizes,
+    /// with a first stage that uses FFTs of the given input and output sizes,
+    /// and optionally a second stage.
+    fn with_stages(
+        (chunk_size_in, chunk_size_out): (usize, usize),
+        (fft_size_in, fft_size_out): (usize, usize),
+        second: Option<SecondStage<T>>,
+        nbr_channels: usize,
+        filter: FftFilter,
+        planner: &mut RealFftPlanner<T>,
+    ) -> Self {
+        let resampler =
+            FftResampler::<T>::new_with_filter(fft_size_in, fft_size_out, filter, planner);
 
-        let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); fft_size_out]; nbr_channels];
+        let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); resampler.overlap_len()]; nbr_channels];
 
         FftFixedInOut {
             nbr_channels,
-            chunk_size_in: fft_size_in,
-            chunk_size_out: fft_size_out,
-            fft_size_in,
+            chunk_size_wanted: chunk_size_out,
+            chunk_size_in,
+            chunk_size_out,
+            frames_max_seen: (0, 0),
             overlaps,
             resampler,
+            second,
+            fine: None,
+            channel_mask: vec![true; nbr_channels],
+            active_channels_mask: vec![true; nbr_channels],
+            inactive_output: InactiveOutput::Skip,
+            validate_input: false,
+            flush_denormals: false,
+            trim: DelayTrim::default(),
+            input_frames_total: 0,
+            output_frames_final: None,
+            output_frames_total: 0,
         }
     }
-}
 
-impl<T> Resampler<T> for FftFixedInOut<T>
-where
-    T: Sample,
-{
-    /// Query for the number of frames needed for the next call to "process".
-    fn nbr_frames_needed(&self) -> usize {
-        self.fft_size_in
+    /// Get the largest input chunk size for which the latency stays within
+    /// `max_latency_frames` output frames, see [new_with_latency](Self::new_with_latency).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a sample rate is zero,
+    /// or if even the smallest chunk that the sample rates allow gives a longer latency.
+    /// The error then holds the smallest latency that can be reached.
+    ///
+    /// Note that [new](Self::new) finds the number of blocks for a given chunk size
+    /// from the output block size, and may give a longer chunk when downsampling.
+    /// Use [new_with_latency](Self::new_with_latency) to get exactly this chunk size.
+    pub fn chunk_size_for_latency(
+        fs_in: usize,
+        fs_out: usize,
+        max_latency_frames: usize,
+    ) -> Result<usize, ResamplerConstructionError> {
+        let fft_chunks = fft_chunks_for_latency(fs_in, fs_out, 1, max_latency_frames)?;
+        Ok(fft_chunks * fs_in / integer::gcd(fs_in, fs_out))
     }
 
-    /// Resample a chunk of audio. The input and output lengths are fixed.
-    /// If the waveform for a channel is empty, this channel will be ignored and produce a
-    /// corresponding empty output waveform.
+    /// Create a new FftFixedInOut with the largest chunk size
+    /// for which the latency stays within `max_latency_frames` output frames.
+    /// The other parameters are the same as for [new](Self::new).
+    ///
+    /// The latency is the duration of one chunk, which must be collected before it can be
+    /// processed, plus the [output delay](crate::Resampler::output_delay_fractional).
+    /// The chunk size is a multiple of the smallest chunk that the sample rates allow,
+    /// which is the input rate divided by the greatest common divisor of the two rates.
+    /// For some pairs of rates, like 44.1 kHz and 32 kHz, this is already large.
+    ///
     /// # Errors
     ///
-    /// The function returns an error if the size of the input data is not equal
-    /// to the number of channels and input size defined when creating the instance.
-    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
-            });
+    /// The function returns an error if a sample rate is zero,
+    /// or if the latency can't be reached, see [chunk_size_for_latency](Self::chunk_size_for_latency).
+    pub fn new_with_latency(
+        fs_in: usize,
+        fs_out: usize,
+        max_latency_frames: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let fft_chunks = fft_chunks_for_latency(fs_in, fs_out, 1, max_latency_frames)?;
+        Ok(Self::with_fft_chunks(
+            fs_in,
+            fs_out,
+            fft_chunks,
+            nbr_channels,
+            FftFilter::default(),
+            &mut RealFftPlanner::new(),
+        ))
+    }
+
+    /// Create a new FftFixedInOut that allows the resample ratio to be adjusted
+    /// by at most `max_ppm_adjust` parts per million in either direction,
+    /// for example to follow the drift between two clocks.
+    /// The parameters are otherwise the same as for [new](Self::new).
+    ///
+    /// The output of the FFT goes through a fine adjustment stage, a [SincFixedBoth]
+    /// with the interpolation parameters of [Quality::High].
+    /// The input and output chunk sizes stay fixed, and an adjustment instead moves
+    /// the output within the FIFO of that stage, which holds one output chunk.
+    /// The stage adds the delay of its sinc filter and of the FIFO to the output delay.
+    /// An adjustment that is kept on the same side of 1.0 for long eventually
+    /// fills up or empties the FIFO, which then drops or repeats frames.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if `max_ppm_adjust` is negative or not finite,
+    /// or if the number of channels is zero.
+    pub fn new_with_adjust(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        nbr_channels: usize,
+        max_ppm_adjust: f64,
+    ) -> Result<Self, ResamplerConstructionError> {
+        if !(max_ppm_adjust >= 0.0 && max_ppm_adjust.is_finite()) {
+            return Err(ResamplerConstructionError::InvalidMaxRatio);
+        }
+        let mut fft = Self::new(fs_in, fs_out, chunk_size_in, nbr_channels);
+        fft.fine = Some(FineAdjust::new(
+            1.0,
+            1.0 + max_ppm_adjust * 1.0e-6,
+            fft.chunk_size_out,
+            nbr_channels,
+        )?);
+        Ok(fft)
+    }
+
+    /// Change the sample rates, reusing the allocations where possible.
+    /// The chunk sizes are found from the new rates in the same way as [new](Self::new) does,
+    /// using the chunk size it was given. The resampler is then [reset](Resampler::reset).
+    /// Unlike [set_sample_rates](Resampler::set_sample_rates), which only changes the ratio
+    /// of an adjustable resampler, this sets up the FFTs for the new rates.
+    ///
+    /// The FFTs for the previous rates are kept, so that switching back and forth
+    /// between two pairs of rates only plans new FFTs the first time.
+    /// Rates that are converted in two stages, see [new](Self::new), keep the FFTs of
+    /// the second stage in the same way, but switching from two stages to one drops them.
+    /// The other internal buffers are reallocated only if the new rates need
+    /// larger buffers than any rates used before.
+    /// A fine adjustment stage is recreated with its current adjustment
+    /// if the output chunk size changes.
+    ///
+    /// After a change, [input_frames_max](Resampler::input_frames_max)
+    /// and [output_frames_max](Resampler::output_frames_max) give the largest values
+    /// for all the rates used so far, so that buffers allocated from them stay large enough.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a sample rate is zero.
+    pub fn change_sample_rates(&mut self, fs_in: usize, fs_out: usize) -> ResampleResult<()> {
+        if fs_in == 0 || fs_out == 0 {
+            return Err(ResampleError::InvalidSampleRate { fs_in, fs_out });
         }
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            let wave = wave.as_ref();
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.chunk_size_in {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.chunk_size_in,
-                        actual: wave.len(),
-                    });
+        trace!("Change sample rates to {} and {}", fs_in, fs_out);
+        self.frames_max_seen = (self.input_frames_max(), self.output_frames_max());
+        let gcd = integer::gcd(fs_in, fs_out);
+        let min_chunk_out = fs_out / gcd;
+        let fft_chunks = (self.chunk_size_wanted as f32 / min_chunk_out as f32).ceil() as usize;
+        let chunk_size_in = fft_chunks * fs_in / gcd;
+        let chunk_size_out = fft_chunks * fs_out / gcd;
+        match two_stage_sizes(fs_in / gcd, fs_out / gcd, fft_chunks) {
+            Some((first, (second_in, second_out))) => {
+                self.resampler.set_sizes(first.0, first.1);
+                let chunk_size_mid = chunk_size_in / first.0 * first.1;
+                match self.second.as_mut() {
+                    Some(second) => {
+                        second.resampler.set_sizes(second_in, second_out);
+                        let overlap_len = second.resampler.overlap_len();
+                        for overlap in second.overlaps.iter_mut() {
+                            overlap.resize(overlap_len, T::zero());
+                        }
+                        second.buffer_mid.resize(chunk_size_mid, T::zero());
+                    }
+                    None => {
+                        self.second = Some(SecondStage::new(
+                            second_in,
+                            second_out,
+                            chunk_size_mid,
+                            self.nbr_channels,
+                            self.resampler.filter_spec,
+                            &mut RealFftPlanner::new(),
+                        ));
+                    }
                 }
             }
+            None => {
+                self.resampler.set_sizes(chunk_size_in, chunk_size_out);
+                self.second = None;
+            }
         }
-        let mut wave_out = vec![Vec::new(); self.nbr_channels];
-        for chan in used_channels.iter() {
-            wave_out[*chan] = vec![T::zero(); self.chunk_size_out];
+        let overlap_len = self.resampler.overlap_len();
+        for overlap in self.overlaps.iter_mut() {
+            overlap.resize(overlap_len, T::zero());
         }
-
-        for n in used_channels.iter() {
-            self.resampler.resample_unit(
-                wave_in[*n].as_ref(),
-                &mut wave_out[*n],
-                &mut self.overlaps[*n],
-            )
+        if chunk_size_out != self.chunk_size_out {
+            if let Some(fine) = self.fine.as_mut() {
+                *fine = FineAdjust::new(
+                    fine.resampler.resample_ratio(),
+                    fine.resampler.max_resample_ratio_relative(),
+                    chunk_size_out,
+                    self.nbr_channels,
+                )
+                .map_err(|_| ResampleError::BadChunkSizeUpdate)?;
+            }
         }
-        Ok(wave_out)
+        self.chunk_size_in = chunk_size_in;
+        self.chunk_size_out = chunk_size_out;
+        self.reset();
+        Ok(())
     }
 
-    /// Update the resample ratio. This is not supported by this resampler and
-    /// always returns an error.
-    fn set_resample_ratio(&mut self, _new_ratio: f64) -> ResampleResult<()> {
-        Err(ResampleError::SyncNotAdjustable)
+    /// Get the number of input frames in each chunk.
+    pub fn chunk_size_in(&self) -> usize {
+        self.chunk_size_in
     }
 
-    /// Update the resample ratio relative to the original one. This is not
-    /// supported by this resampler and always returns an error.
-    fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
-        Err(ResampleError::SyncNotAdjustable)
+    /// Get the number of output frames in each chunk.
+    pub fn chunk_size_out(&self) -> usize {
+        self.chunk_size_out
     }
-}
 
-impl<T> FftFixedOut<T>
-where
-    T: Sample,
-{
-    /// Create a new FftFixedOut
-    ///
-    /// Parameters are:
-    /// - `fs_in`: Input sample rate.
-    /// - `fs_out`: Output sample rate.
-    /// - `chunk_size_out`: length of output data in frames.
-    /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
-    /// - `nbr_channels`: number of channels in input/output.
-    pub fn new(
-        fs_in: usize,
-        fs_out: usize,
-        chunk_size_out: usize,
-        sub_chunks: usize,
-        nbr_channels: usize,
-    ) -> Self {
-        let gcd = integer::gcd(fs_in, fs_out);
-        let min_chunk_out = fs_out / gcd;
-        let wanted_subsize = chunk_size_out / sub_chunks;
-        let fft_chunks = (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize;
-        let fft_size_out = fft_chunks * fs_out / gcd;
-        let fft_size_in = fft_chunks * fs_in / gcd;
+    /// Get the number of FFT stages, which is 2 for rates that are converted in two stages,
+    /// see [new](Self::new), and otherwise 1.
+    pub fn nbr_stages(&self) -> usize {
+        if self.second.is_some() {
+            2
+        } else {
+            1
+        }
+    }
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
+    /// Get the resample ratio of the first stage.
+    fn first_ratio(&self) -> f64 {
+        self.resampler.fft_size_out as f64 / self.resampler.fft_size_in as f64
+    }
 
-        debug!(
-            "Create new FftFixedOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
-            fs_in, fs_out, chunk_size_out, nbr_channels, fft_size_in, fft_size_out
+    /// Get the delay in output frames before trimming,
+    /// including the second stage and the fine adjustment stage if there are any.
+    /// The delay of the first stage is scaled by the ratio of the second.
+    fn untrimmed_delay(&self) -> f64 {
+        let fft_delay = match &self.second {
+            Some(second) => {
+                let second_ratio =
+                    second.resampler.fft_size_out as f64 / second.resampler.fft_size_in as f64;
+                self.resampler.output_delay() * second_ratio + second.resampler.output_delay()
+            }
+            None => self.resampler.output_delay(),
+        };
+        fft_delay
+            + self
+                .fine
+                .as_ref()
+                .map_or(0.0, |fine| fine.resampler.output_delay_fractional())
+    }
+
+    /// Get the length of the FFT used for the input, in frames.
+    /// With two stages, this is the FFT of the first stage.
+    pub fn fft_size_in(&self) -> usize {
+        self.resampler.fft_size_in
+    }
+
+    /// Get the length of the inverse FFT used for the output, in frames.
+    /// With two stages, this is the inverse FFT of the second stage.
+    pub fn fft_size_out(&self) -> usize {
+        match &self.second {
+            Some(second) => second.resampler.fft_size_out,
+            None => self.resampler.fft_size_out,
+        }
+    }
+
+    /// Get the number of sub chunks that each chunk is processed in.
+    /// A chunk is resampled as a sequence of blocks of
+    /// [internal_block_frames](Self::internal_block_frames) input frames, with one FFT each.
+    /// With a single stage, one FFT covers the whole chunk and this is 1.
+    pub fn sub_chunks(&self) -> usize {
+        self.chunk_size_in / self.resampler.fft_size_in
+    }
+
+    /// Get the number of input frames in each sub chunk, see [sub_chunks](Self::sub_chunks).
+    /// This is the same as [fft_size_in](Self::fft_size_in).
+    pub fn internal_block_frames(&self) -> usize {
+        self.resampler.fft_size_in
+    }
+
+    /// Set a gain that is applied to the output, for example to leave headroom.
+    /// The antialiasing filter is normalized to unity gain at DC,
+    /// so with the default gain of 1.0 the level of a signal in the passband is unchanged.
+    /// With two stages, the gain is applied in the first stage.
+    /// The gain takes effect from the next chunk, and isn't included in the
+    /// [frequency_response](Resampler::frequency_response).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the gain is negative or not finite.
+    pub fn set_gain(&mut self, gain: f64) -> ResampleResult<()> {
+        self.resampler.set_gain(gain)
+    }
+
+    /// Get the gain applied to the output, see [set_gain](Self::set_gain).
+    pub fn gain(&self) -> f64 {
+        self.resampler.gain
+    }
+
+    /// Get the response of the antialiasing filter, for combining with other stages.
+    /// This is only used for resamplers with a single stage.
+    pub(crate) fn filter_response(&self) -> FilterResponse {
+        self.resampler.filter_response()
+    }
+
+    /// Resample one chunk from `wave_in` into `wave_out`, for the channels marked as active
+    /// in the channel mask. Returns the number of output frames.
+    fn process_buffers<I, O>(&mut self, wave_in: &I, wave_out: &mut O) -> ResampleResult<usize>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        if self.validate_input {
+            validate_finite(wave_in, &self.channel_mask)?;
+        }
+        let _denormals = DenormalGuard::new(self.flush_denormals);
+        let mut wave_out = TrimmedOutput::new(wave_out, self.trim.remaining());
+        let first_in = self.resampler.fft_size_in;
+        let first_out = self.resampler.fft_size_out;
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                match self.second.as_mut() {
+                    None => {
+                        wave_in.read_into(chan, 0, &mut self.resampler.input_buf[0..first_in]);
+                        self.resampler.resample_input_buf(&mut self.overlaps[chan]);
+                        let output = &self.resampler.output_buf[0..first_out];
+                        match self.fine.as_mut() {
+                            Some(fine) => fine.buffer_in[chan].copy_from_slice(output),
+                            None => wave_out.write_slice(chan, 0, output),
+                        }
+                    }
+                    Some(second) => {
+                        // Run the first stage over the whole chunk,
+                        // then the second stage over its output.
+                        for n in 0..self.chunk_size_in / first_in {
+                            wave_in.read_into(
+                                chan,
+                                n * first_in,
+                                &mut self.resampler.input_buf[0..first_in],
+                            );
+                            self.resampler.resample_input_buf(&mut self.overlaps[chan]);
+                            second.buffer_mid[n * first_out..(n + 1) * first_out]
+                                .copy_from_slice(&self.resampler.output_buf[0..first_out]);
+                        }
+                        let second_in = second.resampler.fft_size_in;
+                        let second_out = second.resampler.fft_size_out;
+                        for n in 0..second.buffer_mid.len() / second_in {
+                            let mid = &second.buffer_mid[n * second_in..(n + 1) * second_in];
+                            second.resampler.input_buf[0..second_in].copy_from_slice(mid);
+                            second
+                                .resampler
+                                .resample_input_buf(&mut second.overlaps[chan]);
+                            let output = &second.resampler.output_buf[0..second_out];
+                            let range = n * second_out..(n + 1) * second_out;
+                            match self.fine.as_mut() {
+                                Some(fine) => fine.buffer_in[chan][range].copy_from_slice(output),
+                                None => wave_out.write_slice(chan, range.start, output),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(fine) = self.fine.as_mut() {
+            fine.resampler.process_into_buffer(
+                &fine.buffer_in,
+                &mut fine.buffer_out,
+                Some(&self.channel_mask),
+            )?;
+            for (chan, active) in self.channel_mask.iter().enumerate() {
+                if *active {
+                    wave_out.write_slice(chan, 0, &fine.buffer_out[chan]);
+                }
+            }
+        }
+        let frames_out = self.trim.consume(self.chunk_size_out);
+        self.input_frames_total += self.chunk_size_in as u64;
+        self.output_frames_total += frames_out as u64;
+        Ok(frames_out)
+    }
+}
+
+impl<T> fmt::Debug for FftFixedInOut<T>
+where
+    T: Sample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FftFixedInOut")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size_in", &self.chunk_size_in)
+            .field("chunk_size_out", &self.chunk_size_out)
+            .field("fft_size_in", &self.resampler.fft_size_in)
+            .field("fft_size_out", &self.fft_size_out())
+            .field("sub_chunks", &self.sub_chunks())
+            .field("internal_block_frames", &self.internal_block_frames())
+            .field("nbr_stages", &self.nbr_stages())
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for FftFixedInOut<T>
+where
+    T: Sample,
+{
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    /// Get the maximum number of input frames, which is the number of frames needed.
+    /// After a change of sample rates, this is the largest for all the rates used.
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size_in.max(self.frames_max_seen.0)
+    }
+
+    /// Get the maximum number of output frames, which is the output chunk size.
+    /// After a change of sample rates, this is the largest for all the rates used.
+    fn output_frames_max(&self) -> usize {
+        self.chunk_size_out.max(self.frames_max_seen.1)
+    }
+
+    /// Reset the resampler state and clear all internal buffers.
+    fn reset(&mut self) {
+        for chan in 0..self.nbr_channels {
+            clear_overlaps(&mut self.overlaps, &mut self.second, chan);
+        }
+        if let Some(fine) = self.fine.as_mut() {
+            fine.resampler.reset();
+        }
+        self.input_frames_total = 0;
+        self.output_frames_final = None;
+        self.output_frames_total = 0;
+        self.trim.restart(self.untrimmed_delay());
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        if channel >= self.nbr_channels {
+            return Err(ResampleError::InvalidChannel {
+                channel,
+                nbr_channels: self.nbr_channels,
+            });
+        }
+        clear_overlaps(&mut self.overlaps, &mut self.second, channel);
+        if let Some(fine) = self.fine.as_mut() {
+            fine.resampler.reset_channel(channel)?;
+        }
+        Ok(())
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        if nbr_channels == 0 {
+            return Err(ResampleError::BadNbrChannelsUpdate);
+        }
+        trace!("Change number of channels to {}", nbr_channels);
+        resize_channels(
+            &mut self.overlaps,
+            nbr_channels,
+            self.resampler.overlap_len(),
         );
+        if let Some(second) = self.second.as_mut() {
+            let overlap_len = second.resampler.overlap_len();
+            resize_channels(&mut second.overlaps, nbr_channels, overlap_len);
+        }
+        if let Some(fine) = self.fine.as_mut() {
+            fine.resampler.set_nbr_channels(nbr_channels)?;
+            resize_channels(&mut fine.buffer_in, nbr_channels, self.chunk_size_out);
+            resize_channels(&mut fine.buffer_out, nbr_channels, self.chunk_size_out);
+        }
+        resize_mask(&mut self.channel_mask, nbr_channels);
+        resize_mask(&mut self.active_channels_mask, nbr_channels);
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    /// Get the resample ratio, given by the output and input chunk sizes,
+    /// and by the adjustment if the resampler was created with
+    /// [new_with_adjust](FftFixedInOut::new_with_adjust).
+    fn resample_ratio(&self) -> f64 {
+        let ratio = self.chunk_size_out as f64 / self.chunk_size_in as f64;
+        match &self.fine {
+            Some(fine) => ratio * fine.resampler.resample_ratio(),
+            None => ratio,
+        }
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
+
+    fn output_frames_total(&self) -> u64 {
+        self.output_frames_total
+    }
+
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        store_mask(&mut self.active_channels_mask, mask)
+    }
+
+    fn active_channels_mask(&self) -> &[bool] {
+        &self.active_channels_mask
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        self.inactive_output = inactive_output;
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        self.validate_input = enabled;
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        let started = self.input_frames_total > 0;
+        self.trim
+            .set_enabled(enabled, self.untrimmed_delay(), started);
+    }
+
+    /// Resample a chunk of audio. The input and output lengths are fixed.
+    /// If the waveform for a channel is empty, this channel will be ignored and produce a
+    /// corresponding empty output waveform.
+    /// # Errors
+    ///
+    /// The function returns an error if the size of the input data is not equal
+    /// to the number of channels and input size defined when creating the instance.
+    fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
+        let overlaps = &mut self.overlaps;
+        let second = &mut self.second;
+        update_mask_from_buffers(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            wave_in,
+            self.chunk_size_in,
+            |chan| clear_overlaps(overlaps, second, chan),
+        )?;
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan] = vec![T::zero(); self.chunk_size_out];
+            }
+        }
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, 0, self.chunk_size_in),
+            &mut SequentialOutput::new(&mut wave_out, 0),
+        )?;
+        for chan in wave_out.iter_mut() {
+            chan.truncate(frames_out);
+        }
+        Ok(wave_out)
+    }
+
+    /// Resample a chunk of interleaved audio. The input and output lengths are fixed.
+    /// # Errors
+    ///
+    /// The function returns an error if the input doesn't contain the number of frames
+    /// given by "nbr_frames_needed", if the output buffer is shorter than "output_frames_max",
+    /// or if the mask doesn't match the number of channels.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        validate_interleaved(
+            wave_in,
+            wave_out,
+            self.nbr_channels,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let overlaps = &mut self.overlaps;
+        let second = &mut self.second;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| clear_overlaps(overlaps, second, chan),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out = self.process_buffers(
+            &InterleavedInput::new(wave_in, self.nbr_channels, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// `input_offset` plus "nbr_frames_needed" frames, if an active output channel holds fewer
+    /// than `output_offset` plus "output_frames_max" frames, or if the number of channels
+    /// of the buffers or the mask is wrong.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let second = &mut self.second;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| clear_overlaps(overlaps, second, chan),
+        )?;
+        validate_sequential(
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = SequentialOutput::new(wave_out, output_offset);
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, input_offset, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers,
+    /// and write the result to an interleaved output buffer.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// "nbr_frames_needed" frames, if the output buffer is shorter than "output_frames_max" frames,
+    /// or if the number of channels of the input or the mask is wrong.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let second = &mut self.second;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| clear_overlaps(overlaps, second, chan),
+        )?;
+        validate_sequential_to_interleaved(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the last frames of a stream, when fewer frames than needed remain.
+    /// The missing input frames are replaced by zeros, and the input may also be `None`,
+    /// which processes only zeros.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds more than
+    /// "nbr_frames_needed" frames, if the active input channels have different lengths,
+    /// if an active output channel holds fewer than "output_frames_max" frames,
+    /// or if the number of channels of the buffers or the mask is wrong.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_needed = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let second = &mut self.second;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| clear_overlaps(overlaps, second, chan),
+        )?;
+        let frames_in = validate_partial(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_needed,
+            self.output_frames_max(),
+        )?;
+        let wave_in = wave_in.unwrap_or(&[]);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    /// Only the output frames that correspond to real input are counted,
+    /// and zero frames are returned once the stream has been flushed.
+    /// # Errors
+    ///
+    /// The function returns an error under the same conditions as "process_partial_into_buffer".
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mut output_frames_final = self.output_frames_final;
+        let result = process_final(
+            self,
+            &mut output_frames_final,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+        );
+        self.output_frames_final = output_frames_final;
+        result
+    }
+
+    /// Update the resample ratio. This is only supported by a resampler created with
+    /// [new_with_adjust](FftFixedInOut::new_with_adjust), within the range given by
+    /// its maximum adjustment. Otherwise it returns an error.
+    fn set_resample_ratio(&mut self, new_ratio: f64) -> ResampleResult<()> {
+        let original = self.chunk_size_out as f64 / self.chunk_size_in as f64;
+        self.set_resample_ratio_relative(new_ratio / original)
+    }
+
+    /// Update the resample ratio relative to the original one. This is only supported by a
+    /// resampler created with [new_with_adjust](FftFixedInOut::new_with_adjust),
+    /// within the range given by its maximum adjustment. Otherwise it returns an error.
+    fn set_resample_ratio_relative(&mut self, rel_ratio: f64) -> ResampleResult<()> {
+        match self.fine.as_mut() {
+            Some(fine) => fine.resampler.set_resample_ratio(rel_ratio),
+            None => Err(ResampleError::SyncNotAdjustable),
+        }
+    }
+
+    /// Get the maximum relative adjustment of the ratio,
+    /// which is 1.0 unless the resampler was created with
+    /// [new_with_adjust](FftFixedInOut::new_with_adjust).
+    fn max_resample_ratio_relative(&self) -> f64 {
+        self.fine
+            .as_ref()
+            .map_or(1.0, |fine| fine.resampler.max_resample_ratio_relative())
+    }
+
+    /// Update the range of allowed resample ratios. This is only supported by a resampler
+    /// created with [new_with_adjust](FftFixedInOut::new_with_adjust).
+    /// Otherwise it returns an error.
+    fn set_max_resample_ratio_relative(&mut self, max_relative: f64) -> ResampleResult<()> {
+        match self.fine.as_mut() {
+            Some(fine) => fine.resampler.set_max_resample_ratio_relative(max_relative),
+            None => Err(ResampleError::SyncNotAdjustable),
+        }
+    }
+
+    /// Get the delay in output frames, given by the length of the FFT.
+    // The ratio is a fraction of two integers, so the number of frames can be calculated exactly.
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64 {
+        let size_in = self.chunk_size_in as u64;
+        let size_out = self.chunk_size_out as u64;
+        self.output_delay() as u64 + (total_input_frames * size_out + size_in - 1) / size_in
+    }
+
+    fn output_delay_fractional(&self) -> f64 {
+        self.untrimmed_delay() - self.trim.frames() as f64
+    }
+
+    /// Get the response of the antialiasing filter, combined for both stages if there are two.
+    /// The frequencies seen by the second stage are scaled by the ratio of the first.
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        let response = self.resampler.filter_response();
+        let second = self
+            .second
+            .as_ref()
+            .map(|second| second.resampler.filter_response());
+        let first_ratio = self.first_ratio();
+        frequency_response(self.resample_ratio(), n_points, |freq| {
+            let second_magnitude = second
+                .as_ref()
+                .map_or(1.0, |second| second.magnitude(freq / first_ratio));
+            response.magnitude(freq) * second_magnitude
+        })
+    }
+
+    /// Get the delay of the antialiasing filter, for both stages if there are two.
+    fn group_delay(&self) -> f64 {
+        let delay = self.resampler.filter_response().delay();
+        match &self.second {
+            Some(second) => delay + second.resampler.filter_response().delay() / self.first_ratio(),
+            None => delay,
+        }
+    }
+}
+
+impl<T> FftFixedOut<T>
+where
+    T: Sample,
+{
+    /// Create a new FftFixedOut
+    ///
+    /// Parameters are:
+    /// - `fs_in`: Input sample rate.
+    /// - `fs_out`: Output sample rate.
+    /// - `chunk_size_out`: length of output data in frames.
+    /// - `sub_chunks`: desired number of subchunks for processing, actual number may be different.
+    /// - `nbr_channels`: number of channels in input/output.
+    pub fn new(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+            FftFilter::default(),
+            &mut RealFftPlanner::new(),
+        )
+    }
+
+    /// Create a new FftFixedOut with the given antialiasing filter, see [FftFilter].
+    /// The other parameters are the same as for [new](Self::new).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the cutoff is not in the range (0, 1],
+    /// or if the transition is negative or wider than twice the cutoff.
+    pub fn new_with_filter(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+    ) -> Result<Self, ResamplerConstructionError> {
+        filter.validate()?;
+        Ok(Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+            filter,
+            &mut RealFftPlanner::new(),
+        ))
+    }
+
+    /// Create a new FftFixedOut that plans its FFTs with the given planner.
+    /// The other parameters are the same as for [new](Self::new).
+    /// See [FftFixedInOut::new_with_planner] for why sharing a planner is useful.
+    pub fn new_with_planner(
+        planner: &mut RealFftPlanner<T>,
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_out,
+            sub_chunks,
+            nbr_channels,
+            FftFilter::default(),
+            planner,
+        )
+    }
+
+    /// Get the largest output chunk size, a multiple of `sub_chunks`, for which the latency
+    /// stays within `max_latency_frames` output frames,
+    /// see [FftFixedInOut::new_with_latency] for how the latency is counted.
+    /// The chunk size can be given to [new](Self::new) together with the same `sub_chunks`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a sample rate or `sub_chunks` is zero,
+    /// or if even the smallest chunks that the sample rates allow give a longer latency.
+    /// The error then holds the smallest latency that can be reached.
+    pub fn chunk_size_for_latency(
+        fs_in: usize,
+        fs_out: usize,
+        max_latency_frames: usize,
+        sub_chunks: usize,
+    ) -> Result<usize, ResamplerConstructionError> {
+        let fft_chunks = fft_chunks_for_latency(fs_in, fs_out, sub_chunks, max_latency_frames)?;
+        Ok(sub_chunks * fft_chunks * fs_out / integer::gcd(fs_in, fs_out))
+    }
+
+    /// Create a new FftFixedOut with the largest chunk size
+    /// for which the latency stays within `max_latency_frames` output frames,
+    /// see [chunk_size_for_latency](Self::chunk_size_for_latency).
+    /// The other parameters are the same as for [new](Self::new).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the latency can't be reached.
+    pub fn new_with_latency(
+        fs_in: usize,
+        fs_out: usize,
+        max_latency_frames: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let chunk_size =
+            Self::chunk_size_for_latency(fs_in, fs_out, max_latency_frames, sub_chunks)?;
+        Ok(Self::new(
+            fs_in,
+            fs_out,
+            chunk_size,
+            sub_chunks,
+            nbr_channels,
+        ))
+    }
+
+    fn with_filter(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_out: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+        planner: &mut RealFftPlanner<T>,
+    ) -> Self {
+        let gcd = integer::gcd(fs_in, fs_out);
+        let min_chunk_out = fs_out / gcd;
+        let wanted_subsize = chunk_size_out / sub_chunks;
+        let fft_chunks = (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize;
+        let fft_size_out = fft_chunks * fs_out / gcd;
+        let fft_size_in = fft_chunks * fs_in / gcd;
+
+        let resampler =
+            FftResampler::<T>::new_with_filter(fft_size_in, fft_size_out, filter, planner);
+
+        debug!(
+            "Create new FftFixedOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
+            fs_in, fs_out, chunk_size_out, nbr_channels, fft_size_in, fft_size_out
+        );
+
+        let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); resampler.overlap_len()]; nbr_channels];
+        let output_buffers: Vec<Vec<T>> =
+            vec![vec![T::zero(); chunk_size_out + fft_size_out]; nbr_channels];
+
+        let saved_frames = 0;
+        let chunks_needed = (chunk_size_out as f32 / fft_size_out as f32).ceil() as usize;
+        let frames_needed = chunks_needed * fft_size_in;
+
+        FftFixedOut {
+            nbr_channels,
+            chunk_size_out,
+            sub_chunks,
+            fft_size_in,
+            fft_size_out,
+            frames_max_seen: (0, 0),
+            overlaps,
+            output_buffers,
+            saved_frames,
+            frames_needed,
+            resampler,
+            channel_mask: vec![true; nbr_channels],
+            active_channels_mask: vec![true; nbr_channels],
+            inactive_output: InactiveOutput::Skip,
+            validate_input: false,
+            flush_denormals: false,
+            trim: DelayTrim::default(),
+            input_frames_total: 0,
+            output_frames_final: None,
+            output_frames_total: 0,
+        }
+    }
+
+    /// Get the number of output frames returned by each call to "process".
+    pub fn chunk_size_out(&self) -> usize {
+        self.chunk_size_out
+    }
+
+    /// Get the length of the FFT used for the input, in frames.
+    pub fn fft_size_in(&self) -> usize {
+        self.fft_size_in
+    }
+
+    /// Get the length of the inverse FFT used for the output, in frames.
+    pub fn fft_size_out(&self) -> usize {
+        self.fft_size_out
+    }
+
+    /// Get the number of sub chunks that each chunk is processed in.
+    /// The output chunk is produced from blocks of
+    /// [internal_block_frames](Self::internal_block_frames) output frames, with one FFT each.
+    /// The blocks are the shortest that the sample rates can be converted in exactly,
+    /// repeated to give at least `chunk_size_out / sub_chunks` frames,
+    /// for the `sub_chunks` given to [new](Self::new).
+    /// The part of a block that doesn't fit in the chunk is kept for the next call,
+    /// so this is the largest number of blocks resampled in one call,
+    /// and may differ from the number that was asked for.
+    pub fn sub_chunks(&self) -> usize {
+        (self.chunk_size_out + self.fft_size_out - 1) / self.fft_size_out
+    }
+
+    /// Get the number of output frames in each sub chunk, see [sub_chunks](Self::sub_chunks).
+    /// This is the same as [fft_size_out](Self::fft_size_out).
+    pub fn internal_block_frames(&self) -> usize {
+        self.fft_size_out
+    }
+
+    /// Set a gain that is applied to the output, for example to leave headroom.
+    /// The antialiasing filter is normalized to unity gain at DC,
+    /// so with the default gain of 1.0 the level of a signal in the passband is unchanged.
+    /// The gain takes effect from the next chunk, and isn't included in the
+    /// [frequency_response](Resampler::frequency_response).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the gain is negative or not finite.
+    pub fn set_gain(&mut self, gain: f64) -> ResampleResult<()> {
+        self.resampler.set_gain(gain)
+    }
+
+    /// Get the gain applied to the output, see [set_gain](Self::set_gain).
+    pub fn gain(&self) -> f64 {
+        self.resampler.gain
+    }
+
+    /// Change the number of output frames returned by the next call to "process".
+    /// The frames already resampled are kept, and are returned before any new ones.
+    /// The number of input frames needed for the next call is updated immediately,
+    /// and is zero if the saved frames are enough to fill the new chunk.
+    /// The internal buffers are reallocated if the new chunk size is larger than any used before.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new chunk size is zero.
+    pub fn set_output_chunk_size(&mut self, chunk_size_out: usize) -> ResampleResult<()> {
+        if chunk_size_out == 0 {
+            return Err(ResampleError::BadChunkSizeUpdate);
+        }
+        trace!("Change output chunk size to {}", chunk_size_out);
+        let buffer_len = chunk_size_out + self.fft_size_out;
+        for buf in self.output_buffers.iter_mut() {
+            if buf.len() < buffer_len {
+                buf.resize(buffer_len, T::zero());
+            }
+        }
+        self.chunk_size_out = chunk_size_out;
+        self.update_frames_needed();
+        Ok(())
+    }
+
+    /// Change the sample rates, reusing the allocations where possible.
+    /// The FFT sizes are found from the new rates in the same way as [new](Self::new) does,
+    /// for the current output chunk size and the number of sub chunks it was given.
+    /// The resampler is then [reset](Resampler::reset).
+    ///
+    /// The FFTs for the previous rates are kept, and the other buffers only grow,
+    /// see [FftFixedInOut::change_sample_rates].
+    /// After a change, [input_frames_max](Resampler::input_frames_max)
+    /// gives the largest value for all the rates used so far.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a sample rate is zero.
+    pub fn change_sample_rates(&mut self, fs_in: usize, fs_out: usize) -> ResampleResult<()> {
+        if fs_in == 0 || fs_out == 0 {
+            return Err(ResampleError::InvalidSampleRate { fs_in, fs_out });
+        }
+        trace!("Change sample rates to {} and {}", fs_in, fs_out);
+        self.frames_max_seen = (self.input_frames_max(), self.output_frames_max());
+        let gcd = integer::gcd(fs_in, fs_out);
+        let min_chunk_out = fs_out / gcd;
+        let wanted_subsize = (self.chunk_size_out / self.sub_chunks).max(1);
+        let fft_chunks = (wanted_subsize as f32 / min_chunk_out as f32).ceil() as usize;
+        let fft_size_in = fft_chunks * fs_in / gcd;
+        let fft_size_out = fft_chunks * fs_out / gcd;
+        self.resampler.set_sizes(fft_size_in, fft_size_out);
+        let overlap_len = self.resampler.overlap_len();
+        for overlap in self.overlaps.iter_mut() {
+            overlap.resize(overlap_len, T::zero());
+        }
+        let buffer_len = self.chunk_size_out + fft_size_out;
+        for buf in self.output_buffers.iter_mut() {
+            if buf.len() < buffer_len {
+                buf.resize(buffer_len, T::zero());
+            }
+        }
+        self.fft_size_in = fft_size_in;
+        self.fft_size_out = fft_size_out;
+        self.reset();
+        Ok(())
+    }
+
+    /// Calculate the number of input frames needed for the next round.
+    fn update_frames_needed(&mut self) {
+        let frames_needed_out = self.chunk_size_out.saturating_sub(self.saved_frames);
+        let chunks_needed = (frames_needed_out as f32 / self.fft_size_out as f32).ceil() as usize;
+        self.frames_needed = chunks_needed * self.fft_size_in;
+    }
+
+    /// Resample one chunk from `wave_in` into `wave_out`, for the channels marked as active
+    /// in the channel mask. Returns the number of input and output frames.
+    fn process_buffers<I, O>(
+        &mut self,
+        wave_in: &I,
+        wave_out: &mut O,
+    ) -> ResampleResult<(usize, usize)>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        if self.validate_input {
+            validate_finite(wave_in, &self.channel_mask)?;
+        }
+        let _denormals = DenormalGuard::new(self.flush_denormals);
+        let mut wave_out = TrimmedOutput::new(wave_out, self.trim.remaining());
+        let frames_in = self.frames_needed;
+        let nbr_chunks = frames_in / self.fft_size_in;
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                for k in 0..nbr_chunks {
+                    wave_in.read_into(
+                        chan,
+                        k * self.fft_size_in,
+                        &mut self.resampler.input_buf[0..self.fft_size_in],
+                    );
+                    self.resampler.resample_input_buf(&mut self.overlaps[chan]);
+                    let start = self.saved_frames + k * self.fft_size_out;
+                    self.output_buffers[chan][start..(start + self.fft_size_out)]
+                        .copy_from_slice(&self.resampler.output_buf[0..self.fft_size_out]);
+                }
+                wave_out.write_slice(chan, 0, &self.output_buffers[chan][0..self.chunk_size_out]);
+            }
+        }
+        let processed_frames = self.saved_frames + self.fft_size_out * nbr_chunks;
+
+        // save extra frames for next round
+        self.saved_frames = processed_frames - self.chunk_size_out;
+        if processed_frames > self.chunk_size_out {
+            for (chan, active) in self.channel_mask.iter().enumerate() {
+                if *active {
+                    self.output_buffers[chan].copy_within(self.chunk_size_out..processed_frames, 0);
+                }
+            }
+        }
+        self.update_frames_needed();
+        let frames_out = self.trim.consume(self.chunk_size_out);
+        self.input_frames_total += frames_in as u64;
+        self.output_frames_total += frames_out as u64;
+        Ok((frames_in, frames_out))
+    }
+}
+
+impl<T> fmt::Debug for FftFixedOut<T>
+where
+    T: Sample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FftFixedOut")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size_out", &self.chunk_size_out)
+            .field("fft_size_in", &self.fft_size_in)
+            .field("fft_size_out", &self.fft_size_out)
+            .field("sub_chunks", &self.sub_chunks())
+            .field("internal_block_frames", &self.internal_block_frames())
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for FftFixedOut<T>
+where
+    T: Sample,
+{
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize {
+        self.frames_needed
+    }
+
+    /// Get the maximum number of input frames.
+    /// This is needed when no frames are saved from the previous call.
+    /// After a change of sample rates, this is the largest for all the rates used.
+    fn input_frames_max(&self) -> usize {
+        let chunks = (self.chunk_size_out + self.fft_size_out - 1) / self.fft_size_out;
+        (chunks * self.fft_size_in).max(self.frames_max_seen.0)
+    }
+
+    /// Get the maximum number of output frames, which is the chunk size.
+    /// After a change of sample rates, this is the largest for all the rates used.
+    fn output_frames_max(&self) -> usize {
+        self.chunk_size_out.max(self.frames_max_seen.1)
+    }
+
+    /// Reset the resampler state and clear all internal buffers.
+    fn reset(&mut self) {
+        for overlap in self.overlaps.iter_mut() {
+            overlap.iter_mut().for_each(|x| *x = T::zero());
+        }
+        for buf in self.output_buffers.iter_mut() {
+            buf.iter_mut().for_each(|x| *x = T::zero());
+        }
+        self.saved_frames = 0;
+        self.update_frames_needed();
+        self.input_frames_total = 0;
+        self.output_frames_final = None;
+        self.output_frames_total = 0;
+        self.trim.restart(self.resampler.output_delay());
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        if channel >= self.nbr_channels {
+            return Err(ResampleError::InvalidChannel {
+                channel,
+                nbr_channels: self.nbr_channels,
+            });
+        }
+        self.overlaps[channel]
+            .iter_mut()
+            .for_each(|x| *x = T::zero());
+        self.output_buffers[channel]
+            .iter_mut()
+            .for_each(|x| *x = T::zero());
+        Ok(())
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        if nbr_channels == 0 {
+            return Err(ResampleError::BadNbrChannelsUpdate);
+        }
+        trace!("Change number of channels to {}", nbr_channels);
+        let overlap_len = self.resampler.overlap_len();
+        resize_channels(&mut self.overlaps, nbr_channels, overlap_len);
+        let buffer_len = self.chunk_size_out + self.fft_size_out;
+        resize_channels(&mut self.output_buffers, nbr_channels, buffer_len);
+        resize_mask(&mut self.channel_mask, nbr_channels);
+        resize_mask(&mut self.active_channels_mask, nbr_channels);
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    /// Get the resample ratio, given by the FFT sizes.
+    fn resample_ratio(&self) -> f64 {
+        self.fft_size_out as f64 / self.fft_size_in as f64
+    }
 
-        let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); fft_size_out]; nbr_channels];
-        let output_buffers: Vec<Vec<T>> =
-            vec![vec![T::zero(); chunk_size_out + fft_size_out]; nbr_channels];
+    fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
 
-        let saved_frames = 0;
-        let chunks_needed = (chunk_size_out as f32 / fft_size_out as f32).ceil() as usize;
-        let frames_needed = chunks_needed * fft_size_in;
+    fn output_frames_total(&self) -> u64 {
+        self.output_frames_total
+    }
 
-        FftFixedOut {
-            nbr_channels,
-            chunk_size_out,
-            fft_size_in,
-            fft_size_out,
-            overlaps,
-            output_buffers,
-            saved_frames,
-            frames_needed,
-            resampler,
-        }
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        store_mask(&mut self.active_channels_mask, mask)
     }
-}
 
-impl<T> Resampler<T> for FftFixedOut<T>
-where
-    T: Sample,
-{
-    /// Query for the number of frames needed for the next call to "process".
-    fn nbr_frames_needed(&self) -> usize {
-        self.frames_needed
+    fn active_channels_mask(&self) -> &[bool] {
+        &self.active_channels_mask
+    }
+
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        self.inactive_output = inactive_output;
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        self.validate_input = enabled;
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        let started = self.input_frames_total > 0;
+        self.trim
+            .set_enabled(enabled, self.resampler.output_delay(), started);
     }
 
     /// Resample a chunk of audio. The required input length is provided by
@@ -353,68 +2182,246 @@ where
     /// equal to the number of channels defined when creating the instance,
     /// and the number of audio frames given by "nbr_frames_needed".
     fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
-            });
-        }
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            let wave = wave.as_ref();
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.frames_needed {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.frames_needed,
-                        actual: wave.len(),
-                    });
-                }
-            }
-        }
-
+        let overlaps = &mut self.overlaps;
+        let output_buffers = &mut self.output_buffers;
+        update_mask_from_buffers(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            wave_in,
+            self.frames_needed,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                output_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
         let mut wave_out = vec![Vec::new(); self.nbr_channels];
-        for chan in used_channels.iter() {
-            wave_out[*chan] = self.output_buffers[*chan].clone();
-        }
-
-        for n in used_channels.iter() {
-            for (in_chunk, out_chunk) in wave_in[*n]
-                .as_ref()
-                .chunks(self.fft_size_in)
-                .zip(wave_out[*n][self.saved_frames..].chunks_mut(self.fft_size_out))
-            {
-                self.resampler
-                    .resample_unit(in_chunk, out_chunk, &mut self.overlaps[*n]);
-            }
-        }
-        let processed_frames =
-            self.saved_frames + self.fft_size_out * (self.frames_needed / self.fft_size_in);
-
-        // save extra frames for next round
-        self.saved_frames = processed_frames - self.chunk_size_out;
-        if processed_frames > self.chunk_size_out {
-            for n in used_channels.iter() {
-                self.output_buffers[*n][0..self.saved_frames].copy_from_slice(
-                    &wave_out[*n][self.chunk_size_out..(self.chunk_size_out + self.saved_frames)],
-                );
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan] = vec![T::zero(); self.chunk_size_out];
             }
         }
-        for n in used_channels.iter() {
-            wave_out[*n].truncate(self.chunk_size_out);
+        let (_, frames_out) = self.process_buffers(
+            &SequentialInput::new(wave_in, 0, self.frames_needed),
+            &mut SequentialOutput::new(&mut wave_out, 0),
+        )?;
+        for chan in wave_out.iter_mut() {
+            chan.truncate(frames_out);
         }
-        //calculate number of needed frames from next round
-        let frames_needed_out = if self.chunk_size_out > self.saved_frames {
-            self.chunk_size_out - self.saved_frames
-        } else {
-            0
-        };
-        let chunks_needed = (frames_needed_out as f32 / self.fft_size_out as f32).ceil() as usize;
-        self.frames_needed = chunks_needed * self.fft_size_in;
         Ok(wave_out)
     }
 
+    /// Resample a chunk of interleaved audio. The required input length is provided by
+    /// the "nbr_frames_needed" function, and the output length is fixed.
+    /// # Errors
+    ///
+    /// The function returns an error if the input doesn't contain the number of frames
+    /// given by "nbr_frames_needed", if the output buffer is shorter than "output_frames_max",
+    /// or if the mask doesn't match the number of channels.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        validate_interleaved(
+            wave_in,
+            wave_out,
+            self.nbr_channels,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let overlaps = &mut self.overlaps;
+        let output_buffers = &mut self.output_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                output_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let (frames_in, frames_out) = self.process_buffers(
+            &InterleavedInput::new(wave_in, self.nbr_channels, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// `input_offset` plus "nbr_frames_needed" frames, if an active output channel holds fewer
+    /// than `output_offset` plus "output_frames_max" frames, or if the number of channels
+    /// of the buffers or the mask is wrong.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let output_buffers = &mut self.output_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                output_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential(
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = SequentialOutput::new(wave_out, output_offset);
+        let (frames_in, frames_out) = self.process_buffers(
+            &SequentialInput::new(wave_in, input_offset, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample a chunk of audio from sequential input buffers,
+    /// and write the result to an interleaved output buffer.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// "nbr_frames_needed" frames, if the output buffer is shorter than "output_frames_max" frames,
+    /// or if the number of channels of the input or the mask is wrong.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let output_buffers = &mut self.output_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                output_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential_to_interleaved(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let (frames_in, frames_out) =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the last frames of a stream, when fewer frames than needed remain.
+    /// The missing input frames are replaced by zeros, and the input may also be `None`,
+    /// which processes only zeros.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds more than
+    /// "nbr_frames_needed" frames, if the active input channels have different lengths,
+    /// if an active output channel holds fewer than "output_frames_max" frames,
+    /// or if the number of channels of the buffers or the mask is wrong.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_needed = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let output_buffers = &mut self.output_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                output_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_in = validate_partial(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_needed,
+            self.output_frames_max(),
+        )?;
+        let wave_in = wave_in.unwrap_or(&[]);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let (_, frames_out) =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    /// Only the output frames that correspond to real input are counted,
+    /// and zero frames are returned once the stream has been flushed.
+    /// # Errors
+    ///
+    /// The function returns an error under the same conditions as "process_partial_into_buffer".
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mut output_frames_final = self.output_frames_final;
+        let result = process_final(
+            self,
+            &mut output_frames_final,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+        );
+        self.output_frames_final = output_frames_final;
+        result
+    }
+
     /// Update the resample ratio. This is not supported by this resampler and
     /// always returns an error.
     fn set_resample_ratio(&mut self, _new_ratio: f64) -> ResampleResult<()> {
@@ -426,6 +2433,40 @@ where
     fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
         Err(ResampleError::SyncNotAdjustable)
     }
+
+    /// The resample ratio can't be changed, so this always returns 1.0.
+    fn max_resample_ratio_relative(&self) -> f64 {
+        1.0
+    }
+
+    /// Update the range of allowed resample ratios. This is not supported by this resampler
+    /// and always returns an error.
+    fn set_max_resample_ratio_relative(&mut self, _max_relative: f64) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// Get the delay in output frames, given by the length of the FFT.
+    // The ratio is a fraction of two integers, so the number of frames can be calculated exactly.
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64 {
+        let size_in = self.fft_size_in as u64;
+        let size_out = self.fft_size_out as u64;
+        self.output_delay() as u64 + (total_input_frames * size_out + size_in - 1) / size_in
+    }
+
+    fn output_delay_fractional(&self) -> f64 {
+        self.resampler.output_delay() - self.trim.frames() as f64
+    }
+
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        let response = self.resampler.filter_response();
+        frequency_response(self.resample_ratio(), n_points, |freq| {
+            response.magnitude(freq)
+        })
+    }
+
+    fn group_delay(&self) -> f64 {
+        self.resampler.filter_response().delay()
+    }
 }
 
 impl<T> FftFixedIn<T>
@@ -446,6 +2487,121 @@ where
         chunk_size_in: usize,
         sub_chunks: usize,
         nbr_channels: usize,
+    ) -> Self {
+        Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+            FftFilter::default(),
+            &mut RealFftPlanner::new(),
+        )
+    }
+
+    /// Create a new FftFixedIn with the given antialiasing filter, see [FftFilter].
+    /// The other parameters are the same as for [new](Self::new).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the cutoff is not in the range (0, 1],
+    /// or if the transition is negative or wider than twice the cutoff.
+    pub fn new_with_filter(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+    ) -> Result<Self, ResamplerConstructionError> {
+        filter.validate()?;
+        Ok(Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+            filter,
+            &mut RealFftPlanner::new(),
+        ))
+    }
+
+    /// Create a new FftFixedIn that plans its FFTs with the given planner.
+    /// The other parameters are the same as for [new](Self::new).
+    /// See [FftFixedInOut::new_with_planner] for why sharing a planner is useful.
+    pub fn new_with_planner(
+        planner: &mut RealFftPlanner<T>,
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Self {
+        Self::with_filter(
+            fs_in,
+            fs_out,
+            chunk_size_in,
+            sub_chunks,
+            nbr_channels,
+            FftFilter::default(),
+            planner,
+        )
+    }
+
+    /// Get the largest input chunk size, a multiple of `sub_chunks`, for which the latency
+    /// stays within `max_latency_frames` output frames,
+    /// see [FftFixedInOut::new_with_latency] for how the latency is counted.
+    /// The chunk size can be given to [new](Self::new) together with the same `sub_chunks`.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a sample rate or `sub_chunks` is zero,
+    /// or if even the smallest chunks that the sample rates allow give a longer latency.
+    /// The error then holds the smallest latency that can be reached.
+    pub fn chunk_size_for_latency(
+        fs_in: usize,
+        fs_out: usize,
+        max_latency_frames: usize,
+        sub_chunks: usize,
+    ) -> Result<usize, ResamplerConstructionError> {
+        let fft_chunks = fft_chunks_for_latency(fs_in, fs_out, sub_chunks, max_latency_frames)?;
+        Ok(sub_chunks * fft_chunks * fs_in / integer::gcd(fs_in, fs_out))
+    }
+
+    /// Create a new FftFixedIn with the largest chunk size
+    /// for which the latency stays within `max_latency_frames` output frames,
+    /// see [chunk_size_for_latency](Self::chunk_size_for_latency).
+    /// The other parameters are the same as for [new](Self::new).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the latency can't be reached.
+    pub fn new_with_latency(
+        fs_in: usize,
+        fs_out: usize,
+        max_latency_frames: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let chunk_size =
+            Self::chunk_size_for_latency(fs_in, fs_out, max_latency_frames, sub_chunks)?;
+        Ok(Self::new(
+            fs_in,
+            fs_out,
+            chunk_size,
+            sub_chunks,
+            nbr_channels,
+        ))
+    }
+
+    fn with_filter(
+        fs_in: usize,
+        fs_out: usize,
+        chunk_size_in: usize,
+        sub_chunks: usize,
+        nbr_channels: usize,
+        filter: FftFilter,
+        planner: &mut RealFftPlanner<T>,
     ) -> Self {
         let gcd = integer::gcd(fs_in, fs_out);
         let min_chunk_in = fs_in / gcd;
@@ -454,38 +2610,330 @@ where
         let fft_size_out = fft_chunks * fs_out / gcd;
         let fft_size_in = fft_chunks * fs_in / gcd;
 
-        let resampler = FftResampler::<T>::new(fft_size_in, fft_size_out);
-        debug!(
-            "Create new FftFixedOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
-            fs_in, fs_out, chunk_size_in, nbr_channels, fft_size_in, fft_size_out
-        );
+        let resampler =
+            FftResampler::<T>::new_with_filter(fft_size_in, fft_size_out, filter, planner);
+        debug!(
+            "Create new FftFixedOut, fs_in: {}, fs_out: {} chunk_size_in: {}, channels: {}, fft_size_in: {}, fft_size_out: {}",
+            fs_in, fs_out, chunk_size_in, nbr_channels, fft_size_in, fft_size_out
+        );
+
+        let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); resampler.overlap_len()]; nbr_channels];
+        let input_buffers: Vec<Vec<T>> =
+            vec![vec![T::zero(); chunk_size_in + fft_size_in]; nbr_channels];
+
+        let saved_frames = 0;
+
+        FftFixedIn {
+            nbr_channels,
+            chunk_size_in,
+            sub_chunks,
+            fft_size_in,
+            fft_size_out,
+            frames_max_seen: (0, 0),
+            overlaps,
+            input_buffers,
+            saved_frames,
+            resampler,
+            channel_mask: vec![true; nbr_channels],
+            active_channels_mask: vec![true; nbr_channels],
+            inactive_output: InactiveOutput::Skip,
+            validate_input: false,
+            flush_denormals: false,
+            trim: DelayTrim::default(),
+            input_frames_total: 0,
+            output_frames_final: None,
+            output_frames_total: 0,
+        }
+    }
+
+    /// Get the number of input frames expected by each call to "process".
+    pub fn chunk_size_in(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    /// Get the length of the FFT used for the input, in frames.
+    pub fn fft_size_in(&self) -> usize {
+        self.fft_size_in
+    }
+
+    /// Get the length of the inverse FFT used for the output, in frames.
+    pub fn fft_size_out(&self) -> usize {
+        self.fft_size_out
+    }
+
+    /// Get the number of sub chunks that each chunk is processed in.
+    /// The input chunk is resampled in blocks of
+    /// [internal_block_frames](Self::internal_block_frames) input frames, with one FFT each.
+    /// The blocks are the shortest that the sample rates can be converted in exactly,
+    /// repeated to give at least `chunk_size_in / sub_chunks` frames,
+    /// for the `sub_chunks` given to [new](Self::new).
+    /// Frames that don't fill a block are kept for the next call,
+    /// so this is the largest number of blocks resampled in one call,
+    /// and may differ from the number that was asked for.
+    pub fn sub_chunks(&self) -> usize {
+        (self.chunk_size_in + self.fft_size_in - 1) / self.fft_size_in
+    }
+
+    /// Get the number of input frames in each sub chunk, see [sub_chunks](Self::sub_chunks).
+    /// This is the same as [fft_size_in](Self::fft_size_in).
+    pub fn internal_block_frames(&self) -> usize {
+        self.fft_size_in
+    }
+
+    /// Set a gain that is applied to the output, for example to leave headroom.
+    /// The antialiasing filter is normalized to unity gain at DC,
+    /// so with the default gain of 1.0 the level of a signal in the passband is unchanged.
+    /// The gain takes effect from the next chunk, and isn't included in the
+    /// [frequency_response](Resampler::frequency_response).
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the gain is negative or not finite.
+    pub fn set_gain(&mut self, gain: f64) -> ResampleResult<()> {
+        self.resampler.set_gain(gain)
+    }
+
+    /// Get the gain applied to the output, see [set_gain](Self::set_gain).
+    pub fn gain(&self) -> f64 {
+        self.resampler.gain
+    }
+
+    /// Change the number of input frames expected by the next call to "process".
+    /// Input frames that are waiting for a complete FFT chunk are kept,
+    /// so the output continues without interruption.
+    /// The internal buffers are reallocated if the new chunk size is larger than any used before.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if the new chunk size is zero.
+    pub fn set_chunk_size(&mut self, chunk_size_in: usize) -> ResampleResult<()> {
+        if chunk_size_in == 0 {
+            return Err(ResampleError::BadChunkSizeUpdate);
+        }
+        trace!("Change chunk size to {}", chunk_size_in);
+        let buffer_len = chunk_size_in + self.fft_size_in;
+        for buf in self.input_buffers.iter_mut() {
+            if buf.len() < buffer_len {
+                buf.resize(buffer_len, T::zero());
+            }
+        }
+        self.chunk_size_in = chunk_size_in;
+        Ok(())
+    }
+
+    /// Change the sample rates, reusing the allocations where possible.
+    /// The FFT sizes are found from the new rates in the same way as [new](Self::new) does,
+    /// for the current input chunk size and the number of sub chunks it was given.
+    /// The resampler is then [reset](Resampler::reset).
+    ///
+    /// The FFTs for the previous rates are kept, and the other buffers only grow,
+    /// see [FftFixedInOut::change_sample_rates].
+    /// After a change, [output_frames_max](Resampler::output_frames_max)
+    /// gives the largest value for all the rates used so far.
+    ///
+    /// # Errors
+    ///
+    /// The function returns an error if a sample rate is zero.
+    pub fn change_sample_rates(&mut self, fs_in: usize, fs_out: usize) -> ResampleResult<()> {
+        if fs_in == 0 || fs_out == 0 {
+            return Err(ResampleError::InvalidSampleRate { fs_in, fs_out });
+        }
+        trace!("Change sample rates to {} and {}", fs_in, fs_out);
+        self.frames_max_seen = (self.input_frames_max(), self.output_frames_max());
+        let gcd = integer::gcd(fs_in, fs_out);
+        let min_chunk_in = fs_in / gcd;
+        let wanted_subsize = (self.chunk_size_in / self.sub_chunks).max(1);
+        let fft_chunks = (wanted_subsize as f32 / min_chunk_in as f32).ceil() as usize;
+        let fft_size_in = fft_chunks * fs_in / gcd;
+        let fft_size_out = fft_chunks * fs_out / gcd;
+        self.resampler.set_sizes(fft_size_in, fft_size_out);
+        let overlap_len = self.resampler.overlap_len();
+        for overlap in self.overlaps.iter_mut() {
+            overlap.resize(overlap_len, T::zero());
+        }
+        let buffer_len = self.chunk_size_in + fft_size_in;
+        for buf in self.input_buffers.iter_mut() {
+            if buf.len() < buffer_len {
+                buf.resize(buffer_len, T::zero());
+            }
+        }
+        self.fft_size_in = fft_size_in;
+        self.fft_size_out = fft_size_out;
+        self.reset();
+        Ok(())
+    }
+
+    /// Resample one chunk from `wave_in` into `wave_out`, for the channels marked as active
+    /// in the channel mask. Returns the number of output frames.
+    fn process_buffers<I, O>(&mut self, wave_in: &I, wave_out: &mut O) -> ResampleResult<usize>
+    where
+        I: InputBuffer<T>,
+        O: OutputBuffer<T>,
+    {
+        if self.validate_input {
+            validate_finite(wave_in, &self.channel_mask)?;
+        }
+        let _denormals = DenormalGuard::new(self.flush_denormals);
+        let mut wave_out = TrimmedOutput::new(wave_out, self.trim.remaining());
+        let total_frames = self.saved_frames + self.chunk_size_in;
+        let nbr_chunks_ready = total_frames / self.fft_size_in;
+        let frames_in_used = nbr_chunks_ready * self.fft_size_in;
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                // copy new samples to input buffer
+                wave_in.read_into(
+                    chan,
+                    0,
+                    &mut self.input_buffers[chan][self.saved_frames..total_frames],
+                );
+                for k in 0..nbr_chunks_ready {
+                    self.resampler.input_buf[0..self.fft_size_in].copy_from_slice(
+                        &self.input_buffers[chan][k * self.fft_size_in..(k + 1) * self.fft_size_in],
+                    );
+                    self.resampler.resample_input_buf(&mut self.overlaps[chan]);
+                    wave_out.write_slice(
+                        chan,
+                        k * self.fft_size_out,
+                        &self.resampler.output_buf[0..self.fft_size_out],
+                    );
+                }
+                // save extra frames for next round
+                self.input_buffers[chan].copy_within(frames_in_used..total_frames, 0);
+            }
+        }
+        self.saved_frames = total_frames - frames_in_used;
+        let frames_out = self.trim.consume(nbr_chunks_ready * self.fft_size_out);
+        self.input_frames_total += self.chunk_size_in as u64;
+        self.output_frames_total += frames_out as u64;
+        Ok(frames_out)
+    }
+}
+
+impl<T> fmt::Debug for FftFixedIn<T>
+where
+    T: Sample,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FftFixedIn")
+            .field("nbr_channels", &self.nbr_channels)
+            .field("chunk_size_in", &self.chunk_size_in)
+            .field("fft_size_in", &self.fft_size_in)
+            .field("fft_size_out", &self.fft_size_out)
+            .field("sub_chunks", &self.sub_chunks())
+            .field("internal_block_frames", &self.internal_block_frames())
+            .finish()
+    }
+}
+
+impl<T> Resampler<T> for FftFixedIn<T>
+where
+    T: Sample,
+{
+    /// Query for the number of frames needed for the next call to "process".
+    fn nbr_frames_needed(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    /// Get the maximum number of input frames, which is always the input chunk size.
+    fn input_frames_max(&self) -> usize {
+        self.chunk_size_in
+    }
+
+    /// Get the maximum number of output frames.
+    /// This is reached when the frames saved from the previous call complete an extra FFT chunk.
+    /// After a change of sample rates, this is the largest for all the rates used.
+    fn output_frames_max(&self) -> usize {
+        let chunks = (self.chunk_size_in as f32 / self.fft_size_in as f32).ceil() as usize;
+        (chunks * self.fft_size_out).max(self.frames_max_seen.1)
+    }
+
+    /// Reset the resampler state and clear all internal buffers.
+    fn reset(&mut self) {
+        for overlap in self.overlaps.iter_mut() {
+            overlap.iter_mut().for_each(|x| *x = T::zero());
+        }
+        for buf in self.input_buffers.iter_mut() {
+            buf.iter_mut().for_each(|x| *x = T::zero());
+        }
+        self.saved_frames = 0;
+        self.input_frames_total = 0;
+        self.output_frames_final = None;
+        self.output_frames_total = 0;
+        self.trim.restart(self.resampler.output_delay());
+    }
+
+    fn reset_channel(&mut self, channel: usize) -> ResampleResult<()> {
+        if channel >= self.nbr_channels {
+            return Err(ResampleError::InvalidChannel {
+                channel,
+                nbr_channels: self.nbr_channels,
+            });
+        }
+        self.overlaps[channel]
+            .iter_mut()
+            .for_each(|x| *x = T::zero());
+        self.input_buffers[channel]
+            .iter_mut()
+            .for_each(|x| *x = T::zero());
+        Ok(())
+    }
+
+    fn nbr_channels(&self) -> usize {
+        self.nbr_channels
+    }
+
+    fn set_nbr_channels(&mut self, nbr_channels: usize) -> ResampleResult<()> {
+        if nbr_channels == 0 {
+            return Err(ResampleError::BadNbrChannelsUpdate);
+        }
+        trace!("Change number of channels to {}", nbr_channels);
+        let overlap_len = self.resampler.overlap_len();
+        resize_channels(&mut self.overlaps, nbr_channels, overlap_len);
+        let buffer_len = self.chunk_size_in + self.fft_size_in;
+        resize_channels(&mut self.input_buffers, nbr_channels, buffer_len);
+        resize_mask(&mut self.channel_mask, nbr_channels);
+        resize_mask(&mut self.active_channels_mask, nbr_channels);
+        self.nbr_channels = nbr_channels;
+        Ok(())
+    }
+
+    /// Get the resample ratio, given by the FFT sizes.
+    fn resample_ratio(&self) -> f64 {
+        self.fft_size_out as f64 / self.fft_size_in as f64
+    }
+
+    fn input_frames_total(&self) -> u64 {
+        self.input_frames_total
+    }
 
-        let overlaps: Vec<Vec<T>> = vec![vec![T::zero(); fft_size_out]; nbr_channels];
-        let input_buffers: Vec<Vec<T>> =
-            vec![vec![T::zero(); chunk_size_in + fft_size_out]; nbr_channels];
+    fn output_frames_total(&self) -> u64 {
+        self.output_frames_total
+    }
 
-        let saved_frames = 0;
+    fn set_active_channels_mask(&mut self, mask: Option<&[bool]>) -> ResampleResult<()> {
+        store_mask(&mut self.active_channels_mask, mask)
+    }
 
-        FftFixedIn {
-            nbr_channels,
-            chunk_size_in,
-            fft_size_in,
-            fft_size_out,
-            overlaps,
-            input_buffers,
-            saved_frames,
-            resampler,
-        }
+    fn active_channels_mask(&self) -> &[bool] {
+        &self.active_channels_mask
     }
-}
 
-impl<T> Resampler<T> for FftFixedIn<T>
-where
-    T: Sample,
-{
-    /// Query for the number of frames needed for the next call to "process".
-    fn nbr_frames_needed(&self) -> usize {
-        self.chunk_size_in
+    fn set_inactive_channel_output(&mut self, inactive_output: InactiveOutput) {
+        self.inactive_output = inactive_output;
+    }
+
+    fn set_validate_input(&mut self, enabled: bool) {
+        self.validate_input = enabled;
+    }
+
+    fn set_flush_denormals(&mut self, enabled: bool) {
+        self.flush_denormals = enabled;
+    }
+
+    fn set_trim_delay(&mut self, enabled: bool) {
+        let started = self.input_frames_total > 0;
+        self.trim
+            .set_enabled(enabled, self.resampler.output_delay(), started);
     }
 
     /// Resample a chunk of audio. The required input length is provided by
@@ -498,89 +2946,245 @@ where
     /// equal to the number of channels defined when creating the instance,
     /// and the number of audio frames given by "nbr_frames_needed".
     fn process<V: AsRef<[T]>>(&mut self, wave_in: &[V]) -> ResampleResult<Vec<Vec<T>>> {
-        if wave_in.len() != self.nbr_channels {
-            return Err(ResampleError::WrongNumberOfChannels {
-                expected: self.nbr_channels,
-                actual: wave_in.len(),
-            });
-        }
-        let mut used_channels = Vec::new();
-        for (chan, wave) in wave_in.iter().enumerate() {
-            let wave = wave.as_ref();
-            if !wave.is_empty() {
-                used_channels.push(chan);
-                if wave.len() != self.chunk_size_in {
-                    return Err(ResampleError::WrongNumberOfFrames {
-                        channel: chan,
-                        expected: self.chunk_size_in,
-                        actual: wave.len(),
-                    });
-                }
+        let overlaps = &mut self.overlaps;
+        let input_buffers = &mut self.input_buffers;
+        update_mask_from_buffers(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            wave_in,
+            self.chunk_size_in,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                input_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_out =
+            (self.saved_frames + self.chunk_size_in) / self.fft_size_in * self.fft_size_out;
+        let mut wave_out = vec![Vec::new(); self.nbr_channels];
+        for (chan, active) in self.channel_mask.iter().enumerate() {
+            if *active {
+                wave_out[chan] = vec![T::zero(); frames_out];
             }
         }
-
-        let mut input_temp = vec![Vec::new(); self.nbr_channels];
-        for chan in used_channels.iter() {
-            input_temp[*chan] = vec![T::zero(); self.saved_frames + self.chunk_size_in];
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, 0, self.chunk_size_in),
+            &mut SequentialOutput::new(&mut wave_out, 0),
+        )?;
+        for chan in wave_out.iter_mut() {
+            chan.truncate(frames_out);
         }
+        Ok(wave_out)
+    }
 
-        // copy new samples to input buffer
-        for n in used_channels.iter() {
-            for (input, buffer) in self.input_buffers[*n]
-                .iter()
-                .take(self.saved_frames)
-                .zip(input_temp[*n].iter_mut())
-            {
-                *buffer = *input;
-            }
-        }
-        for n in used_channels.iter() {
-            for (input, buffer) in wave_in[*n].as_ref().iter().zip(
-                input_temp[*n]
-                    .iter_mut()
-                    .skip(self.saved_frames)
-                    .take(self.chunk_size_in),
-            ) {
-                *buffer = *input;
-            }
-        }
-        self.saved_frames += self.chunk_size_in;
+    /// Resample a chunk of interleaved audio. The input length is fixed, and the output varies in length.
+    /// # Errors
+    ///
+    /// The function returns an error if the input doesn't contain the number of frames
+    /// given by "nbr_frames_needed", if the output buffer is shorter than "output_frames_max",
+    /// or if the mask doesn't match the number of channels.
+    fn process_interleaved_into_buffer(
+        &mut self,
+        wave_in: &[T],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        validate_interleaved(
+            wave_in,
+            wave_out,
+            self.nbr_channels,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let overlaps = &mut self.overlaps;
+        let input_buffers = &mut self.input_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                input_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out = self.process_buffers(
+            &InterleavedInput::new(wave_in, self.nbr_channels, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
 
-        let nbr_chunks_ready =
-            (self.saved_frames as f32 / self.fft_size_in as f32).floor() as usize;
-        let mut wave_out = vec![Vec::new(); self.nbr_channels];
-        for chan in used_channels.iter() {
-            wave_out[*chan] = vec![T::zero(); nbr_chunks_ready * self.fft_size_out];
-        }
-        for n in used_channels.iter() {
-            for (in_chunk, out_chunk) in input_temp[*n]
-                .chunks(self.fft_size_in)
-                .take(nbr_chunks_ready)
-                .zip(wave_out[*n].chunks_mut(self.fft_size_out))
-            {
-                self.resampler
-                    .resample_unit(in_chunk, out_chunk, &mut self.overlaps[*n]);
-            }
-        }
+    /// Resample a chunk of audio from sequential input buffers, starting at frame `input_offset`,
+    /// and write the result to sequential output buffers, starting at frame `output_offset`.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// `input_offset` plus "nbr_frames_needed" frames, if an active output channel holds fewer
+    /// than `output_offset` plus "output_frames_max" frames, or if the number of channels
+    /// of the buffers or the mask is wrong.
+    fn process_into_buffer_at<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        input_offset: usize,
+        wave_out: &mut [U],
+        output_offset: usize,
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let input_buffers = &mut self.input_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                input_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential(
+            wave_in,
+            input_offset,
+            wave_out,
+            output_offset,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = SequentialOutput::new(wave_out, output_offset);
+        let frames_out = self.process_buffers(
+            &SequentialInput::new(wave_in, input_offset, frames_in),
+            &mut output,
+        )?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
 
-        // save extra frames for next round
-        let frames_in_used = nbr_chunks_ready * self.fft_size_in;
-        let extra = self.saved_frames - frames_in_used;
-
-        if self.saved_frames > frames_in_used {
-            for n in used_channels.iter() {
-                for (input, buffer) in input_temp[*n]
-                    .iter()
-                    .skip(frames_in_used)
-                    .take(extra)
-                    .zip(self.input_buffers[*n].iter_mut())
-                {
-                    *buffer = *input;
-                }
-            }
-        }
-        self.saved_frames = extra;
-        Ok(wave_out)
+    /// Resample a chunk of audio from sequential input buffers,
+    /// and write the result to an interleaved output buffer.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds fewer than
+    /// "nbr_frames_needed" frames, if the output buffer is shorter than "output_frames_max" frames,
+    /// or if the number of channels of the input or the mask is wrong.
+    fn process_into_interleaved_buffer<V: AsRef<[T]>>(
+        &mut self,
+        wave_in: &[V],
+        wave_out: &mut [T],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_in = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let input_buffers = &mut self.input_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                input_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        validate_sequential_to_interleaved(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_in,
+            self.output_frames_max(),
+        )?;
+        let mut output = InterleavedOutput::new(wave_out, self.nbr_channels);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the last frames of a stream, when fewer frames than needed remain.
+    /// The missing input frames are replaced by zeros, and the input may also be `None`,
+    /// which processes only zeros.
+    /// # Errors
+    ///
+    /// The function returns an error if an active input channel holds more than
+    /// "nbr_frames_needed" frames, if the active input channels have different lengths,
+    /// if an active output channel holds fewer than "output_frames_max" frames,
+    /// or if the number of channels of the buffers or the mask is wrong.
+    fn process_partial_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let frames_needed = self.nbr_frames_needed();
+        let overlaps = &mut self.overlaps;
+        let input_buffers = &mut self.input_buffers;
+        update_mask(
+            &mut self.channel_mask,
+            &self.active_channels_mask,
+            active_channels_mask,
+            |chan| {
+                overlaps[chan].iter_mut().for_each(|x| *x = T::zero());
+                input_buffers[chan].iter_mut().for_each(|x| *x = T::zero());
+            },
+        )?;
+        let frames_in = validate_partial(
+            wave_in,
+            wave_out,
+            &self.channel_mask,
+            frames_needed,
+            self.output_frames_max(),
+        )?;
+        let wave_in = wave_in.unwrap_or(&[]);
+        let mut output = SequentialOutput::new(wave_out, 0);
+        let frames_out =
+            self.process_buffers(&SequentialInput::new(wave_in, 0, frames_in), &mut output)?;
+        fill_inactive(
+            &mut output,
+            &self.channel_mask,
+            self.inactive_output,
+            frames_out,
+        );
+        Ok((frames_in, frames_out))
+    }
+
+    /// Resample the end of a stream, and flush out the frames held back by the resampler.
+    /// Only the output frames that correspond to real input are counted,
+    /// and zero frames are returned once the stream has been flushed.
+    /// # Errors
+    ///
+    /// The function returns an error under the same conditions as "process_partial_into_buffer".
+    fn process_final_into_buffer<V: AsRef<[T]>, U: AsMut<[T]>>(
+        &mut self,
+        wave_in: Option<&[V]>,
+        wave_out: &mut [U],
+        active_channels_mask: Option<&[bool]>,
+    ) -> ResampleResult<(usize, usize)> {
+        let mut output_frames_final = self.output_frames_final;
+        let result = process_final(
+            self,
+            &mut output_frames_final,
+            wave_in,
+            wave_out,
+            active_channels_mask,
+        );
+        self.output_frames_final = output_frames_final;
+        result
     }
 
     /// Update the resample ratio. This is not supported by this resampler and
@@ -594,16 +3198,59 @@ where
     fn set_resample_ratio_relative(&mut self, _rel_ratio: f64) -> ResampleResult<()> {
         Err(ResampleError::SyncNotAdjustable)
     }
+
+    /// The resample ratio can't be changed, so this always returns 1.0.
+    fn max_resample_ratio_relative(&self) -> f64 {
+        1.0
+    }
+
+    /// Update the range of allowed resample ratios. This is not supported by this resampler
+    /// and always returns an error.
+    fn set_max_resample_ratio_relative(&mut self, _max_relative: f64) -> ResampleResult<()> {
+        Err(ResampleError::SyncNotAdjustable)
+    }
+
+    /// Get the delay in output frames, given by the length of the FFT.
+    // The ratio is a fraction of two integers, so the number of frames can be calculated exactly.
+    fn output_frames_for_input(&self, total_input_frames: u64) -> u64 {
+        let size_in = self.fft_size_in as u64;
+        let size_out = self.fft_size_out as u64;
+        self.output_delay() as u64 + (total_input_frames * size_out + size_in - 1) / size_in
+    }
+
+    fn output_delay_fractional(&self) -> f64 {
+        self.resampler.output_delay() - self.trim.frames() as f64
+    }
+
+    fn frequency_response(&self, n_points: usize) -> Vec<(f64, f64)> {
+        let response = self.resampler.filter_response();
+        frequency_response(self.resample_ratio(), n_points, |freq| {
+            response.magnitude(freq)
+        })
+    }
+
+    fn group_delay(&self) -> f64 {
+        self.resampler.filter_response().delay()
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::synchro::{FftFixedIn, FftFixedInOut, FftFixedOut, FftResampler};
-    use crate::Resampler;
+    use crate::sample::CoerceFrom;
+    use crate::synchro::{split_blocks, two_stage_sizes, FftOverlap};
+    use crate::synchro::{FftFilter, FftFixedIn, FftFixedInOut, FftFixedOut, FftResampler};
+    use crate::windows::{make_window, WindowFunction};
+    use crate::{FilterPhase, ResampleError, Resampler, ResamplerConstructionError, Sample};
+    use realfft::RealFftPlanner;
 
     #[test]
     fn resample_unit() {
-        let mut resampler = FftResampler::<f64>::new(147, 1000);
+        let mut resampler = FftResampler::<f64>::new_with_filter(
+            147,
+            1000,
+            FftFilter::default(),
+            &mut RealFftPlanner::new(),
+        );
         let mut wave_in = vec![0.0; 147];
 
         wave_in[0] = 0.3;
@@ -613,9 +3260,10 @@ mod tests {
         wave_in[4] = 0.7;
         wave_in[5] = 0.3;
 
-        let mut wave_out = vec![0.0; 1000];
         let mut overlap = vec![0.0; 1000];
-        resampler.resample_unit(&wave_in, &mut wave_out, &mut overlap);
+        resampler.input_buf[0..147].copy_from_slice(&wave_in);
+        resampler.resample_input_buf(&mut overlap);
+        let wave_out = &resampler.output_buf[0..1000];
         let vecsum = wave_out.iter().sum::<f64>();
         let maxval = wave_out.iter().cloned().fold(0. / 0., f64::max);
         assert!((vecsum - 4.0 * 1000.0 / 147.0).abs() < 1.0e-6);
@@ -737,6 +3385,597 @@ mod tests {
         assert_eq!(out[0].len(), 4411);
     }
 
+    #[test]
+    fn shared_planner() {
+        let mut planner = RealFftPlanner::<f64>::new();
+        let waves = vec![(0..1024)
+            .map(|n| (0.1 * n as f64).sin())
+            .collect::<Vec<f64>>()];
+        let mut reference = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1);
+        let expected = reference.process(&waves).unwrap();
+        for _ in 0..3 {
+            let mut resampler =
+                FftFixedIn::<f64>::new_with_planner(&mut planner, 44100, 48000, 1024, 2, 1);
+            assert_eq!(resampler.process(&waves).unwrap(), expected);
+        }
+        let mut reference = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 1);
+        let mut resampler =
+            FftFixedOut::<f64>::new_with_planner(&mut planner, 44100, 48000, 1024, 2, 1);
+        let waves = vec![vec![0.5; reference.nbr_frames_needed()]];
+        assert_eq!(
+            resampler.process(&waves).unwrap(),
+            reference.process(&waves).unwrap()
+        );
+        let mut reference = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1);
+        let mut resampler =
+            FftFixedInOut::<f64>::new_with_planner(&mut planner, 44100, 48000, 1024, 1);
+        let waves = vec![vec![0.5; reference.nbr_frames_needed()]];
+        assert_eq!(
+            resampler.process(&waves).unwrap(),
+            reference.process(&waves).unwrap()
+        );
+    }
+
+    #[test]
+    fn chunk_size_for_latency() {
+        // A block is 147 input and 160 output frames.
+        // Eight blocks give 1280 + 640 frames, nine give 1440 + 719.5.
+        assert_eq!(
+            FftFixedInOut::<f64>::chunk_size_for_latency(44100, 48000, 2000),
+            Ok(1176)
+        );
+        let resampler = FftFixedInOut::<f64>::new_with_latency(48000, 44100, 2000, 1).unwrap();
+        let latency = resampler.chunk_size_out() as f64 + resampler.output_delay_fractional();
+        assert!(latency <= 2000.0 && latency > 2000.0 - 1.5 * 147.0);
+        // Two FFTs of five blocks give 1600 + 399.5 frames.
+        assert_eq!(
+            FftFixedIn::<f64>::chunk_size_for_latency(44100, 48000, 2000, 2),
+            Ok(1470)
+        );
+        let resampler = FftFixedIn::<f64>::new_with_latency(44100, 48000, 2000, 2, 1).unwrap();
+        assert_eq!(resampler.nbr_frames_needed(), 1470);
+        assert_eq!(
+            FftFixedOut::<f64>::chunk_size_for_latency(44100, 48000, 2000, 2),
+            Ok(1600)
+        );
+        let resampler = FftFixedOut::<f64>::new_with_latency(44100, 48000, 2000, 2, 1).unwrap();
+        assert_eq!(resampler.chunk_size_out(), 1600);
+        assert_eq!(resampler.nbr_frames_needed(), 1470);
+        // A block is 441 input and 320 output frames, with a delay of 220 input frames.
+        assert_eq!(
+            FftFixedInOut::<f64>::chunk_size_for_latency(44100, 32000, 400),
+            Err(ResamplerConstructionError::LatencyTooLow {
+                max_latency: 400,
+                min_latency: 480
+            })
+        );
+        assert_eq!(
+            FftFixedInOut::<f64>::chunk_size_for_latency(44100, 32000, 480),
+            Ok(441)
+        );
+        match FftFixedIn::<f64>::new_with_latency(44100, 32000, 700, 2, 1) {
+            Err(ResamplerConstructionError::LatencyTooLow { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert_eq!(
+            FftFixedOut::<f64>::chunk_size_for_latency(0, 48000, 2000, 2),
+            Err(ResamplerConstructionError::InvalidSampleRate {
+                fs_in: 0,
+                fs_out: 48000
+            })
+        );
+        assert_eq!(
+            FftFixedIn::<f64>::chunk_size_for_latency(44100, 48000, 2000, 0),
+            Err(ResamplerConstructionError::InvalidChunkSize)
+        );
+    }
+
+    fn assert_same_output<R: Resampler<f64>>(resampler: &mut R, reference: &mut R) {
+        for n in 0..3 {
+            let frames = reference.nbr_frames_needed();
+            assert_eq!(resampler.nbr_frames_needed(), frames);
+            let waves = vec![(0..frames)
+                .map(|m| (0.05 * (n * frames + m) as f64).sin())
+                .collect::<Vec<f64>>()];
+            assert_eq!(
+                resampler.process(&waves).unwrap(),
+                reference.process(&waves).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn change_sample_rates() {
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1);
+        resampler.change_sample_rates(48000, 44100).unwrap();
+        let mut reference = FftFixedInOut::<f64>::new(48000, 44100, 1024, 1);
+        assert_same_output(&mut resampler, &mut reference);
+        resampler.change_sample_rates(44100, 48000).unwrap();
+        assert!(resampler.resampler.previous.is_some());
+        let mut reference = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1);
+        assert_same_output(&mut resampler, &mut reference);
+        assert_eq!(resampler.input_frames_max(), 1120);
+        assert_eq!(resampler.output_frames_max(), 1120);
+        match resampler.change_sample_rates(0, 48000) {
+            Err(ResampleError::InvalidSampleRate { .. }) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 1);
+        resampler.change_sample_rates(48000, 44100).unwrap();
+        let mut reference = FftFixedIn::<f64>::new(48000, 44100, 1024, 2, 1);
+        assert_same_output(&mut resampler, &mut reference);
+        assert_eq!(resampler.fft_size_in(), 640);
+        assert_eq!(resampler.output_frames_max(), 1280);
+
+        let mut resampler = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 1);
+        resampler.change_sample_rates(48000, 44100).unwrap();
+        let mut reference = FftFixedOut::<f64>::new(48000, 44100, 1024, 2, 1);
+        assert_same_output(&mut resampler, &mut reference);
+        resampler.change_sample_rates(44100, 48000).unwrap();
+        let mut reference = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 1);
+        assert_same_output(&mut resampler, &mut reference);
+        assert_eq!(resampler.input_frames_max(), 1280);
+
+        let mut resampler =
+            FftFixedInOut::<f64>::new_with_adjust(44100, 48000, 1024, 1, 100.0).unwrap();
+        resampler.set_resample_ratio_relative(1.0001).unwrap();
+        let adjust = resampler.resample_ratio() / (48000.0 / 44100.0);
+        resampler.change_sample_rates(48000, 44100).unwrap();
+        assert_eq!(resampler.chunk_size_out(), 1029);
+        assert!((resampler.resample_ratio() / (44100.0 / 48000.0) - adjust).abs() < 1.0e-9);
+        let waves = vec![vec![0.0; resampler.nbr_frames_needed()]];
+        assert_eq!(resampler.process(&waves).unwrap()[0].len(), 1029);
+    }
+
+    fn impulse_peak_position<R: Resampler<f64>>(resampler: &mut R, impulse_pos: usize) -> usize {
+        let mut output = Vec::new();
+        let mut frames_fed = 0;
+        while frames_fed < impulse_pos + 8192 {
+            let frames = resampler.nbr_frames_needed();
+            let mut waves = vec![vec![0.0f64; frames]];
+            if impulse_pos >= frames_fed && impulse_pos < frames_fed + frames {
+                waves[0][impulse_pos - frames_fed] = 1.0;
+            }
+            frames_fed += frames;
+            output.extend_from_slice(&resampler.process(&waves).unwrap()[0]);
+        }
+        let mut peak = 0;
+        for (n, value) in output.iter().enumerate() {
+            if value.abs() > output[peak].abs() {
+                peak = n;
+            }
+        }
+        peak
+    }
+
+    fn check_output_delay<R: Resampler<f64>>(mut resampler: R, fs_in: usize, fs_out: usize) {
+        let impulse_pos = 300;
+        let expected =
+            impulse_pos as f64 * fs_out as f64 / fs_in as f64 + resampler.output_delay_fractional();
+        let peak = impulse_peak_position(&mut resampler, impulse_pos);
+        assert!(
+            (peak as f64 - expected).abs() < 0.5,
+            "Peak at {}, expected {} for {} -> {}",
+            peak,
+            expected,
+            fs_in,
+            fs_out
+        );
+        assert_eq!(
+            resampler.output_delay(),
+            resampler.output_delay_fractional().round() as usize
+        );
+    }
+
+    #[test]
+    fn output_delay() {
+        let rates = [
+            (44100, 48000),
+            (48000, 96000),
+            (96000, 44100),
+            (44100, 32000),
+            (22050, 48000),
+        ];
+        for (fs_in, fs_out) in rates.iter() {
+            let resampler = FftFixedInOut::<f64>::new(*fs_in, *fs_out, 1024, 1);
+            check_output_delay(resampler, *fs_in, *fs_out);
+            let resampler = FftFixedIn::<f64>::new(*fs_in, *fs_out, 1024, 2, 1);
+            check_output_delay(resampler, *fs_in, *fs_out);
+            let resampler = FftFixedOut::<f64>::new(*fs_in, *fs_out, 1024, 2, 1);
+            check_output_delay(resampler, *fs_in, *fs_out);
+        }
+    }
+
+    #[test]
+    fn two_stage_split() {
+        assert_eq!(split_blocks(441, 320), Some(((21, 16), (21, 20))));
+        assert_eq!(split_blocks(320, 441), Some(((20, 21), (16, 21))));
+        assert_eq!(split_blocks(320, 147), Some(((20, 21), (16, 7))));
+        assert_eq!(split_blocks(147, 320), Some(((7, 16), (21, 20))));
+        // Small enough for a single stage.
+        assert_eq!(split_blocks(147, 160), None);
+        // No split gives small enough blocks, since 4411 is 11 * 401.
+        assert_eq!(split_blocks(4410, 4411), None);
+        assert_eq!(two_stage_sizes(441, 320, 4), Some(((588, 448), (672, 640))));
+        // A chunk shorter than the smallest FFT is handled by a single FFT per stage.
+        assert_eq!(two_stage_sizes(441, 320, 1), Some(((441, 336), (336, 320))));
+    }
+
+    // Resample a sine of `freq` Hz, and fit a sine and a cosine at the output rate
+    // to the output, after skipping the start. Returns the amplitude and the rms of the residual.
+    fn fit_sine<T, R>(resampler: &mut R, fs_in: usize, fs_out: usize, freq: f64) -> (f64, f64)
+    where
+        T: Sample,
+        R: Resampler<T>,
+        f64: CoerceFrom<T>,
+    {
+        resampler.reset();
+        let omega_in = 2.0 * std::f64::consts::PI * freq / fs_in as f64;
+        let mut position = 0;
+        let mut output = Vec::new();
+        while output.len() < 20000 {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<T> = (position..position + frames)
+                .map(|n| T::coerce((omega_in * n as f64).sin()))
+                .collect();
+            position += frames;
+            let wave_out = resampler.process(&[wave]).unwrap();
+            output.extend(wave_out[0].iter().map(|y| f64::coerce_from(*y)));
+        }
+        let omega_out = 2.0 * std::f64::consts::PI * freq / fs_out as f64;
+        let skip = 5000;
+        let (mut ss, mut sc, mut cc, mut ys, mut yc) = (0.0, 0.0, 0.0, 0.0, 0.0);
+        for (n, y) in output.iter().enumerate().skip(skip) {
+            let (s, c) = (omega_out * n as f64).sin_cos();
+            ss += s * s;
+            sc += s * c;
+            cc += c * c;
+            ys += y * s;
+            yc += y * c;
+        }
+        let det = ss * cc - sc * sc;
+        let a = (ys * cc - yc * sc) / det;
+        let b = (yc * ss - ys * sc) / det;
+        let residual = output
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .map(|(n, y)| {
+                let (s, c) = (omega_out * n as f64).sin_cos();
+                (y - a * s - b * c).powi(2)
+            })
+            .sum::<f64>();
+        (
+            (a * a + b * b).sqrt(),
+            (residual / (output.len() - skip) as f64).sqrt(),
+        )
+    }
+
+    #[test]
+    fn two_stages() {
+        let rates = [
+            (44100, 32000, 1764, 1280),
+            (32000, 44100, 960, 1323),
+            (48000, 22050, 2240, 1029),
+            (22050, 48000, 588, 1280),
+        ];
+        for (fs_in, fs_out, chunk_in, chunk_out) in rates.iter() {
+            let mut resampler = FftFixedInOut::<f64>::new(*fs_in, *fs_out, 1024, 1);
+            assert_eq!(resampler.nbr_stages(), 2);
+            assert_eq!(resampler.chunk_size_in(), *chunk_in);
+            assert_eq!(
+                resampler.sub_chunks() * resampler.internal_block_frames(),
+                *chunk_in
+            );
+            assert_eq!(resampler.chunk_size_out(), *chunk_out);
+            assert_eq!(resampler.nbr_frames_needed(), *chunk_in);
+            assert_eq!(resampler.output_frames_max(), *chunk_out);
+
+            // A constant level passes through both stages, with the expected number of frames.
+            let input = vec![vec![0.5; 10000]];
+            let output = resampler.process_all(&input).unwrap();
+            let expected = (10000.0 * *fs_out as f64 / *fs_in as f64).ceil() as usize;
+            assert_eq!(output[0].len(), expected);
+            for value in output[0][expected / 4..3 * expected / 4].iter() {
+                assert!(
+                    (value - 0.5).abs() < 1.0e-3,
+                    "{} for {} -> {}",
+                    value,
+                    fs_in,
+                    fs_out
+                );
+            }
+
+            let lower_nyquist = 0.5 * (*fs_in.min(fs_out)) as f64;
+            for freq in [1000.0, 0.8 * lower_nyquist].iter() {
+                let (amplitude, residual) = fit_sine(&mut resampler, *fs_in, *fs_out, *freq);
+                assert!(
+                    (amplitude - 1.0).abs() < 1.0e-3,
+                    "amplitude {} at {} Hz for {} -> {}",
+                    amplitude,
+                    freq,
+                    fs_in,
+                    fs_out
+                );
+                assert!(
+                    residual < 1.0e-4,
+                    "residual {} at {} Hz for {} -> {}",
+                    residual,
+                    freq,
+                    fs_in,
+                    fs_out
+                );
+            }
+            if fs_out < fs_in {
+                // A sine above the output Nyquist frequency is removed.
+                let freq = 0.5 * (lower_nyquist + 0.5 * *fs_in as f64);
+                let (amplitude, residual) = fit_sine(&mut resampler, *fs_in, *fs_out, freq);
+                assert!(amplitude.max(residual) < 1.0e-4, "level {}", residual);
+            }
+        }
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1);
+        assert_eq!(resampler.nbr_stages(), 1);
+    }
+
+    // Check that a sine in the passband keeps its level within 0.01 dB.
+    fn check_unity_gain<T, R>(resampler: &mut R, fs_in: usize, fs_out: usize)
+    where
+        T: Sample,
+        R: Resampler<T>,
+        f64: CoerceFrom<T>,
+    {
+        let lower_nyquist = 0.5 * fs_in.min(fs_out) as f64;
+        for rel_freq in [0.05, 0.4, 0.7].iter() {
+            let freq = rel_freq * lower_nyquist;
+            let (amplitude, _) = fit_sine(resampler, fs_in, fs_out, freq);
+            let level = 20.0 * amplitude.log10();
+            assert!(
+                level.abs() < 0.01,
+                "level {} dB at {} Hz for {} -> {}",
+                level,
+                freq,
+                fs_in,
+                fs_out
+            );
+        }
+    }
+
+    fn check_unity_gain_all<T>(fs_in: usize, fs_out: usize)
+    where
+        T: Sample,
+        f64: CoerceFrom<T>,
+    {
+        let mut resampler = FftFixedIn::<T>::new(fs_in, fs_out, 1024, 2, 1);
+        check_unity_gain(&mut resampler, fs_in, fs_out);
+        let mut resampler = FftFixedOut::<T>::new(fs_in, fs_out, 1024, 2, 1);
+        check_unity_gain(&mut resampler, fs_in, fs_out);
+        let mut resampler = FftFixedInOut::<T>::new(fs_in, fs_out, 1024, 1);
+        check_unity_gain(&mut resampler, fs_in, fs_out);
+    }
+
+    #[test]
+    fn unity_gain() {
+        let rates = [
+            (44100, 48000),
+            (48000, 44100),
+            (44100, 88200),
+            (96000, 44100),
+            (44100, 32000),
+            (22050, 48000),
+        ];
+        for (fs_in, fs_out) in rates.iter() {
+            check_unity_gain_all::<f32>(*fs_in, *fs_out);
+            check_unity_gain_all::<f64>(*fs_in, *fs_out);
+        }
+    }
+
+    #[test]
+    fn set_gain() {
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 32000, 1024, 1);
+        assert_eq!(resampler.gain(), 1.0);
+        resampler.set_gain(0.5).unwrap();
+        let (amplitude, _) = fit_sine(&mut resampler, 44100, 32000, 1000.0);
+        assert!((amplitude - 0.5).abs() < 1.0e-3, "amplitude {}", amplitude);
+        // The gain is kept when the sample rates change.
+        resampler.change_sample_rates(48000, 44100).unwrap();
+        assert_eq!(resampler.gain(), 0.5);
+        let (amplitude, _) = fit_sine(&mut resampler, 48000, 44100, 1000.0);
+        assert!((amplitude - 0.5).abs() < 1.0e-3, "amplitude {}", amplitude);
+
+        let mut resampler = FftFixedIn::<f32>::new(44100, 48000, 1024, 2, 1);
+        resampler.set_gain(0.0).unwrap();
+        let (amplitude, _) = fit_sine(&mut resampler, 44100, 48000, 1000.0);
+        assert_eq!(amplitude, 0.0);
+        let mut resampler = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 1);
+        for gain in [-0.5, std::f64::NAN, std::f64::INFINITY].iter() {
+            match resampler.set_gain(*gain) {
+                Err(ResampleError::BadGainUpdate) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+        assert_eq!(resampler.gain(), 1.0);
+    }
+
+    fn sqrt_hann() -> FftFilter {
+        FftFilter {
+            overlap: FftOverlap::SqrtHann,
+            ..FftFilter::default()
+        }
+    }
+
+    // Resample a noise-like signal at a ratio of 1:1,
+    // and check that the output is the input delayed by the output delay.
+    fn check_reconstruction<R: Resampler<f64>>(mut resampler: R) {
+        let input: Vec<f64> = (0..20000)
+            .map(|n| ((n * 7919) % 1000) as f64 / 500.0 - 1.0)
+            .collect();
+        let mut output = Vec::new();
+        let mut position = 0;
+        while output.len() < 10000 {
+            let frames = resampler.nbr_frames_needed();
+            let chunk = &input[position..position + frames];
+            position += frames;
+            output.extend_from_slice(&resampler.process(&[chunk]).unwrap()[0]);
+        }
+        let delay = resampler.output_delay();
+        for (n, value) in output.iter().enumerate().skip(delay) {
+            assert!(
+                (value - input[n - delay]).abs() < 1.0e-12,
+                "{} instead of {} at {}",
+                value,
+                input[n - delay],
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn sqrt_hann_reconstruction() {
+        let resampler = FftFixedInOut::<f64>::new_with_filter(48000, 48000, 512, 1, sqrt_hann());
+        let resampler = resampler.unwrap();
+        assert_eq!(resampler.output_delay(), resampler.fft_size_out());
+        check_reconstruction(resampler);
+        let resampler = FftFixedIn::<f64>::new_with_filter(48000, 48000, 512, 2, 1, sqrt_hann());
+        check_reconstruction(resampler.unwrap());
+        let resampler = FftFixedOut::<f64>::new_with_filter(48000, 48000, 512, 2, 1, sqrt_hann());
+        check_reconstruction(resampler.unwrap());
+    }
+
+    #[test]
+    fn sqrt_hann_resampling() {
+        for (fs_in, fs_out) in [(44100, 48000), (48000, 44100)].iter() {
+            let mut resampler =
+                FftFixedInOut::<f64>::new_with_filter(*fs_in, *fs_out, 1024, 1, sqrt_hann())
+                    .unwrap();
+            // The delay is a whole block instead of half a block.
+            assert_eq!(resampler.output_delay(), resampler.fft_size_out());
+            let lower_nyquist = 0.5 * (*fs_in.min(fs_out)) as f64;
+            for freq in [1000.0, 0.5 * lower_nyquist].iter() {
+                let (amplitude, residual) = fit_sine(&mut resampler, *fs_in, *fs_out, *freq);
+                assert!(
+                    (amplitude - 1.0).abs() < 1.0e-3 && residual < 1.0e-3,
+                    "amplitude {}, residual {} at {} Hz for {} -> {}",
+                    amplitude,
+                    residual,
+                    freq,
+                    fs_in,
+                    fs_out
+                );
+            }
+        }
+    }
+
+    fn minimum_phase() -> FftFilter {
+        FftFilter {
+            phase: FilterPhase::Minimum,
+            ..FftFilter::default()
+        }
+    }
+
+    // Resample a unit impulse at input frame `position`,
+    // and return the output frame with the largest value.
+    fn impulse_peak<R: Resampler<f64>>(resampler: &mut R, position: usize) -> usize {
+        resampler.reset();
+        let mut output = Vec::new();
+        let mut start = 0;
+        while start < 2 * position {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (start..start + frames)
+                .map(|n| if n == position { 1.0 } else { 0.0 })
+                .collect();
+            start += frames;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+        let mut peak = 0;
+        for (n, value) in output.iter().enumerate() {
+            if value.abs() > output[peak].abs() {
+                peak = n;
+            }
+        }
+        peak
+    }
+
+    #[test]
+    fn minimum_phase_latency() {
+        let rates = [
+            (44100, 48000),
+            (48000, 44100),
+            (96000, 48000),
+            (44100, 32000),
+        ];
+        let position = 5000;
+        for (fs_in, fs_out) in rates.iter() {
+            let linear = FftFixedInOut::<f64>::new(*fs_in, *fs_out, 1024, 1);
+            let minimum =
+                FftFixedInOut::<f64>::new_with_filter(*fs_in, *fs_out, 1024, 1, minimum_phase())
+                    .unwrap();
+            assert!(linear.output_delay_fractional() > 100.0);
+            assert!(minimum.output_delay_fractional() < 10.0);
+            // The impulse comes out at the reported delay.
+            let ratio = *fs_out as f64 / *fs_in as f64;
+            for resampler in [linear, minimum].iter_mut() {
+                let expected = position as f64 * ratio + resampler.output_delay_fractional();
+                let peak = impulse_peak(resampler, position);
+                assert!(
+                    (peak as f64 - expected).abs() < 2.5,
+                    "peak at {} instead of {} for {} -> {}",
+                    peak,
+                    expected,
+                    fs_in,
+                    fs_out
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn minimum_phase_magnitude() {
+        let rates = [
+            (44100, 48000),
+            (48000, 44100),
+            (96000, 48000),
+            (44100, 32000),
+        ];
+        for (fs_in, fs_out) in rates.iter() {
+            let mut linear = FftFixedInOut::<f64>::new(*fs_in, *fs_out, 1024, 1);
+            let mut minimum =
+                FftFixedInOut::<f64>::new_with_filter(*fs_in, *fs_out, 1024, 1, minimum_phase())
+                    .unwrap();
+            // The magnitude responses agree in the passband.
+            let response_linear = linear.frequency_response(101);
+            let response_minimum = minimum.frequency_response(101);
+            for ((freq, lin), (_, min)) in response_linear.iter().zip(response_minimum.iter()) {
+                if *freq <= 0.9 {
+                    assert!(
+                        (lin - min).abs() < 0.01,
+                        "{} dB instead of {} dB at {} for {} -> {}",
+                        min,
+                        lin,
+                        freq,
+                        fs_in,
+                        fs_out
+                    );
+                }
+            }
+            // And so do the levels of resampled sines.
+            let lower_nyquist = 0.5 * (*fs_in.min(fs_out)) as f64;
+            for freq in [1000.0, 0.5 * lower_nyquist].iter() {
+                let (amplitude_linear, _) = fit_sine(&mut linear, *fs_in, *fs_out, *freq);
+                let (amplitude_minimum, _) = fit_sine(&mut minimum, *fs_in, *fs_out, *freq);
+                let diff_db = 20.0 * (amplitude_minimum / amplitude_linear).log10();
+                assert!(
+                    diff_db.abs() < 0.01,
+                    "{} dB at {} Hz for {} -> {}",
+                    diff_db,
+                    freq,
+                    fs_in,
+                    fs_out
+                );
+            }
+        }
+    }
+
     #[test]
     fn make_resampler_fo_unusualratio() {
         let mut resampler = FftFixedOut::<f64>::new(44100, 44110, 1024, 2, 2);
@@ -747,4 +3986,282 @@ mod tests {
         assert_eq!(out.len(), 2);
         assert_eq!(out[0].len(), 1024);
     }
+
+    fn process_sine_in_chunks<R, F>(
+        resampler: &mut R,
+        chunk_sizes: &[usize],
+        set_size: F,
+    ) -> Vec<f64>
+    where
+        R: Resampler<f64>,
+        F: Fn(&mut R, usize),
+    {
+        let mut position = 0;
+        let mut output = Vec::new();
+        for chunk_size in chunk_sizes {
+            set_size(resampler, *chunk_size);
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (0.01 * n as f64).sin())
+                .collect();
+            position += frames;
+            output.extend_from_slice(&resampler.process(&[wave]).unwrap()[0]);
+        }
+        output
+    }
+
+    #[test]
+    fn set_chunk_size() {
+        let chunk_sizes = [512, 64, 1000, 1, 300, 2000, 128, 512];
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 1);
+        let expected = process_sine_in_chunks(&mut resampler, &[512; 10], |_, _| {});
+        let mut resampler = FftFixedIn::<f64>::new(44100, 48000, 512, 2, 1);
+        let actual = process_sine_in_chunks(&mut resampler, &chunk_sizes, |r, size| {
+            r.set_chunk_size(size).unwrap();
+            assert_eq!(r.nbr_frames_needed(), size);
+        });
+        let len = expected.len().min(actual.len());
+        assert!(len > 4000);
+        assert_eq!(expected[..len], actual[..len]);
+        assert!(resampler.set_chunk_size(0).is_err());
+    }
+
+    #[test]
+    fn set_output_chunk_size() {
+        let chunk_sizes = [512, 64, 1000, 1, 300, 2000, 128, 512];
+        let mut resampler = FftFixedOut::<f64>::new(48000, 44100, 512, 2, 1);
+        let expected = process_sine_in_chunks(&mut resampler, &[512; 10], |_, _| {});
+        let mut resampler = FftFixedOut::<f64>::new(48000, 44100, 512, 2, 1);
+        let actual = process_sine_in_chunks(&mut resampler, &chunk_sizes, |r, size| {
+            r.set_output_chunk_size(size).unwrap();
+            assert_eq!(r.output_frames_max(), size);
+        });
+        let len = expected.len().min(actual.len());
+        assert!(len > 4000);
+        assert_eq!(expected[..len], actual[..len]);
+        assert!(resampler.set_output_chunk_size(0).is_err());
+    }
+
+    #[test]
+    fn debug_output() {
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2);
+        let text = format!("{:?}", resampler);
+        assert_eq!(
+            text,
+            "FftFixedIn { nbr_channels: 2, chunk_size_in: 1024, fft_size_in: 588, fft_size_out: 640, \
+             sub_chunks: 2, internal_block_frames: 588 }"
+        );
+    }
+
+    #[test]
+    fn construction_getters() {
+        let resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2);
+        assert_eq!(resampler.chunk_size_in(), 1029);
+        assert_eq!(resampler.chunk_size_out(), 1120);
+        assert_eq!(resampler.fft_size_in(), 1029);
+        assert_eq!(resampler.fft_size_out(), 1120);
+        assert_eq!(resampler.sub_chunks(), 1);
+        assert_eq!(resampler.internal_block_frames(), 1029);
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 2, 2);
+        assert_eq!(resampler.chunk_size_in(), 1024);
+        assert_eq!(resampler.fft_size_in(), 588);
+        assert_eq!(resampler.fft_size_out(), 640);
+        assert_eq!(resampler.sub_chunks(), 2);
+        assert_eq!(resampler.internal_block_frames(), 588);
+        let resampler = FftFixedOut::<f64>::new(44100, 48000, 1024, 2, 2);
+        assert_eq!(resampler.chunk_size_out(), 1024);
+        assert_eq!(resampler.fft_size_in(), 588);
+        assert_eq!(resampler.fft_size_out(), 640);
+        assert_eq!(resampler.sub_chunks(), 2);
+        assert_eq!(resampler.internal_block_frames(), 640);
+        // Asking for more sub chunks than fit gives the shortest blocks for the rates.
+        let resampler = FftFixedIn::<f64>::new(44100, 48000, 1024, 16, 2);
+        assert_eq!(resampler.fft_size_in(), 147);
+        assert_eq!(resampler.sub_chunks(), 7);
+    }
+
+    // Only the overlaps are kept per channel. The spectrum and scratch buffers
+    // are shared, and don't grow with the number of channels.
+    #[test]
+    fn shared_spectrum_buffers() {
+        let mut resampler = FftFixedInOut::<f64>::new(44100, 48000, 1024, 64);
+        let two_channels = FftFixedInOut::<f64>::new(44100, 48000, 1024, 2);
+        let fft = &resampler.resampler;
+        assert_eq!(fft.input_f.len(), 1029 + 1);
+        assert_eq!(fft.output_f.len(), 1120 + 1);
+        assert_eq!(fft.input_buf.len(), 2 * 1029);
+        assert_eq!(fft.output_buf.len(), 2 * 1120);
+        assert_eq!(
+            fft.scratch_fw.len(),
+            two_channels.resampler.scratch_fw.len()
+        );
+        assert_eq!(
+            fft.scratch_inv.len(),
+            two_channels.resampler.scratch_inv.len()
+        );
+        assert_eq!(resampler.overlaps.len(), 64);
+        assert!(resampler
+            .overlaps
+            .iter()
+            .all(|overlap| overlap.len() == 1120));
+
+        let mask: Vec<bool> = (0..64).map(|chan| chan % 3 != 0).collect();
+        resampler.set_active_channels_mask(Some(&mask)).unwrap();
+        let waves = vec![vec![1.0; 1029]; 64];
+        let mut waves_out = vec![vec![0.0; 1120]; 64];
+        resampler
+            .process_into_buffer(&waves, &mut waves_out, None)
+            .unwrap();
+        for (chan, active) in mask.iter().enumerate() {
+            let is_zero = resampler.overlaps[chan].iter().all(|x| *x == 0.0);
+            assert_eq!(is_zero, !*active);
+        }
+    }
+
+    // Follow a drifting clock with small adjustments of the ratio,
+    // and check that the spectrum of a resampled sine has no spurs above -90 dB.
+    #[test]
+    fn fine_adjustment() {
+        let mut resampler =
+            FftFixedInOut::<f64>::new_with_adjust(44100, 48000, 1024, 1, 200.0).unwrap();
+        assert!((resampler.max_resample_ratio_relative() - 1.0002).abs() < 1.0e-12);
+        match resampler.set_resample_ratio_relative(1.001) {
+            Err(ResampleError::BadRatioUpdate) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let chunk_in = resampler.nbr_frames_needed();
+        let chunk_out = resampler.output_frames_max();
+        let mut output = Vec::new();
+        let mut position = 0;
+        for n in 0..40 {
+            let drift = 1.0e-4 * (n as f64 / 20.0 - 1.0);
+            resampler.set_resample_ratio_relative(1.0 + drift).unwrap();
+            let wave: Vec<f64> = (position..position + chunk_in)
+                .map(|m| (0.1 * m as f64).sin())
+                .collect();
+            position += chunk_in;
+            let out = resampler.process(&[wave]).unwrap();
+            assert_eq!(out[0].len(), chunk_out);
+            output.extend_from_slice(&out[0]);
+        }
+
+        let len = 32768;
+        let window = make_window::<f64>(len, WindowFunction::BlackmanHarris2);
+        let mut input: Vec<f64> = output[output.len() - len..]
+            .iter()
+            .zip(window.iter())
+            .map(|(x, w)| x * w)
+            .collect();
+        let fft = RealFftPlanner::<f64>::new().plan_fft_forward(len);
+        let mut spectrum = fft.make_output_vec();
+        fft.process(&mut input, &mut spectrum).unwrap();
+        let magnitudes: Vec<f64> = spectrum.iter().map(|c| c.norm()).collect();
+        let (peak, peak_magnitude) =
+            magnitudes.iter().enumerate().fold(
+                (0, 0.0),
+                |acc, (n, m)| if *m > acc.1 { (n, *m) } else { acc },
+            );
+        for (n, m) in magnitudes.iter().enumerate() {
+            if (n as isize - peak as isize).abs() > 30 {
+                let level = 20.0 * (m / peak_magnitude).log10();
+                assert!(level < -90.0, "{} dB at bin {}", level, n);
+            }
+        }
+
+        match FftFixedInOut::<f64>::new_with_adjust(44100, 48000, 1024, 1, -1.0) {
+            Err(ResamplerConstructionError::InvalidMaxRatio) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+        let mut fixed = FftFixedInOut::<f64>::new(44100, 48000, 1024, 1);
+        match fixed.set_resample_ratio_relative(1.0) {
+            Err(ResampleError::SyncNotAdjustable) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    // Resample a sine at `freq` relative to the output Nyquist frequency,
+    // and return the level of the output in dB.
+    fn sine_level(resampler: &mut FftFixedIn<f64>, freq: f64) -> f64 {
+        resampler.reset();
+        let omega = std::f64::consts::PI * freq * 44100.0 / 96000.0;
+        let mut position = 0;
+        let mut sum = 0.0;
+        let mut count = 0;
+        for chunk in 0..12 {
+            let frames = resampler.nbr_frames_needed();
+            let wave: Vec<f64> = (position..position + frames)
+                .map(|n| (omega * n as f64).sin())
+                .collect();
+            position += frames;
+            let output = resampler.process(&[wave]).unwrap();
+            if chunk >= 4 {
+                sum += output[0].iter().map(|v| v * v).sum::<f64>();
+                count += output[0].len();
+            }
+        }
+        10.0 * (2.0 * sum / count as f64).log10()
+    }
+
+    #[test]
+    fn filter_cutoff_and_transition() {
+        let filter = FftFilter {
+            cutoff: Some(0.9),
+            transition: 0.1,
+            ..FftFilter::default()
+        };
+        let mut resampler =
+            FftFixedIn::<f64>::new_with_filter(96000, 44100, 1024, 1, 1, filter).unwrap();
+        let passband = sine_level(&mut resampler, 0.5);
+        assert!(passband.abs() < 0.1, "{} dB in the passband", passband);
+        let cutoff = sine_level(&mut resampler, 0.9);
+        assert!((cutoff + 6.02).abs() < 0.5, "{} dB at the cutoff", cutoff);
+        let transition = sine_level(&mut resampler, 0.93);
+        assert!(
+            transition < -10.0,
+            "{} dB in the transition band",
+            transition
+        );
+        for freq in [1.05, 1.2, 1.5].iter() {
+            let level = sine_level(&mut resampler, *freq);
+            assert!(level < -90.0, "{} dB at {}", level, freq);
+        }
+
+        // The default filter keeps the response flat close to the Nyquist frequency.
+        let mut resampler = FftFixedIn::<f64>::new(96000, 44100, 1024, 1, 1);
+        let level = sine_level(&mut resampler, 0.93);
+        assert!(
+            level.abs() < 0.1,
+            "{} dB at 0.93 with the default filter",
+            level
+        );
+
+        let invalid = [
+            FftFilter {
+                cutoff: Some(0.0),
+                transition: 0.0,
+                ..FftFilter::default()
+            },
+            FftFilter {
+                cutoff: Some(1.1),
+                transition: 0.0,
+                ..FftFilter::default()
+            },
+            FftFilter {
+                cutoff: None,
+                transition: -0.1,
+                ..FftFilter::default()
+            },
+            FftFilter {
+                cutoff: Some(0.4),
+                transition: 1.0,
+                ..FftFilter::default()
+            },
+        ];
+        for filter in invalid.iter() {
+            match FftFixedInOut::<f64>::new_with_filter(96000, 44100, 1024, 1, *filter) {
+                Err(ResamplerConstructionError::UnachievableFilterSpec) => {}
+                other => panic!("unexpected result {:?}", other),
+            }
+        }
+    }
 }
@@ -1,4 +1,6 @@
+use crate::sinc::MAX_SINC_LEN;
 use crate::Sample;
+use realfft::RealFftPlanner;
 
 /// Different window functions that can be used to window the sinc function.
 #[derive(Debug, Clone, Copy)]
@@ -15,6 +17,237 @@ pub enum WindowFunction {
     Hann,
     /// Squared Hann, slower rolloff and higher attenuation than simple Hann
     Hann2,
+    /// Nuttall, the four term window with a continuous first derivative.
+    /// Slightly faster rolloff and better attenuation than Blackman-Harris.
+    Nuttall,
+    /// Flat top. Slower rolloff than Blackman-Harris, with better attenuation.
+    FlatTop,
+    /// Kaiser with the given beta, which trades rolloff against attenuation.
+    /// A larger beta gives slower rolloff and higher attenuation,
+    /// for example about 54 dB for a beta of 5 and 100 dB for a beta of 10.
+    /// A beta of zero gives a rectangular window. The sign of beta is ignored.
+    Kaiser(f64),
+    /// Tukey, or tapered cosine, with the given taper fraction between 0 and 1.
+    /// The window is flat in the middle, and tapers to zero at the ends with the halves
+    /// of a Hann window, over the given fraction of its length.
+    /// A fraction of 0 gives a rectangular window, and 1 gives a Hann window.
+    /// Fast rolloff, but low attenuation unless the fraction is close to 1.
+    /// Fractions outside the range are clamped to it.
+    Tukey(f64),
+    /// A window given by a function, called as `window(x, npoints)` to get point `x`
+    /// of a window of `npoints` points. Like the other windows, it should be largest
+    /// at point `npoints / 2` and symmetric around it.
+    /// The cutoff can't be designed for a custom window, see
+    /// [with_custom_window](crate::InterpolationParameters::with_custom_window).
+    Custom(fn(usize, usize) -> f64),
+}
+
+/// All the window functions, used when choosing one from a specification.
+pub const WINDOW_FUNCTIONS: [WindowFunction; 8] = [
+    WindowFunction::Hann,
+    WindowFunction::Hann2,
+    WindowFunction::Blackman,
+    WindowFunction::Blackman2,
+    WindowFunction::BlackmanHarris,
+    WindowFunction::BlackmanHarris2,
+    WindowFunction::Nuttall,
+    WindowFunction::FlatTop,
+];
+
+impl WindowFunction {
+    /// The attenuation in dB of the highest sidelobe of a sinc windowed by this function.
+    /// The values were measured, and rounded down slightly.
+    /// The Kaiser window uses the approximation by Kaiser instead, lowered by 2 %
+    /// to cover its error at large values of beta.
+    /// The Tukey window uses the value measured for the nearest taper fraction at or below
+    /// the given one, see [TUKEY_MEASURED].
+    /// The attenuation of a custom window is unknown, and given as zero
+    /// so that it is never chosen from a specification.
+    pub(crate) fn stopband_attenuation(self) -> f64 {
+        match self {
+            WindowFunction::Hann => 43.5,
+            WindowFunction::Hann2 => 61.0,
+            WindowFunction::Blackman => 75.0,
+            WindowFunction::Blackman2 => 102.0,
+            WindowFunction::BlackmanHarris => 109.0,
+            WindowFunction::BlackmanHarris2 => 142.0,
+            WindowFunction::Nuttall => 111.5,
+            WindowFunction::FlatTop => 114.0,
+            WindowFunction::Kaiser(beta) => 0.98 * kaiser_attenuation(beta.abs()),
+            WindowFunction::Tukey(alpha) => {
+                let alpha = tukey_fraction(alpha);
+                let below = TUKEY_MEASURED.iter().rev().find(|m| m.0 <= alpha);
+                below.map_or(TUKEY_MEASURED[0].1, |m| m.1)
+            }
+            WindowFunction::Custom(_) => 0.0,
+        }
+    }
+
+    /// The width of the transition band of a sinc windowed by this function,
+    /// relative to the Nyquist frequency, times the length of the sinc in frames.
+    /// The band ends where the attenuation reaches
+    /// [stopband_attenuation](WindowFunction::stopband_attenuation).
+    /// The values were measured, and rounded up slightly.
+    /// The Kaiser window uses the approximation by Kaiser instead, raised by 1 %.
+    /// The Tukey window uses the value measured for the nearest taper fraction at or above
+    /// the given one, see [TUKEY_MEASURED].
+    /// The width for a custom window is unknown, and given as infinite.
+    pub(crate) fn transition_width(self) -> f64 {
+        match self {
+            WindowFunction::Hann => 6.4,
+            WindowFunction::Hann2 => 10.5,
+            WindowFunction::Blackman => 11.3,
+            WindowFunction::Blackman2 => 18.7,
+            WindowFunction::BlackmanHarris => 15.7,
+            WindowFunction::BlackmanHarris2 => 26.9,
+            WindowFunction::Nuttall => 15.6,
+            WindowFunction::FlatTop => 19.5,
+            WindowFunction::Kaiser(beta) => {
+                1.01 * (kaiser_attenuation(beta.abs()) - 7.95) / (2.285 * std::f64::consts::PI)
+            }
+            WindowFunction::Tukey(alpha) => {
+                let alpha = tukey_fraction(alpha);
+                let above = TUKEY_MEASURED.iter().find(|m| m.0 >= alpha);
+                above.map_or(TUKEY_MEASURED[5].2, |m| m.2)
+            }
+            WindowFunction::Custom(_) => std::f64::INFINITY,
+        }
+    }
+}
+
+/// The taper fraction, attenuation in dB and transition width measured for a sinc
+/// windowed by a Tukey window, rounded like the values of the other windows.
+/// The attenuation stays close to that of the rectangular window until the fraction
+/// approaches 1, so interpolating between the rectangular and Hann windows would overestimate it.
+/// Other fractions instead use the nearest measured one on the safe side.
+const TUKEY_MEASURED: [(f64, f64, f64); 6] = [
+    (0.0, 20.5, 1.9),
+    (0.25, 21.0, 2.2),
+    (0.5, 21.5, 2.7),
+    (0.75, 24.5, 3.5),
+    (0.9, 29.5, 4.6),
+    (1.0, 43.5, 6.4),
+];
+
+/// Clamp a Tukey taper fraction to the range 0 to 1, with NaN giving 0.
+fn tukey_fraction(alpha: f64) -> f64 {
+    alpha.max(0.0).min(1.0)
+}
+
+/// The attenuation in dB of a sinc windowed by a Kaiser window with the given beta.
+/// This inverts the formula by Kaiser for the beta that gives an attenuation,
+/// which is linear above 50 dB, and is solved by bisection below that.
+fn kaiser_attenuation(beta: f64) -> f64 {
+    let beta_for =
+        |attenuation: f64| 0.5842 * (attenuation - 21.0).powf(0.4) + 0.07886 * (attenuation - 21.0);
+    if beta >= beta_for(50.0) {
+        return beta / 0.1102 + 8.7;
+    }
+    let (mut low, mut high) = (21.0, 50.0);
+    for _ in 0..40 {
+        let mid = 0.5 * (low + high);
+        if beta_for(mid) < beta {
+            low = mid;
+        } else {
+            high = mid;
+        }
+    }
+    low
+}
+
+/// Get the highest cutoff for a sinc of `sinc_len` points windowed by `window`,
+/// that keeps the stopband attenuation of the window for all frequencies above
+/// the Nyquist frequency. The cutoff is relative to the Nyquist frequency, like
+/// [f_cutoff](crate::InterpolationParameters::f_cutoff).
+///
+/// The sinc has -6 dB at the cutoff, in the middle of the transition band,
+/// and the width of the band is inversely proportional to the length of the sinc.
+/// The widths were measured for each window, see [calculate_sinc_len] for the inverse.
+/// The attenuation and width of a custom window are unknown, and the cutoff is then zero.
+pub fn calculate_cutoff(sinc_len: usize, window: WindowFunction) -> f32 {
+    (1.0 - window.transition_width() / (2.0 * sinc_len as f64)).max(0.0) as f32
+}
+
+/// Get the shortest length of a sinc windowed by `window` for a cutoff,
+/// as the inverse of [calculate_cutoff]. The length is rounded up to a multiple of 8,
+/// and is accepted if it reaches the cutoff within the precision of an f32.
+///
+/// The length is at least 8, and at most the longest sinc that the resamplers accept,
+/// which is 8192 points. The longest sinc is also given for a cutoff of 1.0 or above,
+/// and for a custom window.
+pub fn calculate_sinc_len(f_cutoff: f32, window: WindowFunction) -> usize {
+    if f_cutoff.is_nan() || f_cutoff >= 1.0 {
+        return MAX_SINC_LEN;
+    }
+    let margin = 1.0 - f_cutoff as f64 + std::f32::EPSILON as f64;
+    let blocks = (window.transition_width() / (16.0 * margin)).ceil() as usize;
+    8 * blocks.max(1).min(MAX_SINC_LEN / 8)
+}
+
+/// Design a Kaiser window for a sinc with the given stopband attenuation in dB,
+/// and transition band width relative to the Nyquist frequency.
+/// Returns the length of the sinc, rounded up to a multiple of 8, and the beta of the window.
+///
+/// Beta is found with the formula by Kaiser, for an attenuation that is 2 % higher
+/// than asked for to cover the error of the formula,
+/// see [WindowFunction::Kaiser]. The length is then what gives the width of the transition band.
+/// Use [InterpolationParameters::kaiser](crate::InterpolationParameters::kaiser)
+/// to also get the cutoff, and to check that the design is within the limits of the resamplers.
+pub fn kaiser_design(stopband_db: f64, transition_width: f64) -> (usize, f64) {
+    let attenuation = stopband_db / 0.98;
+    let beta = if attenuation > 50.0 {
+        0.1102 * (attenuation - 8.7)
+    } else if attenuation > 21.0 {
+        0.5842 * (attenuation - 21.0).powf(0.4) + 0.07886 * (attenuation - 21.0)
+    } else {
+        0.0
+    };
+    let width = WindowFunction::Kaiser(beta).transition_width();
+    let sinc_len = ((width / transition_width / 8.0).ceil() as usize)
+        .max(1)
+        .saturating_mul(8);
+    (sinc_len, beta)
+}
+
+/// The modified Bessel function of the first kind and order zero,
+/// evaluated by its power series until the terms no longer change the sum.
+fn bessel_i0(x: f64) -> f64 {
+    let quarter_x2 = 0.25 * x * x;
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    loop {
+        term *= quarter_x2 / (k * k);
+        if term <= sum * std::f64::EPSILON {
+            return sum + term;
+        }
+        sum += term;
+        k += 1.0;
+    }
+}
+
+/// Helper function. Point `x` of a window of `npoints` points given by a cosine series,
+/// `a0 - a1 * cos(2 * pi * x / npoints) + a2 * cos(4 * pi * x / npoints) - ...`.
+///
+/// The windows are symmetric around point `npoints / 2`, which is where the sinc is centered.
+/// This is the periodic form of the window, and also the symmetric window of `npoints + 1` points
+/// without the last point, which is equal to the first.
+/// The terms are calculated from the distance to the center, where the alternating signs cancel,
+/// so that the points at the same distance on either side are exactly equal.
+/// This keeps the windowed sinc exactly symmetric.
+fn cosine_series_at<T>(x: usize, npoints: usize, coefficients: &[f64]) -> T
+where
+    T: Sample,
+{
+    let twice_distance = (2 * x).max(npoints) - (2 * x).min(npoints);
+    let phase = T::PI * T::coerce(twice_distance) / T::coerce(npoints);
+    coefficients
+        .iter()
+        .enumerate()
+        .skip(1)
+        .fold(T::coerce(coefficients[0]), |acc, (k, a)| {
+            acc + T::coerce(*a) * (T::coerce(k) * phase).cos()
+        })
 }
 
 /// Helper function. Standard Blackman-Harris window
@@ -24,22 +257,20 @@ where
 {
     trace!("Making a BlackmanHarris windows with {} points", npoints);
     let mut window = vec![T::zero(); npoints];
-    let pi2 = T::coerce(2.0) * T::PI;
-    let pi4 = T::coerce(4.0) * T::PI;
-    let pi6 = T::coerce(6.0) * T::PI;
-    let np_f = T::coerce(npoints);
-    let a = T::coerce(0.35875);
-    let b = T::coerce(0.48829);
-    let c = T::coerce(0.14128);
-    let d = T::coerce(0.01168);
     for (x, item) in window.iter_mut().enumerate() {
-        let x_float = T::coerce(x);
-        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos()
-            - d * (pi6 * x_float / np_f).cos();
+        *item = blackman_harris_at(x, npoints);
     }
     window
 }
 
+/// Helper function. Point `x` of a standard Blackman-Harris window of `npoints` points.
+pub fn blackman_harris_at<T>(x: usize, npoints: usize) -> T
+where
+    T: Sample,
+{
+    cosine_series_at(x, npoints, &[0.35875, 0.48829, 0.14128, 0.01168])
+}
+
 /// Helper function. Standard Blackman window
 pub fn blackman<T>(npoints: usize) -> Vec<T>
 where
@@ -47,19 +278,20 @@ where
 {
     trace!("Making a Blackman windows with {} points", npoints);
     let mut window = vec![T::zero(); npoints];
-    let pi2 = T::coerce(2.0) * T::PI;
-    let pi4 = T::coerce(4.0) * T::PI;
-    let np_f = T::coerce(npoints);
-    let a = T::coerce(0.42);
-    let b = T::coerce(0.5);
-    let c = T::coerce(0.08);
     for (x, item) in window.iter_mut().enumerate() {
-        let x_float = T::coerce(x);
-        *item = a - b * (pi2 * x_float / np_f).cos() + c * (pi4 * x_float / np_f).cos();
+        *item = blackman_at(x, npoints);
     }
     window
 }
 
+/// Helper function. Point `x` of a standard Blackman window of `npoints` points.
+pub fn blackman_at<T>(x: usize, npoints: usize) -> T
+where
+    T: Sample,
+{
+    cosine_series_at(x, npoints, &[0.42, 0.5, 0.08])
+}
+
 /// Standard Hann window
 pub fn hann<T>(npoints: usize) -> Vec<T>
 where
@@ -67,16 +299,126 @@ where
 {
     trace!("Making a Hann windows with {} points", npoints);
     let mut window = vec![T::zero(); npoints];
-    let pi2 = T::coerce(2.0) * T::PI;
-    let np_f = T::coerce(npoints);
-    let a = T::coerce(0.5);
     for (x, item) in window.iter_mut().enumerate() {
-        let x_float = T::coerce(x);
-        *item = a - a * (pi2 * x_float / np_f).cos();
+        *item = hann_at(x, npoints);
+    }
+    window
+}
+
+/// Point `x` of a standard Hann window of `npoints` points.
+pub fn hann_at<T>(x: usize, npoints: usize) -> T
+where
+    T: Sample,
+{
+    cosine_series_at(x, npoints, &[0.5, 0.5])
+}
+
+/// Helper function. Standard four term Nuttall window
+pub fn nuttall<T>(npoints: usize) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a Nuttall windows with {} points", npoints);
+    let mut window = vec![T::zero(); npoints];
+    for (x, item) in window.iter_mut().enumerate() {
+        *item = nuttall_at(x, npoints);
+    }
+    window
+}
+
+/// Helper function. Point `x` of a standard four term Nuttall window of `npoints` points.
+pub fn nuttall_at<T>(x: usize, npoints: usize) -> T
+where
+    T: Sample,
+{
+    cosine_series_at(x, npoints, &[0.355768, 0.487396, 0.144232, 0.012604])
+}
+
+/// Helper function. Standard five term flat top window
+pub fn flat_top<T>(npoints: usize) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!("Making a flat top windows with {} points", npoints);
+    let mut window = vec![T::zero(); npoints];
+    for (x, item) in window.iter_mut().enumerate() {
+        *item = flat_top_at(x, npoints);
     }
     window
 }
 
+/// Helper function. Point `x` of a standard five term flat top window of `npoints` points.
+/// The window is negative near the ends.
+pub fn flat_top_at<T>(x: usize, npoints: usize) -> T
+where
+    T: Sample,
+{
+    cosine_series_at(
+        x,
+        npoints,
+        &[
+            0.21557895,
+            0.41663158,
+            0.277263158,
+            0.083578947,
+            0.006947368,
+        ],
+    )
+}
+
+/// Kaiser window with parameter `beta`.
+/// The points are calculated in f64, and only rounded to the sample type at the end.
+pub fn kaiser<T>(npoints: usize, beta: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Kaiser window with {} points and beta {}",
+        npoints,
+        beta
+    );
+    (0..npoints).map(|x| kaiser_at(x, npoints, beta)).collect()
+}
+
+/// Point `x` of a Kaiser window of `npoints` points with parameter `beta`.
+pub fn kaiser_at<T>(x: usize, npoints: usize, beta: f64) -> T
+where
+    T: Sample,
+{
+    let relative = (2.0 * x as f64 - npoints as f64) / npoints as f64;
+    let arg = beta * (1.0 - relative * relative).max(0.0).sqrt();
+    T::coerce(bessel_i0(arg) / bessel_i0(beta))
+}
+
+/// Tukey window with taper fraction `alpha`.
+pub fn tukey<T>(npoints: usize, alpha: f64) -> Vec<T>
+where
+    T: Sample,
+{
+    trace!(
+        "Making a Tukey window with {} points and taper fraction {}",
+        npoints,
+        alpha
+    );
+    (0..npoints).map(|x| tukey_at(x, npoints, alpha)).collect()
+}
+
+/// Point `x` of a Tukey window of `npoints` points with taper fraction `alpha`.
+/// Like the cosine windows, the point is calculated from the distance to the center.
+pub fn tukey_at<T>(x: usize, npoints: usize, alpha: f64) -> T
+where
+    T: Sample,
+{
+    let twice_distance = (2 * x).max(npoints) - (2 * x).min(npoints);
+    let twice_edge = (npoints - twice_distance) as f64;
+    let taper = tukey_fraction(alpha) * npoints as f64;
+    if twice_edge < taper {
+        T::coerce(0.5 - 0.5 * (std::f64::consts::PI * twice_edge / taper).cos())
+    } else {
+        T::coerce(1.0)
+    }
+}
+
 /// Make the selected window function
 pub fn make_window<T>(npoints: usize, windowfunc: WindowFunction) -> Vec<T>
 where
@@ -88,6 +430,13 @@ where
         }
         WindowFunction::Blackman | WindowFunction::Blackman2 => blackman::<T>(npoints),
         WindowFunction::Hann | WindowFunction::Hann2 => hann::<T>(npoints),
+        WindowFunction::Nuttall => nuttall::<T>(npoints),
+        WindowFunction::FlatTop => flat_top::<T>(npoints),
+        WindowFunction::Kaiser(beta) => kaiser::<T>(npoints, beta),
+        WindowFunction::Tukey(alpha) => tukey::<T>(npoints, alpha),
+        WindowFunction::Custom(window) => (0..npoints)
+            .map(|x| T::coerce(window(x, npoints)))
+            .collect(),
     };
     match windowfunc {
         WindowFunction::Blackman2 | WindowFunction::BlackmanHarris2 | WindowFunction::Hann2 => {
@@ -98,13 +447,95 @@ where
     window
 }
 
+/// Get point `x` of the selected window function of `npoints` points,
+/// without making the whole window.
+pub fn window_at<T>(x: usize, npoints: usize, windowfunc: WindowFunction) -> T
+where
+    T: Sample,
+{
+    let value = match windowfunc {
+        WindowFunction::BlackmanHarris | WindowFunction::BlackmanHarris2 => {
+            blackman_harris_at::<T>(x, npoints)
+        }
+        WindowFunction::Blackman | WindowFunction::Blackman2 => blackman_at::<T>(x, npoints),
+        WindowFunction::Hann | WindowFunction::Hann2 => hann_at::<T>(x, npoints),
+        WindowFunction::Nuttall => nuttall_at::<T>(x, npoints),
+        WindowFunction::FlatTop => flat_top_at::<T>(x, npoints),
+        WindowFunction::Kaiser(beta) => kaiser_at::<T>(x, npoints, beta),
+        WindowFunction::Tukey(alpha) => tukey_at::<T>(x, npoints, alpha),
+        WindowFunction::Custom(window) => T::coerce(window(x, npoints)),
+    };
+    match windowfunc {
+        WindowFunction::Blackman2 | WindowFunction::BlackmanHarris2 | WindowFunction::Hann2 => {
+            value * value
+        }
+        _ => value,
+    }
+}
+
+/// Get the level in dB of the highest sidelobe of a window of `npoints` points,
+/// relative to the main lobe.
+/// The spectrum is found by an FFT of the window, zero padded to 16 times its length.
+/// The main lobe ends at the first minimum after the spectrum has fallen below half its peak.
+///
+/// This is meant for comparing windows, and allocates.
+/// The attenuation of a sinc windowed by the window is higher than the sidelobe level.
+pub fn window_sidelobe_level<T>(npoints: usize, window: WindowFunction) -> T
+where
+    T: Sample,
+{
+    let fft_len = 16 * npoints;
+    let fft = RealFftPlanner::<f64>::new().plan_fft_forward(fft_len);
+    let mut time = fft.make_input_vec();
+    let mut spectrum = fft.make_output_vec();
+    time[..npoints].copy_from_slice(&make_window::<f64>(npoints, window));
+    fft.process(&mut time, &mut spectrum).unwrap();
+    let magnitudes: Vec<f64> = spectrum.iter().map(|val| val.norm()).collect();
+    let peak = magnitudes[0];
+    let mut end = magnitudes
+        .iter()
+        .position(|val| *val < 0.5 * peak)
+        .unwrap_or(magnitudes.len() - 1);
+    while end + 1 < magnitudes.len() && magnitudes[end + 1] < magnitudes[end] {
+        end += 1;
+    }
+    let sidelobe = magnitudes[end..]
+        .iter()
+        .fold(0.0f64, |acc, val| acc.max(*val));
+    T::coerce(20.0 * (sidelobe / peak).log10())
+}
+
+/// Get the equivalent noise bandwidth of a window of `npoints` points, in FFT bins.
+/// This is the width of the rectangular filter with the same peak that passes
+/// as much white noise as the window, and is larger for windows with a wider main lobe.
+/// The bandwidth of a rectangular window is 1, and of a Hann window 1.5.
+pub fn window_enbw<T>(npoints: usize, window: WindowFunction) -> T
+where
+    T: Sample,
+{
+    let values = make_window::<f64>(npoints, window);
+    let sum: f64 = values.iter().sum();
+    let sum_squares: f64 = values.iter().map(|val| val * val).sum();
+    T::coerce(npoints as f64 * sum_squares / (sum * sum))
+}
+
 #[cfg(test)]
 mod tests {
     use crate::windows::blackman;
     use crate::windows::blackman_harris;
+    use crate::windows::flat_top;
     use crate::windows::hann;
+    use crate::windows::kaiser;
     use crate::windows::make_window;
+    use crate::windows::nuttall;
+    use crate::windows::tukey;
+    use crate::windows::window_at;
     use crate::windows::WindowFunction;
+    use crate::windows::{calculate_cutoff, calculate_sinc_len};
+    use crate::windows::{kaiser_design, WINDOW_FUNCTIONS};
+    use crate::windows::{window_enbw, window_sidelobe_level};
+    use crate::{InterpolationParameters, InterpolationType, ResamplerConstructionError};
+    use realfft::RealFftPlanner;
 
     #[test]
     fn test_blackman_harris() {
@@ -141,4 +572,320 @@ mod tests {
         assert!(wnd[0] < 0.000001);
         assert!(wnd[15] < 0.1);
     }
+
+    #[test]
+    fn test_nuttall() {
+        let wnd = nuttall::<f64>(16);
+        assert!((wnd[8] - 1.0).abs() < 0.000001);
+        assert!(wnd[0] < 0.000001);
+        assert!(wnd[15] < 0.1);
+    }
+
+    #[test]
+    fn test_flat_top() {
+        let wnd = flat_top::<f64>(16);
+        assert!((wnd[8] - 1.0).abs() < 0.000001);
+        assert!((wnd[0] + 4.21051e-4).abs() < 1.0e-6);
+        assert!(wnd[2] < 0.0);
+        assert!(wnd[15] < 0.0);
+    }
+
+    #[test]
+    fn symmetric_windows() {
+        let mut windows = WINDOW_FUNCTIONS.to_vec();
+        windows.push(WindowFunction::Kaiser(8.0));
+        windows.push(WindowFunction::Tukey(0.5));
+        for window in windows.iter() {
+            for npoints in [16, 64, 96].iter() {
+                let wnd = make_window::<f64>(*npoints, *window);
+                let center = npoints / 2;
+                for distance in 1..center {
+                    assert_eq!(wnd[center - distance], wnd[center + distance]);
+                }
+            }
+        }
+        // The values are those of the periodic window.
+        let wnd = blackman::<f64>(64);
+        for (x, value) in wnd.iter().enumerate() {
+            let phase = 2.0 * std::f64::consts::PI * x as f64 / 64.0;
+            let expected = 0.42 - 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            assert!((value - expected).abs() < 1.0e-14);
+        }
+    }
+
+    #[test]
+    fn test_window_at() {
+        for windowfunc in [
+            WindowFunction::Blackman,
+            WindowFunction::Blackman2,
+            WindowFunction::BlackmanHarris,
+            WindowFunction::BlackmanHarris2,
+            WindowFunction::Hann,
+            WindowFunction::Hann2,
+            WindowFunction::Nuttall,
+            WindowFunction::FlatTop,
+            WindowFunction::Kaiser(8.0),
+            WindowFunction::Tukey(0.5),
+        ]
+        .iter()
+        {
+            let wnd = make_window::<f64>(16, *windowfunc);
+            for (x, value) in wnd.iter().enumerate() {
+                assert_eq!(*value, window_at::<f64>(x, 16, *windowfunc));
+            }
+        }
+    }
+
+    #[test]
+    fn test_kaiser() {
+        // Reference values from scipy.signal.windows.kaiser(16, 8.0, sym=False).
+        let expected = [
+            2.338830512733326e-3,
+            2.369490262783184e-2,
+            8.273981505097752e-2,
+            1.967267027173052e-1,
+            3.689727226158333e-1,
+            5.801448192819524e-1,
+            7.887524466053491e-1,
+            9.430220043362005e-1,
+            1.0,
+            9.430220043362005e-1,
+            7.887524466053491e-1,
+            5.801448192819524e-1,
+            3.689727226158333e-1,
+            1.967267027173052e-1,
+            8.273981505097752e-2,
+            2.369490262783184e-2,
+        ];
+        let wnd = kaiser::<f64>(16, 8.0);
+        for (value, reference) in wnd.iter().zip(expected.iter()) {
+            assert!(
+                (value - reference).abs() < 1.0e-12,
+                "{} {}",
+                value,
+                reference
+            );
+        }
+        assert_eq!(kaiser::<f64>(16, -8.0), wnd);
+        assert!(kaiser::<f64>(16, 0.0).iter().all(|value| *value == 1.0));
+    }
+
+    #[test]
+    fn test_tukey() {
+        assert!(tukey::<f64>(16, 0.0).iter().all(|value| *value == 1.0));
+        assert!(tukey::<f64>(16, -0.5).iter().all(|value| *value == 1.0));
+        let wnd = tukey::<f64>(16, 1.0);
+        for (value, reference) in wnd.iter().zip(hann::<f64>(16).iter()) {
+            assert!((value - reference).abs() < 1.0e-12);
+        }
+        assert_eq!(tukey::<f64>(16, 1.5), wnd);
+        // With half the length tapered, the middle half is flat.
+        let wnd = tukey::<f64>(16, 0.5);
+        assert_eq!(wnd[0], 0.0);
+        assert!((wnd[2] - 0.5).abs() < 1.0e-12);
+        assert!(wnd[1..4].iter().all(|value| *value > 0.0 && *value < 1.0));
+        assert!(wnd[4..13].iter().all(|value| *value == 1.0));
+        assert!(wnd[13..].iter().all(|value| *value > 0.0 && *value < 1.0));
+    }
+
+    #[test]
+    fn kaiser_model() {
+        // The attenuation and transition width measured for a sinc of 64 points
+        // with the cutoff at half the Nyquist frequency, for a few values of beta.
+        let measured = [
+            (2.0, 29.1, 2.97),
+            (5.0, 54.2, 6.44),
+            (10.0, 99.7, 12.76),
+            (14.0, 133.8, 17.81),
+        ];
+        for (beta, attenuation, width) in measured.iter() {
+            let window = WindowFunction::Kaiser(*beta);
+            let model_attenuation = window.stopband_attenuation();
+            let model_width = window.transition_width();
+            assert!(
+                model_attenuation <= *attenuation && model_attenuation > attenuation - 3.0,
+                "attenuation {} for beta {}",
+                model_attenuation,
+                beta
+            );
+            assert!(
+                model_width >= *width && model_width < 1.05 * width,
+                "width {} for beta {}",
+                model_width,
+                beta
+            );
+        }
+    }
+
+    // Make a windowed sinc at twice the length and half the cutoff, like when downsampling by 2,
+    // and get the attenuation in dB of the largest peak of its spectrum
+    // from where the stopband starts at half the Nyquist frequency.
+    fn downsampled_attenuation(window: WindowFunction, sinc_len: usize, f_cutoff: f32) -> f64 {
+        let npoints = 2 * sinc_len;
+        let cutoff = f_cutoff as f64 / 2.0;
+        let mut taps: Vec<f64> = make_window::<f64>(npoints, window)
+            .iter()
+            .enumerate()
+            .map(|(x, w)| {
+                let arg = std::f64::consts::PI * (x as f64 - sinc_len as f64) * cutoff;
+                if arg == 0.0 {
+                    *w
+                } else {
+                    w * arg.sin() / arg
+                }
+            })
+            .collect();
+        let sum: f64 = taps.iter().sum();
+        taps.iter_mut().for_each(|tap| *tap /= sum);
+        let fft_len = 16 * npoints;
+        taps.resize(fft_len, 0.0);
+        let fft = RealFftPlanner::<f64>::new().plan_fft_forward(fft_len);
+        let mut spectrum = fft.make_output_vec();
+        fft.process(&mut taps, &mut spectrum).unwrap();
+        let peak = spectrum[fft_len / 4..]
+            .iter()
+            .map(|value| value.norm())
+            .fold(0.0, f64::max);
+        -20.0 * peak.log10()
+    }
+
+    // Place the cutoff so that the stopband starts at the Nyquist frequency,
+    // and check that the attenuation is at least what the tables give.
+    #[test]
+    fn stopband_tables() {
+        for window in WINDOW_FUNCTIONS.iter() {
+            let sinc_len = 64;
+            let f_cutoff = 1.0 - window.transition_width() / (2.0 * sinc_len as f64);
+            let attenuation = downsampled_attenuation(*window, sinc_len, f_cutoff as f32);
+            assert!(
+                attenuation > window.stopband_attenuation(),
+                "{} dB for {:?}",
+                attenuation,
+                window
+            );
+        }
+        // Also between the measured taper fractions of the Tukey window.
+        for alpha in [0.0, 0.1, 0.3, 0.5, 0.6, 0.8, 0.95, 0.99, 1.0].iter() {
+            let window = WindowFunction::Tukey(*alpha);
+            let sinc_len = 64;
+            let f_cutoff = 1.0 - window.transition_width() / (2.0 * sinc_len as f64);
+            let attenuation = downsampled_attenuation(window, sinc_len, f_cutoff as f32);
+            assert!(
+                attenuation > window.stopband_attenuation(),
+                "{} dB for {:?}",
+                attenuation,
+                window
+            );
+        }
+        let hann = WindowFunction::Hann;
+        let tapered = WindowFunction::Tukey(1.0);
+        assert_eq!(tapered.stopband_attenuation(), hann.stopband_attenuation());
+        assert_eq!(tapered.transition_width(), hann.transition_width());
+    }
+
+    #[test]
+    fn kaiser_design_attenuation() {
+        let specs = [
+            (40.0, 0.1),
+            (60.0, 0.1),
+            (80.0, 0.05),
+            (100.0, 0.1),
+            (140.0, 0.05),
+        ];
+        for (stopband, width) in specs.iter() {
+            let params =
+                InterpolationParameters::kaiser(*stopband, *width, InterpolationType::Cubic)
+                    .unwrap();
+            let (sinc_len, beta) = kaiser_design(*stopband, *width);
+            assert_eq!(params.sinc_len, sinc_len);
+            assert_eq!(sinc_len % 8, 0);
+            match params.window {
+                WindowFunction::Kaiser(b) => assert_eq!(b, beta),
+                other => panic!("unexpected window {:?}", other),
+            }
+            let attenuation = downsampled_attenuation(params.window, sinc_len, params.f_cutoff);
+            assert!(
+                attenuation > *stopband && attenuation < stopband + 3.0,
+                "{} dB for {:?}",
+                attenuation,
+                params
+            );
+        }
+        for (stopband, width) in [(0.0, 0.1), (100.0, 0.0), (100.0, 1.5), (100.0, 1.0e-6)].iter() {
+            assert_eq!(
+                InterpolationParameters::kaiser(*stopband, *width, InterpolationType::Cubic)
+                    .unwrap_err(),
+                ResamplerConstructionError::UnachievableFilterSpec
+            );
+        }
+    }
+
+    #[test]
+    fn sinc_len_for_cutoff() {
+        let mut windows = WINDOW_FUNCTIONS.to_vec();
+        windows.push(WindowFunction::Kaiser(10.0));
+        for window in windows.iter() {
+            for sinc_len in (32..=2048).step_by(8) {
+                let f_cutoff = calculate_cutoff(sinc_len, *window);
+                assert_eq!(calculate_sinc_len(f_cutoff, *window), sinc_len);
+            }
+            for f_cutoff in [0.5, 0.9, 0.95, 0.98, 0.99].iter() {
+                let sinc_len = calculate_sinc_len(*f_cutoff, *window);
+                assert_eq!(sinc_len % 8, 0);
+                assert!(calculate_cutoff(sinc_len, *window) >= f_cutoff - std::f32::EPSILON);
+                assert!(calculate_cutoff(sinc_len - 8, *window) < *f_cutoff);
+            }
+            assert_eq!(calculate_sinc_len(1.0, *window), 8192);
+            assert_eq!(calculate_sinc_len(0.9999, *window), 8192);
+        }
+        assert_eq!(calculate_sinc_len(0.0, WindowFunction::Hann), 8);
+        assert_eq!(
+            calculate_sinc_len(std::f32::NAN, WindowFunction::Hann),
+            8192
+        );
+        let custom = WindowFunction::Custom(|_x, _npoints| 1.0);
+        assert_eq!(calculate_sinc_len(0.9, custom), 8192);
+        assert_eq!(calculate_cutoff(256, custom), 0.0);
+    }
+
+    #[test]
+    fn window_analysis() {
+        let rectangular = WindowFunction::Kaiser(0.0);
+        assert!((window_sidelobe_level::<f64>(64, rectangular) + 13.26).abs() < 0.05);
+        assert!((window_enbw::<f64>(64, rectangular) - 1.0).abs() < 1.0e-12);
+        assert!((window_sidelobe_level::<f64>(64, WindowFunction::Hann) + 31.47).abs() < 0.05);
+        assert!((window_enbw::<f64>(64, WindowFunction::Hann) - 1.5).abs() < 1.0e-12);
+        assert!((window_enbw::<f32>(64, WindowFunction::Hann) - 1.5).abs() < 1.0e-6);
+    }
+
+    // The ordering of the attenuation and rolloff described for each window.
+    #[test]
+    fn window_ordering() {
+        let level = |window| window_sidelobe_level::<f64>(64, window);
+        let enbw = |window| window_enbw::<f64>(64, window);
+        assert!(level(WindowFunction::Hann) > level(WindowFunction::Blackman));
+        assert!(level(WindowFunction::Blackman) > level(WindowFunction::BlackmanHarris));
+        assert!(level(WindowFunction::BlackmanHarris) > level(WindowFunction::Nuttall));
+        assert!(enbw(WindowFunction::Hann) < enbw(WindowFunction::Blackman));
+        assert!(enbw(WindowFunction::Blackman) < enbw(WindowFunction::BlackmanHarris));
+        assert!(enbw(WindowFunction::BlackmanHarris) < enbw(WindowFunction::FlatTop));
+        let squared = [
+            (WindowFunction::Hann, WindowFunction::Hann2),
+            (WindowFunction::Blackman, WindowFunction::Blackman2),
+            (
+                WindowFunction::BlackmanHarris,
+                WindowFunction::BlackmanHarris2,
+            ),
+        ];
+        for (window, window2) in squared.iter() {
+            assert!(level(*window) > level(*window2));
+            assert!(enbw(*window) < enbw(*window2));
+        }
+        for beta in [2.0, 5.0, 10.0].iter() {
+            let window = WindowFunction::Kaiser(*beta);
+            let wider = WindowFunction::Kaiser(beta + 2.0);
+            assert!(level(window) > level(wider));
+            assert!(enbw(window) < enbw(wider));
+        }
+    }
 }
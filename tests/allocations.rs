@@ -0,0 +1,147 @@
+use rubato::{
+    Accumulation, FftFixedIn, FftFixedInOut, FftFixedOut, FilterPhase, InterpolationParameters,
+    InterpolationType, Resampler, SincFixedIn, SincFixedOut, SincStorage, WindowFunction,
+};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+// An allocator that counts the allocations made by the current thread.
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = Cell::new(0);
+}
+
+fn count_allocation() {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        count_allocation();
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        count_allocation();
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(|count| count.get())
+}
+
+fn sinc_parameters() -> InterpolationParameters {
+    InterpolationParameters {
+        sinc_len: 64,
+        f_cutoff: 0.95,
+        interpolation: InterpolationType::Cubic,
+        oversampling_factor: 16,
+        window: WindowFunction::BlackmanHarris2,
+        phase: FilterPhase::Linear,
+        storage: SincStorage::Table,
+        accumulation: Accumulation::Native,
+    }
+}
+
+// Drain a resampler with partial chunks, and check that nothing is allocated.
+fn check_partial<R: Resampler<f64>>(mut resampler: R) {
+    let waves_in = vec![vec![0.5; resampler.nbr_frames_needed() / 2]; 2];
+    let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+    let before = allocations();
+    resampler
+        .process_partial_into_buffer(Some(&waves_in), &mut waves_out, None)
+        .unwrap();
+    resampler
+        .process_partial_into_buffer(None::<&[Vec<f64>]>, &mut waves_out, None)
+        .unwrap();
+    assert_eq!(allocations(), before);
+}
+
+#[test]
+fn process_partial_does_not_allocate() {
+    check_partial(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+    check_partial(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+    check_partial(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+    check_partial(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+    check_partial(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+    check_partial(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+}
+
+// Run a resampler through the processing calls, mask changes and resets that are meant
+// for realtime use, starting right after construction, and check that nothing is allocated.
+fn check_realtime<R: Resampler<f64>>(mut resampler: R) {
+    let waves_in = vec![vec![0.5; resampler.input_frames_max()]; 2];
+    let mut waves_out = vec![vec![0.0; resampler.output_frames_max()]; 2];
+    let interleaved_in = vec![0.5; 2 * resampler.input_frames_max()];
+    let mut interleaved_out = vec![0.0; 2 * resampler.output_frames_max()];
+    let first_only = [true, false];
+    let before = allocations();
+    for _ in 0..3 {
+        resampler
+            .process_into_buffer(&waves_in, &mut waves_out, None)
+            .unwrap();
+    }
+    // Masks that only select channels, given with the call and stored.
+    resampler
+        .process_into_buffer(&waves_in, &mut waves_out, Some(&first_only[..]))
+        .unwrap();
+    resampler
+        .set_active_channels_mask(Some(&first_only[..]))
+        .unwrap();
+    resampler
+        .process_into_buffer(&waves_in, &mut waves_out, None)
+        .unwrap();
+    resampler.set_active_channels_mask(None).unwrap();
+    resampler
+        .process_into_buffer(&waves_in, &mut waves_out, None)
+        .unwrap();
+    let frames = resampler.nbr_frames_needed();
+    resampler
+        .process_interleaved_into_buffer(&interleaved_in[..2 * frames], &mut interleaved_out, None)
+        .unwrap();
+    resampler
+        .process_into_interleaved_buffer(&waves_in, &mut interleaved_out, None)
+        .unwrap();
+    resampler
+        .process_partial_into_buffer(None::<&[Vec<f64>]>, &mut waves_out, None)
+        .unwrap();
+    resampler.reset_channel(1).unwrap();
+    resampler.reset();
+    resampler
+        .process_into_buffer(&waves_in, &mut waves_out, None)
+        .unwrap();
+    while resampler
+        .process_final_into_buffer(None::<&[Vec<f64>]>, &mut waves_out, None)
+        .unwrap()
+        .1
+        > 0
+    {}
+    assert_eq!(allocations(), before);
+}
+
+#[test]
+fn realtime_processing_does_not_allocate() {
+    check_realtime(FftFixedInOut::<f64>::new(44100, 48000, 512, 2));
+    check_realtime(FftFixedInOut::<f64>::new(44100, 32000, 512, 2));
+    check_realtime(FftFixedInOut::<f64>::new_with_adjust(44100, 48000, 512, 2, 100.0).unwrap());
+    check_realtime(FftFixedIn::<f64>::new(44100, 48000, 512, 2, 2));
+    check_realtime(FftFixedIn::<f64>::new(48000, 16000, 1200, 2, 2));
+    check_realtime(FftFixedOut::<f64>::new(48000, 44100, 512, 2, 2));
+    check_realtime(FftFixedOut::<f64>::new(44100, 192000, 1024, 2, 2));
+    check_realtime(SincFixedIn::<f64>::new(1.2, sinc_parameters(), 256, 2));
+    check_realtime(SincFixedOut::<f64>::new(0.8, sinc_parameters(), 256, 2));
+}